@@ -1,6 +1,7 @@
 /// Committee debate agent definitions — personas, system prompts, and round templates.
 /// Agents are stored as a registry (registry.json) + individual prompt files (.md).
 
+use crate::safe_path;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -18,12 +19,110 @@ pub struct AgentInfo {
     pub sort_order: u32,
     #[serde(default = "default_voice_gender")]
     pub voice_gender: String, // "male" or "female"
+    #[serde(default)]
+    pub generation: GenerationParams,
 }
 
 fn default_voice_gender() -> String {
     "male".to_string()
 }
 
+impl AgentInfo {
+    /// The agent's own generation settings, with unset fields taken from the
+    /// built-in tuning for its key.
+    pub fn generation_params(&self) -> GenerationParams {
+        let base = if self.builtin { builtin_generation(&self.key) } else { GenerationParams::default() };
+        self.generation.clone().or(base)
+    }
+}
+
+/// Sampling settings for an agent's debate calls. `None` falls through to the
+/// built-in tuning, then to the request defaults in `llm.rs`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Sent as OpenRouter's `provider` field; other providers ignore it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_routing: Option<ProviderRouting>,
+}
+
+/// OpenRouter provider routing preferences. Field names match the API.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProviderRouting {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_collection: Option<String>, // "allow" or "deny"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>, // "price", "throughput" or "latency"
+}
+
+impl GenerationParams {
+    /// Fill unset fields from `base`.
+    pub fn or(self, base: GenerationParams) -> GenerationParams {
+        GenerationParams {
+            temperature: self.temperature.or(base.temperature),
+            top_p: self.top_p.or(base.top_p),
+            max_tokens: self.max_tokens.or(base.max_tokens),
+            seed: self.seed.or(base.seed),
+            provider_routing: self.provider_routing.or(base.provider_routing),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(t) = self.temperature {
+            if !(0.0..=2.0).contains(&t) {
+                return Err("Temperature must be between 0 and 2".to_string());
+            }
+        }
+        if let Some(p) = self.top_p {
+            if p <= 0.0 || p > 1.0 {
+                return Err("top_p must be greater than 0 and at most 1".to_string());
+            }
+        }
+        if self.max_tokens == Some(0) {
+            return Err("max_tokens must be at least 1".to_string());
+        }
+        if let Some(routing) = &self.provider_routing {
+            if let Some(dc) = routing.data_collection.as_deref() {
+                if !["allow", "deny"].contains(&dc) {
+                    return Err("data_collection must be \"allow\" or \"deny\"".to_string());
+                }
+            }
+            if let Some(sort) = routing.sort.as_deref() {
+                if !["price", "throughput", "latency"].contains(&sort) {
+                    return Err("sort must be \"price\", \"throughput\" or \"latency\"".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Built-in tuning: a cold, repeatable Rationalist, a looser Visionary and a
+/// moderator with room for a full synthesis.
+pub fn builtin_generation(key: &str) -> GenerationParams {
+    match key {
+        "rationalist" => GenerationParams { temperature: Some(0.2), seed: Some(7), ..GenerationParams::default() },
+        "visionary" => GenerationParams { temperature: Some(1.0), top_p: Some(0.95), ..GenerationParams::default() },
+        "moderator" => GenerationParams { max_tokens: Some(4096), ..GenerationParams::default() },
+        _ => GenerationParams::default(),
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AgentFileInfo {
     pub filename: String,
@@ -42,12 +141,12 @@ struct AgentRegistry {
 
 pub fn builtin_agents() -> Vec<AgentInfo> {
     vec![
-        AgentInfo { key: "rationalist".into(), label: "Rationalist".into(), emoji: "\u{1f9ee}".into(), color: "blue".into(), role: "debater".into(), builtin: true, sort_order: 0, voice_gender: "male".into() },
-        AgentInfo { key: "advocate".into(), label: "Advocate".into(), emoji: "\u{1f49c}".into(), color: "purple".into(), role: "debater".into(), builtin: true, sort_order: 1, voice_gender: "female".into() },
-        AgentInfo { key: "contrarian".into(), label: "Contrarian".into(), emoji: "\u{1f534}".into(), color: "red".into(), role: "debater".into(), builtin: true, sort_order: 2, voice_gender: "male".into() },
-        AgentInfo { key: "visionary".into(), label: "Visionary".into(), emoji: "\u{1f52d}".into(), color: "teal".into(), role: "debater".into(), builtin: true, sort_order: 3, voice_gender: "female".into() },
-        AgentInfo { key: "pragmatist".into(), label: "Pragmatist".into(), emoji: "\u{1f527}".into(), color: "orange".into(), role: "debater".into(), builtin: true, sort_order: 4, voice_gender: "male".into() },
-        AgentInfo { key: "moderator".into(), label: "Moderator".into(), emoji: "\u{1f3af}".into(), color: "amber".into(), role: "moderator".into(), builtin: true, sort_order: 100, voice_gender: "male".into() },
+        AgentInfo { key: "rationalist".into(), label: "Rationalist".into(), emoji: "\u{1f9ee}".into(), color: "blue".into(), role: "debater".into(), builtin: true, sort_order: 0, voice_gender: "male".into(), generation: GenerationParams::default() },
+        AgentInfo { key: "advocate".into(), label: "Advocate".into(), emoji: "\u{1f49c}".into(), color: "purple".into(), role: "debater".into(), builtin: true, sort_order: 1, voice_gender: "female".into(), generation: GenerationParams::default() },
+        AgentInfo { key: "contrarian".into(), label: "Contrarian".into(), emoji: "\u{1f534}".into(), color: "red".into(), role: "debater".into(), builtin: true, sort_order: 2, voice_gender: "male".into(), generation: GenerationParams::default() },
+        AgentInfo { key: "visionary".into(), label: "Visionary".into(), emoji: "\u{1f52d}".into(), color: "teal".into(), role: "debater".into(), builtin: true, sort_order: 3, voice_gender: "female".into(), generation: GenerationParams::default() },
+        AgentInfo { key: "pragmatist".into(), label: "Pragmatist".into(), emoji: "\u{1f527}".into(), color: "orange".into(), role: "debater".into(), builtin: true, sort_order: 4, voice_gender: "male".into(), generation: GenerationParams::default() },
+        AgentInfo { key: "moderator".into(), label: "Moderator".into(), emoji: "\u{1f3af}".into(), color: "amber".into(), role: "moderator".into(), builtin: true, sort_order: 100, voice_gender: "male".into(), generation: GenerationParams::default() },
     ]
 }
 
@@ -70,22 +169,30 @@ pub fn get_agents_dir(app_data_dir: &PathBuf) -> PathBuf {
     app_data_dir.join("agents")
 }
 
+pub const REGISTRY_FILENAME: &str = "registry.json";
+
 fn registry_path(app_data_dir: &PathBuf) -> PathBuf {
-    get_agents_dir(app_data_dir).join("registry.json")
+    get_agents_dir(app_data_dir).join(REGISTRY_FILENAME)
 }
 
 /// Load the agent registry from disk, creating it with built-in defaults if missing.
-pub fn load_registry(app_data_dir: &PathBuf) -> Vec<AgentInfo> {
-    let path = registry_path(app_data_dir);
-    if let Ok(content) = fs::read_to_string(&path) {
-        if let Ok(registry) = serde_json::from_str::<AgentRegistry>(&content) {
-            return registry.agents;
+/// A registry that no longer parses is reported and left alone, since users edit it by hand.
+pub fn load_registry(app_data_dir: &PathBuf) -> Result<Vec<AgentInfo>, String> {
+    match fs::read_to_string(registry_path(app_data_dir)) {
+        Ok(content) => parse_registry(&content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let agents = builtin_agents();
+            save_registry(app_data_dir, &agents)?;
+            Ok(agents)
         }
+        Err(e) => Err(format!("Could not read agents/{}: {}", REGISTRY_FILENAME, e)),
     }
-    // Registry missing or corrupt — seed with built-ins and save
-    let agents = builtin_agents();
-    let _ = save_registry(app_data_dir, &agents);
-    agents
+}
+
+pub fn parse_registry(content: &str) -> Result<Vec<AgentInfo>, String> {
+    serde_json::from_str::<AgentRegistry>(content).map(|r| r.agents).map_err(|e| {
+        format!("agents/{} is corrupt ({}). Fix it, or delete it to restore the built-in agents.", REGISTRY_FILENAME, e)
+    })
 }
 
 /// Save the agent registry to disk.
@@ -100,13 +207,15 @@ pub fn save_registry(app_data_dir: &PathBuf, agents: &[AgentInfo]) -> Result<(),
 // ── Agent prompt file I/O ──
 
 /// Ensure all built-in agent prompt files exist on disk, writing defaults for any missing ones.
-/// Also ensures the registry.json exists.
+/// Also ensures the registry.json exists. A corrupt registry is left alone for
+/// `load_registry` to report; the prompt files don't depend on it.
 pub fn init_agent_files(app_data_dir: &PathBuf) -> Result<(), String> {
     let dir = get_agents_dir(app_data_dir);
     fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
 
-    // Ensure registry exists (creates from builtins if missing)
-    let _agents = load_registry(app_data_dir);
+    if !registry_path(app_data_dir).exists() {
+        save_registry(app_data_dir, &builtin_agents())?;
+    }
 
     // Ensure built-in prompt files exist
     let builtins = [
@@ -140,12 +249,14 @@ pub fn read_agent_prompt(app_data_dir: &PathBuf, agent_key: &str) -> String {
     })
 }
 
-/// Read all agent prompt files with metadata, ordered by registry sort_order.
+/// Read all agent prompt files with metadata, ordered by registry sort_order. The files
+/// are listed even when the registry is corrupt (in built-in order); `load_registry`
+/// reports that error on its own.
 pub fn read_all_agent_files(app_data_dir: &PathBuf) -> Result<Vec<AgentFileInfo>, String> {
     let dir = get_agents_dir(app_data_dir);
     init_agent_files(app_data_dir)?;
 
-    let registry = load_registry(app_data_dir);
+    let registry = load_registry(app_data_dir).unwrap_or_else(|_| builtin_agents());
 
     let mut files = Vec::new();
     let entries = fs::read_dir(&dir).map_err(|e| e.to_string())?;
@@ -184,8 +295,8 @@ pub fn read_all_agent_files(app_data_dir: &PathBuf) -> Result<Vec<AgentFileInfo>
 /// Write an agent prompt file.
 pub fn write_agent_file(app_data_dir: &PathBuf, filename: &str, content: &str) -> Result<(), String> {
     let dir = get_agents_dir(app_data_dir);
+    let path = safe_path::resolve(&dir, filename)?;
     fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
-    let path = dir.join(filename);
     fs::write(&path, content).map_err(|e| e.to_string())?;
     Ok(())
 }
@@ -198,7 +309,7 @@ pub fn create_custom_agent(
     prompt: &str,
     voice_gender: &str,
 ) -> Result<AgentInfo, String> {
-    let mut registry = load_registry(app_data_dir);
+    let mut registry = load_registry(app_data_dir)?;
 
     // Generate key from label
     let key = label
@@ -243,6 +354,7 @@ pub fn create_custom_agent(
         builtin: false,
         sort_order: max_debater_order + 1,
         voice_gender: voice_gender.to_string(),
+        generation: GenerationParams::default(),
     };
 
     // Write prompt file
@@ -255,9 +367,22 @@ pub fn create_custom_agent(
     Ok(agent)
 }
 
+/// Replace an agent's generation settings in the registry.
+pub fn save_agent_generation(app_data_dir: &PathBuf, agent_key: &str, params: GenerationParams) -> Result<AgentInfo, String> {
+    params.validate()?;
+    let mut registry = load_registry(app_data_dir)?;
+    let agent = registry.iter_mut()
+        .find(|a| a.key == agent_key)
+        .ok_or_else(|| format!("Agent '{}' not found", agent_key))?;
+    agent.generation = params;
+    let updated = agent.clone();
+    save_registry(app_data_dir, &registry)?;
+    Ok(updated)
+}
+
 /// Delete a custom (non-builtin) agent.
 pub fn delete_custom_agent(app_data_dir: &PathBuf, agent_key: &str) -> Result<(), String> {
-    let mut registry = load_registry(app_data_dir);
+    let mut registry = load_registry(app_data_dir)?;
 
     let agent = registry.iter()
         .find(|a| a.key == agent_key)
@@ -510,6 +635,33 @@ Synthesize this debate into a clear recommendation. Structure your response as:
     )
 }
 
+/// Structured variant of `moderator_prompt`: the same synthesis, returned as the
+/// `moderator_verdict` JSON object instead of markdown sections.
+pub fn moderator_verdict_prompt(brief: &str, transcript: &str, participants: &str) -> String {
+    format!(
+        r#"{brief}
+
+The following committee members participated in this debate: {participants}
+
+Here is the full committee debate:
+
+{transcript}
+
+Synthesize this debate into a clear recommendation and return it as a JSON object with these fields:
+
+- consensus_points: key points of consensus, one per item
+- key_disagreements: where members differed and who had the stronger argument, one per item
+- biases_identified: cognitive biases surfaced during the debate, one per item
+- recommendation.choice: the clear choice
+- recommendation.confidence: "high", "medium" or "low"
+- recommendation.reasoning: why this is the right call, weighing the debate
+- recommendation.tradeoffs: what the person gives up with this choice
+- recommendation.next_steps: specific next steps with timeline, one per item
+
+Return only the JSON object."#
+    )
+}
+
 /// Build a human-readable participant description like "The Rationalist, The Advocate, and The Pragmatist"
 pub fn format_participant_names(debaters: &[AgentInfo]) -> String {
     let names: Vec<String> = debaters.iter().map(|a| format!("The {}", a.label)).collect();
@@ -648,7 +800,7 @@ mod tests {
         init_agent_files(&app_data_dir).expect("agent files should initialize");
 
         // Registry should exist
-        let registry = load_registry(&app_data_dir);
+        let registry = load_registry(&app_data_dir).unwrap();
         assert_eq!(registry.len(), 6);
 
         // Prompt files should exist
@@ -674,7 +826,7 @@ mod tests {
         assert_eq!(agent.voice_gender, "female");
 
         // Registry should now have 7 agents
-        let registry = load_registry(&app_data_dir);
+        let registry = load_registry(&app_data_dir).unwrap();
         assert_eq!(registry.len(), 7);
 
         // Prompt file should exist
@@ -683,7 +835,7 @@ mod tests {
 
         // Delete custom agent
         delete_custom_agent(&app_data_dir, "economist").expect("should delete agent");
-        let registry = load_registry(&app_data_dir);
+        let registry = load_registry(&app_data_dir).unwrap();
         assert_eq!(registry.len(), 6);
 
         // Cannot delete builtin
@@ -691,6 +843,41 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn integration_agent_generation_overrides_builtin_tuning_field_by_field() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        init_agent_files(&app_data_dir).expect("agent files should initialize");
+
+        let registry = load_registry(&app_data_dir).unwrap();
+        let rationalist = registry.iter().find(|a| a.key == "rationalist").unwrap();
+        assert_eq!(rationalist.generation_params().temperature, Some(0.2));
+        assert!(rationalist.generation_params().seed.is_some());
+
+        let updated = save_agent_generation(&app_data_dir, "rationalist", GenerationParams {
+            temperature: Some(0.0),
+            provider_routing: Some(ProviderRouting { order: vec!["anthropic".to_string()], ..ProviderRouting::default() }),
+            ..GenerationParams::default()
+        })
+        .expect("valid params should save");
+        let effective = updated.generation_params();
+        assert_eq!(effective.temperature, Some(0.0));
+        assert_eq!(effective.seed, builtin_generation("rationalist").seed);
+        assert_eq!(load_registry(&app_data_dir).unwrap()[0].generation, updated.generation);
+
+        let too_hot = GenerationParams { temperature: Some(2.5), ..GenerationParams::default() };
+        assert!(save_agent_generation(&app_data_dir, "rationalist", too_hot).is_err());
+        let bad_sort = GenerationParams {
+            provider_routing: Some(ProviderRouting { sort: Some("vibes".to_string()), ..ProviderRouting::default() }),
+            ..GenerationParams::default()
+        };
+        assert!(bad_sort.validate().is_err());
+        assert!(save_agent_generation(&app_data_dir, "nobody", GenerationParams::default()).is_err());
+
+        let custom = create_custom_agent(&app_data_dir, "Economist", "\u{1f4b0}", "Custom prompt", "female").unwrap();
+        assert_eq!(custom.generation_params(), GenerationParams::default());
+    }
+
     #[test]
     fn integration_read_agent_prompt_with_override() {
         let dir = tempdir().expect("temp directory should exist");
@@ -703,4 +890,38 @@ mod tests {
         let custom_prompt = read_agent_prompt(&app_data_dir, "rationalist");
         assert_eq!(custom_prompt, "custom prompt");
     }
+
+    #[test]
+    fn integration_write_agent_file_rejects_paths_outside_the_agents_dir() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        init_agent_files(&app_data_dir).expect("agent files should initialize");
+
+        let err = write_agent_file(&app_data_dir, "../config.json", "{}").unwrap_err();
+        assert_eq!(err, "Filename '../config.json' must not contain '..'");
+        assert!(!app_data_dir.join("config.json").exists());
+        assert!(write_agent_file(&app_data_dir, "registry.json", "[]").unwrap_err().contains("must end in .md"));
+        assert!(write_agent_file(&app_data_dir, "../profile/career.md", "x").is_err());
+        assert_eq!(load_registry(&app_data_dir).unwrap().len(), builtin_agents().len());
+    }
+
+    #[test]
+    fn integration_corrupt_registry_is_reported_not_reseeded() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        init_agent_files(&app_data_dir).expect("agent files should initialize");
+        let path = registry_path(&app_data_dir);
+        let corrupt = "{ \"version\": 1, \"agents\": [ { \"key\": \"rationalist\" ";
+        fs::write(&path, corrupt).unwrap();
+
+        let err = load_registry(&app_data_dir).unwrap_err();
+        assert!(err.starts_with("agents/registry.json is corrupt ("));
+        assert!(init_agent_files(&app_data_dir).is_ok());
+        assert_eq!(read_all_agent_files(&app_data_dir).unwrap().len(), builtin_agents().len());
+        assert!(create_custom_agent(&app_data_dir, "Skeptic", "🤨", "Doubt it.", "male").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), corrupt);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(load_registry(&app_data_dir).unwrap().len(), builtin_agents().len());
+    }
 }
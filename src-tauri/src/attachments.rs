@@ -0,0 +1,360 @@
+//! Documents attached to a decision — offer letters, leases, spreadsheets. Files are
+//! copied under `attachments/<decision_id>/` and their text is extracted once at
+//! import, so the assistant and the committee read the same words the user did.
+
+use crate::db::{Attachment, Database};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Larger files are refused rather than copied into app data.
+const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Extracted text beyond this is dropped; nothing downstream could send more anyway.
+const MAX_EXTRACTED_CHARS: usize = 200_000;
+
+/// Characters returned per `read_attachment` call.
+pub const READ_CHUNK_CHARS: usize = 12_000;
+
+/// Per-document and total excerpt sizes in a debate brief.
+const BRIEF_EXCERPT_CHARS: usize = 4_000;
+const BRIEF_TOTAL_CHARS: usize = 16_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Pdf,
+    Docx,
+    Markdown,
+    Csv,
+    Text,
+}
+
+impl AttachmentKind {
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let extension = Path::new(filename).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "pdf" => Some(AttachmentKind::Pdf),
+            "docx" => Some(AttachmentKind::Docx),
+            "md" | "markdown" => Some(AttachmentKind::Markdown),
+            "csv" => Some(AttachmentKind::Csv),
+            "txt" | "text" => Some(AttachmentKind::Text),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AttachmentKind::Pdf => "pdf",
+            AttachmentKind::Docx => "docx",
+            AttachmentKind::Markdown => "markdown",
+            AttachmentKind::Csv => "csv",
+            AttachmentKind::Text => "text",
+        }
+    }
+}
+
+pub fn get_attachments_dir(app_data_dir: &Path, decision_id: &str) -> PathBuf {
+    app_data_dir.join("attachments").join(decision_id)
+}
+
+// ── Import & removal ──
+
+/// An attachment copied into its decision's folder, with its text extracted, that
+/// isn't recorded in the database yet.
+pub struct StoredAttachment {
+    decision_id: String,
+    filename: String,
+    kind: AttachmentKind,
+    size_bytes: u64,
+    path: PathBuf,
+    text: String,
+}
+
+/// Extract `source`'s text and copy it into the decision's attachment folder. Large
+/// PDFs take a while, so commands call this without holding the app state lock and
+/// only lock for `save_stored_attachment`.
+pub fn store_attachment(app_data_dir: &Path, decision_id: &str, source: &Path) -> Result<StoredAttachment, String> {
+    let filename = source
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or("Attachment path has no file name")?
+        .to_string();
+    let kind = AttachmentKind::from_filename(&filename)
+        .ok_or_else(|| format!("Unsupported attachment type: {} (use PDF, DOCX, Markdown, CSV or text)", filename))?;
+    let size_bytes = fs::metadata(source).map_err(|e| format!("Cannot read {}: {}", filename, e))?.len();
+    if size_bytes > MAX_ATTACHMENT_BYTES {
+        return Err(format!("{} is larger than {} MB", filename, MAX_ATTACHMENT_BYTES / (1024 * 1024)));
+    }
+
+    let text = extract_text(source, kind)?;
+    if text.trim().is_empty() {
+        return Err(format!("No text could be extracted from {}", filename));
+    }
+
+    let dir = get_attachments_dir(app_data_dir, decision_id);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let (path, mut copy) = claim_unused_path(&dir, &filename)?;
+    let copied = fs::File::open(source).and_then(|mut original| std::io::copy(&mut original, &mut copy));
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&path);
+        return Err(format!("Cannot copy {}: {}", filename, e));
+    }
+    Ok(StoredAttachment { decision_id: decision_id.to_string(), filename, kind, size_bytes, path, text })
+}
+
+/// Record a stored attachment; its copy is removed again if that fails.
+pub fn save_stored_attachment(db: &Database, stored: StoredAttachment) -> Result<Attachment, String> {
+    let stored_name = stored.path.file_name().and_then(|n| n.to_str()).unwrap_or(&stored.filename).to_string();
+    db.save_attachment(
+        &stored.decision_id,
+        &stored_name,
+        stored.kind.as_str(),
+        stored.size_bytes as i64,
+        &stored.path.to_string_lossy(),
+        &stored.text,
+    )
+    .map_err(|e| {
+        let _ = fs::remove_file(&stored.path);
+        e.to_string()
+    })
+}
+
+/// Delete an attachment's row and its stored copy.
+pub fn remove_attachment(db: &Database, attachment_id: &str) -> Result<(), String> {
+    let attachment = db
+        .get_attachment(attachment_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Attachment {} not found", attachment_id))?;
+    db.delete_attachment(attachment_id).map_err(|e| e.to_string())?;
+    let path = PathBuf::from(&attachment.stored_path);
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Create `name.pdf`, or else `name (2).pdf`, ... so two attachments never share a
+/// file. Creating rather than checking keeps concurrent imports apart.
+fn claim_unused_path(dir: &Path, filename: &str) -> Result<(PathBuf, fs::File), String> {
+    let path = Path::new(filename);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("attachment");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let candidates = std::iter::once(dir.join(filename))
+        .chain((2..).map(|n| dir.join(format!("{} ({}).{}", stem, n, extension))));
+    for candidate in candidates {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&candidate) {
+            Ok(file) => return Ok((candidate, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    unreachable!("some numbered name is free")
+}
+
+// ── Text extraction ──
+
+pub fn extract_text(path: &Path, kind: AttachmentKind) -> Result<String, String> {
+    let text = match kind {
+        AttachmentKind::Pdf => extract_pdf(path)?,
+        AttachmentKind::Docx => extract_docx(path)?,
+        AttachmentKind::Markdown | AttachmentKind::Csv | AttachmentKind::Text => {
+            let bytes = fs::read(path).map_err(|e| e.to_string())?;
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+    };
+    let text = text.replace("\r\n", "\n");
+    Ok(truncate_chars(text.trim(), MAX_EXTRACTED_CHARS).to_string())
+}
+
+fn extract_pdf(path: &Path) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    // pdf-extract panics on some malformed files instead of returning an error.
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(&bytes))
+        .map_err(|_| "Could not read this PDF".to_string())?
+        .map_err(|e| format!("Could not read this PDF: {}", e))
+}
+
+fn extract_docx(path: &Path) -> Result<String, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Not a valid DOCX file: {}", e))?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .map_err(|_| "Not a valid DOCX file: word/document.xml is missing".to_string())?
+        .read_to_string(&mut xml)
+        .map_err(|e| e.to_string())?;
+    Ok(docx_xml_to_text(&xml))
+}
+
+/// Text runs (`<w:t>`) of a DOCX body, one line per paragraph.
+fn docx_xml_to_text(xml: &str) -> String {
+    let mut text = String::new();
+    let mut in_run_text = false;
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        if in_run_text {
+            text.push_str(&unescape_xml(&rest[..open]));
+        }
+        let Some(close) = rest[open..].find('>') else { break };
+        let tag = &rest[open + 1..open + close];
+        let name = tag.trim_end_matches('/').split_whitespace().next().unwrap_or("");
+        match name {
+            "w:t" => in_run_text = !tag.ends_with('/'),
+            "/w:t" => in_run_text = false,
+            "/w:p" | "w:br" | "w:cr" => text.push('\n'),
+            "w:tab" => text.push('\t'),
+            _ => {}
+        }
+        rest = &rest[open + close + 1..];
+    }
+    text
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
+
+// ── Reading ──
+
+/// Result of the `read_attachment` tool: one chunk of a document, or the list of
+/// attachments when no filename is given or it matches none.
+pub fn read_attachment_for_tool(db: &Database, decision_id: &str, filename: Option<&str>, offset: usize) -> Result<String, String> {
+    let attachments = db.get_attachments(decision_id).map_err(|e| e.to_string())?;
+    if attachments.is_empty() {
+        return Ok("No documents are attached to this decision.".to_string());
+    }
+    let listing = || {
+        attachments
+            .iter()
+            .map(|a| format!("- {} ({}, {} characters)", a.filename, a.kind, a.text_chars))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let Some(filename) = filename.filter(|f| !f.trim().is_empty()) else {
+        return Ok(format!("Attached documents:\n{}", listing()));
+    };
+    let attachment = attachments
+        .iter()
+        .find(|a| a.filename.eq_ignore_ascii_case(filename.trim()))
+        .ok_or_else(|| format!("Error: no attachment named '{}'. Attached documents:\n{}", filename, listing()))?;
+    let text = db
+        .get_attachment_text(&attachment.id)
+        .map_err(|e| e.to_string())?
+        .unwrap_or_default();
+
+    let total = text.chars().count();
+    let chunk: String = text.chars().skip(offset).take(READ_CHUNK_CHARS).collect();
+    let end = offset + chunk.chars().count();
+    let footer = if end < total {
+        format!("\n\n[Characters {}-{} of {}. Call read_attachment again with offset {} for more.]", offset, end, total, end)
+    } else {
+        format!("\n\n[End of document, {} characters.]", total)
+    };
+    Ok(format!("# {}\n\n{}{}", attachment.filename, chunk, footer))
+}
+
+/// "Attached Documents" section for a debate brief, or `None` without attachments.
+pub fn brief_section(db: &Database, decision_id: &str) -> Result<Option<String>, String> {
+    let attachments = db.get_attachments(decision_id).map_err(|e| e.to_string())?;
+    if attachments.is_empty() {
+        return Ok(None);
+    }
+    let per_document = BRIEF_EXCERPT_CHARS.min(BRIEF_TOTAL_CHARS / attachments.len()).max(500);
+    let mut sections = Vec::new();
+    for attachment in &attachments {
+        let text = db
+            .get_attachment_text(&attachment.id)
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        let excerpt = truncate_chars(&text, per_document);
+        let note = if excerpt.len() < text.len() {
+            format!("\n\n_(Excerpt: first {} of {} characters.)_", excerpt.chars().count(), attachment.text_chars)
+        } else {
+            String::new()
+        };
+        sections.push(format!("### {}\n{}{}", attachment.filename, excerpt, note));
+    }
+    Ok(Some(format!(
+        "## Attached Documents\nQuote these when they bear on your argument.\n\n{}",
+        sections.join("\n\n")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn write_docx(path: &Path, document_xml: &str) {
+        let file = fs::File::create(path).expect("docx should be created");
+        let mut zip = zip::ZipWriter::new(file);
+        zip.start_file("word/document.xml", zip::write::SimpleFileOptions::default())
+            .expect("zip entry should start");
+        zip.write_all(document_xml.as_bytes()).expect("zip entry should write");
+        zip.finish().expect("zip should finish");
+    }
+
+    #[test]
+    fn unit_docx_xml_to_text_keeps_runs_paragraphs_and_entities() {
+        let xml = r#"<w:document><w:body><w:p><w:r><w:t>Base salary:</w:t></w:r><w:r><w:tab/><w:t xml:space="preserve"> $180,000 &amp; equity</w:t></w:r></w:p><w:p><w:r><w:instrText>PAGE</w:instrText><w:t>Start date: June 1</w:t></w:r></w:p></w:body></w:document>"#;
+        assert_eq!(docx_xml_to_text(xml), "Base salary:\t $180,000 & equity\nStart date: June 1\n");
+    }
+
+    #[test]
+    fn integration_import_extracts_text_and_feeds_tool_and_brief() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().join("data");
+        let db = Database::new(":memory:").expect("in-memory database should initialize");
+        let conv = db.create_conversation_with_type("Take the job?", "decision").unwrap();
+        let decision = db.create_decision(&conv.id, "Take the job?").unwrap();
+
+        let offer = dir.path().join("offer.docx");
+        write_docx(&offer, "<w:p><w:r><w:t>Signing bonus: $20,000</w:t></w:r></w:p><w:p><w:r><w:t>Clawback if you leave within 12 months.</w:t></w:r></w:p>");
+        let lease = dir.path().join("lease.md");
+        fs::write(&lease, "# Lease\nRent: $2,400/month\r\nBreak fee: two months").unwrap();
+        let import = |source: &Path| {
+            store_attachment(&app_data_dir, &decision.id, source).and_then(|stored| save_stored_attachment(&db, stored))
+        };
+
+        let offer_attachment = import(&offer).expect("docx should import");
+        assert_eq!(offer_attachment.kind, "docx");
+        assert!(get_attachments_dir(&app_data_dir, &decision.id).join("offer.docx").exists());
+        let lease_attachment = import(&lease).expect("markdown should import");
+        let duplicate = import(&lease).unwrap();
+        assert_eq!(duplicate.filename, "lease (2).md");
+
+        let unsupported = dir.path().join("photo.png");
+        fs::write(&unsupported, [0u8; 4]).unwrap();
+        assert!(import(&unsupported).unwrap_err().contains("Unsupported"));
+
+        let listing = read_attachment_for_tool(&db, &decision.id, None, 0).unwrap();
+        assert!(listing.contains("- offer.docx (docx"));
+        let read = read_attachment_for_tool(&db, &decision.id, Some("OFFER.docx"), 0).unwrap();
+        assert!(read.contains("Signing bonus: $20,000\nClawback"));
+        assert!(read.contains("[End of document"));
+        let paged = read_attachment_for_tool(&db, &decision.id, Some("lease.md"), 8).unwrap();
+        assert!(paged.starts_with("# lease.md\n\nRent: $2,400/month\nBreak fee"));
+        assert!(read_attachment_for_tool(&db, &decision.id, Some("contract.pdf"), 0).is_err());
+
+        let brief = brief_section(&db, &decision.id).unwrap().expect("brief section should exist");
+        assert!(brief.contains("### offer.docx\nSigning bonus"));
+        assert!(brief.contains("Break fee: two months"));
+
+        remove_attachment(&db, &lease_attachment.id).unwrap();
+        assert!(!get_attachments_dir(&app_data_dir, &decision.id).join("lease.md").exists());
+        assert_eq!(db.get_attachments(&decision.id).unwrap().len(), 2);
+        assert!(brief_section(&db, "other-decision").unwrap().is_none());
+    }
+}
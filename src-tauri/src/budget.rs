@@ -0,0 +1,192 @@
+//! Spending caps for debates. Before each debate round the expected cost is
+//! estimated from prompt size and cached per-model pricing, then checked
+//! against what has already been spent on this debate, today and this month.
+
+use crate::config::BudgetConfig;
+use crate::db::Database;
+use crate::usage::{self, TokenUsage};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+/// Typical length of a spoken debate turn; turns are capped at 2048 tokens but rarely get close.
+pub const EXPECTED_TURN_COMPLETION_TOKENS: i64 = 450;
+
+/// Rough allowance for agent system prompts, style overlay and round instructions.
+pub const PROMPT_OVERHEAD_CHARS: usize = 5_000;
+
+/// Moderator steering replies are a short JSON object.
+pub const EXPECTED_STEERING_COMPLETION_TOKENS: i64 = 60;
+
+/// Committee synthesis (agreements, disagreements, recommendation, action plan).
+pub const EXPECTED_VERDICT_COMPLETION_TOKENS: i64 = 900;
+
+/// Standalone verdicts are capped at ~130 words.
+pub const EXPECTED_STANDALONE_VERDICT_COMPLETION_TOKENS: i64 = 250;
+
+pub const CHARS_PER_TOKEN: i64 = 4;
+
+/// Spoken pace of the TTS voices (~150 words per minute).
+pub const TTS_CHARS_PER_SECOND: i64 = 15;
+
+// ── Estimation ──
+
+/// ~4 characters per token for English text — good enough for budgeting.
+pub fn estimate_tokens(chars: usize) -> i64 {
+    chars.div_ceil(CHARS_PER_TOKEN as usize) as i64
+}
+
+/// Expected token volume and cost of one or more LLM calls.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostEstimate {
+    pub calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost_usd: f64,
+    /// Calls on models with no known pricing; they contribute no cost.
+    pub unpriced_calls: i64,
+}
+
+impl CostEstimate {
+    pub fn add_call(&mut self, model: &str, prompt_tokens: i64, completion_tokens: i64) {
+        self.calls += 1;
+        self.prompt_tokens += prompt_tokens;
+        self.completion_tokens += completion_tokens;
+        let usage = TokenUsage { prompt_tokens, completion_tokens, reported_cost: None };
+        match usage::compute_cost(model, &usage) {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_calls += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &CostEstimate) {
+        self.calls += other.calls;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost_usd += other.cost_usd;
+        self.unpriced_calls += other.unpriced_calls;
+    }
+}
+
+/// Estimate a sequential round: every speaker sees the shared prompt plus the
+/// turns already given earlier in the same round.
+pub fn estimate_round(speaker_models: &[&str], shared_prompt_chars: usize) -> CostEstimate {
+    let mut estimate = CostEstimate::default();
+    let base_tokens = estimate_tokens(shared_prompt_chars + PROMPT_OVERHEAD_CHARS);
+    for (position, model) in speaker_models.iter().enumerate() {
+        let prompt_tokens = base_tokens + position as i64 * EXPECTED_TURN_COMPLETION_TOKENS;
+        estimate.add_call(model, prompt_tokens, EXPECTED_TURN_COMPLETION_TOKENS);
+    }
+    estimate
+}
+
+// ── Enforcement ──
+
+/// Actual spend recorded in `llm_usage`, in USD.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spend {
+    pub debate_usd: f64,
+    pub day_usd: f64,
+    pub month_usd: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetExceeded {
+    pub scope: &'static str, // "debate", "day" or "month"
+    pub limit_usd: f64,
+    pub spent_usd: f64,
+    pub estimated_usd: f64,
+}
+
+impl BudgetExceeded {
+    pub fn message(&self) -> String {
+        let scope = match self.scope {
+            "debate" => "Per-debate",
+            "day" => "Daily",
+            _ => "Monthly",
+        };
+        format!(
+            "{} budget of ${:.2} would be exceeded (spent ${:.2}, next round ~${:.2}). Raise the cap in Settings to continue.",
+            scope, self.limit_usd, self.spent_usd, self.estimated_usd
+        )
+    }
+}
+
+/// Spend for this debate run (since `run_started_at`), today and this month (UTC).
+pub fn current_spend(db: &Database, decision_id: &str, run_started_at: &str) -> Result<Spend, String> {
+    let now = Utc::now();
+    let day_start = now.format("%Y-%m-%dT00:00:00").to_string();
+    let month_start = now.format("%Y-%m-01T00:00:00").to_string();
+    Ok(Spend {
+        debate_usd: db.get_usage_cost_since(Some(decision_id), run_started_at).map_err(|e| e.to_string())?,
+        day_usd: db.get_usage_cost_since(None, &day_start).map_err(|e| e.to_string())?,
+        month_usd: db.get_usage_cost_since(None, &month_start).map_err(|e| e.to_string())?,
+    })
+}
+
+/// Check every configured cap against `spent + estimated`, narrowest scope first.
+pub fn check(budget: &BudgetConfig, spent: &Spend, estimated_usd: f64) -> Result<(), BudgetExceeded> {
+    let caps = [
+        ("debate", budget.per_debate_usd, spent.debate_usd),
+        ("day", budget.per_day_usd, spent.day_usd),
+        ("month", budget.per_month_usd, spent.month_usd),
+    ];
+    for (scope, limit, spent_usd) in caps {
+        if let Some(limit_usd) = limit {
+            if spent_usd + estimated_usd > limit_usd {
+                return Err(BudgetExceeded { scope, limit_usd, spent_usd, estimated_usd });
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn has_caps(budget: &BudgetConfig) -> bool {
+    budget.per_debate_usd.is_some() || budget.per_day_usd.is_some() || budget.per_month_usd.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage::ModelPricing;
+
+    #[test]
+    fn unit_check_reports_narrowest_exceeded_scope() {
+        let budget = BudgetConfig {
+            per_debate_usd: Some(1.0),
+            per_day_usd: Some(5.0),
+            per_month_usd: Some(20.0),
+        };
+        let spent = Spend { debate_usd: 0.8, day_usd: 4.0, month_usd: 10.0 };
+
+        assert!(check(&budget, &spent, 0.1).is_ok());
+
+        let exceeded = check(&budget, &spent, 0.3).expect_err("debate cap should trip");
+        assert_eq!(exceeded.scope, "debate");
+        assert!(exceeded.message().starts_with("Per-debate budget of $1.00"));
+
+        let no_debate_cap = BudgetConfig { per_debate_usd: None, ..budget };
+        let exceeded = check(&no_debate_cap, &spent, 1.5).expect_err("daily cap should trip");
+        assert_eq!(exceeded.scope, "day");
+        assert!(check(&BudgetConfig::default(), &spent, 1_000.0).is_ok());
+    }
+
+    #[test]
+    fn unit_estimate_round_grows_prompt_per_speaker_and_tracks_unpriced_models() {
+        usage::remember_pricing("budget-test/model", ModelPricing {
+            prompt_per_million: 10.0,
+            completion_per_million: 30.0,
+        });
+
+        let estimate = estimate_round(&["budget-test/model", "budget-test/model", "budget-test/unpriced"], 6_000);
+        let base = estimate_tokens(6_000 + PROMPT_OVERHEAD_CHARS);
+        assert_eq!(estimate.calls, 3);
+        assert_eq!(estimate.prompt_tokens, base * 3 + EXPECTED_TURN_COMPLETION_TOKENS * 3);
+        assert_eq!(estimate.completion_tokens, EXPECTED_TURN_COMPLETION_TOKENS * 3);
+        assert_eq!(estimate.unpriced_calls, 1);
+
+        let expected = ((base * 2 + EXPECTED_TURN_COMPLETION_TOKENS) as f64 * 10.0
+            + (EXPECTED_TURN_COMPLETION_TOKENS * 2) as f64 * 30.0)
+            / 1_000_000.0;
+        assert!((estimate.cost_usd - expected).abs() < 1e-9);
+    }
+}
@@ -1,32 +1,41 @@
 use crate::agents;
-use crate::config::{self, AppConfig};
-use crate::db::{Database, DebateAudio, DebateRound, Decision};
+use crate::attachments;
+use crate::config::{self, AppConfig, BudgetConfig, FallbackModel};
+use crate::context;
+use crate::db::{Attachment, Database, DebateAudio, DebateRound, Decision, PendingProfileChange, StoredVerdict, UsageTotals, MESSAGE_STATUS_CANCELLED, MESSAGE_STATUS_COMPLETE};
 use crate::debate;
 use crate::llm;
 use crate::profile;
-use crate::profile::ProfileFileInfo;
-use crate::llm::StreamEvent;
+use crate::profile::{ProfileFact, ProfileFileInfo};
+use crate::profile_history::{self, ProfileDiff, ProfileRevision};
+use crate::prompts;
+use crate::providers;
+use crate::llm::{ChatCancel, StreamEvent};
 use crate::tts;
+use crate::usage::{self, UsageContext};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tauri::ipc::Channel;
-use tauri::State;
+use tauri::{Runtime, State};
 use std::sync::Mutex;
 
 pub struct AppState {
     pub db: Database,
     pub app_data_dir: PathBuf,
     pub debate_cancel_flags: HashMap<String, Arc<AtomicBool>>,
+    /// Stop handles of in-flight chat turns, keyed by the request id the frontend picked.
+    pub chat_cancel_handles: HashMap<String, ChatCancel>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SendMessageResponse {
     pub conversation_id: String,
     pub response: String,
+    pub cancelled: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,6 +48,50 @@ pub struct SettingsResponse {
     pub elevenlabs_api_key_preview: String,
     pub tts_provider: String,
     pub elevenlabs_model: String,
+    pub default_provider: String,
+    pub providers: Vec<providers::ProviderSummary>,
+    pub agent_providers: HashMap<String, String>,
+    pub budget: BudgetConfig,
+    pub agent_fallbacks: HashMap<String, Vec<FallbackModel>>,
+    pub save_reasoning: bool,
+    pub review_mode: bool,
+}
+
+/// What the settings screen saves. Empty keys and unset fields keep their saved values.
+#[derive(Debug, Deserialize)]
+pub struct SettingsUpdate {
+    pub api_key: String,
+    pub model: String,
+    #[serde(default)]
+    pub elevenlabs_api_key: Option<String>,
+    #[serde(default)]
+    pub tts_provider: Option<String>,
+    #[serde(default)]
+    pub elevenlabs_model: Option<String>,
+    #[serde(default)]
+    pub default_provider: Option<String>,
+    #[serde(default)]
+    pub save_reasoning: Option<bool>,
+    #[serde(default)]
+    pub review_mode: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DebateCostResponse {
+    pub decision_id: String,
+    pub calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost_usd: f64,
+    pub unpriced_calls: i64,
+    pub by_agent: Vec<UsageTotals>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonthlySpendResponse {
+    pub month: String,
+    pub total_cost_usd: f64,
+    pub by_model: Vec<UsageTotals>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -146,6 +199,7 @@ fn build_standalone_sandbox(selected_models: &[String]) -> Result<StandaloneSand
             builtin: true,
             sort_order: idx as u32,
             voice_gender: voice_gender.to_string(),
+            generation: agents::GenerationParams::default(),
         });
         model_map.insert(key, model_id.clone());
     }
@@ -159,6 +213,7 @@ fn build_standalone_sandbox(selected_models: &[String]) -> Result<StandaloneSand
         builtin: true,
         sort_order: 100,
         voice_gender: "male".to_string(),
+        generation: agents::GenerationParams::default(),
     });
 
     Ok(StandaloneSandboxMeta {
@@ -167,45 +222,109 @@ fn build_standalone_sandbox(selected_models: &[String]) -> Result<StandaloneSand
     })
 }
 
+/// How a chat turn changes the active branch before the assistant replies.
+enum ChatTurn {
+    /// A new user message at the end of the active branch.
+    Send { conversation_id: Option<String>, message: String },
+    /// A rewrite of an earlier user message, added as its sibling on a new branch.
+    Edit { conversation_id: String, message_id: String, content: String },
+    /// Another answer to the user message an assistant reply responded to.
+    Regenerate { conversation_id: String, message_id: String },
+}
+
 #[tauri::command]
-pub async fn send_message(
-    app_handle: tauri::AppHandle,
+pub async fn send_message<R: Runtime>(
+    app_handle: tauri::AppHandle<R>,
     state: State<'_, Mutex<AppState>>,
     conversation_id: Option<String>,
     message: String,
+    request_id: String,
     on_event: Channel<StreamEvent>,
 ) -> Result<SendMessageResponse, String> {
-    let (api_key, model, conv_id, history_messages, conv_type, decision_id) = {
-        let state = state.lock().map_err(|e| e.to_string())?;
-        let config = config::load_config(&state.app_data_dir);
+    run_chat_turn(app_handle, state, request_id, ChatTurn::Send { conversation_id, message }, on_event).await
+}
 
-        if config.openrouter_api_key.is_empty() {
-            return Err("API key not set. Please go to Settings to add your OpenRouter API key.".to_string());
-        }
+/// Replace an earlier user message. The original and everything after it stay on
+/// their own branch; the edit starts a new one and gets a fresh reply.
+#[tauri::command]
+pub async fn edit_message<R: Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    state: State<'_, Mutex<AppState>>,
+    conversation_id: String,
+    message_id: String,
+    content: String,
+    request_id: String,
+    on_event: Channel<StreamEvent>,
+) -> Result<SendMessageResponse, String> {
+    run_chat_turn(app_handle, state, request_id, ChatTurn::Edit { conversation_id, message_id, content }, on_event).await
+}
+
+/// Ask for a new answer in place of an assistant reply, keeping the old one as a branch.
+#[tauri::command]
+pub async fn regenerate_message<R: Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    state: State<'_, Mutex<AppState>>,
+    conversation_id: String,
+    message_id: String,
+    request_id: String,
+    on_event: Channel<StreamEvent>,
+) -> Result<SendMessageResponse, String> {
+    run_chat_turn(app_handle, state, request_id, ChatTurn::Regenerate { conversation_id, message_id }, on_event).await
+}
 
-        let conv_id = match conversation_id {
-            Some(id) => id,
-            None => {
-                let title = if message.len() > 50 {
-                    format!("{}...", &message[..50])
-                } else {
-                    message.clone()
+async fn run_chat_turn<R: Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    state: State<'_, Mutex<AppState>>,
+    request_id: String,
+    turn: ChatTurn,
+    on_event: Channel<StreamEvent>,
+) -> Result<SendMessageResponse, String> {
+    let cancel = ChatCancel::default();
+    // The reply's id is picked now so the tool calls made for it can point at it.
+    let reply_id = uuid::Uuid::new_v4().to_string();
+    let (endpoint, model, save_reasoning, conv_id, conv_type, decision_id, app_data_dir, previous_tip) = {
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        let config = config::load_config(&state.app_data_dir);
+        let endpoint = providers::default_endpoint(&config)?;
+
+        // Where the active branch pointed before an edit or regenerate, restored if no new reply is saved.
+        let mut previous_tip = None;
+        let conv_id = match turn {
+            ChatTurn::Send { conversation_id, message } => {
+                let conv_id = match conversation_id {
+                    Some(id) => id,
+                    None => {
+                        let title = if message.len() > 50 {
+                            format!("{}...", &message[..50])
+                        } else {
+                            message.clone()
+                        };
+                        let conv = state.db.create_conversation(&title).map_err(db_err)?;
+                        conv.id
+                    }
                 };
-                let conv = state.db.create_conversation(&title).map_err(db_err)?;
-                conv.id
+                state.db.add_message(&conv_id, "user", &message).map_err(db_err)?;
+                conv_id
+            }
+            ChatTurn::Edit { conversation_id, message_id, content } => {
+                if content.trim().is_empty() {
+                    return Err("Message cannot be empty".to_string());
+                }
+                let original = message_in_conversation(&state.db, &conversation_id, &message_id, "user")?;
+                previous_tip = Some(state.db.get_active_message_id(&conversation_id).map_err(db_err)?);
+                state.db.set_active_message(&conversation_id, original.parent_id.as_deref()).map_err(db_err)?;
+                state.db.add_message(&conversation_id, "user", &content).map_err(db_err)?;
+                conversation_id
+            }
+            ChatTurn::Regenerate { conversation_id, message_id } => {
+                let reply = message_in_conversation(&state.db, &conversation_id, &message_id, "assistant")?;
+                let prompt_id = reply.parent_id.ok_or("This reply has no message to answer")?;
+                previous_tip = Some(state.db.get_active_message_id(&conversation_id).map_err(db_err)?);
+                state.db.set_active_message(&conversation_id, Some(&prompt_id)).map_err(db_err)?;
+                conversation_id
             }
         };
 
-        state.db.add_message(&conv_id, "user", &message).map_err(db_err)?;
-
-        let messages = state.db.get_messages(&conv_id).map_err(db_err)?;
-        let history: Vec<serde_json::Value> = messages.iter().map(|m| {
-            json!({
-                "role": m.role,
-                "content": m.content,
-            })
-        }).collect();
-
         let conv = state.db.get_conversation(&conv_id).map_err(db_err)?;
         let conv_type = conv.map(|c| c.conv_type).unwrap_or_else(|| "chat".to_string());
 
@@ -217,36 +336,91 @@ pub async fn send_message(
             None
         };
 
-        (config.openrouter_api_key, config.model, conv_id, history, conv_type, decision_id)
+        state.chat_cancel_handles.insert(request_id.clone(), cancel.clone());
+        (endpoint, config.model, config.save_reasoning, conv_id, conv_type, decision_id, state.app_data_dir.clone(), previous_tip)
     };
 
-    let app_data_dir = {
-        let state = state.lock().map_err(|e| e.to_string())?;
-        state.app_data_dir.clone()
-    };
+    let reply = async {
+        // Trim history to the model's window, folding older turns into the rolling summary.
+        context::prefetch_context_length(&endpoint, &model, &app_data_dir).await;
+        let history_budget = context::chat_history_budget(&model, &app_data_dir, &conv_type);
+        let window = context::prepare_window(
+            &app_handle,
+            &endpoint,
+            &model,
+            &conv_id,
+            decision_id.as_deref(),
+            history_budget,
+        ).await?;
+
+        let call = llm::LlmCall {
+            endpoint: &endpoint,
+            model: &model,
+            params: &agents::GenerationParams::default(),
+            usage_ctx: &UsageContext { message_id: Some(reply_id.clone()), ..UsageContext::chat(&conv_id, decision_id.as_deref()) },
+        };
+        llm::send_message(&call, &window, &app_data_dir, &on_event, &conv_type, &app_handle, &cancel).await
+    }.await;
 
-    let response_text = llm::send_message(
-        &api_key,
-        &model,
-        history_messages,
-        &app_data_dir,
-        &on_event,
-        &conv_type,
-        decision_id.as_deref(),
-        &app_handle,
-    ).await?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    // A reused request id may belong to a newer turn by now; leave its handle alone.
+    if state.chat_cancel_handles.get(&request_id).is_some_and(|handle| handle.is_same(&cancel)) {
+        state.chat_cancel_handles.remove(&request_id);
+    }
+    let discard_reply = |db: &Database| {
+        db.unlink_tool_calls_from_message(&reply_id).map_err(db_err)?;
+        db.unlink_usage_from_message(&reply_id).map_err(db_err)?;
+        match &previous_tip {
+            Some(tip) => db.set_active_message(&conv_id, tip.as_deref()).map_err(db_err),
+            None => Ok(()),
+        }
+    };
+    let reply = match reply {
+        Ok(reply) => reply,
+        Err(e) => {
+            discard_reply(&state.db)?;
+            return Err(e);
+        }
+    };
 
-    {
-        let state = state.lock().map_err(|e| e.to_string())?;
-        state.db.add_message(&conv_id, "assistant", &response_text).map_err(db_err)?;
+    // A reply stopped before its first token leaves nothing worth keeping.
+    if !reply.cancelled || !reply.text.is_empty() {
+        let status = if reply.cancelled { MESSAGE_STATUS_CANCELLED } else { MESSAGE_STATUS_COMPLETE };
+        let assistant_message = state.db
+            .add_message_with_id(&reply_id, &conv_id, "assistant", &reply.text, status)
+            .map_err(db_err)?;
+        if save_reasoning && !reply.reasoning.is_empty() {
+            state.db.set_message_reasoning(&assistant_message.id, &reply.reasoning).map_err(db_err)?;
+        }
+    } else {
+        discard_reply(&state.db)?;
     }
 
     Ok(SendMessageResponse {
         conversation_id: conv_id,
-        response: response_text,
+        response: reply.text,
+        cancelled: reply.cancelled,
     })
 }
 
+/// The message being edited or regenerated, checked against its conversation and role.
+fn message_in_conversation(db: &Database, conversation_id: &str, message_id: &str, role: &str) -> Result<crate::db::Message, String> {
+    db.get_message(message_id)
+        .map_err(db_err)?
+        .filter(|m| m.conversation_id == conversation_id && m.role == role)
+        .ok_or_else(|| format!("No {} message {} in this conversation", role, message_id))
+}
+
+/// Stop the streaming chat reply of the turn started with `request_id`.
+#[tauri::command]
+pub fn cancel_message(state: State<'_, Mutex<AppState>>, request_id: String) -> Result<(), String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    if let Some(cancel) = state.chat_cancel_handles.get(&request_id) {
+        cancel.cancel();
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn get_conversations(state: State<'_, Mutex<AppState>>) -> Result<Vec<crate::db::Conversation>, String> {
     let state = state.lock().map_err(|e| e.to_string())?;
@@ -259,6 +433,47 @@ pub fn get_messages(state: State<'_, Mutex<AppState>>, conversation_id: String)
     state.db.get_messages(&conversation_id).map_err(db_err)
 }
 
+/// Audit log of the assistant's tool calls in a conversation, or of those touching
+/// one profile file. With both, the conversation's calls are narrowed to the file.
+#[tauri::command]
+pub fn get_tool_calls(
+    state: State<'_, Mutex<AppState>>,
+    conversation_id: Option<String>,
+    filename: Option<String>,
+) -> Result<Vec<crate::db::ToolCallRecord>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    match (conversation_id, filename) {
+        (Some(conversation_id), filename) => {
+            let mut calls = state.db.get_tool_calls_for_conversation(&conversation_id).map_err(db_err)?;
+            if let Some(filename) = filename {
+                calls.retain(|c| c.files.contains(&filename));
+            }
+            Ok(calls)
+        }
+        (None, Some(filename)) => state.db.get_tool_calls_for_file(&filename).map_err(db_err),
+        (None, None) => Err("Pass a conversation_id or a filename".to_string()),
+    }
+}
+
+/// Every branch of a conversation, for picking which one to continue.
+#[tauri::command]
+pub fn get_conversation_branches(state: State<'_, Mutex<AppState>>, conversation_id: String) -> Result<Vec<crate::db::ConversationBranch>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    state.db.get_branches(&conversation_id).map_err(db_err)
+}
+
+/// Make the branch through `message_id` active and return its messages.
+#[tauri::command]
+pub fn switch_branch(state: State<'_, Mutex<AppState>>, conversation_id: String, message_id: String) -> Result<Vec<crate::db::Message>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    state.db.get_message(&message_id)
+        .map_err(db_err)?
+        .filter(|m| m.conversation_id == conversation_id)
+        .ok_or_else(|| format!("No message {} in this conversation", message_id))?;
+    state.db.switch_branch(&conversation_id, &message_id).map_err(db_err)?;
+    state.db.get_messages(&conversation_id).map_err(db_err)
+}
+
 #[tauri::command]
 pub fn get_settings(state: State<'_, Mutex<AppState>>) -> Result<SettingsResponse, String> {
     let state = state.lock().map_err(|e| e.to_string())?;
@@ -277,6 +492,7 @@ pub fn get_settings(state: State<'_, Mutex<AppState>>) -> Result<SettingsRespons
     } else {
         String::new()
     };
+    let provider_list = providers::provider_summaries(&config);
     Ok(SettingsResponse {
         api_key_set: !config.openrouter_api_key.is_empty(),
         api_key_preview: preview,
@@ -286,11 +502,25 @@ pub fn get_settings(state: State<'_, Mutex<AppState>>) -> Result<SettingsRespons
         elevenlabs_api_key_preview: elevenlabs_preview,
         tts_provider: config.tts_provider,
         elevenlabs_model: config.elevenlabs_model,
+        default_provider: config.default_provider,
+        providers: provider_list,
+        agent_providers: config.agent_providers,
+        budget: config.budget,
+        agent_fallbacks: config.agent_fallbacks,
+        save_reasoning: config.save_reasoning,
+        review_mode: config.review_mode,
     })
 }
 
 #[tauri::command]
-pub async fn get_openrouter_models() -> Result<Vec<OpenRouterModelInfo>, String> {
+pub async fn get_openrouter_models(state: State<'_, Mutex<AppState>>) -> Result<Vec<OpenRouterModelInfo>, String> {
+    let app_data_dir = state.lock().map_err(|e| e.to_string())?.app_data_dir.clone();
+    fetch_openrouter_models(&app_data_dir).await
+}
+
+/// The OpenRouter models list. Context lengths and prices are cached on the way, and
+/// prices saved under `app_data_dir` for the next launch.
+pub async fn fetch_openrouter_models(app_data_dir: &Path) -> Result<Vec<OpenRouterModelInfo>, String> {
     let response = reqwest::Client::new()
         .get("https://openrouter.ai/api/v1/models")
         .send()
@@ -316,6 +546,16 @@ pub async fn get_openrouter_models() -> Result<Vec<OpenRouterModelInfo>, String>
             let completion_price = parse_price(entry.pricing.as_ref().and_then(|p| p.completion.as_deref()));
             let is_free = prompt_price.unwrap_or(0.0) == 0.0 && completion_price.unwrap_or(0.0) == 0.0;
 
+            if let Some(context_length) = entry.context_length {
+                context::remember_context_length(&entry.id, context_length);
+            }
+            if let (Some(prompt), Some(completion)) = (prompt_price, completion_price) {
+                usage::remember_pricing(&entry.id, usage::ModelPricing {
+                    prompt_per_million: prompt,
+                    completion_per_million: completion,
+                });
+            }
+
             OpenRouterModelInfo {
                 id: entry.id.clone(),
                 name: entry.name.unwrap_or_else(|| entry.id),
@@ -328,37 +568,44 @@ pub async fn get_openrouter_models() -> Result<Vec<OpenRouterModelInfo>, String>
         .collect();
 
     models.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    if let Err(e) = usage::save_pricing(app_data_dir) {
+        eprintln!("Failed to save model pricing: {}", e);
+    }
     Ok(models)
 }
 
 #[tauri::command]
-pub fn save_settings(
-    state: State<'_, Mutex<AppState>>,
-    api_key: String,
-    model: String,
-    elevenlabs_api_key: Option<String>,
-    tts_provider: Option<String>,
-    elevenlabs_model: Option<String>,
-) -> Result<(), String> {
+pub fn save_settings(state: State<'_, Mutex<AppState>>, settings: SettingsUpdate) -> Result<(), String> {
     let state = state.lock().map_err(|e| e.to_string())?;
     let existing = config::load_config(&state.app_data_dir);
-    let final_key = if api_key.is_empty() { existing.openrouter_api_key } else { api_key };
-    let final_elevenlabs_key = match elevenlabs_api_key {
+    let final_key = if settings.api_key.is_empty() { existing.openrouter_api_key } else { settings.api_key };
+    let final_elevenlabs_key = match settings.elevenlabs_api_key {
         Some(k) if !k.is_empty() => k,
         _ => existing.elevenlabs_api_key,
     };
-    let final_elevenlabs_model = match elevenlabs_model {
+    let final_elevenlabs_model = match settings.elevenlabs_model {
         Some(m) if !m.trim().is_empty() => m.trim().to_string(),
         _ => existing.elevenlabs_model,
     };
     let config = AppConfig {
         openrouter_api_key: final_key,
-        model,
+        model: settings.model,
         agent_models: existing.agent_models,
         elevenlabs_api_key: final_elevenlabs_key,
-        tts_provider: tts_provider.unwrap_or(existing.tts_provider),
+        tts_provider: settings.tts_provider.unwrap_or(existing.tts_provider),
         elevenlabs_model: final_elevenlabs_model,
         voices: existing.voices,
+        default_provider: match settings.default_provider {
+            Some(p) if !p.trim().is_empty() => p.trim().to_string(),
+            _ => existing.default_provider,
+        },
+        providers: existing.providers,
+        agent_providers: existing.agent_providers,
+        budget: existing.budget,
+        agent_fallbacks: existing.agent_fallbacks,
+        save_reasoning: settings.save_reasoning.unwrap_or(existing.save_reasoning),
+        review_mode: settings.review_mode.unwrap_or(existing.review_mode),
+        profile_embeddings: existing.profile_embeddings,
     };
     config::save_config(&state.app_data_dir, &config)
 }
@@ -380,7 +627,18 @@ pub fn open_profile_folder(state: State<'_, Mutex<AppState>>) -> Result<String,
 #[tauri::command]
 pub fn delete_conversation(state: State<'_, Mutex<AppState>>, conversation_id: String) -> Result<(), String> {
     let state = state.lock().map_err(|e| e.to_string())?;
-    state.db.delete_conversation(&conversation_id).map_err(db_err)
+    let decision = state.db.get_decision_by_conversation(&conversation_id).map_err(db_err)?;
+    state.db.delete_conversation(&conversation_id).map_err(db_err)?;
+    // The rows are gone either way; a folder left behind only costs disk space.
+    if let Some(decision) = decision {
+        let dir = attachments::get_attachments_dir(&state.app_data_dir, &decision.id);
+        if dir.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                eprintln!("Failed to remove attachments of decision {}: {}", decision.id, e);
+            }
+        }
+    }
+    Ok(())
 }
 
 // ── Decision Commands ──
@@ -448,6 +706,34 @@ pub fn update_decision_status(
         .ok_or_else(|| "Decision not found after update".to_string())
 }
 
+// ── Attachment Commands ──
+
+/// Attach a local document (PDF, DOCX, Markdown, CSV or text) to a decision.
+#[tauri::command]
+pub async fn add_attachment(state: State<'_, Mutex<AppState>>, decision_id: String, path: String) -> Result<Attachment, String> {
+    let app_data_dir = {
+        let state = state.lock().map_err(|e| e.to_string())?;
+        state.db.get_decision(&decision_id).map_err(db_err)?.ok_or("Decision not found")?;
+        state.app_data_dir.clone()
+    };
+    // Extraction can take seconds on a large PDF; other commands keep running meanwhile.
+    let stored = attachments::store_attachment(&app_data_dir, &decision_id, std::path::Path::new(&path))?;
+    let state = state.lock().map_err(|e| e.to_string())?;
+    attachments::save_stored_attachment(&state.db, stored)
+}
+
+#[tauri::command]
+pub fn get_attachments(state: State<'_, Mutex<AppState>>, decision_id: String) -> Result<Vec<Attachment>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    state.db.get_attachments(&decision_id).map_err(db_err)
+}
+
+#[tauri::command]
+pub fn delete_attachment(state: State<'_, Mutex<AppState>>, attachment_id: String) -> Result<(), String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    attachments::remove_attachment(&state.db, &attachment_id)
+}
+
 // ── Profile Viewer Commands ──
 
 #[tauri::command]
@@ -460,19 +746,7 @@ pub fn get_profile_files_detailed(state: State<'_, Mutex<AppState>>) -> Result<V
 pub fn update_profile_file(state: State<'_, Mutex<AppState>>, filename: String, content: String) -> Result<ProfileFileInfo, String> {
     let state = state.lock().map_err(|e| e.to_string())?;
     profile::write_profile_file(&state.app_data_dir, &filename, &content)?;
-    let dir = profile::get_profile_dir(&state.app_data_dir);
-    let path = dir.join(&filename);
-    let metadata = std::fs::metadata(&path).map_err(|e| e.to_string())?;
-    let modified = metadata.modified().map_err(|e| e.to_string())?;
-    let modified_at = chrono::DateTime::<chrono::Utc>::from(modified)
-        .format("%Y-%m-%dT%H:%M:%SZ")
-        .to_string();
-    Ok(ProfileFileInfo {
-        filename,
-        content,
-        modified_at,
-        size_bytes: metadata.len(),
-    })
+    profile::profile_file_info(&state.app_data_dir, &filename)
 }
 
 #[tauri::command]
@@ -482,12 +756,123 @@ pub fn remove_profile_file(state: State<'_, Mutex<AppState>>, filename: String)
     Ok(())
 }
 
+/// Saved revisions of a profile file, newest first; deleted files keep theirs.
+#[tauri::command]
+pub fn get_profile_revisions(state: State<'_, Mutex<AppState>>, filename: String) -> Result<Vec<ProfileRevision>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    profile_history::list_revisions(&state.app_data_dir, &filename)
+}
+
+#[tauri::command]
+pub fn diff_profile_revisions(
+    state: State<'_, Mutex<AppState>>,
+    filename: String,
+    from_revision: u32,
+    to_revision: u32,
+) -> Result<ProfileDiff, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    profile_history::diff_revisions(&state.app_data_dir, &filename, from_revision, to_revision)
+}
+
+#[tauri::command]
+pub fn restore_profile_revision(state: State<'_, Mutex<AppState>>, filename: String, revision: u32) -> Result<ProfileFileInfo, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    profile::restore_profile_revision(&state.app_data_dir, &filename, revision)?;
+    profile::profile_file_info(&state.app_data_dir, &filename)
+}
+
+/// Facts across the profile with their metadata; `stale_only` keeps those due for
+/// re-confirmation after `max_age_days` (default `profile::STALE_FACT_DAYS`).
+#[tauri::command]
+pub fn get_profile_facts(
+    state: State<'_, Mutex<AppState>>,
+    stale_only: Option<bool>,
+    max_age_days: Option<i64>,
+) -> Result<Vec<ProfileFact>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    if stale_only.unwrap_or(false) {
+        profile::stale_profile_facts(&state.app_data_dir, max_age_days.unwrap_or(profile::STALE_FACT_DAYS))
+    } else {
+        profile::list_profile_facts(&state.app_data_dir)
+    }
+}
+
+#[tauri::command]
+pub fn confirm_profile_fact(
+    state: State<'_, Mutex<AppState>>,
+    filename: String,
+    fact: String,
+    confidence: Option<String>,
+) -> Result<ProfileFileInfo, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    profile::confirm_profile_fact(&state.app_data_dir, &filename, &fact, confidence.as_deref())?;
+    profile::profile_file_info(&state.app_data_dir, &filename)
+}
+
+/// Profile edits the assistant proposed in review mode; `status` defaults to `pending`.
+#[tauri::command]
+pub fn get_pending_profile_changes(state: State<'_, Mutex<AppState>>, status: Option<String>) -> Result<Vec<PendingProfileChange>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    state.db.get_pending_profile_changes(status.as_deref().unwrap_or("pending")).map_err(db_err)
+}
+
+/// Apply a pending change to the profile files. If it no longer applies (say, its
+/// section was removed since), the error is returned and the change stays pending.
+/// If its files were edited since it was proposed, it isn't applied either: its diff
+/// is redone against the files as they are now, and it waits to be reviewed again.
+#[tauri::command]
+pub fn approve_profile_change(state: State<'_, Mutex<AppState>>, change_id: String) -> Result<PendingProfileChange, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let change = pending_change(&state.db, &change_id)?;
+    let edit: profile::ProfileEdit = serde_json::from_value(change.edit)
+        .map_err(|e| format!("Pending change {} is unreadable: {}", change_id, e))?;
+    let changes = edit.preview(&state.app_data_dir)?;
+    let base_hashes = json!(profile::base_hashes(&changes));
+    if base_hashes != change.base_hashes {
+        let diff: Vec<profile_history::FileDiff> = changes.iter().map(profile_history::diff_change).collect();
+        state.db
+            .refresh_pending_profile_change(&change_id, &json!(diff).to_string(), &base_hashes.to_string())
+            .map_err(db_err)?;
+        return Err(format!(
+            "{} changed since this change was proposed. Its diff now shows what it would do to the current files; review it again.",
+            change.files.join(" and ")
+        ));
+    }
+    let result = edit.apply(&state.app_data_dir, change.conversation_id.as_deref())?;
+    resolve_change(&state.db, &change_id, "approved", Some(&result))
+}
+
+#[tauri::command]
+pub fn reject_profile_change(state: State<'_, Mutex<AppState>>, change_id: String) -> Result<PendingProfileChange, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    pending_change(&state.db, &change_id)?;
+    resolve_change(&state.db, &change_id, "rejected", None)
+}
+
+/// A change that is still waiting for a decision.
+fn pending_change(db: &Database, change_id: &str) -> Result<PendingProfileChange, String> {
+    let change = db.get_pending_profile_change(change_id)
+        .map_err(db_err)?
+        .ok_or_else(|| format!("Pending change {} not found", change_id))?;
+    if change.status != "pending" {
+        return Err(format!("Change {} was already {}", change_id, change.status));
+    }
+    Ok(change)
+}
+
+fn resolve_change(db: &Database, change_id: &str, status: &str, result: Option<&str>) -> Result<PendingProfileChange, String> {
+    db.resolve_pending_profile_change(change_id, status, result).map_err(db_err)?;
+    db.get_pending_profile_change(change_id)
+        .map_err(db_err)?
+        .ok_or_else(|| format!("Pending change {} not found", change_id))
+}
+
 // ── Committee Agent Commands ──
 
 #[tauri::command]
 pub fn get_agent_registry(state: State<'_, Mutex<AppState>>) -> Result<Vec<agents::AgentInfo>, String> {
     let state = state.lock().map_err(|e| e.to_string())?;
-    Ok(agents::load_registry(&state.app_data_dir))
+    agents::load_registry(&state.app_data_dir)
 }
 
 #[tauri::command]
@@ -531,6 +916,132 @@ pub fn save_agent_model(
     config::save_config(&state.app_data_dir, &config)
 }
 
+#[tauri::command]
+pub fn get_providers(state: State<'_, Mutex<AppState>>) -> Result<Vec<providers::ProviderSummary>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let config = config::load_config(&state.app_data_dir);
+    Ok(providers::provider_summaries(&config))
+}
+
+#[tauri::command]
+pub fn save_provider(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+    label: String,
+    kind: String,
+    base_url: String,
+    api_key: Option<String>,
+) -> Result<(), String> {
+    let id = id.trim().to_string();
+    if id.is_empty() {
+        return Err("Provider id cannot be empty".to_string());
+    }
+    if id == providers::OPENROUTER_PROVIDER_ID {
+        return Err("The built-in OpenRouter provider is configured through the main API key setting.".to_string());
+    }
+    providers::ProviderKind::parse(&kind)?;
+
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let mut config = config::load_config(&state.app_data_dir);
+    // Keep the stored key when the UI sends an empty one (it never sees the full key).
+    let api_key = match api_key {
+        Some(k) if !k.is_empty() => k,
+        _ => config.providers.iter()
+            .find(|p| p.id == id)
+            .map(|p| p.api_key.clone())
+            .unwrap_or_default(),
+    };
+
+    let provider = config::ProviderConfig {
+        id: id.clone(),
+        label: label.trim().to_string(),
+        kind: kind.trim().to_lowercase(),
+        base_url: base_url.trim().to_string(),
+        api_key,
+    };
+    match config.providers.iter_mut().find(|p| p.id == id) {
+        Some(slot) => *slot = provider,
+        None => config.providers.push(provider),
+    }
+    config::save_config(&state.app_data_dir, &config)
+}
+
+#[tauri::command]
+pub fn delete_provider(state: State<'_, Mutex<AppState>>, id: String) -> Result<(), String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let mut config = config::load_config(&state.app_data_dir);
+    config.providers.retain(|p| p.id != id);
+    config.agent_providers.retain(|_, provider_id| *provider_id != id);
+    for fallbacks in config.agent_fallbacks.values_mut() {
+        fallbacks.retain(|f| f.provider != id);
+    }
+    config.agent_fallbacks.retain(|_, fallbacks| !fallbacks.is_empty());
+    if config.default_provider == id {
+        config.default_provider = providers::OPENROUTER_PROVIDER_ID.to_string();
+    }
+    config::save_config(&state.app_data_dir, &config)
+}
+
+#[tauri::command]
+pub fn save_agent_provider(
+    state: State<'_, Mutex<AppState>>,
+    agent_key: String,
+    provider_id: String,
+) -> Result<(), String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let mut config = config::load_config(&state.app_data_dir);
+    if provider_id.is_empty() {
+        config.agent_providers.remove(&agent_key);
+    } else {
+        if !providers::all_providers(&config).iter().any(|p| p.id == provider_id) {
+            return Err(format!("Provider '{}' is not configured", provider_id));
+        }
+        config.agent_providers.insert(agent_key, provider_id);
+    }
+    config::save_config(&state.app_data_dir, &config)
+}
+
+/// Replace an agent's fallback models; an empty list removes them.
+#[tauri::command]
+pub fn save_agent_fallbacks(
+    state: State<'_, Mutex<AppState>>,
+    agent_key: String,
+    fallbacks: Vec<FallbackModel>,
+) -> Result<(), String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let mut config = config::load_config(&state.app_data_dir);
+    let known_providers = providers::all_providers(&config);
+    let mut cleaned = Vec::new();
+    for fallback in fallbacks {
+        let model = fallback.model.trim().to_string();
+        let provider = fallback.provider.trim().to_string();
+        if model.is_empty() {
+            continue;
+        }
+        if !provider.is_empty() && !known_providers.iter().any(|p| p.id == provider) {
+            return Err(format!("Provider '{}' is not configured", provider));
+        }
+        cleaned.push(FallbackModel { provider, model });
+    }
+    if cleaned.is_empty() {
+        config.agent_fallbacks.remove(&agent_key);
+    } else {
+        config.agent_fallbacks.insert(agent_key, cleaned);
+    }
+    config::save_config(&state.app_data_dir, &config)
+}
+
+/// Replace an agent's temperature, top_p, max_tokens, seed and provider routing.
+#[tauri::command]
+pub fn save_agent_generation(
+    state: State<'_, Mutex<AppState>>,
+    agent_key: String,
+    params: agents::GenerationParams,
+) -> Result<agents::AgentInfo, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    agents::save_agent_generation(&state.app_data_dir, &agent_key, params)
+}
+
 #[tauri::command]
 pub fn open_agents_folder(state: State<'_, Mutex<AppState>>) -> Result<String, String> {
     let state = state.lock().map_err(|e| e.to_string())?;
@@ -541,6 +1052,7 @@ pub fn open_agents_folder(state: State<'_, Mutex<AppState>>) -> Result<String, S
 
 #[tauri::command]
 pub async fn create_custom_agent(
+    app_handle: tauri::AppHandle,
     state: State<'_, Mutex<AppState>>,
     label: String,
     emoji: String,
@@ -548,17 +1060,21 @@ pub async fn create_custom_agent(
     voice_gender: String,
 ) -> Result<agents::AgentInfo, String> {
     // Generate prompt via LLM
-    let (api_key, model, app_data_dir) = {
+    let (endpoint, model, app_data_dir) = {
         let state = state.lock().map_err(|e| e.to_string())?;
         let config = config::load_config(&state.app_data_dir);
-        if config.openrouter_api_key.is_empty() {
-            return Err("API key not set. Please go to Settings to add your OpenRouter API key.".to_string());
-        }
-        (config.openrouter_api_key, config.model, state.app_data_dir.clone())
+        let endpoint = providers::default_endpoint(&config)?;
+        (endpoint, config.model, state.app_data_dir.clone())
     };
 
     let (system_prompt, user_prompt) = agents::agent_generation_prompt(&label, &description);
-    let generated_prompt = llm::call_llm_simple(&api_key, &model, &system_prompt, &user_prompt).await?;
+    let call = llm::LlmCall {
+        endpoint: &endpoint,
+        model: &model,
+        params: &agents::GenerationParams::default(),
+        usage_ctx: &UsageContext::purpose(usage::PURPOSE_AGENT_GENERATION),
+    };
+    let generated_prompt = llm::call_llm_simple(&call, &system_prompt, &user_prompt, &app_handle).await?;
 
     agents::create_custom_agent(&app_data_dir, &label, &emoji, &generated_prompt, &voice_gender)
 }
@@ -570,14 +1086,48 @@ pub fn delete_custom_agent(
 ) -> Result<(), String> {
     let state = state.lock().map_err(|e| e.to_string())?;
 
-    // Also remove model override from config
+    // Also remove model and provider overrides from config
     let mut config = config::load_config(&state.app_data_dir);
     config.agent_models.remove(&agent_key);
+    config.agent_providers.remove(&agent_key);
+    config.agent_fallbacks.remove(&agent_key);
     config::save_config(&state.app_data_dir, &config)?;
 
     agents::delete_custom_agent(&state.app_data_dir, &agent_key)
 }
 
+// ── System Prompt Commands ──
+
+#[tauri::command]
+pub fn get_system_prompts(state: State<'_, Mutex<AppState>>) -> Result<Vec<prompts::SystemPromptInfo>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    prompts::list_system_prompts(&state.app_data_dir)
+}
+
+#[tauri::command]
+pub fn update_system_prompt(state: State<'_, Mutex<AppState>>, key: String, content: String) -> Result<prompts::SystemPromptInfo, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    prompts::write_system_prompt(&state.app_data_dir, &key, &content)
+}
+
+#[tauri::command]
+pub fn reset_system_prompt(state: State<'_, Mutex<AppState>>, key: String) -> Result<prompts::SystemPromptInfo, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    prompts::reset_system_prompt(&state.app_data_dir, &key)
+}
+
+#[tauri::command]
+pub fn get_system_prompt_versions(state: State<'_, Mutex<AppState>>, key: String) -> Result<Vec<prompts::SystemPromptVersion>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    prompts::list_system_prompt_versions(&state.app_data_dir, &key)
+}
+
+#[tauri::command]
+pub fn restore_system_prompt_version(state: State<'_, Mutex<AppState>>, key: String, version: u32) -> Result<prompts::SystemPromptInfo, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    prompts::restore_system_prompt_version(&state.app_data_dir, &key, version)
+}
+
 // ── Debate Commands ──
 
 #[tauri::command]
@@ -627,11 +1177,7 @@ pub async fn start_debate(
             dec_id.clone(),
             quick_mode,
             cancel_flag,
-            selected,
-            None,
-            None,
-            None,
-            None,
+            debate::DebateSetup { selected_agent_keys: selected, ..debate::DebateSetup::default() },
         ).await {
             eprintln!("Debate error: {}", e);
             let _ = tauri::Emitter::emit(&app_handle, "debate-error", serde_json::json!({
@@ -644,12 +1190,29 @@ pub async fn start_debate(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn estimate_debate(
+    app_handle: tauri::AppHandle,
+    decision_id: String,
+    quick_mode: bool,
+    selected_agents: Option<Vec<String>>,
+) -> Result<debate::DebateEstimate, String> {
+    let setup = debate::DebateSetup { selected_agent_keys: selected_agents, ..debate::DebateSetup::default() };
+    debate::estimate_debate(&app_handle, &decision_id, quick_mode, setup).await
+}
+
 #[tauri::command]
 pub fn get_debate(state: State<'_, Mutex<AppState>>, decision_id: String) -> Result<Vec<DebateRound>, String> {
     let state = state.lock().map_err(|e| e.to_string())?;
     state.db.get_debate_rounds(&decision_id).map_err(db_err)
 }
 
+#[tauri::command]
+pub fn get_moderator_verdict(state: State<'_, Mutex<AppState>>, decision_id: String) -> Result<Option<StoredVerdict>, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    state.db.get_moderator_verdict(&decision_id).map_err(db_err)
+}
+
 #[tauri::command]
 pub fn cancel_debate(state: State<'_, Mutex<AppState>>, decision_id: String) -> Result<(), String> {
     let mut state = state.lock().map_err(|e| e.to_string())?;
@@ -661,6 +1224,60 @@ pub fn cancel_debate(state: State<'_, Mutex<AppState>>, decision_id: String) ->
     Ok(())
 }
 
+// ── Usage & Cost Commands ──
+
+#[tauri::command]
+pub fn get_debate_cost(state: State<'_, Mutex<AppState>>, decision_id: String) -> Result<DebateCostResponse, String> {
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let by_agent = state.db.get_debate_usage_by_agent(&decision_id).map_err(db_err)?;
+    Ok(DebateCostResponse {
+        decision_id,
+        calls: by_agent.iter().map(|a| a.calls).sum(),
+        prompt_tokens: by_agent.iter().map(|a| a.prompt_tokens).sum(),
+        completion_tokens: by_agent.iter().map(|a| a.completion_tokens).sum(),
+        cost_usd: by_agent.iter().map(|a| a.cost_usd).sum(),
+        unpriced_calls: by_agent.iter().map(|a| a.unpriced_calls).sum(),
+        by_agent,
+    })
+}
+
+/// Spend per model for `month` (`YYYY-MM`, UTC); defaults to the current month.
+#[tauri::command]
+pub fn get_monthly_spend(state: State<'_, Mutex<AppState>>, month: Option<String>) -> Result<MonthlySpendResponse, String> {
+    let month = month
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| chrono::Utc::now().format("%Y-%m").to_string());
+    if chrono::NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").is_err() {
+        return Err(format!("Invalid month '{}'. Use YYYY-MM.", month));
+    }
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let by_model = state.db.get_monthly_usage_by_model(&month).map_err(db_err)?;
+    Ok(MonthlySpendResponse {
+        month,
+        total_cost_usd: by_model.iter().map(|m| m.cost_usd).sum(),
+        by_model,
+    })
+}
+
+/// Set the spending caps in USD; `None` (or a non-positive value) removes a cap.
+#[tauri::command]
+pub fn save_budget(
+    state: State<'_, Mutex<AppState>>,
+    per_debate_usd: Option<f64>,
+    per_day_usd: Option<f64>,
+    per_month_usd: Option<f64>,
+) -> Result<(), String> {
+    let cap = |value: Option<f64>| value.filter(|v| v.is_finite() && *v > 0.0);
+    let state = state.lock().map_err(|e| e.to_string())?;
+    let mut config = config::load_config(&state.app_data_dir);
+    config.budget = BudgetConfig {
+        per_debate_usd: cap(per_debate_usd),
+        per_day_usd: cap(per_day_usd),
+        per_month_usd: cap(per_month_usd),
+    };
+    config::save_config(&state.app_data_dir, &config)
+}
+
 // ── Audio Commands ──
 
 #[tauri::command]
@@ -680,7 +1297,7 @@ pub async fn generate_debate_audio(
     }
 
     let config = config::load_config(&app_data_dir);
-    let registry = agents::load_registry(&app_data_dir);
+    let registry = agents::load_registry(&app_data_dir)?;
 
     let manifest = tts::generate_debate_audio(
         &app_handle,
@@ -733,6 +1350,71 @@ pub fn create_standalone_debate(
     })
 }
 
+/// Brief for a standalone debate: the decision title plus the request prompt or stored context.
+fn standalone_brief(db: &Database, decision_id: &str, prompt: Option<&str>) -> Result<String, String> {
+    let decision = db
+        .get_decision(decision_id)
+        .map_err(db_err)?
+        .ok_or_else(|| "Standalone debate decision not found".to_string())?;
+
+    let debate_title = decision.title.trim().to_string();
+
+    let from_request = prompt
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+    let from_db = decision
+        .debate_brief
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+
+    let resolved_context = from_request.or(from_db).unwrap_or_default();
+
+    Ok(if resolved_context.is_empty() {
+        format!(
+            "# Debate Topic\n\n{}\n\nDebate this topic thoroughly from your unique perspective. Engage with each other's arguments directly.",
+            debate_title
+        )
+    } else {
+        format!(
+            "# Debate Topic\n\n{}\n\n## Context\n{}\n\nDebate this topic thoroughly from your unique perspective. Engage with each other's arguments directly.",
+            debate_title,
+            resolved_context
+        )
+    })
+}
+
+#[tauri::command]
+pub async fn estimate_standalone_debate(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Mutex<AppState>>,
+    decision_id: String,
+    quick_mode: bool,
+    selected_models: Vec<String>,
+    prompt: Option<String>,
+    debate_config: Option<debate::StandaloneDebateConfig>,
+) -> Result<debate::DebateEstimate, String> {
+    let sandbox = build_standalone_sandbox(&selected_models)?;
+    let brief = {
+        let state = state.lock().map_err(|e| e.to_string())?;
+        standalone_brief(&state.db, &decision_id, prompt.as_deref())?
+    };
+    debate::estimate_debate(
+        &app_handle,
+        &decision_id,
+        quick_mode,
+        debate::DebateSetup {
+            selected_agent_keys: None,
+            brief_override: Some(brief),
+            standalone_participants: Some(sandbox.participants),
+            standalone_model_map: Some(sandbox.model_map),
+            standalone_config: debate_config,
+        },
+    ).await
+}
+
 #[tauri::command]
 pub async fn start_standalone_debate(
     app_handle: tauri::AppHandle,
@@ -744,6 +1426,10 @@ pub async fn start_standalone_debate(
     debate_config: Option<debate::StandaloneDebateConfig>,
 ) -> Result<(), String> {
     let sandbox = build_standalone_sandbox(&selected_models)?;
+    let brief = {
+        let state = state.lock().map_err(|e| e.to_string())?;
+        standalone_brief(&state.db, &decision_id, prompt.as_deref())?
+    };
     let normalized_config = {
         let fallback_exchanges = if quick_mode { 0 } else { 2 };
         match debate_config {
@@ -767,33 +1453,6 @@ pub async fn start_standalone_debate(
         }
     };
 
-    let (debate_title, resolved_context) = {
-        let state = state.lock().map_err(|e| e.to_string())?;
-        let decision = state
-            .db
-            .get_decision(&decision_id)
-            .map_err(db_err)?
-            .ok_or_else(|| "Standalone debate decision not found".to_string())?;
-
-        let title = decision.title.trim().to_string();
-
-        let from_request = prompt
-            .as_deref()
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .map(str::to_string);
-        let from_db = decision
-            .debate_brief
-            .as_deref()
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .map(str::to_string);
-
-        let context = from_request.or(from_db).unwrap_or_default();
-
-        (title, context)
-    };
-
     let cancel_flag = Arc::new(AtomicBool::new(false));
     {
         let mut state = state.lock().map_err(|e| e.to_string())?;
@@ -808,19 +1467,6 @@ pub async fn start_standalone_debate(
         state.db.update_decision_summary(&decision_id, &sandbox_json).map_err(db_err)?;
     }
 
-    let brief = if resolved_context.is_empty() {
-        format!(
-            "# Debate Topic\n\n{}\n\nDebate this topic thoroughly from your unique perspective. Engage with each other's arguments directly.",
-            debate_title
-        )
-    } else {
-        format!(
-            "# Debate Topic\n\n{}\n\n## Context\n{}\n\nDebate this topic thoroughly from your unique perspective. Engage with each other's arguments directly.",
-            debate_title,
-            resolved_context
-        )
-    };
-
     let dec_id = decision_id.clone();
     let selected = sandbox
         .participants
@@ -838,11 +1484,13 @@ pub async fn start_standalone_debate(
             dec_id.clone(),
             quick_mode,
             cancel_flag,
-            Some(selected),
-            Some(brief),
-            Some(participants),
-            Some(model_map),
-            Some(standalone_config),
+            debate::DebateSetup {
+                selected_agent_keys: Some(selected),
+                brief_override: Some(brief),
+                standalone_participants: Some(participants),
+                standalone_model_map: Some(model_map),
+                standalone_config: Some(standalone_config),
+            },
         ).await {
             eprintln!("Standalone debate error: {}", e);
             let _ = tauri::Emitter::emit(&app_handle, "debate-error", serde_json::json!({
@@ -925,3 +1573,197 @@ pub fn save_pdf(
 
     Ok(output_path.to_string_lossy().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_llm::{test_support, FixtureResponse, MockLlmServer};
+    use std::fs;
+    use std::sync::atomic::Ordering;
+    use tauri::Manager;
+    use tempfile::tempdir;
+
+    /// A mock provider that echoes the newest user message, or fails while `failing` is set.
+    async fn echo_server(failing: Arc<AtomicBool>) -> MockLlmServer {
+        MockLlmServer::scripted(move |request| {
+            if failing.load(Ordering::SeqCst) {
+                return Some(FixtureResponse::error(400, "bad request", None));
+            }
+            let last_user = request["messages"].as_array()?.iter().rev().find(|m| m["role"] == "user")?;
+            Some(FixtureResponse::text(&format!("Re: {}", last_user["content"].as_str()?)))
+        })
+        .await
+        .expect("mock server should start")
+    }
+
+    fn contents(messages: &[crate::db::Message]) -> Vec<&str> {
+        messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn e2e_edit_regenerate_and_switch_branch_keep_every_branch() {
+        let dir = tempdir().expect("temp directory should exist");
+        let server = echo_server(Arc::new(AtomicBool::new(false))).await;
+        let app = test_support::mock_app_with_state(dir.path(), server.base_url());
+        let channel = || Channel::new(|_| Ok(()));
+        let messages = |conv_id: &str| get_messages(app.state(), conv_id.to_string()).unwrap();
+
+        let conv_id = send_message(app.handle().clone(), app.state(), None, "One offer".to_string(), "turn-1".to_string(), channel())
+            .await
+            .expect("first turn should answer")
+            .conversation_id;
+        let original = messages(&conv_id);
+        assert_eq!(contents(&original), vec!["One offer", "Re: One offer"]);
+
+        let edited = edit_message(app.handle().clone(), app.state(), conv_id.clone(), original[0].id.clone(), "Two offers".to_string(), "turn-2".to_string(), channel())
+            .await
+            .expect("edit should answer");
+        assert_eq!(edited.response, "Re: Two offers");
+        let edited_branch = messages(&conv_id);
+        assert_eq!(contents(&edited_branch), vec!["Two offers", "Re: Two offers"]);
+
+        regenerate_message(app.handle().clone(), app.state(), conv_id.clone(), edited_branch[1].id.clone(), "turn-3".to_string(), channel())
+            .await
+            .expect("regenerate should answer");
+        let regenerated_branch = messages(&conv_id);
+        assert_eq!(regenerated_branch[0].id, edited_branch[0].id);
+        assert_ne!(regenerated_branch[1].id, edited_branch[1].id);
+
+        let branches = get_conversation_branches(app.state(), conv_id.clone()).unwrap();
+        assert_eq!(branches.len(), 3);
+        let active: Vec<_> = branches.iter().filter(|b| b.is_active).collect();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].leaf_message_id, regenerated_branch[1].id);
+
+        let switched = switch_branch(app.state(), conv_id.clone(), original[0].id.clone()).unwrap();
+        assert_eq!(switched.iter().map(|m| &m.id).collect::<Vec<_>>(), original.iter().map(|m| &m.id).collect::<Vec<_>>());
+        let switched = switch_branch(app.state(), conv_id.clone(), edited_branch[1].id.clone()).unwrap();
+        assert_eq!(switched[1].id, edited_branch[1].id);
+        assert!(switch_branch(app.state(), "other".to_string(), original[0].id.clone()).is_err());
+    }
+
+    #[tokio::test]
+    async fn e2e_failed_edit_or_regenerate_leaves_the_active_branch_alone() {
+        let dir = tempdir().expect("temp directory should exist");
+        let failing = Arc::new(AtomicBool::new(false));
+        let server = echo_server(Arc::clone(&failing)).await;
+        let app = test_support::mock_app_with_state(dir.path(), server.base_url());
+        let channel = || Channel::new(|_| Ok(()));
+        let message_ids = |conv_id: &str| -> Vec<String> {
+            get_messages(app.state(), conv_id.to_string()).unwrap().into_iter().map(|m| m.id).collect()
+        };
+
+        let conv_id = send_message(app.handle().clone(), app.state(), None, "One offer".to_string(), "turn-1".to_string(), channel())
+            .await
+            .unwrap()
+            .conversation_id;
+        let before = message_ids(&conv_id);
+        failing.store(true, Ordering::SeqCst);
+
+        let edit = edit_message(app.handle().clone(), app.state(), conv_id.clone(), before[0].clone(), "Two offers".to_string(), "turn-2".to_string(), channel()).await;
+        assert!(edit.is_err());
+        assert_eq!(message_ids(&conv_id), before);
+
+        let regenerate = regenerate_message(app.handle().clone(), app.state(), conv_id.clone(), before[1].clone(), "turn-3".to_string(), channel()).await;
+        assert!(regenerate.is_err());
+        assert_eq!(message_ids(&conv_id), before);
+    }
+
+    #[tokio::test]
+    async fn e2e_cancel_message_stops_only_the_turn_it_names() {
+        use tauri::ipc::InvokeResponseBody;
+
+        let dir = tempdir().expect("temp directory should exist");
+        let server = echo_server(Arc::new(AtomicBool::new(false))).await;
+        let app = test_support::mock_app_with_state(dir.path(), server.base_url());
+        let conv_id = {
+            let state: State<'_, Mutex<AppState>> = app.state();
+            let guard = state.lock().unwrap();
+            guard.db.create_conversation("Offers").unwrap().id
+        };
+
+        // The first turn is stopped at its first token, while the second is still streaming
+        // in the same conversation.
+        let handle = app.handle().clone();
+        let stop_first = Channel::new(move |body| {
+            if let InvokeResponseBody::Json(payload) = body {
+                if payload.contains("\"token\"") {
+                    cancel_message(handle.state(), "turn-a".to_string()).unwrap();
+                }
+            }
+            Ok(())
+        });
+        let (first, second) = tokio::join!(
+            send_message(app.handle().clone(), app.state(), Some(conv_id.clone()), "One offer".to_string(), "turn-a".to_string(), stop_first),
+            send_message(app.handle().clone(), app.state(), Some(conv_id.clone()), "Two offers".to_string(), "turn-b".to_string(), Channel::new(|_| Ok(()))),
+        );
+        assert!(first.expect("cancelled turn should return its partial text").cancelled);
+        let second = second.expect("second turn should answer");
+        assert!(!second.cancelled);
+        assert_eq!(second.response, "Re: Two offers");
+
+        // Each turn removed its own handle, and a stale id cancels nothing.
+        let state: State<'_, Mutex<AppState>> = app.state();
+        assert!(state.lock().unwrap().chat_cancel_handles.is_empty());
+        cancel_message(app.state(), "turn-a".to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn e2e_review_mode_changes_are_approved_rejected_and_rechecked_against_hand_edits() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        // Each user message becomes a proposed rewrite of career.md with that message as its bullet.
+        let server = MockLlmServer::scripted(|request| {
+            let messages = request["messages"].as_array()?;
+            if messages.last()?["role"] == "tool" {
+                return Some(FixtureResponse::text("Noted."));
+            }
+            let last_user = messages.iter().rev().find(|m| m["role"] == "user")?;
+            let content = format!("# Career\n- {}\n", last_user["content"].as_str()?);
+            Some(FixtureResponse::tool_call("call_1", "write_profile_file", json!({ "filename": "career.md", "content": content })))
+        })
+        .await
+        .expect("mock server should start");
+        let app = test_support::mock_app_with_state(dir.path(), server.base_url());
+        let mut config = config::load_config(&app_data_dir);
+        config.review_mode = true;
+        config::save_config(&app_data_dir, &config).unwrap();
+        profile::write_profile_file(&app_data_dir, "career.md", "# Career\n- Engineer\n").unwrap();
+        let career = || profile::read_all_profiles(&app_data_dir).unwrap()["career.md"].clone();
+        let propose = |message: &str| {
+            send_message(app.handle().clone(), app.state(), None, message.to_string(), message.to_string(), Channel::new(|_| Ok(())))
+        };
+
+        propose("Manager").await.expect("turn should answer");
+        let pending = get_pending_profile_changes(app.state(), None).unwrap();
+        assert_eq!(pending.len(), 1);
+        let change_id = pending[0].id.clone();
+        assert!(career().contains("- Engineer"));
+
+        // A hand edit after the proposal: approving refuses and re-diffs against it.
+        fs::write(profile::get_profile_dir(&app_data_dir).join("career.md"), "# Career\n- Engineer\n- Berlin\n").unwrap();
+        let err = approve_profile_change(app.state(), change_id.clone()).unwrap_err();
+        assert!(err.contains("career.md changed since this change was proposed"), "{}", err);
+        assert!(career().contains("- Berlin"));
+        let refreshed = get_pending_profile_changes(app.state(), None).unwrap();
+        assert_eq!(refreshed[0].id, change_id);
+        let diff: Vec<profile_history::FileDiff> = serde_json::from_value(refreshed[0].diff.clone()).unwrap();
+        assert!(diff[0].lines.iter().any(|l| l.kind == "removed" && l.text == "- Berlin"));
+
+        // Once reviewed against the current file it applies.
+        let approved = approve_profile_change(app.state(), change_id.clone()).unwrap();
+        assert_eq!(approved.status, "approved");
+        assert!(approved.result.is_some());
+        assert!(career().contains("- Manager") && !career().contains("- Berlin"));
+        assert!(approve_profile_change(app.state(), change_id.clone()).unwrap_err().contains("already approved"));
+
+        propose("Director").await.expect("turn should answer");
+        let change_id = get_pending_profile_changes(app.state(), None).unwrap()[0].id.clone();
+        let rejected = reject_profile_change(app.state(), change_id.clone()).unwrap();
+        assert_eq!(rejected.status, "rejected");
+        assert!(career().contains("- Manager") && !career().contains("- Director"));
+        assert!(approve_profile_change(app.state(), change_id.clone()).unwrap_err().contains("already rejected"));
+        assert!(reject_profile_change(app.state(), "missing".to_string()).unwrap_err().contains("not found"));
+        assert!(get_pending_profile_changes(app.state(), None).unwrap().is_empty());
+    }
+}
@@ -19,12 +19,77 @@ pub struct AppConfig {
     pub elevenlabs_model: String,
     #[serde(default)]
     pub voices: HashMap<String, String>, // agent_key -> voice_id overrides
+    #[serde(default = "default_provider")]
+    pub default_provider: String,
+    #[serde(default)]
+    pub providers: Vec<ProviderConfig>,
+    #[serde(default)]
+    pub agent_providers: HashMap<String, String>, // agent_key -> provider id overrides
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub agent_fallbacks: HashMap<String, Vec<FallbackModel>>, // agent_key -> models to try, in order
+    #[serde(default)]
+    pub save_reasoning: bool, // keep streamed reasoning with chat messages and debate rounds
+    #[serde(default)]
+    pub review_mode: bool, // queue the assistant's profile edits until the user approves them
+    #[serde(default)]
+    pub profile_embeddings: Option<EmbeddingsConfig>, // blend embeddings into profile retrieval
+}
+
+/// Embeddings model used to rank profile sections alongside BM25. Every profile
+/// section is sent to it, so it's off unless `enabled` is set and is only used on a
+/// server on this machine (e.g. `nomic-embed-text` on a local Ollama).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EmbeddingsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub provider: String, // empty -> default provider
+    pub model: String,
+}
+
+/// Spending caps in USD. `None` means no cap.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BudgetConfig {
+    #[serde(default)]
+    pub per_debate_usd: Option<f64>,
+    #[serde(default)]
+    pub per_day_usd: Option<f64>,
+    #[serde(default)]
+    pub per_month_usd: Option<f64>,
+}
+
+/// A model to fall back to when an agent's own model keeps failing.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FallbackModel {
+    #[serde(default)]
+    pub provider: String, // empty -> the agent's provider
+    pub model: String,
+}
+
+/// A chat-completions endpoint the user has configured in addition to the
+/// built-in OpenRouter provider.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProviderConfig {
+    pub id: String,
+    #[serde(default)]
+    pub label: String,
+    pub kind: String, // "openrouter", "openai", "anthropic" or "openai_compatible"
+    #[serde(default)]
+    pub base_url: String, // empty -> kind default
+    #[serde(default)]
+    pub api_key: String,
 }
 
 fn default_model() -> String {
     "anthropic/claude-sonnet-4-5".to_string()
 }
 
+fn default_provider() -> String {
+    "openrouter".to_string()
+}
+
 fn default_tts_provider() -> String {
     "elevenlabs".to_string()
 }
@@ -43,6 +108,14 @@ impl Default for AppConfig {
             tts_provider: default_tts_provider(),
             elevenlabs_model: default_elevenlabs_model(),
             voices: HashMap::new(),
+            default_provider: default_provider(),
+            providers: Vec::new(),
+            agent_providers: HashMap::new(),
+            budget: BudgetConfig::default(),
+            agent_fallbacks: HashMap::new(),
+            save_reasoning: false,
+            review_mode: false,
+            profile_embeddings: None,
         }
     }
 }
@@ -86,6 +159,12 @@ mod tests {
         assert_eq!(loaded.tts_provider, "elevenlabs");
         assert_eq!(loaded.elevenlabs_model, "eleven_flash_v2_5");
         assert!(loaded.voices.is_empty());
+        assert_eq!(loaded.default_provider, "openrouter");
+        assert!(loaded.providers.is_empty());
+        assert!(loaded.agent_providers.is_empty());
+        assert_eq!(loaded.budget, BudgetConfig::default());
+        assert!(loaded.agent_fallbacks.is_empty());
+        assert!(!loaded.save_reasoning);
     }
 
     #[test]
@@ -104,6 +183,31 @@ mod tests {
             tts_provider: "openai".to_string(),
             elevenlabs_model: "eleven_turbo_v2_5".to_string(),
             voices: HashMap::new(),
+            default_provider: "openrouter".to_string(),
+            providers: vec![ProviderConfig {
+                id: "ollama".to_string(),
+                label: "Ollama".to_string(),
+                kind: "openai_compatible".to_string(),
+                base_url: "http://localhost:11434/v1".to_string(),
+                api_key: String::new(),
+            }],
+            agent_providers: HashMap::from([("contrarian".to_string(), "ollama".to_string())]),
+            budget: BudgetConfig {
+                per_debate_usd: Some(2.5),
+                per_day_usd: None,
+                per_month_usd: Some(40.0),
+            },
+            agent_fallbacks: HashMap::from([(
+                "contrarian".to_string(),
+                vec![FallbackModel { provider: "ollama".to_string(), model: "llama3.1".to_string() }],
+            )]),
+            save_reasoning: true,
+            review_mode: true,
+            profile_embeddings: Some(EmbeddingsConfig {
+                enabled: true,
+                provider: "ollama".to_string(),
+                model: "nomic-embed-text".to_string(),
+            }),
         };
 
         save_config(&app_data_dir, &config).expect("config should save");
@@ -118,6 +222,21 @@ mod tests {
         assert_eq!(loaded.elevenlabs_api_key, "sk-eleven-test");
         assert_eq!(loaded.tts_provider, "openai");
         assert_eq!(loaded.elevenlabs_model, "eleven_turbo_v2_5");
+        assert_eq!(loaded.providers.len(), 1);
+        assert_eq!(loaded.providers[0].base_url, "http://localhost:11434/v1");
+        assert_eq!(
+            loaded.agent_providers.get("contrarian").map(String::as_str),
+            Some("ollama")
+        );
+        assert_eq!(loaded.budget.per_debate_usd, Some(2.5));
+        assert_eq!(loaded.budget.per_day_usd, None);
+        assert_eq!(loaded.budget.per_month_usd, Some(40.0));
+        assert_eq!(loaded.agent_fallbacks["contrarian"][0].model, "llama3.1");
+        assert!(loaded.save_reasoning);
+        assert!(loaded.review_mode);
+        let embeddings = loaded.profile_embeddings.unwrap();
+        assert!(embeddings.enabled);
+        assert_eq!(embeddings.model, "nomic-embed-text");
     }
 
     #[test]
@@ -135,5 +254,6 @@ mod tests {
         assert!(loaded.elevenlabs_api_key.is_empty());
         assert_eq!(loaded.tts_provider, "elevenlabs");
         assert_eq!(loaded.elevenlabs_model, "eleven_flash_v2_5");
+        assert_eq!(loaded.default_provider, "openrouter");
     }
 }
@@ -0,0 +1,431 @@
+//! Context-window management for conversation history. When a conversation
+//! outgrows the model's window, the newest turns are sent verbatim and the older
+//! ones are folded into a rolling LLM-written summary kept in `conversation_summaries`.
+
+use crate::agents::GenerationParams;
+use crate::budget;
+use crate::commands::{self, AppState};
+use crate::config::AppConfig;
+use crate::db::{ConversationSummary, Database, Message};
+use crate::llm;
+use crate::providers::LlmEndpoint;
+use crate::usage::UsageContext;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use tauri::{Manager, Runtime};
+
+/// Window assumed for models missing from the OpenRouter list (local servers, direct APIs).
+pub const DEFAULT_CONTEXT_TOKENS: i64 = 32_000;
+
+/// Completion space reserved in every chat request (`max_tokens` in `llm::send_message`).
+pub const CHAT_RESPONSE_TOKENS: i64 = 4_096;
+
+/// Room kept free for the rolling summary itself.
+pub const SUMMARY_MAX_TOKENS: i64 = 1_000;
+
+/// Debate prompts also carry the profile, agent instructions and a growing
+/// transcript, so the conversation only gets a quarter of the window there.
+const BRIEF_WINDOW_DIVISOR: i64 = 4;
+
+/// Floor for tiny windows; the newest message is always kept regardless.
+const MIN_HISTORY_TOKENS: i64 = 1_000;
+
+/// Role marker and separators around each chat message.
+const MESSAGE_OVERHEAD_TOKENS: i64 = 4;
+
+const SUMMARY_SYSTEM_PROMPT: &str = r#"You maintain a running summary of a conversation between a user and an AI decision-making assistant. The summary replaces the older turns, so the assistant will only see what you keep.
+
+Keep:
+- Facts the user shared about themselves, their situation and constraints
+- The decision being discussed, the options and what was said about each
+- Preferences, concerns and priorities the user expressed
+- Conclusions reached and open questions
+
+Write in third person ("The user ...") as compact bullet points under 500 words. Fold the new turns into the existing summary and return only the updated summary."#;
+
+// ── Context lengths ──
+
+// Filled from the OpenRouter models list, like the pricing cache in `usage`.
+static CONTEXT_LENGTHS: OnceLock<Mutex<HashMap<String, i64>>> = OnceLock::new();
+
+fn context_table() -> &'static Mutex<HashMap<String, i64>> {
+    CONTEXT_LENGTHS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn remember_context_length(model: &str, tokens: u32) {
+    if let Ok(mut table) = context_table().lock() {
+        table.insert(model.to_string(), tokens as i64);
+    }
+}
+
+fn cached_context_length(model: &str) -> Option<i64> {
+    context_table().lock().ok()?.get(model).copied()
+}
+
+pub fn context_length(model: &str) -> i64 {
+    cached_context_length(model).unwrap_or(DEFAULT_CONTEXT_TOKENS)
+}
+
+/// Fetch the OpenRouter models list when the model's window is not cached yet.
+pub async fn prefetch_context_length(endpoint: &LlmEndpoint, model: &str, app_data_dir: &Path) {
+    if endpoint.is_openrouter() && cached_context_length(model).is_none() {
+        let _ = commands::fetch_openrouter_models(app_data_dir).await;
+    }
+}
+
+// ── Budgets ──
+
+/// Tokens left for history in a chat request after the system prompt, tool
+/// definitions, summary and response are accounted for.
+pub fn chat_history_budget(model: &str, app_data_dir: &Path, conv_type: &str) -> i64 {
+    let overhead = budget::estimate_tokens(llm::chat_prompt_overhead_chars(app_data_dir, conv_type));
+    (context_length(model) - CHAT_RESPONSE_TOKENS - SUMMARY_MAX_TOKENS - overhead).max(MIN_HISTORY_TOKENS)
+}
+
+/// Conversation budget for a debate brief, sized for the smallest window among
+/// the default and per-agent models.
+pub fn brief_conversation_budget(config: &AppConfig) -> i64 {
+    let smallest = std::iter::once(&config.model)
+        .chain(config.agent_models.values())
+        .filter(|m| !m.is_empty())
+        .map(|m| context_length(m))
+        .min()
+        .unwrap_or(DEFAULT_CONTEXT_TOKENS);
+    (smallest / BRIEF_WINDOW_DIVISOR - SUMMARY_MAX_TOKENS).max(MIN_HISTORY_TOKENS)
+}
+
+fn message_tokens(message: &Message) -> i64 {
+    budget::estimate_tokens(message.content.len()) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Index of the first message to send verbatim: the longest suffix that fits the
+/// budget, moved forward so it opens on a user turn. The newest message is always kept.
+pub fn first_kept_index(messages: &[Message], budget_tokens: i64) -> usize {
+    let mut used = 0;
+    let mut start = messages.len();
+    for (index, message) in messages.iter().enumerate().rev() {
+        let tokens = message_tokens(message);
+        if used + tokens > budget_tokens && start < messages.len() {
+            break;
+        }
+        used += tokens;
+        start = index;
+    }
+    while start > 0 && start + 1 < messages.len() && messages[start].role != "user" {
+        start += 1;
+    }
+    start
+}
+
+/// A stored summary only applies while the messages it covers are unchanged.
+fn usable_summary(stored: Option<ConversationSummary>, messages: &[Message]) -> Option<ConversationSummary> {
+    stored.filter(|s| {
+        let count = s.summarized_count as usize;
+        count > 0 && count <= messages.len() && messages[count - 1].id == s.last_message_id
+    })
+}
+
+// ── Windows ──
+
+/// What to send for a conversation: the rolling summary (if any) plus the
+/// newest messages verbatim.
+#[derive(Debug, Clone, Default)]
+pub struct ConversationWindow {
+    pub summary: Option<String>,
+    pub recent: Vec<Message>,
+}
+
+impl ConversationWindow {
+    /// Chat-completions messages for the recent turns. The summary isn't among them:
+    /// it goes into the system prompt (`system_prompt_with_summary`).
+    pub fn to_chat_messages(&self) -> Vec<Value> {
+        self.recent.iter().map(|m| json!({"role": m.role, "content": m.content})).collect()
+    }
+
+    /// Plain-text transcript for debate briefs.
+    pub fn to_transcript(&self) -> String {
+        let turns = self.recent
+            .iter()
+            .map(|m| format!("{}: {}", if m.role == "user" { "User" } else { "AI" }, m.content))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        match &self.summary {
+            Some(summary) if turns.is_empty() => format!("Summary of the conversation:\n{}", summary),
+            Some(summary) => format!("Summary of the earlier conversation:\n{}\n\nMost recent turns:\n\n{}", summary, turns),
+            None => turns,
+        }
+    }
+}
+
+/// The chat system prompt with the rolling summary, if any, appended, so the request
+/// carries a single system message.
+pub fn system_prompt_with_summary(system_prompt: &str, summary: Option<&str>) -> String {
+    match summary {
+        Some(summary) => format!("{}\n\n## Summary of the earlier part of this conversation\n{}", system_prompt, summary),
+        None => system_prompt.to_string(),
+    }
+}
+
+/// Window built without calling a model: the stored summary plus whatever newer
+/// turns fit. Turns between the two are left out. Used for cost estimates.
+pub fn peek_window(db: &Database, conversation_id: &str, budget_tokens: i64) -> Result<ConversationWindow, String> {
+    let messages = db.get_messages(conversation_id).map_err(|e| e.to_string())?;
+    let stored = db.get_conversation_summary(conversation_id).map_err(|e| e.to_string())?;
+    let summary = usable_summary(stored, &messages);
+    let covered = summary.as_ref().map(|s| s.summarized_count as usize).unwrap_or(0);
+    let start = first_kept_index(&messages, budget_tokens).max(covered);
+    Ok(ConversationWindow {
+        summary: summary.map(|s| s.summary),
+        recent: messages[start..].to_vec(),
+    })
+}
+
+/// Window for the next request. Turns that no longer fit are first folded into
+/// the rolling summary, in batches small enough for the summarizing model. If
+/// summarizing fails, the turns it didn't cover are dropped instead and the
+/// request goes ahead with the summary as it was.
+pub async fn prepare_window<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    endpoint: &LlmEndpoint,
+    model: &str,
+    conversation_id: &str,
+    decision_id: Option<&str>,
+    budget_tokens: i64,
+) -> Result<ConversationWindow, String> {
+    let (messages, stored) = {
+        let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+        let state_guard = state.lock().map_err(|e| e.to_string())?;
+        let messages = state_guard.db.get_messages(conversation_id).map_err(|e| e.to_string())?;
+        let stored = state_guard.db.get_conversation_summary(conversation_id).map_err(|e| e.to_string())?;
+        (messages, stored)
+    };
+
+    let had_stored = stored.is_some();
+    let summary = usable_summary(stored, &messages);
+    let mut covered = summary.as_ref().map(|s| s.summarized_count as usize).unwrap_or(0);
+    let mut summary_text = summary.map(|s| s.summary);
+    if had_stored && summary_text.is_none() {
+        let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+        let state_guard = state.lock().map_err(|e| e.to_string())?;
+        state_guard.db.delete_conversation_summary(conversation_id).map_err(|e| e.to_string())?;
+    }
+
+    let start = first_kept_index(&messages, budget_tokens);
+    let batch_budget = context_length(model) / 2;
+    let usage_ctx = UsageContext::context_summary(conversation_id, decision_id);
+    while covered < start {
+        let mut batch_end = covered;
+        let mut batch_tokens = 0;
+        while batch_end < start && (batch_end == covered || batch_tokens + message_tokens(&messages[batch_end]) <= batch_budget) {
+            batch_tokens += message_tokens(&messages[batch_end]);
+            batch_end += 1;
+        }
+
+        let updated = match summarize_turns(
+            app_handle,
+            endpoint,
+            model,
+            summary_text.as_deref(),
+            &messages[covered..batch_end],
+            &usage_ctx,
+        ).await {
+            Ok(updated) => updated,
+            Err(e) => {
+                eprintln!("Conversation summary failed, dropping older turns instead: {}", e);
+                break;
+            }
+        };
+
+        let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+        let state_guard = state.lock().map_err(|e| e.to_string())?;
+        state_guard.db
+            .save_conversation_summary(conversation_id, &updated, batch_end as i64, &messages[batch_end - 1].id)
+            .map_err(|e| e.to_string())?;
+        covered = batch_end;
+        summary_text = Some(updated);
+    }
+
+    Ok(ConversationWindow {
+        summary: summary_text,
+        recent: messages[covered.max(start)..].to_vec(),
+    })
+}
+
+async fn summarize_turns<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    endpoint: &LlmEndpoint,
+    model: &str,
+    previous: Option<&str>,
+    turns: &[Message],
+    usage_ctx: &UsageContext,
+) -> Result<String, String> {
+    let transcript = ConversationWindow { summary: None, recent: turns.to_vec() }.to_transcript();
+    let user_prompt = format!(
+        "Existing summary:\n{}\n\nNew turns to fold in:\n\n{}",
+        previous.unwrap_or("(none yet)"),
+        transcript,
+    );
+    let call = llm::LlmCall { endpoint, model, params: &GenerationParams::default(), usage_ctx };
+    let summary = llm::call_llm_simple(&call, SUMMARY_SYSTEM_PROMPT, &user_prompt, app_handle).await?;
+    Ok(summary.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_llm::{test_support, FixtureResponse, MockLlmServer};
+    use crate::providers;
+    use tempfile::tempdir;
+
+    fn message(id: &str, role: &str, chars: usize) -> Message {
+        Message {
+            id: id.to_string(),
+            conversation_id: "conv".to_string(),
+            role: role.to_string(),
+            content: "x".repeat(chars),
+            created_at: String::new(),
+            status: crate::db::MESSAGE_STATUS_COMPLETE.to_string(),
+            reasoning: None,
+            parent_id: None,
+        }
+    }
+
+    #[test]
+    fn unit_first_kept_index_fits_budget_and_opens_on_user_turn() {
+        let messages = vec![
+            message("m1", "user", 400),
+            message("m2", "assistant", 400),
+            message("m3", "user", 400),
+            message("m4", "assistant", 400),
+        ];
+        assert_eq!(first_kept_index(&messages, 10_000), 0);
+        // 104 tokens per message: three fit, but the suffix would open on an assistant reply.
+        assert_eq!(first_kept_index(&messages, 320), 2);
+        // The newest message is kept even when it alone exceeds the budget.
+        assert_eq!(first_kept_index(&messages, 10), 3);
+    }
+
+    #[test]
+    fn unit_context_budgets_use_cached_window_or_default() {
+        remember_context_length("context-test/small", 16_000);
+        assert_eq!(context_length("context-test/small"), 16_000);
+        assert_eq!(context_length("context-test/unknown"), DEFAULT_CONTEXT_TOKENS);
+        let app_data_dir = Path::new("/nonexistent");
+        assert!(
+            chat_history_budget("context-test/small", app_data_dir, "decision")
+                < chat_history_budget("context-test/unknown", app_data_dir, "decision")
+        );
+
+        let config = AppConfig {
+            model: "context-test/unknown".to_string(),
+            agent_models: HashMap::from([("contrarian".to_string(), "context-test/small".to_string())]),
+            ..AppConfig::default()
+        };
+        assert_eq!(brief_conversation_budget(&config), 16_000 / BRIEF_WINDOW_DIVISOR - SUMMARY_MAX_TOKENS);
+    }
+
+    #[tokio::test]
+    async fn integration_prepare_window_rolls_old_turns_into_stored_summary() {
+        let dir = tempdir().expect("temp directory should exist");
+        let server = MockLlmServer::scripted(|request| {
+            let prompt = test_support::request_text(request);
+            Some(FixtureResponse::text(if prompt.contains("(none yet)") {
+                "- The user is weighing two job offers."
+            } else {
+                "- The user is weighing two job offers.\n- Salary matters most."
+            }))
+        })
+        .await
+        .expect("mock server should start");
+        let app = test_support::mock_app_with_state(dir.path(), server.base_url());
+        let config = crate::config::load_config(&dir.path().to_path_buf());
+        let endpoint = providers::default_endpoint(&config).expect("fixture provider should resolve");
+
+        let conv_id = {
+            let state: tauri::State<'_, Mutex<AppState>> = app.state();
+            let state_guard = state.lock().unwrap();
+            let conv = state_guard.db.create_conversation("Offers").unwrap();
+            for i in 0..6 {
+                let role = if i % 2 == 0 { "user" } else { "assistant" };
+                state_guard.db.add_message(&conv.id, role, &format!("turn {} {}", i, "y".repeat(400))).unwrap();
+            }
+            conv.id
+        };
+
+        // Two turns fit: the first four are folded into the summary.
+        let window = prepare_window(app.handle(), &endpoint, "fixture/model", &conv_id, None, 250)
+            .await
+            .expect("window should be prepared");
+        assert_eq!(window.recent.len(), 2);
+        assert!(window.recent[0].content.starts_with("turn 4"));
+        assert_eq!(window.summary.as_deref(), Some("- The user is weighing two job offers."));
+        let chat = window.to_chat_messages();
+        assert_eq!(chat.len(), 2);
+        assert!(chat.iter().all(|m| m["role"] != "system"));
+        let system_prompt = system_prompt_with_summary("You are helpful.", window.summary.as_deref());
+        assert!(system_prompt.starts_with("You are helpful.\n\n## Summary"));
+        assert!(system_prompt.ends_with("two job offers."));
+
+        // A later turn only folds the newly overflowing messages into the existing summary.
+        {
+            let state: tauri::State<'_, Mutex<AppState>> = app.state();
+            let state_guard = state.lock().unwrap();
+            state_guard.db.add_message(&conv_id, "user", &format!("turn 6 {}", "y".repeat(400))).unwrap();
+            state_guard.db.add_message(&conv_id, "assistant", &format!("turn 7 {}", "y".repeat(400))).unwrap();
+        }
+        let window = prepare_window(app.handle(), &endpoint, "fixture/model", &conv_id, None, 250)
+            .await
+            .expect("window should be prepared");
+        assert!(window.recent[0].content.starts_with("turn 6"));
+        assert!(window.summary.as_deref().unwrap_or_default().contains("Salary"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(test_support::request_text(&requests[1]).contains("turn 4"));
+        assert!(!test_support::request_text(&requests[1]).contains("turn 3"));
+
+        let state: tauri::State<'_, Mutex<AppState>> = app.state();
+        let state_guard = state.lock().unwrap();
+        let stored = state_guard.db.get_conversation_summary(&conv_id).unwrap().expect("summary should be stored");
+        assert_eq!(stored.summarized_count, 6);
+        let peeked = peek_window(&state_guard.db, &conv_id, 250).unwrap();
+        assert_eq!(peeked.recent.len(), 2);
+        assert!(peeked.to_transcript().starts_with("Summary of the earlier conversation:"));
+    }
+
+    #[tokio::test]
+    async fn integration_prepare_window_drops_old_turns_when_summarizing_fails() {
+        let dir = tempdir().expect("temp directory should exist");
+        let server = MockLlmServer::scripted(|_| Some(FixtureResponse::error(500, "summarizer down", None)))
+            .await
+            .expect("mock server should start");
+        let app = test_support::mock_app_with_state(dir.path(), server.base_url());
+        let config = crate::config::load_config(&dir.path().to_path_buf());
+        let endpoint = providers::default_endpoint(&config).expect("fixture provider should resolve");
+
+        let conv_id = {
+            let state: tauri::State<'_, Mutex<AppState>> = app.state();
+            let state_guard = state.lock().unwrap();
+            let conv = state_guard.db.create_conversation("Offers").unwrap();
+            for i in 0..6 {
+                let role = if i % 2 == 0 { "user" } else { "assistant" };
+                state_guard.db.add_message(&conv.id, role, &format!("turn {} {}", i, "y".repeat(400))).unwrap();
+            }
+            conv.id
+        };
+
+        let window = prepare_window(app.handle(), &endpoint, "fixture/model", &conv_id, None, 250)
+            .await
+            .expect("a failed summary should not fail the turn");
+        assert_eq!(window.recent.len(), 2);
+        assert!(window.recent[0].content.starts_with("turn 4"));
+        assert_eq!(window.summary, None);
+        assert!(!server.requests().is_empty());
+
+        let state: tauri::State<'_, Mutex<AppState>> = app.state();
+        let state_guard = state.lock().unwrap();
+        assert!(state_guard.db.get_conversation_summary(&conv_id).unwrap().is_none());
+    }
+}
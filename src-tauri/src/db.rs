@@ -21,8 +21,32 @@ pub struct Message {
     pub role: String,
     pub content: String,
     pub created_at: String,
+    /// `complete`, or `cancelled` for an assistant reply the user stopped mid-stream.
+    pub status: String,
+    /// Streamed model reasoning, kept only when `save_reasoning` is on.
+    pub reasoning: Option<String>,
+    /// The message this one answers or follows; `None` for the first message.
+    /// Editing or regenerating adds a sibling, so messages form a tree.
+    pub parent_id: Option<String>,
+}
+
+/// One root-to-leaf path through a conversation's message tree.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationBranch {
+    pub leaf_message_id: String,
+    pub message_count: usize,
+    /// Newest user message on the branch, for telling branches apart.
+    pub last_user_message: Option<String>,
+    /// Last message this branch shares with the active one; `None` for the active branch
+    /// or a branch that differs from the very first message.
+    pub forked_from: Option<String>,
+    pub is_active: bool,
+    pub updated_at: String,
 }
 
+pub const MESSAGE_STATUS_COMPLETE: &str = "complete";
+pub const MESSAGE_STATUS_CANCELLED: &str = "cancelled";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Decision {
     pub id: String,
@@ -50,6 +74,8 @@ pub struct DebateRound {
     pub agent: String,
     pub content: String,
     pub created_at: String,
+    /// The agent's streamed reasoning; never part of `content`, so never spoken.
+    pub reasoning: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -62,6 +88,136 @@ pub struct DebateAudio {
     pub audio_dir: String,
 }
 
+/// Rolling summary of the oldest `summarized_count` messages of a conversation.
+/// `last_message_id` is the newest message folded in; if it no longer sits at
+/// that position the history changed and the summary is rebuilt.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConversationSummary {
+    pub conversation_id: String,
+    pub summary: String,
+    pub summarized_count: i64,
+    pub last_message_id: String,
+    pub updated_at: String,
+}
+
+/// The moderator's typed verdict for a decision, stored as JSON.
+/// `source` is "structured" when the model returned schema-valid JSON and
+/// "markdown" when it had to be scraped from a prose synthesis.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StoredVerdict {
+    pub decision_id: String,
+    pub verdict_json: String,
+    pub source: String,
+    pub created_at: String,
+}
+
+/// A document attached to a decision. The file is copied under
+/// `attachments/<decision_id>/`; its extracted text lives in the row.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attachment {
+    pub id: String,
+    pub decision_id: String,
+    pub filename: String,
+    /// `pdf`, `docx`, `markdown`, `csv` or `text`.
+    pub kind: String,
+    pub size_bytes: i64,
+    pub stored_path: String,
+    pub text_chars: i64,
+    pub created_at: String,
+}
+
+/// One LLM call to persist in `llm_usage`.
+pub struct NewLlmUsage<'a> {
+    pub purpose: &'a str,
+    pub conversation_id: Option<&'a str>,
+    /// The assistant reply a chat call produced; `None` for calls that serve no single reply.
+    pub message_id: Option<&'a str>,
+    pub decision_id: Option<&'a str>,
+    pub round_number: Option<i32>,
+    pub exchange_number: Option<i32>,
+    pub agent: Option<&'a str>,
+    pub provider: &'a str,
+    pub model: &'a str,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost_usd: Option<f64>,
+}
+
+/// One tool call the chat assistant made, for the `tool_calls` audit log.
+pub struct NewToolCall<'a> {
+    pub conversation_id: Option<&'a str>,
+    pub decision_id: Option<&'a str>,
+    /// The reply the call was made for; see `UsageContext::message_id`.
+    pub message_id: Option<&'a str>,
+    pub tool_name: &'a str,
+    pub arguments: &'a str,
+    pub result: &'a str,
+    pub success: bool,
+    /// Profile files the call read or changed.
+    pub files: &'a [String],
+}
+
+/// A row of the `tool_calls` audit log. `message_id` is the assistant reply the
+/// call was made for; it is cleared if that reply is never saved.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCallRecord {
+    pub id: String,
+    pub conversation_id: Option<String>,
+    pub message_id: Option<String>,
+    pub decision_id: Option<String>,
+    pub tool_name: String,
+    pub arguments: String,
+    pub result: String,
+    pub success: bool,
+    pub files: Vec<String>,
+    pub created_at: String,
+}
+
+/// A profile edit the assistant proposed while review mode was on.
+pub struct NewPendingProfileChange<'a> {
+    pub conversation_id: Option<&'a str>,
+    pub decision_id: Option<&'a str>,
+    pub tool_name: &'a str,
+    /// The `profile::ProfileEdit` to apply on approval, as JSON.
+    pub edit: &'a str,
+    /// Per-file line diffs shown to the user, as JSON.
+    pub diff: &'a str,
+    pub files: &'a [String],
+    /// Hash of each file's content when the diff was made (`null` for a missing file), as JSON.
+    pub base_hashes: &'a str,
+}
+
+/// A row of `pending_profile_changes`. `status` is `pending`, `approved` or `rejected`;
+/// `result` holds the outcome of applying an approved change.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PendingProfileChange {
+    pub id: String,
+    pub conversation_id: Option<String>,
+    pub decision_id: Option<String>,
+    pub tool_name: String,
+    pub edit: serde_json::Value,
+    pub diff: serde_json::Value,
+    pub files: Vec<String>,
+    /// Filename to content hash at proposal time; approving checks the files still match.
+    pub base_hashes: serde_json::Value,
+    pub status: String,
+    pub result: Option<String>,
+    pub created_at: String,
+    pub resolved_at: Option<String>,
+}
+
+/// Aggregated usage for one group (agent, model, ...). `unpriced_calls` counts
+/// calls whose cost could not be determined and is excluded from `cost_usd`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    pub key: String,
+    pub calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost_usd: f64,
+    pub unpriced_calls: i64,
+}
+
 pub struct Database {
     conn: Mutex<Connection>,
 }
@@ -80,7 +236,8 @@ impl Database {
                 title TEXT NOT NULL,
                 type TEXT NOT NULL DEFAULT 'chat',
                 created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
+                updated_at TEXT NOT NULL,
+                active_message_id TEXT
             );
             CREATE TABLE IF NOT EXISTS messages (
                 id TEXT PRIMARY KEY,
@@ -88,6 +245,9 @@ impl Database {
                 role TEXT NOT NULL,
                 content TEXT NOT NULL,
                 created_at TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'complete',
+                reasoning TEXT,
+                parent_id TEXT,
                 FOREIGN KEY (conversation_id) REFERENCES conversations(id)
             );
             CREATE TABLE IF NOT EXISTS decisions (
@@ -115,6 +275,7 @@ impl Database {
                 agent TEXT NOT NULL,
                 content TEXT NOT NULL,
                 created_at TEXT NOT NULL,
+                reasoning TEXT,
                 FOREIGN KEY (decision_id) REFERENCES decisions(id)
             );
             CREATE TABLE IF NOT EXISTS debate_audio (
@@ -126,6 +287,78 @@ impl Database {
                 audio_dir TEXT NOT NULL,
                 FOREIGN KEY (decision_id) REFERENCES decisions(id)
             );
+            CREATE TABLE IF NOT EXISTS llm_usage (
+                id TEXT PRIMARY KEY,
+                purpose TEXT NOT NULL,
+                conversation_id TEXT,
+                message_id TEXT,
+                decision_id TEXT,
+                round_number INTEGER,
+                exchange_number INTEGER,
+                agent TEXT,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL DEFAULT 0,
+                completion_tokens INTEGER NOT NULL DEFAULT 0,
+                cost_usd REAL,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_llm_usage_decision ON llm_usage(decision_id);
+            CREATE INDEX IF NOT EXISTS idx_llm_usage_created ON llm_usage(created_at);
+            CREATE TABLE IF NOT EXISTS tool_calls (
+                id TEXT PRIMARY KEY,
+                conversation_id TEXT,
+                message_id TEXT,
+                decision_id TEXT,
+                tool_name TEXT NOT NULL,
+                arguments TEXT NOT NULL,
+                result TEXT NOT NULL,
+                success INTEGER NOT NULL,
+                files TEXT NOT NULL DEFAULT '[]',
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_tool_calls_conversation ON tool_calls(conversation_id);
+            CREATE TABLE IF NOT EXISTS pending_profile_changes (
+                id TEXT PRIMARY KEY,
+                conversation_id TEXT,
+                decision_id TEXT,
+                tool_name TEXT NOT NULL,
+                edit TEXT NOT NULL,
+                diff TEXT NOT NULL,
+                files TEXT NOT NULL DEFAULT '[]',
+                base_hashes TEXT NOT NULL DEFAULT '{}',
+                status TEXT NOT NULL DEFAULT 'pending',
+                result TEXT,
+                created_at TEXT NOT NULL,
+                resolved_at TEXT
+            );
+            CREATE TABLE IF NOT EXISTS conversation_summaries (
+                conversation_id TEXT PRIMARY KEY,
+                summary TEXT NOT NULL,
+                summarized_count INTEGER NOT NULL,
+                last_message_id TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (conversation_id) REFERENCES conversations(id)
+            );
+            CREATE TABLE IF NOT EXISTS attachments (
+                id TEXT PRIMARY KEY,
+                decision_id TEXT NOT NULL,
+                filename TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                stored_path TEXT NOT NULL,
+                extracted_text TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (decision_id) REFERENCES decisions(id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_attachments_decision ON attachments(decision_id);
+            CREATE TABLE IF NOT EXISTS moderator_verdicts (
+                decision_id TEXT PRIMARY KEY,
+                verdict_json TEXT NOT NULL,
+                source TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (decision_id) REFERENCES decisions(id)
+            );
         ")?;
 
         // Migration: add type column if missing (existing databases)
@@ -138,6 +371,53 @@ impl Database {
             conn.execute_batch("ALTER TABLE conversations ADD COLUMN type TEXT NOT NULL DEFAULT 'chat';")?;
         }
 
+        // Migration: add status column to messages if missing
+        let has_message_status: bool = conn
+            .prepare("SELECT COUNT(*) FROM pragma_table_info('messages') WHERE name='status'")
+            .and_then(|mut s| s.query_row([], |r| r.get::<_, i64>(0)))
+            .map(|c| c > 0)
+            .unwrap_or(false);
+        if !has_message_status {
+            conn.execute_batch("ALTER TABLE messages ADD COLUMN status TEXT NOT NULL DEFAULT 'complete';")?;
+        }
+
+        // Migration: add reasoning columns to messages and debate_rounds if missing
+        for table in ["messages", "debate_rounds"] {
+            let has_reasoning: bool = conn
+                .prepare(&format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name='reasoning'", table))
+                .and_then(|mut s| s.query_row([], |r| r.get::<_, i64>(0)))
+                .map(|c| c > 0)
+                .unwrap_or(false);
+            if !has_reasoning {
+                conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN reasoning TEXT;", table))?;
+            }
+        }
+
+        // Migration: turn linear histories into single-branch message trees
+        let has_parent_id: bool = conn
+            .prepare("SELECT COUNT(*) FROM pragma_table_info('messages') WHERE name='parent_id'")
+            .and_then(|mut s| s.query_row([], |r| r.get::<_, i64>(0)))
+            .map(|c| c > 0)
+            .unwrap_or(false);
+        if !has_parent_id {
+            conn.execute_batch("
+                ALTER TABLE messages ADD COLUMN parent_id TEXT;
+                ALTER TABLE conversations ADD COLUMN active_message_id TEXT;
+                UPDATE messages SET parent_id = (
+                    SELECT prev.id FROM messages prev
+                    WHERE prev.conversation_id = messages.conversation_id
+                      AND (prev.created_at < messages.created_at
+                           OR (prev.created_at = messages.created_at AND prev.rowid < messages.rowid))
+                    ORDER BY prev.created_at DESC, prev.rowid DESC LIMIT 1
+                );
+                UPDATE conversations SET active_message_id = (
+                    SELECT m.id FROM messages m WHERE m.conversation_id = conversations.id
+                    ORDER BY m.created_at DESC, m.rowid DESC LIMIT 1
+                );
+            ")?;
+        }
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_messages_parent ON messages(parent_id);")?;
+
         // Migration: add debate columns to decisions table if missing
         let has_debate_brief: bool = conn
             .prepare("SELECT COUNT(*) FROM pragma_table_info('decisions') WHERE name='debate_brief'")
@@ -230,39 +510,193 @@ impl Database {
     }
 
     pub fn add_message(&self, conversation_id: &str, role: &str, content: &str) -> Result<Message, rusqlite::Error> {
+        self.add_message_with_status(conversation_id, role, content, MESSAGE_STATUS_COMPLETE)
+    }
+
+    /// Append a message to the end of the active branch and make it the new tip.
+    pub fn add_message_with_status(&self, conversation_id: &str, role: &str, content: &str, status: &str) -> Result<Message, rusqlite::Error> {
+        self.add_message_with_id(&Uuid::new_v4().to_string(), conversation_id, role, content, status)
+    }
+
+    /// `add_message_with_status` for a message whose id was picked in advance.
+    pub fn add_message_with_id(&self, id: &str, conversation_id: &str, role: &str, content: &str, status: &str) -> Result<Message, rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
-        let id = Uuid::new_v4().to_string();
+        let id = id.to_string();
         let now = Utc::now().to_rfc3339();
+        let parent_id: Option<String> = conn
+            .query_row(
+                "SELECT active_message_id FROM conversations WHERE id = ?1",
+                params![conversation_id],
+                |row| row.get(0),
+            )
+            .or_else(|e| if e == rusqlite::Error::QueryReturnedNoRows { Ok(None) } else { Err(e) })?;
         conn.execute(
-            "INSERT INTO messages (id, conversation_id, role, content, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![id, conversation_id, role, content, now],
+            "INSERT INTO messages (id, conversation_id, role, content, created_at, status, parent_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![id, conversation_id, role, content, now, status, parent_id],
         )?;
         conn.execute(
-            "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
-            params![now, conversation_id],
+            "UPDATE conversations SET updated_at = ?1, active_message_id = ?2 WHERE id = ?3",
+            params![now, id, conversation_id],
         )?;
-        Ok(Message { id, conversation_id: conversation_id.to_string(), role: role.to_string(), content: content.to_string(), created_at: now })
+        Ok(Message {
+            id,
+            conversation_id: conversation_id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: now,
+            status: status.to_string(),
+            reasoning: None,
+            parent_id,
+        })
     }
 
+    pub fn set_message_reasoning(&self, message_id: &str, reasoning: &str) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE messages SET reasoning = ?1 WHERE id = ?2", params![reasoning, message_id])?;
+        Ok(())
+    }
+
+    /// Messages on the conversation's active branch, oldest first.
     pub fn get_messages(&self, conversation_id: &str) -> Result<Vec<Message>, rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, conversation_id, role, content, created_at FROM messages WHERE conversation_id = ?1 ORDER BY created_at ASC")?;
-        let rows = stmt.query_map(params![conversation_id], |row| {
-            Ok(Message {
-                id: row.get(0)?,
-                conversation_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                created_at: row.get(4)?,
-            })
-        })?;
+        let mut stmt = conn.prepare("
+            WITH RECURSIVE path(id, depth) AS (
+                SELECT active_message_id, 0 FROM conversations WHERE id = ?1
+                UNION ALL
+                SELECT m.parent_id, path.depth + 1 FROM path JOIN messages m ON m.id = path.id
+                WHERE m.parent_id IS NOT NULL AND path.depth < ?2
+            )
+            SELECT m.id, m.conversation_id, m.role, m.content, m.created_at, m.status, m.reasoning, m.parent_id
+            FROM path JOIN messages m ON m.id = path.id
+            ORDER BY path.depth DESC
+        ")?;
+        let rows = stmt.query_map(params![conversation_id, MAX_BRANCH_DEPTH], message_from_row)?;
+        rows.collect()
+    }
+
+    /// Every message of the conversation across all branches, oldest first.
+    pub fn get_message_tree(&self, conversation_id: &str) -> Result<Vec<Message>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, conversation_id, role, content, created_at, status, reasoning, parent_id FROM messages WHERE conversation_id = ?1 ORDER BY created_at ASC, rowid ASC")?;
+        let rows = stmt.query_map(params![conversation_id], message_from_row)?;
         rows.collect()
     }
 
+    pub fn get_message(&self, message_id: &str) -> Result<Option<Message>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, conversation_id, role, content, created_at, status, reasoning, parent_id FROM messages WHERE id = ?1")?;
+        let mut rows = stmt.query_map(params![message_id], message_from_row)?;
+        rows.next().transpose()
+    }
+
+    pub fn get_active_message_id(&self, conversation_id: &str) -> Result<Option<String>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT active_message_id FROM conversations WHERE id = ?1",
+            params![conversation_id],
+            |row| row.get(0),
+        )
+        .or_else(|e| if e == rusqlite::Error::QueryReturnedNoRows { Ok(None) } else { Err(e) })
+    }
+
+    /// Point the active branch at `message_id`; the next message added becomes its child.
+    /// `None` makes the next message a new root.
+    pub fn set_active_message(&self, conversation_id: &str, message_id: Option<&str>) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE conversations SET active_message_id = ?1 WHERE id = ?2",
+            params![message_id, conversation_id],
+        )?;
+        Ok(())
+    }
+
+    /// Make the branch through `message_id` active, following its newest
+    /// descendants down to a leaf.
+    pub fn switch_branch(&self, conversation_id: &str, message_id: &str) -> Result<(), rusqlite::Error> {
+        let all = self.get_message_tree(conversation_id)?;
+        let mut leaf = message_id.to_string();
+        while let Some(child) = all.iter().rev().find(|m| m.parent_id.as_deref() == Some(leaf.as_str())) {
+            leaf = child.id.clone();
+        }
+        self.set_active_message(conversation_id, Some(&leaf))
+    }
+
+    /// All root-to-leaf branches of a conversation, most recently updated first.
+    pub fn get_branches(&self, conversation_id: &str) -> Result<Vec<ConversationBranch>, rusqlite::Error> {
+        let active_tip = self.get_active_message_id(conversation_id)?;
+        let conn = self.conn.lock().unwrap();
+        // Every leaf, and the active tip, walked up to the root in one pass.
+        let mut stmt = conn.prepare("
+            WITH RECURSIVE
+            tips(tip_id) AS (
+                SELECT m.id FROM messages m
+                WHERE m.conversation_id = ?1 AND NOT EXISTS (SELECT 1 FROM messages c WHERE c.parent_id = m.id)
+                UNION
+                SELECT active_message_id FROM conversations WHERE id = ?1 AND active_message_id IS NOT NULL
+            ),
+            paths(tip_id, id, depth) AS (
+                SELECT tip_id, tip_id, 0 FROM tips
+                UNION ALL
+                SELECT paths.tip_id, m.parent_id, paths.depth + 1 FROM paths JOIN messages m ON m.id = paths.id
+                WHERE m.parent_id IS NOT NULL AND paths.depth < ?2
+            )
+            SELECT paths.tip_id, EXISTS (SELECT 1 FROM messages c WHERE c.parent_id = paths.tip_id),
+                   m.id, m.role, m.content, m.created_at
+            FROM paths JOIN messages m ON m.id = paths.id
+            ORDER BY paths.tip_id, paths.depth DESC
+        ")?;
+        let rows = stmt.query_map(params![conversation_id, MAX_BRANCH_DEPTH], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?, BranchStep {
+                id: row.get(2)?,
+                role: row.get(3)?,
+                content: row.get(4)?,
+                created_at: row.get(5)?,
+            }))
+        })?;
+
+        // Rows arrive grouped by tip, each path root first.
+        let mut paths: Vec<(String, bool, Vec<BranchStep>)> = Vec::new();
+        for row in rows {
+            let (tip_id, has_children, step) = row?;
+            match paths.last_mut() {
+                Some((id, _, path)) if *id == tip_id => path.push(step),
+                _ => paths.push((tip_id, has_children, vec![step])),
+            }
+        }
+        let active_path: Vec<&str> = paths
+            .iter()
+            .find(|(tip_id, _, _)| Some(tip_id) == active_tip.as_ref())
+            .map(|(_, _, path)| path.iter().map(|step| step.id.as_str()).collect())
+            .unwrap_or_default();
+
+        let mut branches: Vec<ConversationBranch> = paths
+            .iter()
+            .filter(|(_, has_children, _)| !has_children)
+            .map(|(tip_id, _, path)| {
+                let is_active = active_path.last() == Some(&tip_id.as_str());
+                let shared = path.iter().zip(&active_path).take_while(|(step, id)| step.id == **id).count();
+                ConversationBranch {
+                    leaf_message_id: tip_id.clone(),
+                    message_count: path.len(),
+                    last_user_message: path.iter().rev().find(|step| step.role == "user").map(|step| step.content.clone()),
+                    forked_from: if is_active || shared == 0 { None } else { Some(path[shared - 1].id.clone()) },
+                    is_active,
+                    updated_at: path.last().map(|step| step.created_at.clone()).unwrap_or_default(),
+                }
+            })
+            .collect();
+        branches.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(branches)
+    }
+
     pub fn delete_conversation(&self, conversation_id: &str) -> Result<(), rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM debate_audio WHERE decision_id IN (SELECT id FROM decisions WHERE conversation_id = ?1)", params![conversation_id])?;
         conn.execute("DELETE FROM debate_rounds WHERE decision_id IN (SELECT id FROM decisions WHERE conversation_id = ?1)", params![conversation_id])?;
+        conn.execute("DELETE FROM moderator_verdicts WHERE decision_id IN (SELECT id FROM decisions WHERE conversation_id = ?1)", params![conversation_id])?;
+        conn.execute("DELETE FROM attachments WHERE decision_id IN (SELECT id FROM decisions WHERE conversation_id = ?1)", params![conversation_id])?;
+        conn.execute("DELETE FROM conversation_summaries WHERE conversation_id = ?1", params![conversation_id])?;
+        conn.execute("DELETE FROM tool_calls WHERE conversation_id = ?1", params![conversation_id])?;
         conn.execute("DELETE FROM messages WHERE conversation_id = ?1", params![conversation_id])?;
         conn.execute("DELETE FROM decisions WHERE conversation_id = ?1", params![conversation_id])?;
         conn.execute("DELETE FROM conversations WHERE id = ?1", params![conversation_id])?;
@@ -472,13 +906,20 @@ impl Database {
             agent: agent.to_string(),
             content: content.to_string(),
             created_at: now,
+            reasoning: None,
         })
     }
 
+    pub fn set_debate_round_reasoning(&self, round_id: &str, reasoning: &str) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE debate_rounds SET reasoning = ?1 WHERE id = ?2", params![reasoning, round_id])?;
+        Ok(())
+    }
+
     pub fn get_debate_rounds(&self, decision_id: &str) -> Result<Vec<DebateRound>, rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, decision_id, round_number, exchange_number, agent, content, created_at FROM debate_rounds WHERE decision_id = ?1 ORDER BY round_number ASC, exchange_number ASC, created_at ASC"
+            "SELECT id, decision_id, round_number, exchange_number, agent, content, created_at, reasoning FROM debate_rounds WHERE decision_id = ?1 ORDER BY round_number ASC, exchange_number ASC, created_at ASC"
         )?;
         let rows = stmt.query_map(params![decision_id], |row| {
             Ok(DebateRound {
@@ -489,6 +930,7 @@ impl Database {
                 agent: row.get(4)?,
                 content: row.get(5)?,
                 created_at: row.get(6)?,
+                reasoning: row.get(7)?,
             })
         })?;
         rows.collect()
@@ -497,6 +939,99 @@ impl Database {
     pub fn delete_debate_rounds(&self, decision_id: &str) -> Result<(), rusqlite::Error> {
         let conn = self.conn.lock().unwrap();
         conn.execute("DELETE FROM debate_rounds WHERE decision_id = ?1", params![decision_id])?;
+        conn.execute("DELETE FROM moderator_verdicts WHERE decision_id = ?1", params![decision_id])?;
+        Ok(())
+    }
+
+    pub fn save_moderator_verdict(&self, decision_id: &str, verdict_json: &str, source: &str) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO moderator_verdicts (decision_id, verdict_json, source, created_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(decision_id) DO UPDATE SET verdict_json = ?2, source = ?3, created_at = ?4",
+            params![decision_id, verdict_json, source, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_moderator_verdict(&self, decision_id: &str) -> Result<Option<StoredVerdict>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT decision_id, verdict_json, source, created_at FROM moderator_verdicts WHERE decision_id = ?1"
+        )?;
+        let mut rows = stmt.query_map(params![decision_id], |row| {
+            Ok(StoredVerdict {
+                decision_id: row.get(0)?,
+                verdict_json: row.get(1)?,
+                source: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?;
+        match rows.next() {
+            Some(row) => Ok(Some(row?)),
+            None => Ok(None),
+        }
+    }
+
+    // ── Attachment methods ──
+
+    pub fn save_attachment(
+        &self,
+        decision_id: &str,
+        filename: &str,
+        kind: &str,
+        size_bytes: i64,
+        stored_path: &str,
+        extracted_text: &str,
+    ) -> Result<Attachment, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO attachments (id, decision_id, filename, kind, size_bytes, stored_path, extracted_text, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![id, decision_id, filename, kind, size_bytes, stored_path, extracted_text, now],
+        )?;
+        Ok(Attachment {
+            id,
+            decision_id: decision_id.to_string(),
+            filename: filename.to_string(),
+            kind: kind.to_string(),
+            size_bytes,
+            stored_path: stored_path.to_string(),
+            text_chars: extracted_text.chars().count() as i64,
+            created_at: now,
+        })
+    }
+
+    pub fn get_attachments(&self, decision_id: &str) -> Result<Vec<Attachment>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, decision_id, filename, kind, size_bytes, stored_path, length(extracted_text), created_at FROM attachments WHERE decision_id = ?1 ORDER BY created_at ASC"
+        )?;
+        let rows = stmt.query_map(params![decision_id], attachment_from_row)?;
+        rows.collect()
+    }
+
+    pub fn get_attachment(&self, attachment_id: &str) -> Result<Option<Attachment>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, decision_id, filename, kind, size_bytes, stored_path, length(extracted_text), created_at FROM attachments WHERE id = ?1"
+        )?;
+        let mut rows = stmt.query_map(params![attachment_id], attachment_from_row)?;
+        rows.next().transpose()
+    }
+
+    pub fn get_attachment_text(&self, attachment_id: &str) -> Result<Option<String>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT extracted_text FROM attachments WHERE id = ?1")?;
+        let mut rows = stmt.query_map(params![attachment_id], |row| row.get(0))?;
+        rows.next().transpose()
+    }
+
+    pub fn delete_attachment(&self, attachment_id: &str) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM attachments WHERE id = ?1", params![attachment_id])?;
         Ok(())
     }
 
@@ -594,6 +1129,304 @@ impl Database {
         conn.execute("DELETE FROM debate_audio WHERE decision_id = ?1", params![decision_id])?;
         Ok(())
     }
+
+    // ── Conversation summary methods ──
+
+    pub fn get_conversation_summary(&self, conversation_id: &str) -> Result<Option<ConversationSummary>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT conversation_id, summary, summarized_count, last_message_id, updated_at FROM conversation_summaries WHERE conversation_id = ?1"
+        )?;
+        let mut rows = stmt.query_map(params![conversation_id], |row| {
+            Ok(ConversationSummary {
+                conversation_id: row.get(0)?,
+                summary: row.get(1)?,
+                summarized_count: row.get(2)?,
+                last_message_id: row.get(3)?,
+                updated_at: row.get(4)?,
+            })
+        })?;
+        match rows.next() {
+            Some(row) => Ok(Some(row?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save_conversation_summary(
+        &self,
+        conversation_id: &str,
+        summary: &str,
+        summarized_count: i64,
+        last_message_id: &str,
+    ) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO conversation_summaries (conversation_id, summary, summarized_count, last_message_id, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(conversation_id) DO UPDATE SET summary = ?2, summarized_count = ?3, last_message_id = ?4, updated_at = ?5",
+            params![conversation_id, summary, summarized_count, last_message_id, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_conversation_summary(&self, conversation_id: &str) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM conversation_summaries WHERE conversation_id = ?1", params![conversation_id])?;
+        Ok(())
+    }
+
+    // ── LLM usage methods ──
+
+    pub fn record_llm_usage(&self, usage: &NewLlmUsage) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO llm_usage (id, purpose, conversation_id, message_id, decision_id, round_number, exchange_number, agent, provider, model, prompt_tokens, completion_tokens, cost_usd, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                id,
+                usage.purpose,
+                usage.conversation_id,
+                usage.message_id,
+                usage.decision_id,
+                usage.round_number,
+                usage.exchange_number,
+                usage.agent,
+                usage.provider,
+                usage.model,
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.cost_usd,
+                now,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // ── Pending profile change methods ──
+
+    pub fn create_pending_profile_change(&self, change: &NewPendingProfileChange) -> Result<PendingProfileChange, rusqlite::Error> {
+        let id = Uuid::new_v4().to_string();
+        {
+            let conn = self.conn.lock().unwrap();
+            let files = serde_json::to_string(change.files).unwrap_or_else(|_| "[]".to_string());
+            conn.execute(
+                "INSERT INTO pending_profile_changes (id, conversation_id, decision_id, tool_name, edit, diff, files, base_hashes, status, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'pending', ?9)",
+                params![id, change.conversation_id, change.decision_id, change.tool_name, change.edit, change.diff, files, change.base_hashes, Utc::now().to_rfc3339()],
+            )?;
+        }
+        self.get_pending_profile_change(&id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn get_pending_profile_change(&self, id: &str) -> Result<Option<PendingProfileChange>, rusqlite::Error> {
+        Ok(self.query_pending_profile_changes("WHERE id = ?1", params![id])?.into_iter().next())
+    }
+
+    /// Changes with the given status, oldest first.
+    pub fn get_pending_profile_changes(&self, status: &str) -> Result<Vec<PendingProfileChange>, rusqlite::Error> {
+        self.query_pending_profile_changes("WHERE status = ?1", params![status])
+    }
+
+    /// Mark a pending change approved or rejected. Returns false if it wasn't pending.
+    pub fn resolve_pending_profile_change(&self, id: &str, status: &str, result: Option<&str>) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE pending_profile_changes SET status = ?1, result = ?2, resolved_at = ?3 WHERE id = ?4 AND status = 'pending'",
+            params![status, result, Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Replace the diff and base hashes of a still-pending change after its files moved on.
+    pub fn refresh_pending_profile_change(&self, id: &str, diff: &str, base_hashes: &str) -> Result<bool, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE pending_profile_changes SET diff = ?1, base_hashes = ?2 WHERE id = ?3 AND status = 'pending'",
+            params![diff, base_hashes, id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    fn query_pending_profile_changes(&self, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<PendingProfileChange>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, conversation_id, decision_id, tool_name, edit, diff, files, base_hashes, status, result, created_at, resolved_at FROM pending_profile_changes {} ORDER BY created_at ASC, rowid ASC",
+            filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            let edit: String = row.get(4)?;
+            let diff: String = row.get(5)?;
+            let files: String = row.get(6)?;
+            let base_hashes: String = row.get(7)?;
+            Ok(PendingProfileChange {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                decision_id: row.get(2)?,
+                tool_name: row.get(3)?,
+                edit: serde_json::from_str(&edit).unwrap_or_default(),
+                diff: serde_json::from_str(&diff).unwrap_or_default(),
+                files: serde_json::from_str(&files).unwrap_or_default(),
+                base_hashes: serde_json::from_str(&base_hashes).unwrap_or_default(),
+                status: row.get(8)?,
+                result: row.get(9)?,
+                created_at: row.get(10)?,
+                resolved_at: row.get(11)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    // ── Tool call audit methods ──
+
+    pub fn record_tool_call(&self, call: &NewToolCall) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        let files = serde_json::to_string(call.files).unwrap_or_else(|_| "[]".to_string());
+        conn.execute(
+            "INSERT INTO tool_calls (id, conversation_id, message_id, decision_id, tool_name, arguments, result, success, files, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![id, call.conversation_id, call.message_id, call.decision_id, call.tool_name, call.arguments, call.result, call.success, files, now],
+        )?;
+        Ok(())
+    }
+
+    /// Drop the link from tool calls to a reply that was never saved (the turn failed).
+    pub fn unlink_tool_calls_from_message(&self, message_id: &str) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE tool_calls SET message_id = NULL WHERE message_id = ?1", params![message_id])?;
+        Ok(())
+    }
+
+    /// Tool calls of one conversation, oldest first.
+    pub fn get_tool_calls_for_conversation(&self, conversation_id: &str) -> Result<Vec<ToolCallRecord>, rusqlite::Error> {
+        self.query_tool_calls("WHERE conversation_id = ?1", params![conversation_id])
+    }
+
+    /// Tool calls that read or changed a profile file, oldest first.
+    pub fn get_tool_calls_for_file(&self, filename: &str) -> Result<Vec<ToolCallRecord>, rusqlite::Error> {
+        self.query_tool_calls(
+            "WHERE EXISTS (SELECT 1 FROM json_each(tool_calls.files) WHERE json_each.value = ?1)",
+            params![filename],
+        )
+    }
+
+    fn query_tool_calls(&self, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<ToolCallRecord>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, conversation_id, message_id, decision_id, tool_name, arguments, result, success, files, created_at FROM tool_calls {} ORDER BY created_at ASC, rowid ASC",
+            filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            let files: String = row.get(8)?;
+            Ok(ToolCallRecord {
+                id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                message_id: row.get(2)?,
+                decision_id: row.get(3)?,
+                tool_name: row.get(4)?,
+                arguments: row.get(5)?,
+                result: row.get(6)?,
+                success: row.get(7)?,
+                files: serde_json::from_str(&files).unwrap_or_default(),
+                created_at: row.get(9)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Detach usage rows from a reply that was discarded; the calls still count, for no message.
+    pub fn unlink_usage_from_message(&self, message_id: &str) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE llm_usage SET message_id = NULL WHERE message_id = ?1", params![message_id])?;
+        Ok(())
+    }
+
+    fn query_usage_totals(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<UsageTotals>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(UsageTotals {
+                key: row.get(0)?,
+                calls: row.get(1)?,
+                prompt_tokens: row.get(2)?,
+                completion_tokens: row.get(3)?,
+                cost_usd: row.get(4)?,
+                unpriced_calls: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Debate spend for a decision, grouped by agent (turns and moderator steering).
+    pub fn get_debate_usage_by_agent(&self, decision_id: &str) -> Result<Vec<UsageTotals>, rusqlite::Error> {
+        self.query_usage_totals(
+            "SELECT COALESCE(agent, ''), COUNT(*), SUM(prompt_tokens), SUM(completion_tokens), COALESCE(SUM(cost_usd), 0.0), SUM(cost_usd IS NULL)
+             FROM llm_usage
+             WHERE decision_id = ?1 AND purpose IN ('debate_turn', 'debate_steering')
+             GROUP BY agent ORDER BY agent ASC",
+            &[&decision_id],
+        )
+    }
+
+    /// Total priced spend since an RFC 3339 timestamp, optionally for a single decision.
+    pub fn get_usage_cost_since(&self, decision_id: Option<&str>, since: &str) -> Result<f64, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COALESCE(SUM(cost_usd), 0.0) FROM llm_usage WHERE created_at >= ?1 AND (?2 IS NULL OR decision_id = ?2)",
+            params![since, decision_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Spend per model for a calendar month given as `YYYY-MM` (UTC).
+    pub fn get_monthly_usage_by_model(&self, month: &str) -> Result<Vec<UsageTotals>, rusqlite::Error> {
+        let prefix = format!("{}%", month);
+        self.query_usage_totals(
+            "SELECT model, COUNT(*), SUM(prompt_tokens), SUM(completion_tokens), COALESCE(SUM(cost_usd), 0.0), SUM(cost_usd IS NULL)
+             FROM llm_usage
+             WHERE created_at LIKE ?1
+             GROUP BY model ORDER BY COALESCE(SUM(cost_usd), 0.0) DESC, model ASC",
+            &[&prefix],
+        )
+    }
+}
+
+fn message_from_row(row: &rusqlite::Row) -> Result<Message, rusqlite::Error> {
+    Ok(Message {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        created_at: row.get(4)?,
+        status: row.get(5)?,
+        reasoning: row.get(6)?,
+        parent_id: row.get(7)?,
+    })
+}
+
+fn attachment_from_row(row: &rusqlite::Row) -> Result<Attachment, rusqlite::Error> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        decision_id: row.get(1)?,
+        filename: row.get(2)?,
+        kind: row.get(3)?,
+        size_bytes: row.get(4)?,
+        stored_path: row.get(5)?,
+        text_chars: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+/// Parent pointers followed at most this far; a cycle would otherwise recurse forever.
+const MAX_BRANCH_DEPTH: i64 = 100_000;
+
+/// One message on a branch, as `get_branches` needs it.
+struct BranchStep {
+    id: String,
+    role: String,
+    content: String,
+    created_at: String,
 }
 
 #[cfg(test)]
@@ -626,6 +1459,82 @@ mod tests {
         assert_eq!(messages[1].role, "assistant");
     }
 
+    #[test]
+    fn integration_cancelled_assistant_message_keeps_its_status() {
+        let db = new_test_db();
+        let conv = db.create_conversation("Stopped reply").unwrap();
+        db.add_message(&conv.id, "user", "Tell me everything").unwrap();
+        db.add_message_with_status(&conv.id, "assistant", "Well, first", MESSAGE_STATUS_CANCELLED).unwrap();
+
+        let messages = db.get_messages(&conv.id).unwrap();
+        assert_eq!(messages[0].status, MESSAGE_STATUS_COMPLETE);
+        assert_eq!(messages[1].status, MESSAGE_STATUS_CANCELLED);
+        assert_eq!(messages[1].content, "Well, first");
+    }
+
+    #[test]
+    fn integration_edits_and_regenerations_branch_and_history_follows_the_active_branch() {
+        let db = new_test_db();
+        let conv = db.create_conversation("Job offer").unwrap();
+        let question = db.add_message(&conv.id, "user", "Should I take the offer?").unwrap();
+        let first_reply = db.add_message(&conv.id, "assistant", "What does it pay?").unwrap();
+        assert_eq!(first_reply.parent_id.as_deref(), Some(question.id.as_str()));
+        let follow_up = db.add_message(&conv.id, "user", "20% more").unwrap();
+        db.add_message(&conv.id, "assistant", "Then yes").unwrap();
+
+        // Regenerate the first reply: back up to the question and answer again.
+        db.set_active_message(&conv.id, Some(&question.id)).unwrap();
+        let second_reply = db.add_message(&conv.id, "assistant", "How long is the commute?").unwrap();
+        let contents: Vec<String> = db.get_messages(&conv.id).unwrap().into_iter().map(|m| m.content).collect();
+        assert_eq!(contents, vec!["Should I take the offer?", "How long is the commute?"]);
+
+        // Edit the follow-up on the original branch: a sibling of "20% more".
+        db.switch_branch(&conv.id, &first_reply.id).unwrap();
+        assert_eq!(db.get_messages(&conv.id).unwrap().len(), 4, "switching follows the branch to its leaf");
+        db.set_active_message(&conv.id, follow_up.parent_id.as_deref()).unwrap();
+        let edited = db.add_message(&conv.id, "user", "10% less").unwrap();
+        assert_eq!(edited.parent_id.as_deref(), Some(first_reply.id.as_str()));
+
+        let branches = db.get_branches(&conv.id).unwrap();
+        assert_eq!(branches.len(), 3);
+        let active = branches.iter().find(|b| b.is_active).expect("one branch should be active");
+        assert_eq!(active.leaf_message_id, edited.id);
+        assert_eq!(active.forked_from, None);
+        let regenerated = branches.iter().find(|b| b.leaf_message_id == second_reply.id).unwrap();
+        assert_eq!(regenerated.message_count, 2);
+        assert_eq!(regenerated.forked_from.as_deref(), Some(question.id.as_str()));
+        let original = branches.iter().find(|b| b.message_count == 4).unwrap();
+        assert_eq!(original.forked_from.as_deref(), Some(first_reply.id.as_str()));
+        assert_eq!(original.last_user_message.as_deref(), Some("20% more"));
+
+        assert_eq!(db.get_message_tree(&conv.id).unwrap().len(), 6);
+        assert_eq!(db.get_message(&edited.id).unwrap().unwrap().content, "10% less");
+    }
+
+    #[test]
+    fn integration_migration_links_legacy_messages_into_a_single_branch() {
+        let dir = tempfile::tempdir().expect("temp directory should exist");
+        let path = dir.path().join("legacy.sqlite");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch("
+                CREATE TABLE conversations (id TEXT PRIMARY KEY, title TEXT NOT NULL, type TEXT NOT NULL DEFAULT 'chat', created_at TEXT NOT NULL, updated_at TEXT NOT NULL);
+                CREATE TABLE messages (id TEXT PRIMARY KEY, conversation_id TEXT NOT NULL, role TEXT NOT NULL, content TEXT NOT NULL, created_at TEXT NOT NULL);
+                INSERT INTO conversations VALUES ('c1', 'Old chat', 'chat', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');
+                INSERT INTO messages VALUES ('m1', 'c1', 'user', 'Hi', '2024-01-01T00:00:01Z');
+                INSERT INTO messages VALUES ('m2', 'c1', 'assistant', 'Hello', '2024-01-01T00:00:02Z');
+                INSERT INTO messages VALUES ('m3', 'c1', 'user', 'Help me decide', '2024-01-01T00:00:03Z');
+            ").unwrap();
+        }
+
+        let db = Database::new(path.to_str().unwrap()).expect("legacy database should migrate");
+        let messages = db.get_messages("c1").unwrap();
+        let parents: Vec<Option<&str>> = messages.iter().map(|m| m.parent_id.as_deref()).collect();
+        assert_eq!(parents, vec![None, Some("m1"), Some("m2")]);
+        let reply = db.add_message("c1", "assistant", "Sure").unwrap();
+        assert_eq!(reply.parent_id.as_deref(), Some("m3"));
+    }
+
     #[test]
     fn integration_delete_conversation_removes_messages_decision_and_debate_rounds() {
         let db = new_test_db();
@@ -790,4 +1699,127 @@ mod tests {
         assert_eq!(stored_audio_dir, audio_dir);
         assert!(chrono::DateTime::parse_from_rfc3339(&generated_at).is_ok());
     }
+
+    #[test]
+    fn integration_tool_calls_are_browsable_by_conversation_and_file() {
+        let db = new_test_db();
+        let conv = db.create_conversation("Values").unwrap();
+        let call = |tool_name, result, success, files: &[String]| {
+            db.record_tool_call(&NewToolCall {
+                conversation_id: Some(&conv.id),
+                decision_id: None,
+                message_id: Some("reply-1"),
+                tool_name,
+                arguments: "{}",
+                result,
+                success,
+                files,
+            })
+            .unwrap()
+        };
+        let values = vec!["values.md".to_string()];
+        let rename = vec!["goals.md".to_string(), "values.md".to_string()];
+
+        call("write_profile_file", "Updated values.md", true, &values);
+        call("rename_profile_file", "Refusing to overwrite values.md", false, &rename);
+        call("update_decision_status", "Status updated", true, &[]);
+        let reply = db.add_message_with_id("reply-1", &conv.id, "assistant", "Done.", MESSAGE_STATUS_COMPLETE).unwrap();
+        assert_eq!(reply.id, "reply-1");
+
+        let in_conversation = db.get_tool_calls_for_conversation(&conv.id).unwrap();
+        assert_eq!(in_conversation.len(), 3);
+        assert!(in_conversation.iter().all(|c| c.message_id.as_deref() == Some("reply-1")));
+        db.unlink_tool_calls_from_message("reply-1").unwrap();
+        assert!(db.get_tool_calls_for_conversation(&conv.id).unwrap().iter().all(|c| c.message_id.is_none()));
+
+        let touching_values = db.get_tool_calls_for_file("values.md").unwrap();
+        assert_eq!(touching_values.len(), 2);
+        assert!(touching_values.iter().any(|c| c.tool_name == "rename_profile_file" && !c.success));
+        assert_eq!(db.get_tool_calls_for_file("goals.md").unwrap().len(), 1);
+        assert!(db.get_tool_calls_for_file("career.md").unwrap().is_empty());
+
+        db.delete_conversation(&conv.id).unwrap();
+        assert!(db.get_tool_calls_for_file("values.md").unwrap().is_empty());
+    }
+
+    #[test]
+    fn integration_llm_usage_aggregates_by_debate_agent_and_month() {
+        let db = new_test_db();
+        let usage = |purpose, decision_id, agent, model, cost| NewLlmUsage {
+            purpose,
+            conversation_id: None,
+            message_id: None,
+            decision_id,
+            round_number: Some(1),
+            exchange_number: Some(1),
+            agent,
+            provider: "openrouter",
+            model,
+            prompt_tokens: 1000,
+            completion_tokens: 200,
+            cost_usd: cost,
+        };
+
+        db.record_llm_usage(&usage("debate_turn", Some("d1"), Some("rationalist"), "m/a", Some(0.01))).unwrap();
+        db.record_llm_usage(&usage("debate_turn", Some("d1"), Some("rationalist"), "m/a", Some(0.02))).unwrap();
+        db.record_llm_usage(&usage("debate_steering", Some("d1"), Some("moderator"), "m/b", None)).unwrap();
+        db.record_llm_usage(&usage("chat", Some("d1"), None, "m/b", Some(0.5))).unwrap();
+        db.record_llm_usage(&usage("debate_turn", Some("d2"), Some("rationalist"), "m/a", Some(1.0))).unwrap();
+
+        let by_agent = db.get_debate_usage_by_agent("d1").expect("debate usage should load");
+        assert_eq!(by_agent.len(), 2);
+        assert_eq!(by_agent[0].key, "moderator");
+        assert_eq!(by_agent[0].unpriced_calls, 1);
+        assert_eq!(by_agent[1].key, "rationalist");
+        assert_eq!(by_agent[1].calls, 2);
+        assert_eq!(by_agent[1].prompt_tokens, 2000);
+        assert!((by_agent[1].cost_usd - 0.03).abs() < 1e-9);
+
+        let month = Utc::now().format("%Y-%m").to_string();
+        let by_model = db.get_monthly_usage_by_model(&month).expect("monthly usage should load");
+        assert_eq!(by_model[0].key, "m/a");
+        assert!((by_model[0].cost_usd - 1.03).abs() < 1e-9);
+        assert_eq!(by_model[1].calls, 2);
+        assert!(db.get_monthly_usage_by_model("1999-01").unwrap().is_empty());
+    }
+    #[test]
+    fn integration_usage_rows_keep_their_own_reply() {
+        let db = new_test_db();
+        let conv = db.create_conversation("Usage").unwrap();
+        let usage = |purpose, message_id| NewLlmUsage {
+            purpose,
+            conversation_id: Some(&conv.id),
+            message_id,
+            decision_id: None,
+            round_number: None,
+            exchange_number: None,
+            agent: None,
+            provider: "openrouter",
+            model: "m/a",
+            prompt_tokens: 100,
+            completion_tokens: 20,
+            cost_usd: Some(0.001),
+        };
+        db.record_llm_usage(&usage("context_summary", None)).unwrap();
+        db.record_llm_usage(&usage("chat", Some("reply-1"))).unwrap();
+        db.record_llm_usage(&usage("chat", Some("reply-2"))).unwrap();
+        db.unlink_usage_from_message("reply-2").unwrap();
+
+        let conn = db.conn.lock().unwrap();
+        let linked: Vec<(String, Option<String>)> = conn
+            .prepare("SELECT purpose, message_id FROM llm_usage ORDER BY purpose, message_id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            linked,
+            vec![
+                ("chat".to_string(), None),
+                ("chat".to_string(), Some("reply-1".to_string())),
+                ("context_summary".to_string(), None),
+            ]
+        );
+    }
 }
@@ -1,16 +1,26 @@
-use crate::agents::{self, AgentInfo};
-use crate::commands::AppState;
+use crate::agents::{self, AgentInfo, GenerationParams};
+use crate::attachments;
+use crate::budget;
+use crate::commands::{self, AppState};
 use crate::config;
+use crate::context;
 use crate::decisions;
-use crate::llm;
+use crate::llm::{self, DebateReply, LlmError};
 use crate::profile;
+use crate::profile_index;
+use crate::providers::{self, LlmEndpoint};
+use crate::retry::{self, RetryPolicy};
 use crate::tts;
+use crate::usage::{self, UsageContext};
+use crate::verdict::{self, ModeratorVerdict, VerdictSource};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use tauri::{Emitter, Manager};
+use tauri::{Emitter, Manager, Runtime};
 
 const STANDALONE_MODE_FIXED: &str = "fixed";
 const STANDALONE_MODE_MODERATOR_AUTO: &str = "moderator_auto";
@@ -24,6 +34,17 @@ pub struct StandaloneDebateConfig {
     pub max_exchanges: Option<u32>,
 }
 
+/// Who debates and on what. Committee debates set at most `selected_agent_keys`;
+/// standalone debates bring their own brief, participants, model ids and exchanges.
+#[derive(Debug, Clone, Default)]
+pub struct DebateSetup {
+    pub selected_agent_keys: Option<Vec<String>>,
+    pub brief_override: Option<String>,
+    pub standalone_participants: Option<Vec<AgentInfo>>,
+    pub standalone_model_map: Option<HashMap<String, String>>,
+    pub standalone_config: Option<StandaloneDebateConfig>,
+}
+
 /// Normalize model output so spoken debate feels conversational in UI + TTS.
 fn normalize_spoken_debate_output(text: &str) -> String {
     let labels = [
@@ -120,9 +141,9 @@ struct LiveTtsState {
 }
 
 /// Spawn a TTS generation task for a single debate round segment.
-fn spawn_segment_tts(
+fn spawn_segment_tts<R: Runtime>(
     tts_state: &LiveTtsState,
-    app_handle: &tauri::AppHandle,
+    app_handle: &tauri::AppHandle<R>,
     decision_id: &str,
     round: &crate::db::DebateRound,
 ) {
@@ -174,40 +195,68 @@ fn spawn_segment_tts(
 }
 
 /// Build the decision brief from profile files + decision data + conversation messages.
-fn compile_brief(
-    app_handle: &tauri::AppHandle,
+/// The conversation is trimmed to fit the debaters' windows; with `summarize_overflow`
+/// older turns are folded into the rolling summary first (a model call), otherwise the
+/// stored summary is used as is.
+async fn compile_brief<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
     decision_id: &str,
+    summarize_overflow: bool,
 ) -> Result<String, String> {
-    let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
-    let state_guard = state.lock().map_err(|e| e.to_string())?;
-
-    let decision = state_guard.db
-        .get_decision(decision_id)
-        .map_err(|e| e.to_string())?
-        .ok_or("Decision not found")?;
+    let (decision, profiles, extra_sections, llm_config, stored_window, app_data_dir) = {
+        let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+        let state_guard = state.lock().map_err(|e| e.to_string())?;
 
-    // Read profile files
-    let profiles = profile::read_all_profiles(&state_guard.app_data_dir)
-        .unwrap_or_default();
+        let decision = state_guard.db
+            .get_decision(decision_id)
+            .map_err(|e| e.to_string())?
+            .ok_or("Decision not found")?;
+
+        // Read profile files
+        let profiles = profile::read_all_profiles(&state_guard.app_data_dir)
+            .unwrap_or_default();
+        let extra_sections: Vec<String> = [
+            attachments::brief_section(&state_guard.db, decision_id)?,
+            decisions::related_decisions_section(&state_guard.db, &decision)?,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let llm_config = config::load_config(&state_guard.app_data_dir);
+        let stored_window = if summarize_overflow {
+            None
+        } else {
+            let budget_tokens = context::brief_conversation_budget(&llm_config);
+            Some(context::peek_window(&state_guard.db, &decision.conversation_id, budget_tokens)?)
+        };
+        (decision, profiles, extra_sections, llm_config, stored_window, state_guard.app_data_dir.clone())
+    };
+    // Only the profile sections that bear on this decision
     let profile_text = if profiles.is_empty() {
         "No profile information available.".to_string()
     } else {
-        profiles
-            .iter()
-            .map(|(name, content)| format!("### {}\n{}", name, content))
-            .collect::<Vec<_>>()
-            .join("\n\n")
+        let query = profile_index::decision_query(&decision);
+        let retrieval = profile_index::retrieve(&profiles, &query, profile_index::TOP_K_SECTIONS, &llm_config).await;
+        profile_index::format_sections(&retrieval)
     };
 
-    // Get conversation messages for context
-    let messages = state_guard.db
-        .get_messages(&decision.conversation_id)
-        .map_err(|e| e.to_string())?;
-    let conversation_summary = messages
-        .iter()
-        .map(|m| format!("{}: {}", if m.role == "user" { "User" } else { "AI" }, m.content))
-        .collect::<Vec<_>>()
-        .join("\n\n");
+    // Conversation context, trimmed to the smallest debater window
+    let window = match stored_window {
+        Some(window) => window,
+        None => {
+            let endpoint = providers::default_endpoint(&llm_config)?;
+            context::prefetch_context_length(&endpoint, &llm_config.model, &app_data_dir).await;
+            context::prepare_window(
+                app_handle,
+                &endpoint,
+                &llm_config.model,
+                &decision.conversation_id,
+                Some(decision_id),
+                context::brief_conversation_budget(&llm_config),
+            ).await?
+        }
+    };
+    let conversation_summary = window.to_transcript();
 
     // Parse summary
     let summary_text = if let Some(ref sj) = decision.summary_json {
@@ -268,7 +317,7 @@ fn compile_brief(
         title = decision.title,
     );
 
-    Ok(brief)
+    Ok(std::iter::once(brief).chain(extra_sections).collect::<Vec<_>>().join("\n\n"))
 }
 
 fn standalone_debater_system_prompt(agent_label: &str) -> String {
@@ -402,19 +451,26 @@ fn parse_json_object(raw: &str) -> Option<Value> {
     serde_json::from_str::<Value>(&raw[start..=end]).ok()
 }
 
-async fn request_moderator_direction(
-    api_key: &str,
-    moderator_model: &str,
+/// Ask the moderator where the next exchange should go, and whether to wrap up.
+/// Walks the moderator's fallback chain like the verdict does.
+async fn request_moderator_direction<R: Runtime>(
+    chain: &[(LlmEndpoint, String)],
+    params: &GenerationParams,
     brief: &str,
     transcript: &str,
     participants: &str,
-    exchange_number: i32,
+    turn: DebateTurn<'_>,
+    app_handle: &tauri::AppHandle<R>,
 ) -> Result<(String, bool), String> {
-    let raw = llm::call_llm_simple(
-        api_key,
-        moderator_model,
-        standalone_moderator_steering_system_prompt(),
-        &standalone_moderator_steering_prompt(brief, transcript, participants, exchange_number),
+    let user_prompt = standalone_moderator_steering_prompt(brief, transcript, participants, turn.exchange_number);
+    let usage_ctx = UsageContext::debate_steering(turn.decision_id, turn.exchange_number);
+    let raw = with_model_fallbacks(
+        chain, turn, &retry::DEBATE_RETRY_POLICY, app_handle,
+        |endpoint, model| {
+            let call = llm::LlmCall { endpoint, model, params, usage_ctx: &usage_ctx };
+            let user_prompt = &user_prompt;
+            async move { llm::call_llm_simple(&call, standalone_moderator_steering_system_prompt(), user_prompt, app_handle).await }
+        },
     )
     .await?;
 
@@ -474,55 +530,181 @@ fn format_transcript(rounds: &[crate::db::DebateRound], all_agents: &[AgentInfo]
     sections.join("\n\n")
 }
 
-/// Call a single agent with retry logic, streaming tokens to frontend.
-async fn call_agent_with_retry(
-    api_key: &str,
-    model: &str,
-    agent_key: &str,
-    agent_label: &str,
-    system_prompt: &str,
-    user_prompt: &str,
-    max_retries: u32,
-    app_handle: &tauri::AppHandle,
-    decision_id: &str,
+/// Whose turn a debate call is for, as named in `debate-agent-*` events.
+#[derive(Clone, Copy)]
+struct DebateTurn<'a> {
+    decision_id: &'a str,
     round_number: i32,
     exchange_number: i32,
-) -> Result<String, String> {
-    let mut last_err = String::new();
-    for attempt in 0..=max_retries {
-        match llm::call_llm_streaming_debate(
-            api_key,
-            model,
-            system_prompt,
-            user_prompt,
-            app_handle,
-            decision_id,
-            round_number,
-            exchange_number,
-            agent_key,
-        ).await {
-            Ok(text) => return Ok(text),
-            Err(e) => {
-                last_err = e;
-                if attempt < max_retries {
-                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    agent_key: &'a str,
+    agent_label: &'a str,
+}
+
+impl<'a> DebateTurn<'a> {
+    fn moderator(decision_id: &'a str, round_number: i32, exchange_number: i32) -> Self {
+        DebateTurn { decision_id, round_number, exchange_number, agent_key: "moderator", agent_label: "Moderator" }
+    }
+
+    fn usage_ctx(&self) -> UsageContext {
+        UsageContext::debate_turn(self.decision_id, self.round_number, self.exchange_number, self.agent_key)
+    }
+}
+
+/// Call a single agent, streaming tokens to frontend. Retriable failures are retried
+/// on the same model per `retry_policy`; after that the next model in the agent's
+/// fallback chain takes over. A `debate-agent-retry` event tells the UI to drop
+/// the tokens streamed by the failed attempt.
+async fn call_agent_with_retry<R: Runtime>(
+    chain: &[(LlmEndpoint, String)],
+    turn: DebateTurn<'_>,
+    system_prompt: &str,
+    user_prompt: &str,
+    params: &GenerationParams,
+    retry_policy: &RetryPolicy,
+    app_handle: &tauri::AppHandle<R>,
+) -> Result<DebateReply, String> {
+    let usage_ctx = turn.usage_ctx();
+    with_model_fallbacks(
+        chain, turn, retry_policy, app_handle,
+        |endpoint, model| {
+            let call = llm::LlmCall { endpoint, model, params, usage_ctx: &usage_ctx };
+            async move { llm::call_llm_streaming_debate(&call, system_prompt, user_prompt, app_handle).await }
+        },
+    ).await
+}
+
+/// Run `call` down the agent's model chain with retries, emitting `debate-agent-retry`
+/// for every failed attempt.
+async fn with_model_fallbacks<'c, R, T, F, Fut>(
+    chain: &'c [(LlmEndpoint, String)],
+    turn: DebateTurn<'_>,
+    retry_policy: &RetryPolicy,
+    app_handle: &tauri::AppHandle<R>,
+    call: F,
+) -> Result<T, String>
+where
+    R: Runtime,
+    F: Fn(&'c LlmEndpoint, &'c str) -> Fut,
+    Fut: Future<Output = Result<T, LlmError>>,
+{
+    let mut failures: Vec<String> = Vec::new();
+    let mut failed_providers: Vec<&str> = Vec::new();
+    for (index, (endpoint, model)) in chain.iter().enumerate() {
+        // No point trying other models on a provider that rejected the key or has no credits.
+        if failed_providers.contains(&endpoint.provider_id.as_str()) {
+            continue;
+        }
+        let mut attempt = 0;
+        loop {
+            let error = match call(endpoint, model).await {
+                Ok(result) => return Ok(result),
+                Err(e) => e,
+            };
+
+            let delay = retry_policy.delay_for(attempt, &error);
+            let _ = app_handle.emit("debate-agent-retry", json!({
+                "decision_id": turn.decision_id,
+                "round_number": turn.round_number,
+                "exchange_number": turn.exchange_number,
+                "agent": turn.agent_key,
+                "model": model,
+                "attempt": attempt + 1,
+                "error": error.message,
+                "retry_in_ms": delay.map(|d| d.as_millis() as u64),
+                "fallback_model": chain.get(index + 1).filter(|_| delay.is_none()).map(|(_, m)| m),
+            }));
+
+            match delay {
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => {
+                    if error.affects_provider() {
+                        failed_providers.push(&endpoint.provider_id);
+                    }
+                    failures.push(format!("{}: {}", model, error));
+                    break;
                 }
             }
         }
     }
-    Err(format!("{} failed after {} retries: {}", agent_label, max_retries + 1, last_err))
+    Err(format!("{} failed on every model ({})", turn.agent_label, failures.join("; ")))
+}
+
+/// Ask the moderator for a `ModeratorVerdict` as schema-checked JSON, walking the
+/// fallback chain. If no model manages that, fall back to the streamed markdown
+/// synthesis and scrape it; that second call is checked against the budget like any
+/// other. Returns the reply to show and speak, plus the verdict, or `None` when the
+/// budget stopped the debate.
+async fn request_moderator_verdict<R: Runtime>(
+    chain: &[(LlmEndpoint, String)],
+    system_prompt: &str,
+    params: &GenerationParams,
+    brief: &str,
+    transcript: &str,
+    participants: &str,
+    spending: &DebateBudget<'_, R>,
+) -> Result<Option<(DebateReply, ModeratorVerdict, VerdictSource)>, String> {
+    let (app_handle, decision_id) = (spending.app_handle, spending.decision_id);
+    let turn = DebateTurn::moderator(decision_id, 99, 1);
+    let schema = verdict::verdict_schema();
+    let verdict_prompt = agents::moderator_verdict_prompt(brief, transcript, participants);
+    let usage_ctx = turn.usage_ctx();
+    let structured = with_model_fallbacks(
+        chain, turn, &retry::DEBATE_RETRY_POLICY, app_handle,
+        |endpoint, model| {
+            let (schema, verdict_prompt) = (&schema, &verdict_prompt);
+            let call = llm::LlmCall { endpoint, model, params, usage_ctx: &usage_ctx };
+            async move {
+                let value = llm::call_llm_structured(
+                    &call, system_prompt, verdict_prompt, verdict::VERDICT_SCHEMA_NAME, schema, app_handle,
+                ).await?;
+                ModeratorVerdict::from_structured(&value).map_err(LlmError::rejected)
+            }
+        },
+    ).await;
+
+    let error = match structured {
+        Ok(verdict) => {
+            let reply = DebateReply { text: verdict.to_markdown(), reasoning: String::new() };
+            return Ok(Some((reply, verdict, VerdictSource::Structured)));
+        }
+        Err(e) => e,
+    };
+    let _ = app_handle.emit("debate-agent-retry", json!({
+        "decision_id": decision_id,
+        "round_number": 99,
+        "exchange_number": 1,
+        "agent": "moderator",
+        "error": error,
+        "fallback": "markdown",
+    }));
+    if !spending.allows_moderator_call(transcript, budget::EXPECTED_VERDICT_COMPLETION_TOKENS)? {
+        return Ok(None);
+    }
+    let reply = call_agent_with_retry(
+        chain,
+        turn, system_prompt,
+        &agents::moderator_prompt(brief, transcript, participants),
+        params,
+        &retry::DEBATE_RETRY_POLICY,
+        app_handle,
+    ).await?;
+    let verdict = ModeratorVerdict::from_markdown(&reply.text);
+    Ok(Some((reply, verdict, VerdictSource::Markdown)))
 }
 
 /// Run a full debate round where debaters respond one at a time (sequential streaming).
-async fn run_sequential_round(
-    api_key: &str,
+async fn run_sequential_round<R: Runtime>(
+    llm_config: &config::AppConfig,
     default_model: &str,
     agent_models: &HashMap<String, String>,
     brief: &str,
     existing_rounds: &[crate::db::DebateRound],
     round_number: i32,
     exchange_number: i32,
-    app_handle: &tauri::AppHandle,
+    app_handle: &tauri::AppHandle<R>,
     decision_id: &str,
     cancel_flag: &Arc<AtomicBool>,
     app_data_dir: &std::path::PathBuf,
@@ -616,26 +798,36 @@ async fn run_sequential_round(
             agents::debate_spoken_style_overlay()
         );
         let agent_model = agent_models.get(&agent.key).filter(|m| !m.is_empty()).map(|m| m.as_str()).unwrap_or(default_model);
-        let result = call_agent_with_retry(
-            api_key, agent_model,
-            &agent.key, &agent.label, &system_prompt, &user_prompt, 2,
-            app_handle, decision_id, round_number, exchange_number,
-        ).await;
+        let result = match providers::agent_model_chain(llm_config, &agent.key, agent_model) {
+            Ok(chain) => call_agent_with_retry(
+                &chain,
+                DebateTurn { decision_id, round_number, exchange_number, agent_key: &agent.key, agent_label: &agent.label },
+                &system_prompt, &user_prompt, &agent.generation_params(),
+                &retry::DEBATE_RETRY_POLICY,
+                app_handle,
+            ).await,
+            Err(e) => Err(format!("{}: {}", agent.label, e)),
+        };
 
         match result {
-            Ok(text) => {
-                let normalized_text = normalize_spoken_debate_output(&text);
+            Ok(reply) => {
+                let normalized_text = normalize_spoken_debate_output(&reply.text);
                 // Save to DB
                 let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
                 let round = {
                     let state_guard = state.lock().map_err(|e| e.to_string())?;
-                    state_guard.db.save_debate_round(
+                    let mut round = state_guard.db.save_debate_round(
                         decision_id,
                         round_number,
                         exchange_number,
                         &agent.key,
                         &normalized_text,
-                    ).map_err(|e| e.to_string())?
+                    ).map_err(|e| e.to_string())?;
+                    if llm_config.save_reasoning && !reply.reasoning.is_empty() {
+                        state_guard.db.set_debate_round_reasoning(&round.id, &reply.reasoning).map_err(|e| e.to_string())?;
+                        round.reasoning = Some(reply.reasoning);
+                    }
+                    round
                 };
 
                 // Emit per-agent complete event
@@ -675,44 +867,352 @@ async fn run_sequential_round(
     Ok(new_rounds)
 }
 
-/// Main debate orchestrator. Runs the full debate asynchronously.
-pub async fn run_debate(
-    app_handle: tauri::AppHandle,
-    decision_id: String,
+/// Debaters from the registry, narrowed to the selected keys (kept in registry order).
+fn select_debaters(registry: &[AgentInfo], selected_agent_keys: Option<&[String]>) -> Result<Vec<AgentInfo>, String> {
+    let all_debaters_in_registry: Vec<AgentInfo> = registry.iter()
+        .filter(|a| a.role == "debater")
+        .cloned()
+        .collect();
+
+    let debaters: Vec<AgentInfo> = match selected_agent_keys {
+        Some(keys) if !keys.is_empty() => {
+            // Use selected agents in the order they appear in the registry
+            all_debaters_in_registry.iter()
+                .filter(|a| keys.contains(&a.key))
+                .cloned()
+                .collect()
+        }
+        _ => all_debaters_in_registry,
+    };
+
+    if debaters.is_empty() {
+        return Err("No debaters selected for the debate".to_string());
+    }
+    Ok(debaters)
+}
+
+fn tts_configured(tts_config: &config::AppConfig) -> bool {
+    match tts_config.tts_provider.as_str() {
+        "openai" => !tts_config.openrouter_api_key.is_empty(),
+        _ => !tts_config.elevenlabs_api_key.is_empty(),
+    }
+}
+
+/// Fill the pricing cache from OpenRouter when a participating model has no price yet.
+/// Best effort: models still unpriced afterwards count as free in estimates, and stop
+/// a debate that has spending caps set.
+async fn prefetch_missing_pricing(
+    llm_config: &config::AppConfig,
+    debaters: &[AgentInfo],
+    speaker_models: &[&str],
+    app_data_dir: &Path,
+) {
+    let missing = speaker_models.iter().any(|m| usage::cached_pricing(m).is_none());
+    let uses_openrouter = debaters.iter().any(|a| {
+        providers::endpoint_for_agent(llm_config, &a.key).map(|e| e.is_openrouter()).unwrap_or(false)
+    });
+    if missing && uses_openrouter {
+        let _ = commands::fetch_openrouter_models(app_data_dir).await;
+    }
+}
+
+/// Spending-cap checks for one debate run. Each LLM call the debate makes (speaker
+/// rounds, moderator steering and the verdict) is estimated before it is sent.
+struct DebateBudget<'a, R: Runtime> {
+    app_handle: &'a tauri::AppHandle<R>,
+    caps: &'a config::BudgetConfig,
+    decision_id: &'a str,
+    run_started_at: String,
+    speaker_models: Vec<&'a str>,
+    moderator_model: &'a str,
+    brief_chars: usize,
+}
+
+impl<'a, R: Runtime> DebateBudget<'a, R> {
+    /// Returns `None` after refusing the debate with a `budget_unpriced` error when caps
+    /// are set but a hosted model has no known pricing: its cost can't be estimated, so
+    /// the caps can't be enforced. Self-hosted providers are treated as free. Nothing has
+    /// run yet, so the decision and its previous debate are left as they were.
+    fn start(
+        app_handle: &'a tauri::AppHandle<R>,
+        llm_config: &'a config::AppConfig,
+        decision_id: &'a str,
+        debaters: &[AgentInfo],
+        speaker_models: Vec<&'a str>,
+        moderator_model: &'a str,
+        brief_chars: usize,
+    ) -> Result<Option<Self>, String> {
+        let spending = DebateBudget {
+            app_handle,
+            caps: &llm_config.budget,
+            decision_id,
+            run_started_at: chrono::Utc::now().to_rfc3339(),
+            speaker_models,
+            moderator_model,
+            brief_chars,
+        };
+        if !budget::has_caps(spending.caps) {
+            return Ok(Some(spending));
+        }
+
+        let agents = debaters.iter().map(|a| a.key.as_str()).zip(spending.speaker_models.iter().copied())
+            .chain(std::iter::once(("moderator", moderator_model)));
+        let mut unpriced: Vec<&str> = agents
+            .filter(|(key, model)| {
+                let self_hosted = providers::endpoint_for_agent(llm_config, key)
+                    .map(|endpoint| endpoint.is_self_hosted())
+                    .unwrap_or(false);
+                !self_hosted && usage::cached_pricing(model).is_none()
+            })
+            .map(|(_, model)| model)
+            .collect();
+        if unpriced.is_empty() {
+            return Ok(Some(spending));
+        }
+        unpriced.sort_unstable();
+        unpriced.dedup();
+        let _ = app_handle.emit("debate-error", json!({
+            "decision_id": decision_id,
+            "error": format!(
+                "No pricing is known for {}, so the spending caps can't be checked. Refresh the model list in Settings, or remove the caps to run without them.",
+                unpriced.join(", ")
+            ),
+            "reason": "budget_unpriced",
+            "models": unpriced,
+        }));
+        Ok(None)
+    }
+
+    /// Check the opening round before anything is saved. Returns `false` after refusing
+    /// the debate, leaving the decision and its previous debate as they were.
+    fn allows_start(&self) -> Result<bool, String> {
+        match self.exceeded(&budget::estimate_round(&self.speaker_models, self.brief_chars))? {
+            Some(payload) => {
+                let _ = self.app_handle.emit("debate-error", payload);
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Check the next speaker round. Returns `false` after stopping the debate.
+    fn allows_round(&self, transcript: &str) -> Result<bool, String> {
+        self.allows(&budget::estimate_round(&self.speaker_models, self.brief_chars + transcript.len()))
+    }
+
+    /// Check a moderator call (steering or verdict) over the transcript so far.
+    fn allows_moderator_call(&self, transcript: &str, completion_tokens: i64) -> Result<bool, String> {
+        let mut estimate = budget::CostEstimate::default();
+        estimate.add_call(
+            self.moderator_model,
+            budget::estimate_tokens(self.brief_chars + transcript.len() + budget::PROMPT_OVERHEAD_CHARS),
+            completion_tokens,
+        );
+        self.allows(&estimate)
+    }
+
+    fn allows(&self, estimate: &budget::CostEstimate) -> Result<bool, String> {
+        match self.exceeded(estimate)? {
+            Some(payload) => {
+                stop_debate_early(self.app_handle, self.decision_id, payload)?;
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// The `debate-error` payload when `estimate` would break a cap.
+    fn exceeded(&self, estimate: &budget::CostEstimate) -> Result<Option<Value>, String> {
+        if !budget::has_caps(self.caps) {
+            return Ok(None);
+        }
+        let spent = {
+            let state: tauri::State<'_, Mutex<AppState>> = self.app_handle.state();
+            let state_guard = state.lock().map_err(|e| e.to_string())?;
+            budget::current_spend(&state_guard.db, self.decision_id, &self.run_started_at)?
+        };
+        Ok(budget::check(self.caps, &spent, estimate.cost_usd).err().map(|exceeded| json!({
+            "decision_id": self.decision_id,
+            "error": exceeded.message(),
+            "reason": "budget_exceeded",
+            "budget": exceeded,
+        })))
+    }
+}
+
+// ── Pre-debate estimate ──
+
+/// Expected LLM and TTS volume of a debate, computed before it starts.
+/// Moderator-auto debates are estimated at their exchange cap (`is_upper_bound`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebateEstimate {
+    pub llm_calls: i64,
+    pub brief_tokens: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost_usd: f64,
+    pub unpriced_calls: i64,
+    pub tts_enabled: bool,
+    pub tts_characters: i64,
+    pub estimated_audio_seconds: i64,
+    pub is_upper_bound: bool,
+}
+
+/// Round numbers in run order plus the number of moderator steering calls,
+/// mirroring the branches in `run_debate`.
+fn debate_schedule(
     quick_mode: bool,
-    cancel_flag: Arc<AtomicBool>,
-    selected_agent_keys: Option<Vec<String>>,
-    brief_override: Option<String>,
-    standalone_participants: Option<Vec<AgentInfo>>,
-    standalone_model_map: Option<HashMap<String, String>>,
+    standalone_sandbox: bool,
     standalone_config: Option<StandaloneDebateConfig>,
-) -> Result<(), String> {
-    // 1. Compile brief (or use override for standalone debates)
+) -> (Vec<i32>, i64, bool) {
+    let mut rounds = vec![1];
+    if !standalone_sandbox {
+        if !quick_mode {
+            rounds.extend([2, 2, 3]);
+        }
+        return (rounds, 0, false);
+    }
+
+    let cfg = normalize_standalone_config(standalone_config, quick_mode);
+    if cfg.mode == STANDALONE_MODE_MODERATOR_AUTO {
+        let exchanges = cfg.max_exchanges.unwrap_or(12) as usize;
+        rounds.extend(std::iter::repeat_n(2, exchanges));
+        (rounds, exchanges as i64, true)
+    } else {
+        let exchanges = cfg.exchange_count.unwrap_or(2) as usize;
+        rounds.extend(std::iter::repeat_n(2, exchanges));
+        if exchanges > 0 {
+            rounds.push(3);
+        }
+        (rounds, exchanges.saturating_sub(1) as i64, false)
+    }
+}
+
+/// Estimate a debate with the same inputs as `run_debate`, without calling any model.
+pub async fn estimate_debate<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    decision_id: &str,
+    quick_mode: bool,
+    setup: DebateSetup,
+) -> Result<DebateEstimate, String> {
+    let DebateSetup {
+        selected_agent_keys,
+        brief_override,
+        standalone_participants,
+        standalone_model_map,
+        standalone_config,
+    } = setup;
     let brief = match brief_override {
         Some(b) => b,
-        None => compile_brief(&app_handle, &decision_id)?,
+        None => compile_brief(app_handle, decision_id, false).await?,
     };
-
-    // 2. Save brief and update status
-    {
+    let (llm_config, app_data_dir) = {
         let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
         let state_guard = state.lock().map_err(|e| e.to_string())?;
-        state_guard.db.delete_debate_rounds(&decision_id).map_err(|e| e.to_string())?;
-        state_guard.db.update_debate_brief(&decision_id, &brief).map_err(|e| e.to_string())?;
-        state_guard.db.update_debate_started(&decision_id).map_err(|e| e.to_string())?;
+        (config::load_config(&state_guard.app_data_dir), state_guard.app_data_dir.clone())
+    };
+
+    let mut agent_models = llm_config.agent_models.clone();
+    for (agent_key, model_id) in standalone_model_map.unwrap_or_default() {
+        if !model_id.trim().is_empty() {
+            agent_models.insert(agent_key, model_id);
+        }
     }
 
-    // 3. Emit debate-started
-    let _ = app_handle.emit("debate-started", json!({ "decision_id": decision_id }));
+    let standalone_sandbox = standalone_participants.is_some();
+    let registry = match standalone_participants {
+        Some(participants) => participants,
+        None => agents::load_registry(&app_data_dir)?,
+    };
+    let debaters = select_debaters(&registry, selected_agent_keys.as_deref())?;
+    let model_for = |key: &str| -> String {
+        agent_models.get(key).filter(|m| !m.is_empty()).cloned().unwrap_or_else(|| llm_config.model.clone())
+    };
+    let speaker_model_ids: Vec<String> = debaters.iter().map(|a| model_for(&a.key)).collect();
+    let speaker_models: Vec<&str> = speaker_model_ids.iter().map(String::as_str).collect();
+    let moderator_model = model_for("moderator");
+    prefetch_missing_pricing(&llm_config, &debaters, &speaker_models, &app_data_dir).await;
+
+    let (rounds, steering_calls, is_upper_bound) = debate_schedule(quick_mode, standalone_sandbox, standalone_config);
+    let turn_chars = (budget::EXPECTED_TURN_COMPLETION_TOKENS * budget::CHARS_PER_TOKEN) as usize;
+    let mut estimate = budget::CostEstimate::default();
+    let mut transcript_chars: usize = 0;
+    let mut steering_left = steering_calls;
+
+    for round_number in rounds {
+        // Openings are blind, later rounds see the transcript so far.
+        let shared_chars = if round_number == 1 { brief.len() } else { brief.len() + transcript_chars };
+        estimate.merge(&budget::estimate_round(&speaker_models, shared_chars));
+        transcript_chars += turn_chars * speaker_models.len();
+
+        if round_number == 2 && steering_left > 0 {
+            steering_left -= 1;
+            estimate.add_call(
+                &moderator_model,
+                budget::estimate_tokens(brief.len() + transcript_chars + budget::PROMPT_OVERHEAD_CHARS),
+                budget::EXPECTED_STEERING_COMPLETION_TOKENS,
+            );
+        }
+    }
+
+    let verdict_tokens = if standalone_sandbox {
+        budget::EXPECTED_STANDALONE_VERDICT_COMPLETION_TOKENS
+    } else {
+        budget::EXPECTED_VERDICT_COMPLETION_TOKENS
+    };
+    estimate.add_call(
+        &moderator_model,
+        budget::estimate_tokens(brief.len() + transcript_chars + budget::PROMPT_OVERHEAD_CHARS),
+        verdict_tokens,
+    );
+
+    // Every debater turn and the moderator verdict are voiced.
+    let tts_characters = transcript_chars as i64 + verdict_tokens * budget::CHARS_PER_TOKEN;
+    Ok(DebateEstimate {
+        llm_calls: estimate.calls,
+        brief_tokens: budget::estimate_tokens(brief.len()),
+        prompt_tokens: estimate.prompt_tokens,
+        completion_tokens: estimate.completion_tokens,
+        cost_usd: estimate.cost_usd,
+        unpriced_calls: estimate.unpriced_calls,
+        tts_enabled: tts_configured(&llm_config),
+        tts_characters,
+        estimated_audio_seconds: tts_characters / budget::TTS_CHARS_PER_SECOND,
+        is_upper_bound,
+    })
+}
+
+/// Main debate orchestrator. Runs the full debate asynchronously.
+pub async fn run_debate<R: Runtime>(
+    app_handle: tauri::AppHandle<R>,
+    decision_id: String,
+    quick_mode: bool,
+    cancel_flag: Arc<AtomicBool>,
+    setup: DebateSetup,
+) -> Result<(), String> {
+    let DebateSetup {
+        selected_agent_keys,
+        brief_override,
+        standalone_participants,
+        standalone_model_map,
+        standalone_config,
+    } = setup;
+    // 1. Compile brief (or use override for standalone debates)
+    let brief = match brief_override {
+        Some(b) => b,
+        None => compile_brief(&app_handle, &decision_id, true).await?,
+    };
 
     let standalone_sandbox = standalone_participants.is_some();
 
     // Load LLM config and app_data_dir
-    let (api_key, model, mut agent_models, app_data_dir) = {
+    let (llm_config, model, mut agent_models, app_data_dir) = {
         let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
         let state_guard = state.lock().map_err(|e| e.to_string())?;
         let config = config::load_config(&state_guard.app_data_dir);
-        (config.openrouter_api_key, config.model, config.agent_models, state_guard.app_data_dir.clone())
+        let model = config.model.clone();
+        let agent_models = config.agent_models.clone();
+        (config, model, agent_models, state_guard.app_data_dir.clone())
     };
 
     if let Some(model_overrides) = standalone_model_map {
@@ -729,39 +1229,58 @@ pub async fn run_debate(
     }
 
     // Load agent registry and determine participants
-    let registry = standalone_participants.unwrap_or_else(|| agents::load_registry(&app_data_dir));
-    let all_debaters_in_registry: Vec<AgentInfo> = registry.iter()
-        .filter(|a| a.role == "debater")
-        .cloned()
-        .collect();
-
-    let debaters: Vec<AgentInfo> = match selected_agent_keys {
-        Some(ref keys) if !keys.is_empty() => {
-            // Use selected agents in the order they appear in the registry
-            all_debaters_in_registry.iter()
-                .filter(|a| keys.contains(&a.key))
-                .cloned()
-                .collect()
-        }
-        _ => all_debaters_in_registry,
+    let registry = match standalone_participants {
+        Some(participants) => participants,
+        None => agents::load_registry(&app_data_dir)?,
     };
-
-    if debaters.is_empty() {
-        return Err("No debaters selected for the debate".to_string());
-    }
+    let debaters = select_debaters(&registry, selected_agent_keys.as_deref())?;
 
     // Build participant names for moderator
     let participant_names = agents::format_participant_names(&debaters);
 
     // All agents for transcript formatting (debaters + moderator)
     let all_agents: Vec<AgentInfo> = registry.clone();
+    // The moderator's sampling settings, for its steering calls and the verdict
+    let moderator_params = all_agents.iter()
+        .find(|a| a.key == "moderator")
+        .map(|a| a.generation_params())
+        .unwrap_or_else(|| agents::builtin_generation("moderator"));
+
+    // Models per speaker, for pre-call budget estimates
+    let speaker_models: Vec<&str> = debaters.iter()
+        .map(|a| agent_models.get(&a.key).filter(|m| !m.is_empty()).map(|m| m.as_str()).unwrap_or(&model))
+        .collect();
+    let moderator_model = agent_models.get("moderator").filter(|m| !m.is_empty()).map(|m| m.as_str()).unwrap_or(&model);
+    let moderator_chain = providers::agent_model_chain(&llm_config, "moderator", moderator_model)?;
+    if budget::has_caps(&llm_config.budget) {
+        let priced_models: Vec<&str> = speaker_models.iter().copied().chain([moderator_model]).collect();
+        prefetch_missing_pricing(&llm_config, &debaters, &priced_models, &app_data_dir).await;
+    }
+    // A run the budget refuses stops here, before the previous debate is replaced.
+    let Some(spending) = DebateBudget::start(
+        &app_handle, &llm_config, &decision_id, &debaters, speaker_models, moderator_model, brief.len(),
+    )? else {
+        return Ok(());
+    };
+    if !spending.allows_start()? {
+        return Ok(());
+    }
+
+    // 2. Save brief and update status
+    {
+        let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+        let state_guard = state.lock().map_err(|e| e.to_string())?;
+        state_guard.db.delete_debate_rounds(&decision_id).map_err(|e| e.to_string())?;
+        state_guard.db.update_debate_brief(&decision_id, &brief).map_err(|e| e.to_string())?;
+        state_guard.db.update_debate_started(&decision_id).map_err(|e| e.to_string())?;
+    }
+
+    // 3. Emit debate-started
+    let _ = app_handle.emit("debate-started", json!({ "decision_id": decision_id }));
 
     // Set up live TTS state
     let tts_config = config::load_config(&app_data_dir);
-    let has_tts = match tts_config.tts_provider.as_str() {
-        "openai" => !tts_config.openrouter_api_key.is_empty(),
-        _ => !tts_config.elevenlabs_api_key.is_empty(),
-    };
+    let has_tts = tts_configured(&tts_config);
     let tts_state = LiveTtsState {
         enabled: has_tts,
         config: tts_config,
@@ -773,9 +1292,9 @@ pub async fn run_debate(
 
     let mut all_rounds: Vec<crate::db::DebateRound> = Vec::new();
 
-    // 4. Round 1: Opening Positions
+    // 4. Round 1: Opening Positions (checked by `allows_start`)
     let round1 = run_sequential_round(
-        &api_key, &model, &agent_models,
+        &llm_config, &model, &agent_models,
         &brief, &all_rounds, 1, 1,
         &app_handle, &decision_id, &cancel_flag, &app_data_dir,
         &debaters, &all_agents, &tts_state, standalone_sandbox, None,
@@ -790,11 +1309,6 @@ pub async fn run_debate(
             include_final_positions = false;
             let max_exchanges = cfg.max_exchanges.unwrap_or(12) as i32;
             let participant_names_for_steering = agents::format_participant_names(&debaters);
-            let moderator_model = agent_models
-                .get("moderator")
-                .filter(|m| !m.is_empty())
-                .map(|m| m.as_str())
-                .unwrap_or(&model);
             let mut direction_for_next_exchange: Option<String> = None;
 
             for exchange in 1..=max_exchanges {
@@ -802,8 +1316,12 @@ pub async fn run_debate(
                     return handle_cancellation(&app_handle, &decision_id);
                 }
 
+                if !spending.allows_round(&format_transcript(&all_rounds, &all_agents))? {
+                    return Ok(());
+                }
+
                 let exchange_rounds = run_sequential_round(
-                    &api_key, &model, &agent_models,
+                    &llm_config, &model, &agent_models,
                     &brief, &all_rounds, 2, exchange,
                     &app_handle, &decision_id, &cancel_flag, &app_data_dir,
                     &debaters, &all_agents, &tts_state, standalone_sandbox,
@@ -812,13 +1330,17 @@ pub async fn run_debate(
                 all_rounds.extend(exchange_rounds);
 
                 let transcript = format_transcript(&all_rounds, &all_agents);
+                if !spending.allows_moderator_call(&transcript, budget::EXPECTED_STEERING_COMPLETION_TOKENS)? {
+                    return Ok(());
+                }
                 let (direction, conclude) = request_moderator_direction(
-                    &api_key,
-                    moderator_model,
+                    &moderator_chain,
+                    &moderator_params,
                     &brief,
                     &transcript,
                     &participant_names_for_steering,
-                    exchange,
+                    DebateTurn::moderator(&decision_id, 2, exchange),
+                    &app_handle,
                 )
                 .await
                 .unwrap_or_else(|_| {
@@ -837,19 +1359,17 @@ pub async fn run_debate(
             let exchanges = cfg.exchange_count.unwrap_or(2) as i32;
             include_final_positions = exchanges > 0;
             let participant_names_for_steering = agents::format_participant_names(&debaters);
-            let moderator_model = agent_models
-                .get("moderator")
-                .filter(|m| !m.is_empty())
-                .map(|m| m.as_str())
-                .unwrap_or(&model);
             let mut direction_for_next_exchange: Option<String> = None;
 
             for exchange in 1..=exchanges {
                 if cancel_flag.load(Ordering::Relaxed) {
                     return handle_cancellation(&app_handle, &decision_id);
                 }
+                if !spending.allows_round(&format_transcript(&all_rounds, &all_agents))? {
+                    return Ok(());
+                }
                 let exchange_rounds = run_sequential_round(
-                    &api_key, &model, &agent_models,
+                    &llm_config, &model, &agent_models,
                     &brief, &all_rounds, 2, exchange,
                     &app_handle, &decision_id, &cancel_flag, &app_data_dir,
                     &debaters, &all_agents, &tts_state, standalone_sandbox,
@@ -859,13 +1379,17 @@ pub async fn run_debate(
 
                 if exchange < exchanges {
                     let transcript = format_transcript(&all_rounds, &all_agents);
+                    if !spending.allows_moderator_call(&transcript, budget::EXPECTED_STEERING_COMPLETION_TOKENS)? {
+                        return Ok(());
+                    }
                     let (direction, _) = request_moderator_direction(
-                        &api_key,
-                        moderator_model,
+                        &moderator_chain,
+                        &moderator_params,
                         &brief,
                         &transcript,
                         &participant_names_for_steering,
-                        exchange,
+                        DebateTurn::moderator(&decision_id, 2, exchange),
+                        &app_handle,
                     )
                     .await
                     .unwrap_or_else(|_| {
@@ -884,8 +1408,11 @@ pub async fn run_debate(
             if cancel_flag.load(Ordering::Relaxed) {
                 return handle_cancellation(&app_handle, &decision_id);
             }
+            if !spending.allows_round(&format_transcript(&all_rounds, &all_agents))? {
+                return Ok(());
+            }
             let r2e1 = run_sequential_round(
-                &api_key, &model, &agent_models,
+                &llm_config, &model, &agent_models,
                 &brief, &all_rounds, 2, 1,
                 &app_handle, &decision_id, &cancel_flag, &app_data_dir,
                 &debaters, &all_agents, &tts_state, standalone_sandbox, None,
@@ -896,8 +1423,11 @@ pub async fn run_debate(
             if cancel_flag.load(Ordering::Relaxed) {
                 return handle_cancellation(&app_handle, &decision_id);
             }
+            if !spending.allows_round(&format_transcript(&all_rounds, &all_agents))? {
+                return Ok(());
+            }
             let r2e2 = run_sequential_round(
-                &api_key, &model, &agent_models,
+                &llm_config, &model, &agent_models,
                 &brief, &all_rounds, 2, 2,
                 &app_handle, &decision_id, &cancel_flag, &app_data_dir,
                 &debaters, &all_agents, &tts_state, standalone_sandbox, None,
@@ -910,8 +1440,11 @@ pub async fn run_debate(
         if cancel_flag.load(Ordering::Relaxed) {
             return handle_cancellation(&app_handle, &decision_id);
         }
+        if !spending.allows_round(&format_transcript(&all_rounds, &all_agents))? {
+            return Ok(());
+        }
         let round3 = run_sequential_round(
-            &api_key, &model, &agent_models,
+            &llm_config, &model, &agent_models,
             &brief, &all_rounds, 3, 1,
             &app_handle, &decision_id, &cancel_flag, &app_data_dir,
             &debaters, &all_agents, &tts_state, standalone_sandbox, None,
@@ -925,31 +1458,57 @@ pub async fn run_debate(
     }
 
     let transcript = format_transcript(&all_rounds, &all_agents);
-    let moderator_user_prompt = if standalone_sandbox {
-        standalone_moderator_prompt(&brief, &transcript, &participant_names)
+    let verdict_tokens = if standalone_sandbox {
+        budget::EXPECTED_STANDALONE_VERDICT_COMPLETION_TOKENS
     } else {
-        agents::moderator_prompt(&brief, &transcript, &participant_names)
+        budget::EXPECTED_VERDICT_COMPLETION_TOKENS
     };
+    if !spending.allows_moderator_call(&transcript, verdict_tokens)? {
+        return Ok(());
+    }
     let moderator_system_prompt = if standalone_sandbox {
         standalone_moderator_system_prompt().to_string()
     } else {
         agents::read_agent_prompt(&app_data_dir, "moderator")
     };
 
-    let moderator_model = agent_models.get("moderator").filter(|m| !m.is_empty()).map(|m| m.as_str()).unwrap_or(&model);
-    let moderator_response = call_agent_with_retry(
-        &api_key, moderator_model,
-        "moderator", "Moderator", &moderator_system_prompt, &moderator_user_prompt, 2,
-        &app_handle, &decision_id, 99, 1,
-    ).await?;
+    let (moderator_reply, verdict) = if standalone_sandbox {
+        let reply = call_agent_with_retry(
+            &moderator_chain,
+            DebateTurn::moderator(&decision_id, 99, 1), &moderator_system_prompt,
+            &standalone_moderator_prompt(&brief, &transcript, &participant_names),
+            &moderator_params,
+            &retry::DEBATE_RETRY_POLICY,
+            &app_handle,
+        ).await?;
+        (reply, None)
+    } else {
+        let Some((reply, verdict, source)) = request_moderator_verdict(
+            &moderator_chain, &moderator_system_prompt, &moderator_params, &brief, &transcript, &participant_names,
+            &spending,
+        ).await? else {
+            return Ok(());
+        };
+        (reply, Some((verdict, source)))
+    };
+    let moderator_response = moderator_reply.text;
 
     // Save moderator round
     {
         let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
         let state_guard = state.lock().map_err(|e| e.to_string())?;
-        state_guard.db.save_debate_round(
+        let moderator_round = state_guard.db.save_debate_round(
             &decision_id, 99, 1, "moderator", &moderator_response,
         ).map_err(|e| e.to_string())?;
+        if llm_config.save_reasoning && !moderator_reply.reasoning.is_empty() {
+            state_guard.db.set_debate_round_reasoning(&moderator_round.id, &moderator_reply.reasoning)
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some((verdict, source)) = &verdict {
+            let verdict_json = serde_json::to_string(verdict).map_err(|e| e.to_string())?;
+            state_guard.db.save_moderator_verdict(&decision_id, &verdict_json, source.as_str())
+                .map_err(|e| e.to_string())?;
+        }
     }
 
     let _ = app_handle.emit("debate-agent-response", json!({
@@ -970,6 +1529,7 @@ pub async fn run_debate(
             agent: "moderator".to_string(),
             content: moderator_response.clone(),
             created_at: String::new(),
+            reasoning: None,
         };
         spawn_segment_tts(&tts_state, &app_handle, &decision_id, &moderator_round);
     }
@@ -987,9 +1547,9 @@ pub async fn run_debate(
         }
     };
 
-    // 9. Parse moderator output and update decision summary (skip for standalone debates)
-    if !is_standalone {
-        update_summary_from_debate(&app_handle, &decision_id, &all_rounds, &moderator_response, &debaters)?;
+    // 9. Update decision summary from the moderator's verdict (skip for standalone debates)
+    if let Some((verdict, _)) = verdict.as_ref().filter(|_| !is_standalone) {
+        update_summary_from_debate(&app_handle, &decision_id, &all_rounds, verdict, &debaters)?;
     }
 
     // 10. Mark debate complete
@@ -1048,7 +1608,16 @@ pub async fn run_debate(
     Ok(())
 }
 
-fn handle_cancellation(app_handle: &tauri::AppHandle, decision_id: &str) -> Result<(), String> {
+fn handle_cancellation<R: Runtime>(app_handle: &tauri::AppHandle<R>, decision_id: &str) -> Result<(), String> {
+    stop_debate_early(app_handle, decision_id, json!({
+        "decision_id": decision_id,
+        "error": "Debate cancelled",
+        "reason": "cancelled",
+    }))
+}
+
+/// Reset the decision status after an aborted debate and emit `debate-error` with `payload`.
+fn stop_debate_early<R: Runtime>(app_handle: &tauri::AppHandle<R>, decision_id: &str, payload: Value) -> Result<(), String> {
     let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
     let state_guard = state.lock().map_err(|e| e.to_string())?;
     // Determine cancel status based on conversation type
@@ -1066,19 +1635,17 @@ fn handle_cancellation(app_handle: &tauri::AppHandle, decision_id: &str) -> Resu
         }
     };
     state_guard.db.update_decision_status(decision_id, cancel_status).map_err(|e| e.to_string())?;
-    let _ = app_handle.emit("debate-error", json!({
-        "decision_id": decision_id,
-        "error": "Debate cancelled",
-    }));
+    let _ = app_handle.emit("debate-error", payload);
     Ok(())
 }
 
-/// Extract final votes from the last round and build debate_summary for the decision.
-fn update_summary_from_debate(
-    app_handle: &tauri::AppHandle,
+/// Extract final votes from the last round and merge them with the moderator's
+/// verdict into the decision summary.
+fn update_summary_from_debate<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
     decision_id: &str,
     all_rounds: &[crate::db::DebateRound],
-    moderator_response: &str,
+    verdict: &ModeratorVerdict,
     debaters: &[AgentInfo],
 ) -> Result<(), String> {
     let mut final_votes = serde_json::Map::new();
@@ -1093,19 +1660,7 @@ fn update_summary_from_debate(
         }
     }
 
-    let consensus = extract_section(moderator_response, "Where the Committee Agreed");
-    let disagreements = extract_section(moderator_response, "Key Disagreements");
-    let biases = extract_section(moderator_response, "Biases & Blind Spots Identified");
-
-    let debate_summary = json!({
-        "consensus_points": split_to_points(&consensus),
-        "key_disagreements": split_to_points(&disagreements),
-        "biases_identified": split_to_points(&biases),
-        "final_votes": final_votes,
-    });
-
-    let rec_section = extract_section(moderator_response, "Recommendation");
-    let recommendation = parse_moderator_recommendation(&rec_section, moderator_response);
+    let update = verdict.summary_update(final_votes);
 
     let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
     let state_guard = state.lock().map_err(|e| e.to_string())?;
@@ -1116,17 +1671,6 @@ fn update_summary_from_debate(
         .flatten()
         .and_then(|d| d.summary_json);
 
-    let update = if let Some(rec) = recommendation {
-        json!({
-            "debate_summary": debate_summary,
-            "recommendation": rec,
-        })
-    } else {
-        json!({
-            "debate_summary": debate_summary,
-        })
-    };
-
     let merged = decisions::merge_summary(existing_summary.as_deref(), &update);
     state_guard.db.update_decision_summary(decision_id, &merged).map_err(|e| e.to_string())?;
 
@@ -1139,175 +1683,733 @@ fn update_summary_from_debate(
     Ok(())
 }
 
-/// Extract a markdown section by heading.
-fn extract_section(text: &str, heading: &str) -> String {
-    let marker = format!("## {}", heading);
-    if let Some(start) = text.find(&marker) {
-        let after = &text[start + marker.len()..];
-        let end = after.find("\n## ").unwrap_or(after.len());
-        after[..end].trim().to_string()
-    } else {
-        String::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_llm::{test_support, FixtureResponse, MockLlmServer};
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    const MODERATOR_SYNTHESIS: &str = r#"## Where the Committee Agreed
+- Runway matters most
+
+## Key Disagreements
+- Timing of the move
+
+## Recommendation
+**Choice**: Take the offer
+**Confidence**: High
+**Reasoning**: Growth outweighs the short-term risk.
+
+## Action Plan
+- Negotiate the start date
+"#;
+
+    fn moderator_verdict_json() -> String {
+        json!({
+            "consensus_points": ["Runway matters most"],
+            "key_disagreements": ["Timing of the move"],
+            "biases_identified": [],
+            "recommendation": {
+                "choice": "Take the offer",
+                "confidence": "high",
+                "reasoning": "Growth outweighs the short-term risk.",
+                "tradeoffs": "A less predictable first year.",
+                "next_steps": ["Negotiate the start date"]
+            }
+        })
+        .to_string()
     }
-}
 
-/// Split text into bullet points.
-fn split_to_points(text: &str) -> Vec<String> {
-    if text.is_empty() {
-        return Vec::new();
+    #[test]
+    fn unit_normalize_spoken_debate_output_removes_rigid_markdown_format() {
+        let raw = r#"
+## Opening
+- **Position**: Go with Option B.
+- **Key argument**: Better upside over 5 years.
+1. **Concern**: Burnout risk is still real.
+"#;
+        let cleaned = normalize_spoken_debate_output(raw);
+        assert!(!cleaned.contains("##"));
+        assert!(!cleaned.contains("**"));
+        assert!(!cleaned.contains("- "));
+        assert!(cleaned.contains("Go with Option B."));
+        assert!(cleaned.contains("Better upside over 5 years."));
+        assert!(cleaned.contains("Burnout risk is still real."));
     }
-    text.lines()
-        .map(|l| l.trim().trim_start_matches('-').trim_start_matches('*').trim())
-        .filter(|l| !l.is_empty())
-        .map(|l| l.to_string())
-        .collect()
-}
 
-/// Parse the moderator's recommendation section into a structured Recommendation object.
-fn parse_moderator_recommendation(rec_section: &str, full_text: &str) -> Option<Value> {
-    if rec_section.is_empty() && !full_text.contains("**Choice**") {
-        return None;
+    /// A mock app holding one decision, with its LLM calls answered by `responder`.
+    struct DebateFixture {
+        dir: tempfile::TempDir,
+        server: MockLlmServer,
+        app: tauri::App<tauri::test::MockRuntime>,
+        decision_id: String,
     }
 
-    let text = if rec_section.is_empty() { full_text } else { rec_section };
+    impl DebateFixture {
+        async fn new<F>(conversation_type: &str, responder: F) -> Self
+        where
+            F: Fn(&Value) -> Option<FixtureResponse> + Send + Sync + 'static,
+        {
+            let dir = tempdir().expect("temp directory should exist");
+            let server = MockLlmServer::scripted(responder).await.expect("mock server should start");
+            let app = test_support::mock_app_with_state(dir.path(), server.base_url());
+            let decision_id = {
+                let state: tauri::State<'_, Mutex<AppState>> = app.state();
+                let guard = state.lock().unwrap();
+                let conversation = guard.db.create_conversation_with_type("Job offer", conversation_type).unwrap();
+                guard.db.create_decision(&conversation.id, "Job offer").unwrap().id
+            };
+            DebateFixture { dir, server, app, decision_id }
+        }
 
-    let choice = extract_bold_value(text, "Choice")
-        .unwrap_or_else(|| "See moderator's synthesis".to_string());
-    let confidence = extract_bold_value(text, "Confidence")
-        .unwrap_or_else(|| "medium".to_string())
-        .to_lowercase();
-    let reasoning = extract_bold_value(text, "Reasoning")
-        .unwrap_or_else(|| {
-            rec_section.lines()
-                .filter(|l| !l.starts_with("**"))
-                .map(|l| l.trim())
-                .filter(|l| !l.is_empty())
-                .collect::<Vec<_>>()
-                .join(" ")
-        });
+        fn app_data_dir(&self) -> PathBuf {
+            self.dir.path().to_path_buf()
+        }
 
-    let tradeoffs = extract_section(full_text, "What You're Giving Up");
+        fn update_config(&self, change: impl FnOnce(&mut config::AppConfig)) {
+            let mut llm_config = config::load_config(&self.app_data_dir());
+            change(&mut llm_config);
+            config::save_config(&self.app_data_dir(), &llm_config).unwrap();
+        }
 
-    let action_plan = extract_section(full_text, "Action Plan");
-    let next_steps: Vec<String> = split_to_points(&action_plan);
+        fn with_state<T>(&self, read: impl FnOnce(&AppState) -> T) -> T {
+            let state: tauri::State<'_, Mutex<AppState>> = self.app.state();
+            let guard = state.lock().unwrap();
+            read(&guard)
+        }
 
-    let conf = if confidence.contains("high") {
-        "high"
-    } else if confidence.contains("low") {
-        "low"
-    } else {
-        "medium"
-    };
+        fn write_profile(&self, filename: &str, content: &str) {
+            profile::write_profile_file(&self.app_data_dir(), filename, content).unwrap();
+        }
 
-    Some(json!({
-        "choice": choice,
-        "confidence": conf,
-        "reasoning": reasoning,
-        "tradeoffs": if tradeoffs.is_empty() { None } else { Some(tradeoffs) },
-        "next_steps": if next_steps.is_empty() { None } else { Some(next_steps) },
-    }))
-}
+        /// Run a committee debate between `agent_keys`.
+        async fn run(&self, quick_mode: bool, agent_keys: &[&str]) -> Result<(), String> {
+            run_debate(
+                self.app.handle().clone(),
+                self.decision_id.clone(),
+                quick_mode,
+                Arc::new(AtomicBool::new(false)),
+                DebateSetup { selected_agent_keys: Some(agent_keys.iter().map(|k| k.to_string()).collect()), ..DebateSetup::default() },
+            )
+            .await
+        }
 
-/// Extract a value after a bold label like **Choice**: value
-fn extract_bold_value(text: &str, label: &str) -> Option<String> {
-    let pattern = format!("**{}**:", label);
-    if let Some(pos) = text.find(&pattern) {
-        let after = &text[pos + pattern.len()..];
-        let end = after.find('\n').unwrap_or(after.len());
-        let value = after[..end].trim().to_string();
-        if value.is_empty() { None } else { Some(value) }
-    } else {
-        None
+        /// Run a standalone moderator-auto debate of two debaters and `moderator`, up to five exchanges.
+        async fn run_moderator_auto(&self, moderator: AgentInfo) -> Result<(), String> {
+            run_debate(
+                self.app.handle().clone(),
+                self.decision_id.clone(),
+                false,
+                Arc::new(AtomicBool::new(false)),
+                DebateSetup {
+                    brief_override: Some("# Should I rent or buy?".to_string()),
+                    standalone_participants: Some(vec![
+                        standalone_participant("model_a", "debater"),
+                        standalone_participant("model_b", "debater"),
+                        moderator,
+                    ]),
+                    standalone_config: Some(StandaloneDebateConfig {
+                        mode: STANDALONE_MODE_MODERATOR_AUTO.to_string(),
+                        exchange_count: None,
+                        max_exchanges: Some(5),
+                    }),
+                    ..DebateSetup::default()
+                },
+            )
+            .await
+        }
+
+        fn speakers(&self) -> Vec<String> {
+            self.with_state(|s| s.db.get_debate_rounds(&self.decision_id).unwrap())
+                .into_iter()
+                .map(|r| r.agent)
+                .collect()
+        }
+
+        /// Text of the first request that mentions `needle`.
+        fn request_text_containing(&self, needle: &str) -> String {
+            self.server
+                .requests()
+                .iter()
+                .map(test_support::request_text)
+                .find(|text| text.contains(needle))
+                .unwrap_or_else(|| panic!("no request mentions {}", needle))
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn standalone_participant(key: &str, role: &str) -> AgentInfo {
+        AgentInfo {
+            key: key.to_string(),
+            label: key.to_string(),
+            emoji: String::new(),
+            color: "blue".to_string(),
+            role: role.to_string(),
+            builtin: false,
+            sort_order: 0,
+            voice_gender: "male".to_string(),
+            generation: agents::GenerationParams::default(),
+        }
+    }
 
-    #[test]
-    fn unit_extract_section_reads_content_until_next_heading() {
-        let content = r#"
-## Where the Committee Agreed
-- Shared value
-- Shared risk
+    /// The committee as the happy path sees it: debaters argue, the moderator answers the
+    /// verdict schema, and every call reports usage.
+    fn committee_response(request: &Value) -> Option<FixtureResponse> {
+        let text = test_support::request_text(request);
+        let mut response = if request.get("response_format").is_some() {
+            FixtureResponse::text(&moderator_verdict_json())
+        } else if text.contains("Synthesize this debate") {
+            FixtureResponse::text(MODERATOR_SYNTHESIS)
+        } else {
+            FixtureResponse::text("I would take the offer because the runway is solid.")
+        };
+        response.usage = Some(json!({"prompt_tokens": 800, "completion_tokens": 120, "cost": 0.002}));
+        Some(response)
+    }
 
-## Key Disagreements
-- Cost vs growth
-"#;
+    #[tokio::test]
+    async fn e2e_quick_committee_debate_saves_rounds_and_recommendation() {
+        let fixture = DebateFixture::new("decision", committee_response).await;
+        fixture.run(true, &["rationalist", "contrarian"]).await.expect("debate should complete against the mock server");
+
+        assert_eq!(fixture.speakers(), vec!["rationalist", "contrarian", "moderator"]);
+        fixture.with_state(|state| {
+            let rounds = state.db.get_debate_rounds(&fixture.decision_id).unwrap();
+            assert!(rounds[0].content.contains("runway is solid"));
+            assert!(rounds[2].content.contains("**Choice**: Take the offer"));
+
+            let decision = state.db.get_decision(&fixture.decision_id).unwrap().unwrap();
+            assert_eq!(decision.status, "recommended");
+            let summary: Value = serde_json::from_str(&decision.summary_json.unwrap()).unwrap();
+            assert_eq!(summary["recommendation"]["choice"], "Take the offer");
+            assert_eq!(summary["recommendation"]["next_steps"][0], "Negotiate the start date");
+            assert_eq!(summary["debate_summary"]["consensus_points"][0], "Runway matters most");
+        });
+    }
 
-        let section = extract_section(content, "Where the Committee Agreed");
-        assert!(section.contains("Shared value"));
-        assert!(!section.contains("Key Disagreements"));
+    #[tokio::test]
+    async fn e2e_structured_moderator_verdict_is_stored() {
+        let fixture = DebateFixture::new("decision", committee_response).await;
+        fixture.run(true, &["rationalist"]).await.expect("debate should complete");
+
+        let stored = fixture
+            .with_state(|s| s.db.get_moderator_verdict(&fixture.decision_id).unwrap())
+            .expect("verdict should be stored");
+        assert_eq!(stored.source, "structured");
+        let verdict: ModeratorVerdict = serde_json::from_str(&stored.verdict_json).unwrap();
+        assert_eq!(verdict.recommendation.unwrap().choice, "Take the offer");
     }
 
-    #[test]
-    fn unit_split_to_points_strips_bullets_and_empty_lines() {
-        let points = split_to_points(
-            r#"
-- First
-* Second
-
-Third
-"#,
-        );
-        assert_eq!(points, vec!["First", "Second", "Third"]);
+    #[tokio::test]
+    async fn e2e_debate_usage_is_recorded_per_call() {
+        let fixture = DebateFixture::new("decision", committee_response).await;
+        fixture.run(true, &["rationalist", "contrarian"]).await.expect("debate should complete");
+
+        let usage = fixture.with_state(|s| s.db.get_debate_usage_by_agent(&fixture.decision_id).unwrap());
+        assert_eq!(usage.iter().map(|u| u.calls).sum::<i64>(), 3);
+        assert!((usage.iter().map(|u| u.cost_usd).sum::<f64>() - 0.006).abs() < 1e-9);
     }
 
-    #[test]
-    fn unit_parse_moderator_recommendation_extracts_choice_confidence_and_steps() {
-        let full_text = r#"
-## Recommendation
-**Choice**: Option B
-**Confidence**: High
-**Reasoning**: Better upside with manageable risk.
+    #[tokio::test]
+    async fn e2e_builtin_generation_params_reach_agent_requests() {
+        let fixture = DebateFixture::new("decision", committee_response).await;
+        fixture.run(true, &["rationalist", "contrarian"]).await.expect("debate should complete");
 
-## What You're Giving Up
-- Predictability
-- Familiar team
+        // A seeded, cold Rationalist, a warmer Contrarian and a roomy moderator.
+        let requests = fixture.server.requests();
+        let request_for = |needle: &str| {
+            requests.iter().find(|r| test_support::request_text(r).contains(needle)).unwrap().clone()
+        };
+        let rationalist = request_for("The Rationalist");
+        assert_eq!(rationalist["temperature"], 0.2);
+        assert!(rationalist["seed"].is_u64());
+        let contrarian = request_for("The Contrarian");
+        assert_eq!(contrarian["temperature"], 0.7);
+        assert!(contrarian.get("seed").is_none());
+        let moderator = requests.iter().find(|r| r.get("response_format").is_some()).unwrap();
+        assert_eq!(moderator["max_tokens"], 4096);
+    }
 
-## Action Plan
-- Call recruiter today
-- Draft a 90-day transition plan
-"#;
+    #[tokio::test]
+    async fn e2e_debate_brief_includes_attached_documents() {
+        let fixture = DebateFixture::new("decision", committee_response).await;
+        let offer = fixture.dir.path().join("offer-letter.txt");
+        std::fs::write(&offer, "Base salary $180,000. Equity vests over four years.").unwrap();
+        fixture.with_state(|s| {
+            attachments::store_attachment(&s.app_data_dir, &fixture.decision_id, &offer)
+                .and_then(|stored| attachments::save_stored_attachment(&s.db, stored))
+                .unwrap();
+        });
+        fixture.run(true, &["rationalist"]).await.expect("debate should complete");
 
-        let rec_section = extract_section(full_text, "Recommendation");
-        let recommendation =
-            parse_moderator_recommendation(&rec_section, full_text).expect("recommendation should parse");
+        let brief = fixture.request_text_containing("The Rationalist");
+        assert!(brief.contains("## Attached Documents"));
+        assert!(brief.contains("### offer-letter.txt\nBase salary $180,000"));
+    }
 
-        assert_eq!(recommendation["choice"], "Option B");
-        assert_eq!(recommendation["confidence"], "high");
-        assert_eq!(recommendation["reasoning"], "Better upside with manageable risk.");
-        assert_eq!(
-            recommendation["next_steps"][0],
-            "Call recruiter today"
+    #[tokio::test]
+    async fn e2e_debate_brief_includes_only_relevant_profile_sections() {
+        let fixture = DebateFixture::new("decision", committee_response).await;
+        fixture.write_profile("career.md", "# Career\n\n## Job History\n- Two offers turned down in 2023\n");
+        let filler = "- Paints watercolour landscapes on Sunday mornings.\n".repeat(150);
+        fixture.write_profile("hobbies.md", &format!("# Hobbies\n{}", filler));
+        fixture.run(true, &["rationalist"]).await.expect("debate should complete");
+
+        let brief = fixture.request_text_containing("The Rationalist");
+        assert!(brief.contains("### career.md\n\n## Job History\n- Two offers turned down"));
+        assert!(!brief.contains("watercolour"));
+        assert!(brief.contains("(Not relevant here: hobbies.md)"));
+    }
+
+    #[tokio::test]
+    async fn e2e_debate_requests_leave_out_private_profile_content() {
+        let fixture = DebateFixture::new("decision", committee_response).await;
+        fixture.write_profile(
+            "career.md",
+            "# Career\n\n## Job History\n- Two offers turned down in 2023\n```private\n- Severance terms: 6 months\n```\n",
         );
+        fixture.write_profile("medical.md", "---\nprivate: true\n---\n# Medical\n- Job history of burnout\n");
+        fixture.run(true, &["rationalist", "contrarian"]).await.expect("debate should complete");
+
+        assert!(fixture.request_text_containing("The Rationalist").contains("- Two offers turned down in 2023"));
+        assert!(fixture.server.requests().iter().all(|r| {
+            let text = test_support::request_text(r);
+            !text.contains("Severance") && !text.contains("burnout")
+        }));
+    }
+
+    #[tokio::test]
+    async fn e2e_moderator_falls_back_to_markdown_when_structured_verdict_is_invalid() {
+        let fixture = DebateFixture::new("decision", |request| {
+            let text = test_support::request_text(request);
+            Some(if request.get("response_format").is_some() {
+                FixtureResponse::text("Here is my verdict: take the offer, probably.")
+            } else if text.contains("Synthesize this debate") {
+                FixtureResponse::text(MODERATOR_SYNTHESIS)
+            } else {
+                FixtureResponse::text("I would take the offer because the runway is solid.")
+            })
+        })
+        .await;
+        fixture.run(true, &["rationalist"]).await.expect("debate should complete with the markdown fallback");
+
+        fixture.with_state(|state| {
+            let rounds = state.db.get_debate_rounds(&fixture.decision_id).unwrap();
+            assert_eq!(rounds.last().unwrap().content, MODERATOR_SYNTHESIS);
+
+            let stored = state.db.get_moderator_verdict(&fixture.decision_id).unwrap().expect("verdict should be stored");
+            assert_eq!(stored.source, "markdown");
+            let verdict: ModeratorVerdict = serde_json::from_str(&stored.verdict_json).unwrap();
+            assert_eq!(verdict.key_disagreements, vec!["Timing of the move"]);
+
+            let decision = state.db.get_decision(&fixture.decision_id).unwrap().unwrap();
+            let summary: Value = serde_json::from_str(&decision.summary_json.unwrap()).unwrap();
+            assert_eq!(summary["recommendation"]["choice"], "Take the offer");
+            assert_eq!(summary["recommendation"]["confidence"], "high");
+        });
+    }
+
+    #[tokio::test]
+    async fn e2e_markdown_verdict_fallback_is_checked_against_the_budget() {
+        use tauri::Listener;
+
+        let fixture = DebateFixture::new("decision", |request| {
+            let text = test_support::request_text(request);
+            let (mut response, cost) = if request.get("response_format").is_some() {
+                (FixtureResponse::text("Here is my verdict: take the offer, probably."), 0.9)
+            } else if text.contains("Synthesize this debate") {
+                (FixtureResponse::text(MODERATOR_SYNTHESIS), 0.0)
+            } else {
+                (FixtureResponse::text("I would take the offer because the runway is solid."), 0.0)
+            };
+            response.usage = Some(json!({"prompt_tokens": 10, "completion_tokens": 10, "cost": cost}));
+            Some(response)
+        })
+        .await;
+        usage::remember_pricing("budget-fallback/model", usage::ModelPricing {
+            prompt_per_million: 100.0,
+            completion_per_million: 0.0,
+        });
+        fixture.update_config(|c| {
+            c.model = "budget-fallback/model".to_string();
+            c.budget.per_debate_usd = Some(1.0);
+        });
+
+        let events: Arc<Mutex<Vec<(&str, Value)>>> = Arc::new(Mutex::new(Vec::new()));
+        for name in ["debate-error", "debate-agent-retry"] {
+            let sink = Arc::clone(&events);
+            fixture.app.listen_any(name, move |event| {
+                sink.lock().unwrap().push((name, serde_json::from_str(event.payload()).unwrap()));
+            });
+        }
+
+        fixture.run(true, &["rationalist"]).await.expect("budget stop is a graceful exit");
+
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|(name, payload)| *name == "debate-agent-retry" && payload["fallback"] == "markdown"));
+        let error = events.iter().find(|(name, _)| *name == "debate-error").expect("budget should stop the fallback");
+        assert_eq!(error.1["reason"], "budget_exceeded");
+        let markdown_calls = fixture.server.requests().iter()
+            .filter(|r| r.get("response_format").is_none() && test_support::request_text(r).contains("Synthesize this debate"))
+            .count();
+        assert_eq!(markdown_calls, 0);
+    }
+
+    #[tokio::test]
+    async fn e2e_agent_reasoning_is_streamed_and_saved_outside_the_transcript() {
+        use tauri::Listener;
+
+        let fixture = DebateFixture::new("decision", |request| {
+            Some(if request.get("response_format").is_some() {
+                FixtureResponse::text(&moderator_verdict_json())
+            } else {
+                FixtureResponse {
+                    reasoning: "Private scratchpad: compare runway against risk.".to_string(),
+                    ..FixtureResponse::text("I would take the offer because the runway is solid.")
+                }
+            })
+        })
+        .await;
+        fixture.update_config(|c| c.save_reasoning = true);
+
+        let reasoning_events: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reasoning_events);
+        fixture.app.listen_any("debate-agent-reasoning", move |event| {
+            sink.lock().unwrap().push(serde_json::from_str(event.payload()).unwrap());
+        });
+
+        fixture.run(true, &["rationalist"]).await.expect("debate should complete");
+
+        let rounds = fixture.with_state(|s| s.db.get_debate_rounds(&fixture.decision_id).unwrap());
+        assert!(!rounds[0].content.contains("scratchpad"));
+        assert_eq!(rounds[0].reasoning.as_deref(), Some("Private scratchpad: compare runway against risk."));
+
+        let events = reasoning_events.lock().unwrap();
+        assert!(!events.is_empty());
+        assert_eq!(events[0]["agent"], "rationalist");
+
+        let moderator_request = fixture.server.requests().into_iter().find(|r| r.get("response_format").is_some()).unwrap();
+        assert!(!test_support::request_text(&moderator_request).contains("scratchpad"));
+    }
+
+    #[tokio::test]
+    async fn e2e_standalone_moderator_auto_debate_stops_when_moderator_concludes() {
+        let steering_calls = Arc::new(AtomicUsize::new(0));
+        let steering_counter = Arc::clone(&steering_calls);
+        let fixture = DebateFixture::new("debate", move |request| {
+            let text = test_support::request_text(request);
+            if text.contains("Return ONLY strict JSON") {
+                let call = steering_counter.fetch_add(1, Ordering::SeqCst);
+                let conclude = call >= 1;
+                return Some(FixtureResponse::text(
+                    &json!({"conclude": conclude, "direction": "Debate the cost of waiting another year"}).to_string(),
+                ));
+            }
+            if text.contains("Return a concise synthesis") {
+                return Some(FixtureResponse::text("## Verdict\nBoth sides made fair points."));
+            }
+            Some(FixtureResponse::text("Here is my take on it."))
+        })
+        .await;
+        let moderator = AgentInfo {
+            generation: agents::GenerationParams { temperature: Some(0.1), max_tokens: Some(900), ..Default::default() },
+            ..standalone_participant("moderator", "moderator")
+        };
+
+        fixture.run_moderator_auto(moderator).await.expect("standalone debate should complete against the mock server");
+
+        assert_eq!(steering_calls.load(Ordering::SeqCst), 2);
+        // Opening round + two steered exchanges + moderator verdict.
+        let speakers = fixture.speakers();
+        assert_eq!(speakers.len(), 7);
+        assert_eq!(speakers.last().unwrap(), "moderator");
+        let status = fixture.with_state(|s| s.db.get_decision(&fixture.decision_id).unwrap().unwrap().status);
+        assert_eq!(status, "completed");
+
+        let directed = fixture.server.requests().iter()
+            .filter(|r| test_support::request_text(r).contains("cost of waiting another year"))
+            .count();
+        assert_eq!(directed, 2, "the second exchange should carry the moderator's direction");
+
+        // Steering calls use the moderator's registry settings.
+        let steering: Vec<Value> = fixture.server.requests().into_iter()
+            .filter(|r| test_support::request_text(r).contains("Return ONLY strict JSON"))
+            .collect();
+        assert_eq!(steering.len(), 2);
+        assert!(steering.iter().all(|r| r["temperature"] == json!(0.1) && r["max_tokens"] == json!(900)));
+    }
+
+    #[tokio::test]
+    async fn e2e_moderator_steering_falls_back_to_the_next_model() {
+        use tauri::Listener;
+
+        let fixture = DebateFixture::new("debate", |request| {
+            let text = test_support::request_text(request);
+            Some(if request["model"] == "flaky/model" {
+                FixtureResponse::error(503, "upstream overloaded", Some(0))
+            } else if text.contains("Return ONLY strict JSON") {
+                FixtureResponse::text(&json!({"conclude": true, "direction": ""}).to_string())
+            } else if text.contains("Return a concise synthesis") {
+                FixtureResponse::text("## Verdict\nBoth sides made fair points.")
+            } else {
+                FixtureResponse::text("Here is my take on it.")
+            })
+        })
+        .await;
+        fixture.update_config(|c| {
+            c.agent_models.insert("moderator".to_string(), "flaky/model".to_string());
+            c.agent_fallbacks.insert("moderator".to_string(), vec![config::FallbackModel {
+                provider: String::new(),
+                model: "steady/model".to_string(),
+            }]);
+        });
+        let retries: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&retries);
+        fixture.app.listen_any("debate-agent-retry", move |event| {
+            sink.lock().unwrap().push(serde_json::from_str(event.payload()).unwrap());
+        });
+
+        fixture.run_moderator_auto(standalone_participant("moderator", "moderator")).await.expect("debate should complete");
+
+        // The fallback model's call to conclude ends the debate after the first exchange.
+        assert_eq!(fixture.speakers(), vec!["model_a", "model_b", "model_b", "model_a", "moderator"]);
+        let steering_models: Vec<Value> = fixture.server.requests().into_iter()
+            .filter(|r| test_support::request_text(r).contains("Return ONLY strict JSON"))
+            .map(|r| r["model"].clone())
+            .collect();
+        assert_eq!(steering_models.last(), Some(&json!("steady/model")));
+        assert!(retries.lock().unwrap().iter().any(|r| r["round_number"] == 2 && r["fallback_model"] == "steady/model"));
+    }
+
+    #[tokio::test]
+    async fn e2e_debate_stops_with_budget_reason_when_cap_would_be_exceeded() {
+        use tauri::Listener;
+
+        let fixture = DebateFixture::new("decision", |_| {
+            let mut response = FixtureResponse::text("Spending is fine.");
+            response.usage = Some(json!({"prompt_tokens": 900, "completion_tokens": 100, "cost": 0.004}));
+            Some(response)
+        })
+        .await;
+        fixture.update_config(|c| c.budget.per_debate_usd = Some(0.005));
+
+        let errors: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&errors);
+        fixture.app.listen_any("debate-error", move |event| {
+            sink.lock().unwrap().push(serde_json::from_str(event.payload()).unwrap());
+        });
+
+        fixture.run(false, &["rationalist", "advocate"]).await.expect("budget stop is a graceful exit");
+
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["reason"], "budget_exceeded");
+        assert_eq!(errors[0]["budget"]["scope"], "debate");
+
+        // Only the opening round ran; the moderator never got a turn.
+        assert_eq!(fixture.speakers().len(), 2);
+        let status = fixture.with_state(|s| s.db.get_decision(&fixture.decision_id).unwrap().unwrap().status);
+        assert_eq!(status, "analyzing");
+    }
+
+    #[tokio::test]
+    async fn e2e_debate_stops_before_a_round_whose_estimate_would_exceed_the_cap() {
+        use tauri::Listener;
+
+        // Long turns make the second round's prompt far bigger than the opening's.
+        let long_turn = "The runway holds up under every scenario I can think of. ".repeat(400);
+        let fixture = DebateFixture::new("decision", move |_| {
+            let mut response = FixtureResponse::text(&long_turn);
+            response.usage = Some(json!({"prompt_tokens": 10, "completion_tokens": 10, "cost": 0.0}));
+            Some(response)
+        })
+        .await;
+        usage::remember_pricing("budget-next-round/model", usage::ModelPricing {
+            prompt_per_million: 100.0,
+            completion_per_million: 0.0,
+        });
+        fixture.update_config(|c| {
+            c.model = "budget-next-round/model".to_string();
+            c.budget.per_debate_usd = Some(1.0);
+        });
+
+        let errors: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&errors);
+        fixture.app.listen_any("debate-error", move |event| {
+            sink.lock().unwrap().push(serde_json::from_str(event.payload()).unwrap());
+        });
+
+        fixture.run(false, &["rationalist", "advocate"]).await.expect("budget stop is a graceful exit");
+
+        // Nothing was spent; the opening fit the cap and the next round's estimate did not.
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["reason"], "budget_exceeded");
+        assert_eq!(errors[0]["budget"]["spent_usd"], 0.0);
+        assert!(errors[0]["budget"]["estimated_usd"].as_f64().unwrap() > 1.0);
+        assert_eq!(fixture.speakers().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn e2e_debate_refused_by_the_budget_keeps_the_previous_debate() {
+        use tauri::Listener;
+
+        let fixture = DebateFixture::new("decision", committee_response).await;
+        usage::remember_pricing("budget-rerun/model", usage::ModelPricing {
+            prompt_per_million: 100.0,
+            completion_per_million: 100.0,
+        });
+        fixture.update_config(|c| c.model = "budget-rerun/model".to_string());
+        fixture.run(true, &["rationalist", "contrarian"]).await.expect("first debate should complete");
+        let (rounds_before, decision_before) = fixture.with_state(|s| {
+            (s.db.get_debate_rounds(&fixture.decision_id).unwrap(), s.db.get_decision(&fixture.decision_id).unwrap().unwrap())
+        });
+        let requests_before = fixture.server.requests().len();
+
+        fixture.update_config(|c| c.budget.per_debate_usd = Some(0.000_001));
+        let errors: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&errors);
+        fixture.app.listen_any("debate-error", move |event| {
+            sink.lock().unwrap().push(serde_json::from_str(event.payload()).unwrap());
+        });
+
+        fixture.run(true, &["rationalist", "contrarian"]).await.expect("refusing is a graceful exit");
+
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["reason"], "budget_exceeded");
+        assert_eq!(fixture.server.requests().len(), requests_before);
+        let (rounds_after, decision_after) = fixture.with_state(|s| {
+            (s.db.get_debate_rounds(&fixture.decision_id).unwrap(), s.db.get_decision(&fixture.decision_id).unwrap().unwrap())
+        });
         assert_eq!(
-            recommendation["tradeoffs"],
-            "- Predictability\n- Familiar team"
+            rounds_after.iter().map(|r| &r.id).collect::<Vec<_>>(),
+            rounds_before.iter().map(|r| &r.id).collect::<Vec<_>>()
         );
+        assert_eq!(decision_after.status, decision_before.status);
+        assert_eq!(decision_after.debate_started_at, decision_before.debate_started_at);
     }
 
-    #[test]
-    fn unit_parse_moderator_recommendation_returns_none_without_recommendation_fields() {
-        let no_recommendation = "## Where the Committee Agreed\n- Point A";
-        assert!(parse_moderator_recommendation("", no_recommendation).is_none());
+    #[tokio::test]
+    async fn e2e_capped_debate_refuses_to_start_on_unpriced_hosted_models() {
+        use tauri::Listener;
+
+        let fixture = DebateFixture::new("decision", committee_response).await;
+        let base_url = fixture.server.base_url().to_string();
+        fixture.update_config(|c| {
+            c.model = "budget-unpriced/model".to_string();
+            c.providers[0].kind = "openai".to_string();
+            c.providers[0].base_url = base_url;
+            c.providers[0].api_key = "sk-test".to_string();
+            c.budget.per_day_usd = Some(5.0);
+        });
+
+        let errors: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&errors);
+        fixture.app.listen_any("debate-error", move |event| {
+            sink.lock().unwrap().push(serde_json::from_str(event.payload()).unwrap());
+        });
+
+        fixture.run(true, &["rationalist", "advocate"]).await.expect("refusing is a graceful exit");
+
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["reason"], "budget_unpriced");
+        assert_eq!(errors[0]["models"], json!(["budget-unpriced/model"]));
+        assert!(fixture.server.requests().is_empty());
+        assert!(fixture.speakers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn e2e_agent_falls_back_to_next_model_after_retrying_server_errors() {
+        use tauri::Listener;
+
+        let fixture = DebateFixture::new("decision", |request| {
+            let text = test_support::request_text(request);
+            Some(if request["model"] == "flaky/model" {
+                FixtureResponse::error(503, "upstream overloaded", Some(0))
+            } else if request.get("response_format").is_some() {
+                FixtureResponse::text(&moderator_verdict_json())
+            } else if text.contains("Synthesize this debate") {
+                FixtureResponse::text(MODERATOR_SYNTHESIS)
+            } else {
+                FixtureResponse::text("Waiting a year costs more than it saves.")
+            })
+        })
+        .await;
+        fixture.update_config(|c| {
+            c.agent_models.insert("contrarian".to_string(), "flaky/model".to_string());
+            c.agent_fallbacks.insert("contrarian".to_string(), vec![config::FallbackModel {
+                provider: String::new(),
+                model: "steady/model".to_string(),
+            }]);
+        });
+
+        let retries: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&retries);
+        fixture.app.listen_any("debate-agent-retry", move |event| {
+            sink.lock().unwrap().push(serde_json::from_str(event.payload()).unwrap());
+        });
+
+        fixture.run(true, &["rationalist", "contrarian"]).await.expect("debate should complete on the fallback model");
+
+        assert_eq!(fixture.speakers(), vec!["rationalist", "contrarian", "moderator"]);
+
+        let flaky_calls = fixture.server.requests().iter().filter(|r| r["model"] == "flaky/model").count();
+        assert_eq!(flaky_calls as u32, retry::DEBATE_RETRY_POLICY.max_retries + 1);
+
+        let retries = retries.lock().unwrap();
+        assert_eq!(retries.len(), flaky_calls);
+        assert_eq!(retries[0]["retry_in_ms"], 0);
+        let last = retries.last().unwrap();
+        assert!(last["retry_in_ms"].is_null());
+        assert_eq!(last["fallback_model"], "steady/model");
     }
 
     #[test]
-    fn unit_normalize_spoken_debate_output_removes_rigid_markdown_format() {
-        let raw = r#"
-## Opening
-- **Position**: Go with Option B.
-- **Key argument**: Better upside over 5 years.
-1. **Concern**: Burnout risk is still real.
-"#;
-        let cleaned = normalize_spoken_debate_output(raw);
-        assert!(!cleaned.contains("##"));
-        assert!(!cleaned.contains("**"));
-        assert!(!cleaned.contains("- "));
-        assert!(cleaned.contains("Go with Option B."));
-        assert!(cleaned.contains("Better upside over 5 years."));
-        assert!(cleaned.contains("Burnout risk is still real."));
+    fn unit_debate_schedule_mirrors_run_debate_branches() {
+        assert_eq!(debate_schedule(true, false, None), (vec![1], 0, false));
+        assert_eq!(debate_schedule(false, false, None), (vec![1, 2, 2, 3], 0, false));
+
+        let fixed = StandaloneDebateConfig { mode: "fixed".to_string(), exchange_count: Some(3), max_exchanges: None };
+        assert_eq!(debate_schedule(false, true, Some(fixed)), (vec![1, 2, 2, 2, 3], 2, false));
+
+        let auto = StandaloneDebateConfig { mode: "moderator_auto".to_string(), exchange_count: None, max_exchanges: Some(4) };
+        assert_eq!(debate_schedule(false, true, Some(auto)), (vec![1, 2, 2, 2, 2], 4, true));
+    }
+
+    #[tokio::test]
+    async fn integration_estimate_debate_counts_calls_cost_and_tts_for_committee() {
+        let fixture = DebateFixture::new("decision", |_| None).await;
+        fixture.update_config(|c| c.model = "estimate-test/model".to_string());
+        usage::remember_pricing("estimate-test/model", usage::ModelPricing {
+            prompt_per_million: 1.0,
+            completion_per_million: 2.0,
+        });
+
+        let estimate = estimate_debate(
+            fixture.app.handle(),
+            &fixture.decision_id,
+            false,
+            DebateSetup {
+                selected_agent_keys: Some(vec!["rationalist".to_string(), "advocate".to_string(), "pragmatist".to_string()]),
+                ..DebateSetup::default()
+            },
+        )
+        .await
+        .expect("estimate should not need the network");
+
+        // Four rounds of three speakers plus the moderator verdict.
+        assert_eq!(estimate.llm_calls, 13);
+        assert_eq!(estimate.unpriced_calls, 0);
+        assert!(estimate.brief_tokens > 0);
+        assert!(estimate.cost_usd > 0.0);
+        assert!(!estimate.tts_enabled);
+        assert!(!estimate.is_upper_bound);
+        let turn_chars = budget::EXPECTED_TURN_COMPLETION_TOKENS * budget::CHARS_PER_TOKEN;
+        assert_eq!(
+            estimate.tts_characters,
+            turn_chars * 12 + budget::EXPECTED_VERDICT_COMPLETION_TOKENS * budget::CHARS_PER_TOKEN
+        );
     }
 }
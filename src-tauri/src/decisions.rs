@@ -1,8 +1,12 @@
+use crate::db::{Database, Decision};
+use crate::profile_index;
+use serde::Serialize;
 use serde_json::{json, Value};
+use std::collections::HashSet;
 
 /// Merge new summary fields into existing summary JSON.
 /// Arrays (options, variables, pros_cons) are merged by label/option.
-/// Recommendation is replaced entirely if provided.
+/// Recommendation and debate_summary are replaced entirely if provided.
 pub fn merge_summary(existing_json: Option<&str>, update: &Value) -> String {
     let mut existing: Value = existing_json
         .and_then(|s| serde_json::from_str(s).ok())
@@ -46,6 +50,11 @@ pub fn merge_summary(existing_json: Option<&str>, update: &Value) -> String {
         existing["recommendation"] = rec.clone();
     }
 
+    // Debate summary: replace entirely (one per debate run)
+    if let Some(debate_summary) = update.get("debate_summary") {
+        existing["debate_summary"] = debate_summary.clone();
+    }
+
     serde_json::to_string(&existing).unwrap_or_else(|_| "{}".to_string())
 }
 
@@ -71,6 +80,190 @@ fn merge_array_by_key(existing: Vec<Value>, new_items: &[Value], key: &str) -> V
     result
 }
 
+// ── Past decisions ──
+
+/// A previous decision as returned by `search_past_decisions`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PastDecision {
+    pub decision_id: String,
+    pub title: String,
+    pub status: String,
+    pub created_at: String,
+    /// What the assistant or committee recommended, if anything.
+    pub recommended: Option<String>,
+    pub user_choice: Option<String>,
+    pub outcome: Option<String>,
+}
+
+fn search_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = profile_index::tokenize(query)
+        .into_iter()
+        .filter(|w| w.chars().count() >= 3)
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+/// Keyword relevance on whole words: title hits count most, then what was chosen
+/// and how it turned out, then anything in the structured summary.
+fn relevance(decision: &Decision, terms: &[String]) -> usize {
+    let words = |text: &str| -> HashSet<String> { profile_index::tokenize(text).into_iter().collect() };
+    let title = words(&decision.title);
+    let resolution = words(&format!(
+        "{} {} {}",
+        decision.user_choice.as_deref().unwrap_or(""),
+        decision.user_choice_reasoning.as_deref().unwrap_or(""),
+        decision.outcome.as_deref().unwrap_or(""),
+    ));
+    let summary = words(decision.summary_json.as_deref().unwrap_or(""));
+    terms
+        .iter()
+        .map(|t| 3 * title.contains(t) as usize + 2 * resolution.contains(t) as usize + summary.contains(t) as usize)
+        .sum()
+}
+
+fn recommended_choice(decision: &Decision) -> Option<String> {
+    let summary: Value = serde_json::from_str(decision.summary_json.as_deref()?).ok()?;
+    summary["recommendation"]["choice"].as_str().map(str::to_string)
+}
+
+/// Previous decisions matching `query`, best first. An empty query lists the most
+/// recently updated ones. `resolved_only` keeps decisions the user made or reviewed.
+pub fn search_past_decisions(
+    db: &Database,
+    query: &str,
+    exclude_id: Option<&str>,
+    resolved_only: bool,
+    limit: usize,
+) -> Result<Vec<PastDecision>, String> {
+    let terms = search_terms(query);
+    let mut scored: Vec<(usize, Decision)> = db
+        .get_decisions()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|d| Some(d.id.as_str()) != exclude_id)
+        .filter(|d| !resolved_only || d.user_choice.is_some() || d.outcome.is_some())
+        .map(|d| (relevance(&d, &terms), d))
+        .filter(|(score, _)| terms.is_empty() || *score > 0)
+        .collect();
+    // get_decisions is newest first and the sort is stable, so ties stay newest first.
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    Ok(scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, d)| PastDecision {
+            recommended: recommended_choice(&d),
+            decision_id: d.id,
+            title: d.title,
+            status: d.status,
+            created_at: d.created_at,
+            user_choice: d.user_choice,
+            outcome: d.outcome,
+        })
+        .collect())
+}
+
+/// Everything known about one decision, as markdown for the model: the framing,
+/// the recommendation, the committee's verdict, what the user chose and the outcome.
+pub fn decision_detail(db: &Database, decision_id: &str) -> Result<String, String> {
+    let decision = db
+        .get_decision(decision_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No decision with id {}", decision_id))?;
+    let summary: Value = decision
+        .summary_json
+        .as_deref()
+        .and_then(|s| serde_json::from_str(s).ok())
+        .unwrap_or_else(|| json!({}));
+    let labelled = |items: &Value, key: &str, detail: &str| -> Vec<String> {
+        items[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|item| match item[detail].as_str().filter(|d| !d.is_empty()) {
+                Some(d) => format!("- {}: {}", item["label"].as_str().unwrap_or("?"), d),
+                None => format!("- {}", item["label"].as_str().unwrap_or("?")),
+            })
+            .collect()
+    };
+
+    let mut sections = vec![format!(
+        "# {}\nStatus: {} (created {})",
+        decision.title,
+        decision.status,
+        decision.created_at.get(..10).unwrap_or(&decision.created_at),
+    )];
+    let options = labelled(&summary, "options", "description");
+    if !options.is_empty() {
+        sections.push(format!("## Options\n{}", options.join("\n")));
+    }
+    let variables = labelled(&summary, "variables", "value");
+    if !variables.is_empty() {
+        sections.push(format!("## Key Variables\n{}", variables.join("\n")));
+    }
+    if let Some(choice) = summary["recommendation"]["choice"].as_str() {
+        sections.push(format!(
+            "## Recommendation\n{} (confidence: {})\n{}",
+            choice,
+            summary["recommendation"]["confidence"].as_str().unwrap_or("unknown"),
+            summary["recommendation"]["reasoning"].as_str().unwrap_or(""),
+        ));
+    }
+    if let Some(stored) = db.get_moderator_verdict(decision_id).map_err(|e| e.to_string())? {
+        let verdict: Value = serde_json::from_str(&stored.verdict_json).unwrap_or_default();
+        let disagreements: Vec<String> = verdict["key_disagreements"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|d| d.as_str())
+            .map(|d| format!("- {}", d))
+            .collect();
+        if !disagreements.is_empty() {
+            sections.push(format!("## Committee Disagreements\n{}", disagreements.join("\n")));
+        }
+    }
+    if let Some(choice) = &decision.user_choice {
+        let reasoning = decision.user_choice_reasoning.as_deref().map(|r| format!("\nWhy: {}", r)).unwrap_or_default();
+        sections.push(format!("## What They Chose\n{}{}", choice, reasoning));
+    }
+    if let Some(outcome) = &decision.outcome {
+        let date = decision.outcome_date.as_deref().map(|d| format!(" ({})", d.get(..10).unwrap_or(d))).unwrap_or_default();
+        sections.push(format!("## Outcome{}\n{}", date, outcome));
+    }
+    Ok(sections.join("\n\n"))
+}
+
+/// "Related Past Decisions" section for a debate brief: resolved decisions that
+/// share vocabulary with this one, with what was chosen and how it went.
+pub fn related_decisions_section(db: &Database, decision: &Decision) -> Result<Option<String>, String> {
+    let mut query = decision.title.clone();
+    if let Some(summary) = decision.summary_json.as_deref().and_then(|s| serde_json::from_str::<Value>(s).ok()) {
+        for option in summary["options"].as_array().into_iter().flatten() {
+            query.push(' ');
+            query.push_str(option["label"].as_str().unwrap_or(""));
+        }
+    }
+    let related = search_past_decisions(db, &query, Some(&decision.id), true, 3)?;
+    if related.is_empty() {
+        return Ok(None);
+    }
+    let lines: Vec<String> = related
+        .iter()
+        .map(|d| {
+            let mut line = format!("- **{}** ({})", d.title, d.created_at.get(..10).unwrap_or(&d.created_at));
+            if let Some(choice) = &d.user_choice {
+                line.push_str(&format!(": chose {}", choice));
+            }
+            if let Some(outcome) = &d.outcome {
+                line.push_str(&format!(". Outcome: {}", outcome));
+            }
+            line
+        })
+        .collect();
+    Ok(Some(format!("## Related Past Decisions\n{}", lines.join("\n"))))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,6 +303,10 @@ mod tests {
                 "choice": "Leave",
                 "confidence": "high",
                 "reasoning": "Higher long-term upside"
+            },
+            "debate_summary": {
+                "consensus_points": ["Growth matters"],
+                "final_votes": {"rationalist": "Leave"}
             }
         });
 
@@ -128,6 +325,7 @@ mod tests {
         assert_eq!(merged_json["variables"][0]["value"], "$130k");
         assert_eq!(merged_json["pros_cons"].as_array().expect("pros_cons array").len(), 2);
         assert_eq!(merged_json["recommendation"]["choice"], "Leave");
+        assert_eq!(merged_json["debate_summary"]["final_votes"]["rationalist"], "Leave");
     }
 
     #[test]
@@ -144,4 +342,58 @@ mod tests {
         assert_eq!(merged_json["variables"].as_array().expect("variables array").len(), 1);
         assert_eq!(merged_json["variables"][0]["label"], "Risk tolerance");
     }
+
+    #[test]
+    fn integration_past_decisions_are_searchable_with_choices_outcomes_and_verdicts() {
+        let db = Database::new(":memory:").expect("in-memory database should initialize");
+        let add = |title: &str, choice: Option<&str>, outcome: Option<&str>| {
+            let conv = db.create_conversation_with_type(title, "decision").unwrap();
+            let decision = db.create_decision(&conv.id, title).unwrap();
+            if let Some(choice) = choice {
+                db.update_decision_choice(&decision.id, choice, Some("Closer to family")).unwrap();
+            }
+            if let Some(outcome) = outcome {
+                db.update_decision_outcome(&decision.id, outcome).unwrap();
+            }
+            decision.id
+        };
+        let relocation = add("Relocate to Denver for the job offer?", Some("Relocate"), Some("Happier, but the commute is long"));
+        let car = add("Lease or buy a car", Some("Buy"), None);
+        let open_offer = add("Accept the Stripe job offer?", None, None);
+
+        db.update_decision_summary(&relocation, &merge_summary(None, &json!({
+            "options": [{"label": "Relocate", "description": "Move in March"}, {"label": "Stay"}],
+            "variables": [{"label": "Rent", "value": "$2,100"}],
+            "recommendation": {"choice": "Relocate", "confidence": "medium", "reasoning": "Career growth"}
+        }))).unwrap();
+        db.save_moderator_verdict(&relocation, &json!({"key_disagreements": ["Cost of living"]}).to_string(), "structured").unwrap();
+
+        let found = search_past_decisions(&db, "Should I take the job offer?", Some(&open_offer), false, 5).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].decision_id, relocation);
+        assert_eq!(found[0].recommended.as_deref(), Some("Relocate"));
+        assert_eq!(found[0].outcome.as_deref(), Some("Happier, but the commute is long"));
+
+        // Whole words only: "car" doesn't match the relocation's "Career growth".
+        let found = search_past_decisions(&db, "car", None, false, 5).unwrap();
+        assert_eq!(found.iter().map(|d| d.decision_id.as_str()).collect::<Vec<_>>(), vec![car.as_str()]);
+
+        assert_eq!(search_past_decisions(&db, "", None, false, 5).unwrap().len(), 3);
+        let resolved = search_past_decisions(&db, "", None, true, 5).unwrap();
+        assert!(resolved.iter().all(|d| d.decision_id != open_offer));
+        assert!(resolved.iter().any(|d| d.decision_id == car));
+
+        let detail = decision_detail(&db, &relocation).unwrap();
+        assert!(detail.starts_with("# Relocate to Denver for the job offer?\nStatus: reviewed"));
+        assert!(detail.contains("## Options\n- Relocate: Move in March\n- Stay"));
+        assert!(detail.contains("## Committee Disagreements\n- Cost of living"));
+        assert!(detail.contains("## What They Chose\nRelocate\nWhy: Closer to family"));
+        assert!(detail.contains("Happier, but the commute is long"));
+        assert!(decision_detail(&db, "missing").is_err());
+
+        let current = db.get_decision(&open_offer).unwrap().unwrap();
+        let section = related_decisions_section(&db, &current).unwrap().expect("a related decision should be found");
+        assert!(section.contains("**Relocate to Denver for the job offer?**"));
+        assert!(section.contains(": chose Relocate. Outcome: Happier"));
+    }
 }
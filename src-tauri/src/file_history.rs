@@ -0,0 +1,111 @@
+//! Numbered revisions of a single file, each a JSON snapshot `<n>.json` in a directory
+//! of its own. Profile files (`profile_history`) and the chat system prompts
+//! (`prompts`) keep their history this way.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ── Data types ──
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub revision: u32,
+    /// What made the change: `write`, `delete`, `rename` (either side of one), `restore`,
+    /// `confirm` (a fact's metadata was refreshed) or `reset` (a prompt back to its
+    /// built-in text). `external` marks content found on disk that the history hadn't
+    /// seen: a hand edit, or a file that predates the history.
+    pub action: String,
+    /// The file's content after this revision; `None` once it was deleted or renamed away.
+    pub content: Option<String>,
+    pub saved_at: String,
+}
+
+/// The revisions of one file. Revisions are numbered from 1 without gaps.
+pub struct FileHistory {
+    dir: PathBuf,
+    /// The file's name, for error messages.
+    name: String,
+}
+
+/// Stable hash of some content: FNV-1a 64, which doesn't change across Rust releases
+/// the way std's `DefaultHasher` may, so hashes can be stored and compared later.
+pub fn content_hash(content: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in content.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+// ── Revision storage ──
+
+fn revision_number(path: &Path) -> Option<u32> {
+    if path.extension().and_then(|e| e.to_str()) != Some("json") {
+        return None;
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+impl FileHistory {
+    pub fn new(dir: PathBuf, name: &str) -> Self {
+        FileHistory { dir, name: name.to_string() }
+    }
+
+    pub fn count(&self) -> u32 {
+        fs::read_dir(&self.dir)
+            .map(|entries| entries.filter_map(|e| revision_number(&e.ok()?.path())).max().unwrap_or(0))
+            .unwrap_or(0)
+    }
+
+    pub fn read(&self, revision: u32) -> Result<Revision, String> {
+        let path = self.dir.join(format!("{}.json", revision));
+        let content = fs::read_to_string(&path).map_err(|_| format!("Revision {} of {} not found", revision, self.name))?;
+        serde_json::from_str(&content).map_err(|e| format!("Revision {} of {} is unreadable: {}", revision, self.name, e))
+    }
+
+    /// Every revision, newest first.
+    pub fn list(&self) -> Result<Vec<Revision>, String> {
+        (1..=self.count()).rev().map(|revision| self.read(revision)).collect()
+    }
+
+    fn latest_content(&self) -> Result<Option<Option<String>>, String> {
+        match self.count() {
+            0 => Ok(None),
+            latest => Ok(Some(self.read(latest)?.content)),
+        }
+    }
+
+    fn append(&self, action: &str, content: Option<&str>) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let revision = Revision {
+            revision: self.count() + 1,
+            action: action.to_string(),
+            content: content.map(str::to_string),
+            saved_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let json = serde_json::to_string_pretty(&revision).map_err(|e| e.to_string())?;
+        fs::write(self.dir.join(format!("{}.json", revision.revision)), json).map_err(|e| e.to_string())
+    }
+
+    /// Call before changing the file: content on disk that doesn't match the latest
+    /// revision is kept as an `external` revision so the change can't lose it.
+    pub fn capture(&self, on_disk: &str) -> Result<(), String> {
+        match self.latest_content()? {
+            Some(Some(latest)) if latest == on_disk => Ok(()),
+            _ => self.append("external", Some(on_disk)),
+        }
+    }
+
+    /// Record a change just made to the file (`content` is `None` when it no longer
+    /// exists). A change that leaves the file as the latest revision has it adds nothing.
+    pub fn record(&self, action: &str, content: Option<&str>) -> Result<(), String> {
+        if let Some(latest) = self.latest_content()? {
+            if latest.as_deref() == content {
+                return Ok(());
+            }
+        }
+        self.append(action, content)
+    }
+}
@@ -0,0 +1,201 @@
+//! Watches `profile/` and `agents/` for edits made outside the app (the folders
+//! `open_profile_folder` and `open_agents_folder` reveal) and tells the UI which files
+//! changed, so open views can reload instead of showing stale text.
+//!
+//! The app's own writes (UI saves, assistant edits) are reported too. That is
+//! deliberate: a reload shows the same text, and another open view may be stale.
+
+use crate::agents;
+use crate::profile;
+use crate::safe_path;
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Runtime};
+
+pub const PROFILE_FILES_CHANGED: &str = "profile-files-changed";
+pub const AGENT_FILES_CHANGED: &str = "agent-files-changed";
+
+/// Editors save in bursts (temp file, rename, chmod); events this close together are
+/// reported once.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// A steady stream of changes (a sync tool, a script) is still reported this often.
+const MAX_BATCH_WAIT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilesChanged {
+    pub filenames: Vec<String>,
+    /// Set when an edit left `registry.json` unreadable. The file is not touched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct WatchedDirs {
+    profile: PathBuf,
+    agents: PathBuf,
+}
+
+impl WatchedDirs {
+    fn new(app_data_dir: &PathBuf) -> Result<Self, String> {
+        let canonical = |dir: PathBuf| -> Result<PathBuf, String> {
+            fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            dir.canonicalize().map_err(|e| e.to_string())
+        };
+        Ok(WatchedDirs {
+            profile: canonical(profile::get_profile_dir(app_data_dir))?,
+            agents: canonical(agents::get_agents_dir(app_data_dir))?,
+        })
+    }
+
+    /// Sort changed paths into profile and agent filenames. Only files the app reads
+    /// count: `.md` files directly in either folder, and the agent registry. Revision
+    /// history, editor swap files and the like are dropped.
+    fn changed_files(&self, paths: impl IntoIterator<Item = PathBuf>) -> (BTreeSet<String>, BTreeSet<String>) {
+        let (mut profile, mut agents) = (BTreeSet::new(), BTreeSet::new());
+        for path in paths {
+            let (Some(parent), Some(filename)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+                continue;
+            };
+            let parent = parent.canonicalize().unwrap_or_else(|_| parent.to_path_buf());
+            let readable = safe_path::validate_filename(filename).is_ok();
+            if parent == self.profile && readable {
+                profile.insert(filename.to_string());
+            } else if parent == self.agents && (readable || filename == agents::REGISTRY_FILENAME) {
+                agents.insert(filename.to_string());
+            }
+        }
+        (profile, agents)
+    }
+
+    /// The events to emit for a batch of changed paths.
+    fn events(&self, paths: impl IntoIterator<Item = PathBuf>) -> Vec<(&'static str, FilesChanged)> {
+        let (profile, agents) = self.changed_files(paths);
+        let mut events = Vec::new();
+        if !profile.is_empty() {
+            events.push((PROFILE_FILES_CHANGED, FilesChanged { filenames: profile.into_iter().collect(), error: None }));
+        }
+        if !agents.is_empty() {
+            let error = if agents.contains(agents::REGISTRY_FILENAME) {
+                // A deleted registry is fine: the next load reseeds the built-ins.
+                fs::read_to_string(self.agents.join(agents::REGISTRY_FILENAME))
+                    .ok()
+                    .and_then(|content| agents::parse_registry(&content).err())
+            } else {
+                None
+            };
+            events.push((AGENT_FILES_CHANGED, FilesChanged { filenames: agents.into_iter().collect(), error }));
+        }
+        events
+    }
+}
+
+/// Start watching on a background thread that lives as long as the app.
+pub fn start<R: Runtime>(app_handle: AppHandle<R>, app_data_dir: &PathBuf) -> Result<(), String> {
+    let dirs = WatchedDirs::new(app_data_dir)?;
+    let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| e.to_string())?;
+    for dir in [&dirs.profile, &dirs.agents] {
+        watcher.watch(dir, RecursiveMode::NonRecursive).map_err(|e| e.to_string())?;
+    }
+
+    std::thread::spawn(move || {
+        let _watcher = watcher;
+        let changed_paths = |event: notify::Result<notify::Event>| match event {
+            Ok(event) if !event.kind.is_access() => event.paths,
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                eprintln!("File watcher error: {}", e);
+                Vec::new()
+            }
+        };
+        while let Ok(first) = rx.recv() {
+            let mut paths = changed_paths(first);
+            let deadline = Instant::now() + MAX_BATCH_WAIT;
+            while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                match rx.recv_timeout(DEBOUNCE.min(left)) {
+                    Ok(next) => paths.extend(changed_paths(next)),
+                    Err(_) => break,
+                }
+            }
+            for (event, payload) in dirs.events(paths) {
+                let _ = app_handle.emit(event, payload);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn unit_changes_are_grouped_by_folder_and_filtered_to_app_files() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let dirs = WatchedDirs::new(&app_data_dir).unwrap();
+        let profile_dir = profile::get_profile_dir(&app_data_dir);
+        let agents_dir = agents::get_agents_dir(&app_data_dir);
+
+        let events = dirs.events(vec![
+            profile_dir.join("career.md"),
+            profile_dir.join("career.md"),
+            profile_dir.join(".career.md.swp"),
+            profile_dir.join(".history"),
+            profile_dir.join("notes.txt"),
+            agents_dir.join("contrarian.md"),
+            app_data_dir.join("config.json"),
+        ]);
+        assert_eq!(
+            events,
+            vec![
+                (PROFILE_FILES_CHANGED, FilesChanged { filenames: vec!["career.md".to_string()], error: None }),
+                (AGENT_FILES_CHANGED, FilesChanged { filenames: vec!["contrarian.md".to_string()], error: None }),
+            ]
+        );
+        assert!(dirs.events(vec![profile_dir.join(".history").join("career.md")]).is_empty());
+    }
+
+    #[test]
+    fn integration_corrupt_registry_is_reported_with_the_change() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let dirs = WatchedDirs::new(&app_data_dir).unwrap();
+        let registry = agents::get_agents_dir(&app_data_dir).join(agents::REGISTRY_FILENAME);
+
+        // Deleted: the built-ins come back on the next load, so nothing to report.
+        let events = dirs.events(vec![registry.clone()]);
+        assert_eq!(events[0].1, FilesChanged { filenames: vec!["registry.json".to_string()], error: None });
+
+        fs::write(&registry, "{ \"version\": 1, \"agents\": [").unwrap();
+        let events = dirs.events(vec![registry.clone()]);
+        assert_eq!(events[0].0, AGENT_FILES_CHANGED);
+        assert!(events[0].1.error.as_deref().unwrap().starts_with("agents/registry.json is corrupt"));
+    }
+
+    #[test]
+    fn integration_started_watcher_emits_event_for_an_edited_profile_file() {
+        use tauri::Listener;
+
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let app = tauri::test::mock_app();
+        let (tx, rx) = mpsc::channel::<FilesChanged>();
+        let tx = std::sync::Mutex::new(tx);
+        app.listen_any(PROFILE_FILES_CHANGED, move |event| {
+            let _ = tx.lock().unwrap().send(serde_json::from_str(event.payload()).unwrap());
+        });
+
+        start(app.handle().clone(), &app_data_dir).expect("watcher should start");
+        fs::write(profile::get_profile_dir(&app_data_dir).join("career.md"), "# Career\n- Engineer\n").unwrap();
+
+        let changed = rx.recv_timeout(Duration::from_secs(10)).expect("the edit should be reported");
+        assert_eq!(changed, FilesChanged { filenames: vec!["career.md".to_string()], error: None });
+    }
+}
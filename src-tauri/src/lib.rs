@@ -1,12 +1,26 @@
 mod agents;
+mod attachments;
+mod budget;
 mod commands;
 mod config;
+mod context;
 mod db;
 mod debate;
 mod decisions;
+mod file_history;
+mod file_watcher;
 mod llm;
+mod mock_llm;
 mod profile;
+mod profile_history;
+mod profile_index;
+mod prompts;
+mod providers;
+mod retry;
+mod safe_path;
 mod tts;
+mod usage;
+mod verdict;
 mod video;
 
 use commands::AppState;
@@ -26,18 +40,40 @@ pub fn run() {
             let database = db::Database::new(db_path.to_str().unwrap())
                 .expect("Failed to initialize database");
 
+            // Offline mode for end-to-end testing: serve LLM calls from recorded fixtures.
+            let upstream = providers::default_endpoint(&config::load_config(&app_data_dir)).ok();
+            if let Some(server) = tauri::async_runtime::block_on(
+                mock_llm::start_fixture_mode_from_env(upstream),
+            )? {
+                providers::enable_fixture_mode(server.base_url());
+                app.manage(server);
+            }
+
+            usage::load_pricing(&app_data_dir);
+
+            if let Err(e) = file_watcher::start(app.handle().clone(), &app_data_dir) {
+                eprintln!("Could not watch profile and agent folders: {}", e);
+            }
+
             app.manage(Mutex::new(AppState {
                 db: database,
                 app_data_dir,
                 debate_cancel_flags: std::collections::HashMap::new(),
+                chat_cancel_handles: std::collections::HashMap::new(),
             }));
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::send_message,
+            commands::edit_message,
+            commands::regenerate_message,
+            commands::cancel_message,
             commands::get_conversations,
             commands::get_messages,
+            commands::get_conversation_branches,
+            commands::switch_branch,
+            commands::get_tool_calls,
             commands::get_settings,
             commands::get_openrouter_models,
             commands::save_settings,
@@ -49,23 +85,51 @@ pub fn run() {
             commands::get_decision,
             commands::get_decision_by_conversation,
             commands::update_decision_status,
+            commands::add_attachment,
+            commands::get_attachments,
+            commands::delete_attachment,
             commands::get_profile_files_detailed,
             commands::update_profile_file,
             commands::remove_profile_file,
+            commands::get_profile_revisions,
+            commands::diff_profile_revisions,
+            commands::restore_profile_revision,
+            commands::get_profile_facts,
+            commands::confirm_profile_fact,
+            commands::get_pending_profile_changes,
+            commands::approve_profile_change,
+            commands::reject_profile_change,
             commands::get_agent_registry,
             commands::get_agent_files,
             commands::update_agent_file,
+            commands::get_system_prompts,
+            commands::update_system_prompt,
+            commands::reset_system_prompt,
+            commands::get_system_prompt_versions,
+            commands::restore_system_prompt_version,
             commands::save_agent_model,
+            commands::get_providers,
+            commands::save_provider,
+            commands::delete_provider,
+            commands::save_agent_provider,
+            commands::save_agent_fallbacks,
+            commands::save_agent_generation,
             commands::open_agents_folder,
             commands::create_custom_agent,
             commands::delete_custom_agent,
             commands::start_debate,
+            commands::estimate_debate,
             commands::get_debate,
+            commands::get_moderator_verdict,
             commands::cancel_debate,
+            commands::get_debate_cost,
+            commands::get_monthly_spend,
+            commands::save_budget,
             commands::generate_debate_audio,
             commands::get_debate_audio,
             commands::create_standalone_debate,
             commands::start_standalone_debate,
+            commands::estimate_standalone_debate,
             commands::get_standalone_debates,
             commands::render_video,
             commands::save_pdf,
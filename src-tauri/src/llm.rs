@@ -1,93 +1,128 @@
+use crate::agents::GenerationParams;
+use crate::attachments;
 use crate::commands::AppState;
+use crate::config;
+use crate::context;
+use crate::db::{NewPendingProfileChange, NewToolCall};
 use crate::decisions;
-use crate::profile;
+use crate::profile::{self, ProfileEdit};
+use crate::profile_history;
+use crate::profile_index;
+use crate::prompts;
+use crate::providers::{LlmEndpoint, ProviderKind};
+use crate::usage::{self, TokenUsage, UsageContext};
 use reqwest::Client;
 use serde::Serialize;
 use serde_json::{json, Value};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::ipc::Channel;
-use tauri::{Emitter, Manager};
+use tokio::sync::Notify;
+use tauri::{Emitter, Manager, Runtime};
 
-const OPENROUTER_URL: &str = "https://openrouter.ai/api/v1/chat/completions";
-
-const SYSTEM_PROMPT: &str = r#"You are a personal decision-making assistant. Your primary job right now is to deeply understand the user — who they are, what they value, what their life situation looks like, and what matters most to them.
-
-You have access to a set of profile files stored as markdown on the user's machine. These files contain what you've learned about the user so far. Before every response, you should read the relevant profile files to remind yourself what you know.
-
-As you learn new things about the user through conversation, you should update or create profile files to remember this information. Be organized — create separate files for different aspects of the user's life (career, finances, family, values, goals, health, etc.). Don't ask permission to save — just save what you learn naturally.
-
-When saving profile information:
-- Write in a clear, structured markdown format
-- Use headers and bullet points for organization
-- Include context and nuance, not just bare facts
-- Update existing files rather than duplicating information
-- Create new files when you discover a new significant aspect of the user's life
-
-Be conversational and warm. Ask thoughtful follow-up questions. Don't interrogate — let understanding develop naturally through genuine conversation. You're building a relationship, not filling out a form.
-
-When you have enough context about the user and they bring you a decision to make, you should:
-1. Read all relevant profile files
-2. Consider all variables and how they interact
-3. Weigh tradeoffs against the user's stated values and priorities
-4. Give a clear, committed recommendation with transparent reasoning
-5. Explain what they'd be giving up with your recommended choice
-
-But for now, focus on learning about the user. The better you understand them, the better your future recommendations will be."#;
+// ── Stream event sent to frontend via Channel ──
 
-const DECISION_SYSTEM_PROMPT: &str = r#"You are a personal decision-making assistant. The user is working through a specific decision and needs your help analyzing it thoroughly.
+#[derive(Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum StreamEvent {
+    #[serde(rename = "token")]
+    Token { token: String },
+    /// Model reasoning, streamed apart from the answer text.
+    #[serde(rename = "reasoning")]
+    Reasoning { token: String },
+    #[serde(rename = "tool_use")]
+    ToolUse { tool: String },
+    #[serde(rename = "tool_result")]
+    ToolResult {
+        tool: String,
+        success: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
 
-You have access to the user's profile files — markdown files that contain everything you've learned about them: their values, priorities, life situation, constraints, finances, career, family, and goals. READ THESE FIRST before engaging with the decision.
+/// Stop handle for an in-flight chat response. Cancelling wakes the streaming
+/// loop right away, even while it is waiting on the network.
+#[derive(Debug, Clone, Default)]
+pub struct ChatCancel {
+    flag: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
 
-Your job is to:
+impl ChatCancel {
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
 
-1. GATHER INFORMATION (status: "exploring")
-   - What are they deciding between? (Surface all options, including ones they haven't considered)
-   - What's the timeline? Is this reversible?
-   - What triggered this decision now?
-   - What factors are at play? (financial, career, emotional, relational, health, etc.)
-   - Ask focused questions, one or two at a time. Don't overwhelm.
-   - Push back if the user is framing the decision too narrowly ("should I quit?" is rarely binary)
+    /// Whether both are clones of the same handle.
+    pub fn is_same(&self, other: &ChatCancel) -> bool {
+        Arc::ptr_eq(&self.flag, &other.flag)
+    }
 
-2. UPDATE THE DECISION SUMMARY
-   CRITICAL: Call `update_decision_summary` on EVERY response. This populates the structured panel the user sees alongside the chat.
-   - On your VERY FIRST response: identify the options being considered and key variables, then call the tool with at least `options` and `variables` populated, plus set `status` to "exploring".
-   - On subsequent responses: update the summary progressively — add new options, refine variables, add pros/cons.
-   - Even for seemingly simple or casual decisions (what to watch, where to eat, what to buy), ALWAYS structure them with options and variables. Every decision has factors worth analyzing.
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
 
-3. HAND OFF TO COMMITTEE (status: "analyzing")
-   Once you have gathered enough information to frame the decision clearly — meaning you have identified the realistic options AND the key variables/factors — set `status` to "analyzing" in your next `update_decision_summary` call. This AUTOMATICALLY triggers the committee of AI advisors to debate the decision from multiple perspectives. You do NOT make the recommendation yourself. The committee does that.
-   - Do NOT wait for perfection. 2-3 exchanges is usually enough. If the user has given you the core decision and enough context to identify options and factors, hand it off.
-   - Do NOT try to analyze or recommend yourself. Your role is to gather info and frame the decision, then let the committee handle analysis and recommendation.
-   - The committee debate starts automatically when you set status to "analyzing". Do not tell the user to click anything.
+    async fn cancelled(&self) {
+        loop {
+            // Register before checking the flag so a concurrent cancel() is not missed.
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
 
-Guidelines:
-- Ask focused questions, one or two at a time. Don't overwhelm.
-- Name cognitive biases if you spot them (sunk cost, anchoring, status quo bias, etc.)
-- Be honest even if it's not what they want to hear
-- If you don't have enough information from the profile files, ask for it
-- If new information emerges that should be saved to the profile, update the profile files too
+/// Text produced by a chat turn; `cancelled` marks a reply stopped by the user.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatReply {
+    pub text: String,
+    /// Reasoning streamed alongside the text, empty for non-reasoning models.
+    pub reasoning: String,
+    pub cancelled: bool,
+}
 
-6. REFLECT ON OUTCOMES
-   When you see a message starting with "[DECISION OUTCOME LOGGED]", the user has reported how their decision turned out. This is a critical learning moment:
+/// One debate turn: the spoken text and, separately, any streamed reasoning.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DebateReply {
+    pub text: String,
+    pub reasoning: String,
+}
 
-   a) READ PROFILE FILES first to understand the full context of who this person is
-   b) COMPARE: your recommendation vs. what the user chose vs. what actually happened
-   c) ANALYZE: factors you over/underweighted, biases at play, what the user's intuition captured that your analysis missed (or vice versa), unpredictable external factors vs foreseeable outcomes
-   d) UPDATE PROFILE FILES with lessons learned — create or update a "decision-patterns.md" file tracking what works for this user and what doesn't, and update other relevant profiles if the outcome reveals new info about their values, risk tolerance, or priorities. Be specific — e.g. "user's read on organizational culture tends to be more reliable than quantitative analysis" rather than "user trusts gut feelings"
-   e) SHARE your reflection transparently in the chat. Be honest about what you got right, what you got wrong, and how this will change your future recommendations for this user"#;
+/// Who answers a call and how: the endpoint and model, their generation settings,
+/// and what the call's usage row is linked to.
+#[derive(Clone, Copy)]
+pub struct LlmCall<'a> {
+    pub endpoint: &'a LlmEndpoint,
+    pub model: &'a str,
+    pub params: &'a GenerationParams,
+    pub usage_ctx: &'a UsageContext,
+}
 
-// ── Stream event sent to frontend via Channel ──
+/// Reasoning text in a streamed delta. OpenRouter sends `reasoning`; DeepSeek and
+/// several OpenAI-compatible servers send `reasoning_content`.
+fn reasoning_delta(delta: &Value) -> Option<&str> {
+    delta["reasoning"]
+        .as_str()
+        .or_else(|| delta["reasoning_content"].as_str())
+        .filter(|r| !r.is_empty())
+}
 
-#[derive(Clone, Serialize)]
-#[serde(tag = "type")]
-pub enum StreamEvent {
-    #[serde(rename = "token")]
-    Token { token: String },
-    #[serde(rename = "tool_use")]
-    ToolUse { tool: String },
+/// Characters sent with every chat request besides the history: system prompt and tool schemas.
+pub fn chat_prompt_overhead_chars(app_data_dir: &Path, conv_type: &str) -> usize {
+    let system_prompt = prompts::read_system_prompt(app_data_dir, prompts::prompt_key_for(conv_type));
+    system_prompt.len() + get_tools(conv_type == "decision").to_string().len()
 }
 
+/// Model round-trips allowed per user message. The last one is sent with
+/// `tool_choice: "none"` so the model has to answer in text.
+const MAX_TOOL_ITERATIONS: usize = 8;
+
 // ── OpenAI-compatible tool format (used by OpenRouter) ──
 
 fn get_tools(is_decision: bool) -> Value {
@@ -96,10 +131,19 @@ fn get_tools(is_decision: bool) -> Value {
             "type": "function",
             "function": {
                 "name": "read_profile_files",
-                "description": "Read the list of all profile files and their contents. Call this at the start of conversations to refresh your memory about the user.",
+                "description": "Read what you know about the user. Call this at the start of conversations to refresh your memory. Small profiles come back whole. Larger ones come back as the sections most relevant to `query` (in decision chats, to the decision when no query is given), with the names of the other files.",
                 "parameters": {
                     "type": "object",
-                    "properties": {},
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "What you want to know about the user, e.g. 'savings and monthly expenses'"
+                        },
+                        "all": {
+                            "type": "boolean",
+                            "description": "Return every file in full, e.g. before rewriting one. Defaults to false."
+                        }
+                    },
                     "required": []
                 }
             }
@@ -108,7 +152,7 @@ fn get_tools(is_decision: bool) -> Value {
             "type": "function",
             "function": {
                 "name": "write_profile_file",
-                "description": "Create or update a profile file with information learned about the user. Use descriptive filenames like 'career.md', 'values.md', 'family.md', etc.",
+                "description": "Create a profile file, or rewrite one completely, with information learned about the user. Use descriptive filenames like 'career.md', 'values.md', 'family.md', etc. To change part of an existing file, use append_to_profile_section or replace_profile_section instead of re-sending the whole file.",
                 "parameters": {
                     "type": "object",
                     "properties": {
@@ -125,6 +169,86 @@ fn get_tools(is_decision: bool) -> Value {
                 }
             }
         },
+        {
+            "type": "function",
+            "function": {
+                "name": "append_to_profile_section",
+                "description": "Add lines to the end of one section's own text (ahead of its subsections), leaving the rest of the file untouched. The section is created if it doesn't exist, and the file too. Omit heading to append at the end of the file.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "filename": {
+                            "type": "string",
+                            "description": "The filename (e.g., 'career.md')"
+                        },
+                        "heading": {
+                            "type": "string",
+                            "description": "The section heading, e.g. 'Goals' or '## Goals'"
+                        },
+                        "content": {
+                            "type": "string",
+                            "description": "The markdown to add"
+                        }
+                    },
+                    "required": ["filename", "content"]
+                }
+            }
+        },
+        {
+            "type": "function",
+            "function": {
+                "name": "replace_profile_section",
+                "description": "Replace the text of one markdown section, up to its first subsection. The heading line, its subsections and all other sections are kept; to rewrite a subsection too, include it with its heading.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "filename": {
+                            "type": "string",
+                            "description": "The filename (e.g., 'career.md')"
+                        },
+                        "heading": {
+                            "type": "string",
+                            "description": "The heading of the section to replace"
+                        },
+                        "content": {
+                            "type": "string",
+                            "description": "The new section body, without the heading"
+                        }
+                    },
+                    "required": ["filename", "heading", "content"]
+                }
+            }
+        },
+        {
+            "type": "function",
+            "function": {
+                "name": "rename_profile_file",
+                "description": "Rename a profile file. Fails if the new name is taken; use merge_profile_files to combine two files.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "from": { "type": "string", "description": "The current filename" },
+                        "to": { "type": "string", "description": "The new filename" }
+                    },
+                    "required": ["from", "to"]
+                }
+            }
+        },
+        {
+            "type": "function",
+            "function": {
+                "name": "merge_profile_files",
+                "description": "Move the contents of one profile file to the end of another and delete the first, e.g. to consolidate 'money.md' into 'finances.md'.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "from": { "type": "string", "description": "The file to fold in and delete" },
+                        "into": { "type": "string", "description": "The file that keeps both contents" }
+                    },
+                    "required": ["from", "into"]
+                }
+            }
+        },
         {
             "type": "function",
             "function": {
@@ -146,6 +270,108 @@ fn get_tools(is_decision: bool) -> Value {
 
     if is_decision {
         if let Some(arr) = tools.as_array_mut() {
+            arr.push(json!({
+                "type": "function",
+                "function": {
+                    "name": "search_past_decisions",
+                    "description": "Search the user's earlier decisions by keyword, with what was recommended, what they chose and how it turned out. Use it to find analogous decisions and learn from their outcomes. An empty query lists the most recent ones.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "query": {
+                                "type": "string",
+                                "description": "Keywords, e.g. 'job offer relocation'"
+                            },
+                            "resolved_only": {
+                                "type": "boolean",
+                                "description": "Only decisions the user has made or reported an outcome for"
+                            }
+                        },
+                        "required": []
+                    }
+                }
+            }));
+            arr.push(json!({
+                "type": "function",
+                "function": {
+                    "name": "get_decision_detail",
+                    "description": "Read one past decision in full: options, variables, recommendation, the committee's disagreements, the user's choice and the outcome.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "decision_id": {
+                                "type": "string",
+                                "description": "The decision_id from search_past_decisions"
+                            }
+                        },
+                        "required": ["decision_id"]
+                    }
+                }
+            }));
+            arr.push(json!({
+                "type": "function",
+                "function": {
+                    "name": "list_stale_facts",
+                    "description": "List profile facts the user hasn't confirmed in a while (salary, family situation, where they live, ...), oldest first, with when and where each was learned and how confident it is. Check this before relying on the profile for a decision.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "max_age_days": {
+                                "type": "integer",
+                                "description": "Facts older than this count as stale (default 180; low-confidence facts after a quarter of it)"
+                            }
+                        },
+                        "required": []
+                    }
+                }
+            }));
+            arr.push(json!({
+                "type": "function",
+                "function": {
+                    "name": "confirm_profile_fact",
+                    "description": "Record that the user confirmed a profile fact is still true. If it changed, update the profile file instead.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "filename": {
+                                "type": "string",
+                                "description": "The profile file the fact is in"
+                            },
+                            "fact": {
+                                "type": "string",
+                                "description": "The fact's text as listed by list_stale_facts"
+                            },
+                            "confidence": {
+                                "type": "string",
+                                "enum": ["high", "medium", "low"],
+                                "description": "How sure the user is now (defaults to keeping the current confidence)"
+                            }
+                        },
+                        "required": ["filename", "fact"]
+                    }
+                }
+            }));
+            arr.push(json!({
+                "type": "function",
+                "function": {
+                    "name": "read_attachment",
+                    "description": "Read a document the user attached to this decision (offer letter, lease, spreadsheet, ...). Call without a filename to list the attachments. Long documents come in chunks; pass the offset from the previous result to continue.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "filename": {
+                                "type": "string",
+                                "description": "The attachment's filename, as listed"
+                            },
+                            "offset": {
+                                "type": "integer",
+                                "description": "Character offset to start reading from (default 0)"
+                            }
+                        },
+                        "required": []
+                    }
+                }
+            }));
             arr.push(json!({
                 "type": "function",
                 "function": {
@@ -218,44 +444,112 @@ fn get_tools(is_decision: bool) -> Value {
 
 // ── Shared tool execution ──
 
-fn execute_tool(
+async fn execute_tool<R: Runtime>(
     name: &str,
     input: &Value,
     app_data_dir: &PathBuf,
+    conversation_id: Option<&str>,
     decision_id: Option<&str>,
-    app_handle: &tauri::AppHandle,
-) -> String {
+    app_handle: &tauri::AppHandle<R>,
+) -> Result<String, String> {
+    if let Some(edit) = profile_edit_for_tool(name, input) {
+        if config::load_config(app_data_dir).review_mode {
+            return propose_profile_edit(name, &edit, app_data_dir, conversation_id, decision_id, app_handle);
+        }
+        let text = |key: &str| input[key].as_str().unwrap_or_default();
+        let applied = match name {
+            "append_to_profile_section" => {
+                profile::append_to_profile_section(app_data_dir, text("filename"), input["heading"].as_str(), text("content"), conversation_id)
+            }
+            "replace_profile_section" => {
+                profile::replace_profile_section(app_data_dir, text("filename"), text("heading"), text("content"), conversation_id)
+            }
+            "merge_profile_files" => profile::merge_profile_files(app_data_dir, text("from"), text("into")),
+            _ => edit.apply(app_data_dir, conversation_id),
+        };
+        return applied.map_err(|e| format!("{}: {}", profile_error_context(name), e));
+    }
     match name {
         "read_profile_files" => {
-            match profile::read_all_profiles(app_data_dir) {
-                Ok(files) => serde_json::to_string(&files).unwrap_or_else(|_| "{}".to_string()),
-                Err(e) => format!("Error reading profiles: {}", e),
+            let files = profile::read_all_profiles(app_data_dir)
+                .map_err(|e| format!("Error reading profiles: {}", e))?;
+            let mut query = input["query"].as_str().unwrap_or_default().trim().to_string();
+            if query.is_empty() {
+                if let Some(decision_id) = decision_id {
+                    let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+                    let state_guard = state.lock().map_err(|e| format!("Error locking state: {}", e))?;
+                    if let Some(decision) = state_guard.db.get_decision(decision_id).map_err(|e| e.to_string())? {
+                        query = profile_index::decision_query(&decision);
+                    }
+                }
+            }
+            let config = config::load_config(app_data_dir);
+            let retrieval = profile_index::retrieve(&files, &query, profile_index::TOP_K_SECTIONS, &config).await;
+            if input["all"].as_bool().unwrap_or(false) || query.is_empty() || retrieval.complete {
+                return Ok(serde_json::to_string(&files).unwrap_or_else(|_| "{}".to_string()));
             }
+            let sections: Vec<Value> = retrieval
+                .sections
+                .iter()
+                .map(|s| json!({"filename": s.filename, "section": s.headings.join(" > "), "content": s.content}))
+                .collect();
+            Ok(json!({
+                "query": query,
+                "sections": sections,
+                "other_files": retrieval.omitted_files,
+                "note": "Only the most relevant sections are shown. Search again with another query, or pass all: true for every file.",
+            })
+            .to_string())
+        }
+        "list_stale_facts" => {
+            let max_age_days = input["max_age_days"].as_i64().unwrap_or(profile::STALE_FACT_DAYS);
+            let mut stale = profile::stale_profile_facts(app_data_dir, max_age_days)
+                .map_err(|e| format!("Error reading profile facts: {}", e))?;
+            stale.retain(|f| !f.private);
+            if stale.is_empty() {
+                return Ok("No stale facts; everything in the profile was confirmed recently.".to_string());
+            }
+            Ok(serde_json::to_string(&stale).unwrap_or_else(|_| "[]".to_string()))
         }
-        "write_profile_file" => {
-            let filename = input["filename"].as_str().unwrap_or("unknown.md");
-            let content = input["content"].as_str().unwrap_or("");
-            match profile::write_profile_file(app_data_dir, filename, content) {
-                Ok(msg) => msg,
-                Err(e) => format!("Error writing profile: {}", e),
+        "confirm_profile_fact" => {
+            let filename = input["filename"].as_str().unwrap_or_default();
+            let fact = input["fact"].as_str().unwrap_or_default();
+            if profile::is_private_file(app_data_dir, filename) {
+                return Err(format!("Error confirming fact: {} is private; only the user can change it", filename));
             }
+            profile::confirm_profile_fact(app_data_dir, filename, fact, input["confidence"].as_str())
+                .map_err(|e| format!("Error confirming fact: {}", e))
         }
-        "delete_profile_file" => {
-            let filename = input["filename"].as_str().unwrap_or("");
-            match profile::delete_profile_file(app_data_dir, filename) {
-                Ok(msg) => msg,
-                Err(e) => format!("Error deleting profile: {}", e),
+        "search_past_decisions" => {
+            let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+            let state_guard = state.lock().map_err(|e| format!("Error locking state: {}", e))?;
+            let query = input["query"].as_str().unwrap_or_default();
+            let resolved_only = input["resolved_only"].as_bool().unwrap_or(false);
+            let found = decisions::search_past_decisions(&state_guard.db, query, decision_id, resolved_only, 8)
+                .map_err(|e| format!("Error searching decisions: {}", e))?;
+            if found.is_empty() {
+                return Ok("No matching past decisions.".to_string());
             }
+            Ok(serde_json::to_string(&found).unwrap_or_else(|_| "[]".to_string()))
+        }
+        "get_decision_detail" => {
+            let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+            let state_guard = state.lock().map_err(|e| format!("Error locking state: {}", e))?;
+            let id = input["decision_id"].as_str().unwrap_or_default();
+            decisions::decision_detail(&state_guard.db, id).map_err(|e| format!("Error: {}", e))
+        }
+        "read_attachment" => {
+            let dec_id = decision_id.ok_or("Error: no decision context for read_attachment")?;
+            let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+            let state_guard = state.lock().map_err(|e| format!("Error locking state: {}", e))?;
+            let offset = input["offset"].as_u64().unwrap_or(0) as usize;
+            attachments::read_attachment_for_tool(&state_guard.db, dec_id, input["filename"].as_str(), offset)
+                .map_err(|e| format!("Error reading attachment: {}", e))
         }
         "update_decision_summary" => {
-            let Some(dec_id) = decision_id else {
-                return "Error: no decision context for update_decision_summary".to_string();
-            };
+            let dec_id = decision_id.ok_or("Error: no decision context for update_decision_summary")?;
             let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
-            let state_guard = match state.lock() {
-                Ok(s) => s,
-                Err(e) => return format!("Error locking state: {}", e),
-            };
+            let state_guard = state.lock().map_err(|e| format!("Error locking state: {}", e))?;
 
             let existing_summary = state_guard.db
                 .get_decision(dec_id)
@@ -265,14 +559,12 @@ fn execute_tool(
 
             let merged = decisions::merge_summary(existing_summary.as_deref(), input);
 
-            if let Err(e) = state_guard.db.update_decision_summary(dec_id, &merged) {
-                return format!("Error saving summary: {}", e);
-            }
+            state_guard.db.update_decision_summary(dec_id, &merged)
+                .map_err(|e| format!("Error saving summary: {}", e))?;
 
             if let Some(status) = input.get("status").and_then(|v| v.as_str()) {
-                if let Err(e) = state_guard.db.update_decision_status(dec_id, status) {
-                    return format!("Error updating status: {}", e);
-                }
+                state_guard.db.update_decision_status(dec_id, status)
+                    .map_err(|e| format!("Error updating status: {}", e))?;
             }
 
             let _ = app_handle.emit("decision-summary-updated", json!({
@@ -281,24 +573,324 @@ fn execute_tool(
                 "status": input.get("status").and_then(|v| v.as_str()),
             }));
 
-            "Decision summary updated successfully.".to_string()
+            Ok("Decision summary updated successfully.".to_string())
         }
-        _ => format!("Unknown tool: {}", name),
+        _ => Err(format!("Unknown tool: {}", name)),
     }
 }
 
-// ── Helpers ──
+// ── Review mode ──
+
+/// The profile change a file tool call asks for, if it is one.
+fn profile_edit_for_tool(name: &str, input: &Value) -> Option<ProfileEdit> {
+    let arg = |key: &str| input[key].as_str().unwrap_or_default().to_string();
+    Some(match name {
+        "write_profile_file" => ProfileEdit::Write { filename: arg("filename"), content: arg("content") },
+        "delete_profile_file" => ProfileEdit::Delete { filename: arg("filename") },
+        "append_to_profile_section" => ProfileEdit::AppendSection {
+            filename: arg("filename"),
+            heading: input["heading"].as_str().map(str::to_string),
+            content: arg("content"),
+        },
+        "replace_profile_section" => ProfileEdit::ReplaceSection {
+            filename: arg("filename"),
+            heading: arg("heading"),
+            content: arg("content"),
+        },
+        "rename_profile_file" => ProfileEdit::Rename { from: arg("from"), to: arg("to") },
+        "merge_profile_files" => ProfileEdit::Merge { from: arg("from"), into: arg("into") },
+        _ => return None,
+    })
+}
+
+fn profile_error_context(tool_name: &str) -> &'static str {
+    match tool_name {
+        "write_profile_file" => "Error writing profile",
+        "rename_profile_file" => "Error renaming profile",
+        "merge_profile_files" => "Error merging profiles",
+        "delete_profile_file" => "Error deleting profile",
+        _ => "Error updating profile",
+    }
+}
+
+/// Queue a profile edit for the user's approval instead of applying it. Edits that
+/// couldn't apply (unknown section, bad filename) fail right away as they would
+/// outside review mode.
+fn propose_profile_edit<R: Runtime>(
+    tool_name: &str,
+    edit: &ProfileEdit,
+    app_data_dir: &PathBuf,
+    conversation_id: Option<&str>,
+    decision_id: Option<&str>,
+    app_handle: &tauri::AppHandle<R>,
+) -> Result<String, String> {
+    let changes = edit.preview(app_data_dir).map_err(|e| format!("Error preparing profile change: {}", e))?;
+    let diff: Vec<profile_history::FileDiff> = changes.iter().map(profile_history::diff_change).collect();
+    let files = edit.files();
+
+    let state: tauri::State<'_, Mutex<AppState>> = app_handle.state();
+    let state_guard = state.lock().map_err(|e| format!("Error locking state: {}", e))?;
+    let pending = state_guard
+        .db
+        .create_pending_profile_change(&NewPendingProfileChange {
+            conversation_id,
+            decision_id,
+            tool_name,
+            edit: &serde_json::to_string(edit).map_err(|e| e.to_string())?,
+            diff: &serde_json::to_string(&diff).map_err(|e| e.to_string())?,
+            files: &files,
+            base_hashes: &serde_json::to_string(&profile::base_hashes(&changes)).map_err(|e| e.to_string())?,
+        })
+        .map_err(|e| format!("Error saving pending change: {}", e))?;
+    Ok(format!(
+        "Pending review: your change to {} was NOT applied yet. It is waiting for the user's approval (change {}). \
+         Don't describe it as saved, and don't re-read the profile expecting to see it.",
+        files.join(" and "),
+        pending.id
+    ))
+}
+
+// ── Tool call audit ──
+
+/// Results longer than this are cut before storing; `read_profile_files` returns every profile.
+const MAX_AUDITED_RESULT_CHARS: usize = 10_000;
+
+/// Profile files a tool call touched: those its edit names, or the file of a confirmed fact.
+fn tool_call_files(name: &str, raw_arguments: &str) -> Vec<String> {
+    let input: Value = serde_json::from_str(raw_arguments).unwrap_or(Value::Null);
+    let files = match profile_edit_for_tool(name, &input) {
+        Some(edit) => edit.files(),
+        None if name == "confirm_profile_fact" => input["filename"].as_str().map(str::to_string).into_iter().collect(),
+        None => Vec::new(),
+    };
+    files.into_iter().filter(|f| !f.is_empty()).collect()
+}
+
+/// Log one executed tool call to `tool_calls`, successful or not.
+fn record_tool_call<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    usage_ctx: &UsageContext,
+    name: &str,
+    raw_arguments: &str,
+    result: &Result<String, String>,
+) {
+    let Some(state) = app_handle.try_state::<Mutex<AppState>>() else {
+        return;
+    };
+    let Ok(state_guard) = state.lock() else {
+        return;
+    };
+    let (text, success) = match result {
+        Ok(text) => (text, true),
+        Err(error) => (error, false),
+    };
+    let stored: String = text.chars().take(MAX_AUDITED_RESULT_CHARS).collect();
+    if let Err(e) = state_guard.db.record_tool_call(&NewToolCall {
+        conversation_id: usage_ctx.conversation_id.as_deref(),
+        decision_id: usage_ctx.decision_id.as_deref(),
+        message_id: usage_ctx.message_id.as_deref(),
+        tool_name: name,
+        arguments: raw_arguments,
+        result: &stored,
+        success,
+        files: &tool_call_files(name, raw_arguments),
+    }) {
+        eprintln!("Failed to record tool call: {}", e);
+    }
+}
+
+// ── Tool argument validation ──
+
+/// Parse and validate raw tool-call arguments against the tool's schema in `get_tools`.
+/// Errors are phrased for the model, which gets them back as the tool result.
+fn parse_tool_arguments(tools: &Value, name: &str, raw_arguments: &str) -> Result<Value, String> {
+    let schema = tools
+        .as_array()
+        .and_then(|arr| arr.iter().find(|t| t["function"]["name"] == name))
+        .map(|t| &t["function"]["parameters"])
+        .ok_or_else(|| format!("Error: unknown tool '{}'. Use one of the tools provided.", name))?;
+
+    let raw = if raw_arguments.trim().is_empty() { "{}" } else { raw_arguments };
+    let input: Value = serde_json::from_str(raw).map_err(|e| {
+        format!("Error: arguments for {} are not valid JSON ({}). Retry with a JSON object matching the tool schema.", name, e)
+    })?;
+
+    let mut problems = Vec::new();
+    validate_schema(&input, schema, "arguments", &mut problems);
+    if problems.is_empty() {
+        Ok(input)
+    } else {
+        Err(format!("Error: invalid arguments for {}: {}. Fix them and call the tool again.", name, problems.join("; ")))
+    }
+}
+
+/// Minimal JSON-schema check covering what `get_tools` uses: type, properties,
+/// required, enum and items.
+pub fn validate_schema(value: &Value, schema: &Value, path: &str, problems: &mut Vec<String>) {
+    if let Some(expected) = schema["type"].as_str() {
+        let matches = match expected {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            _ => true,
+        };
+        if !matches {
+            problems.push(format!("{} must be of type {}", path, expected));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema["enum"].as_array() {
+        if !allowed.contains(value) {
+            let options: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            problems.push(format!("{} must be one of {}", path, options.join(", ")));
+        }
+    }
+
+    if let Some(obj) = value.as_object() {
+        for key in schema["required"].as_array().into_iter().flatten().filter_map(|k| k.as_str()) {
+            match obj.get(key) {
+                None | Some(Value::Null) => problems.push(format!("{}.{} is required", path, key)),
+                Some(Value::String(s)) if s.trim().is_empty() => {
+                    problems.push(format!("{}.{} must not be empty", path, key))
+                }
+                _ => {}
+            }
+        }
+        if let Some(properties) = schema["properties"].as_object() {
+            for (key, prop_schema) in properties {
+                if let Some(prop_value) = obj.get(key).filter(|v| !v.is_null()) {
+                    validate_schema(prop_value, prop_schema, &format!("{}.{}", path, key), problems);
+                }
+            }
+        }
+    }
+
+    if let (Some(items), Some(item_schema)) = (value.as_array(), schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            validate_schema(item, item_schema, &format!("{}[{}]", path, index), problems);
+        }
+    }
+}
+
+// ── Errors ──
+
+/// What went wrong with an LLM call, as far as retry and fallback logic cares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmErrorKind {
+    /// 429; the provider may say how long to wait.
+    RateLimited,
+    /// 5xx from the provider or the model behind it.
+    Server,
+    /// Connection failure, timeout or a stream cut off mid-response.
+    Network,
+    /// 402, out of credits.
+    PaymentRequired,
+    /// 401/403, missing or invalid key.
+    Auth,
+    /// Unknown model or endpoint.
+    NotFound,
+    /// Any other rejected or unreadable response.
+    Rejected,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmError {
+    pub kind: LlmErrorKind,
+    pub status: Option<u16>,
+    /// Parsed `Retry-After` header.
+    pub retry_after: Option<Duration>,
+    pub message: String,
+}
 
-fn openrouter_headers(api_key: &str) -> reqwest::header::HeaderMap {
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert("Authorization", format!("Bearer {}", api_key).parse().unwrap());
-    headers.insert("HTTP-Referer", "https://opencouncil.app".parse().unwrap());
-    headers.insert("X-Title", "Open Council".parse().unwrap());
-    headers.insert("Content-Type", "application/json".parse().unwrap());
-    headers
+impl LlmError {
+    fn network(message: String) -> Self {
+        Self { kind: LlmErrorKind::Network, status: None, retry_after: None, message }
+    }
+
+    /// The provider answered, but not with anything usable.
+    pub fn rejected(message: String) -> Self {
+        Self { kind: LlmErrorKind::Rejected, status: None, retry_after: None, message }
+    }
+
+    /// Worth trying the same model again after a pause.
+    pub fn is_retriable(&self) -> bool {
+        matches!(self.kind, LlmErrorKind::RateLimited | LlmErrorKind::Server | LlmErrorKind::Network)
+    }
+
+    /// Fails every model on the same provider (bad key, no credits).
+    pub fn affects_provider(&self) -> bool {
+        matches!(self.kind, LlmErrorKind::PaymentRequired | LlmErrorKind::Auth)
+    }
+}
+
+impl std::fmt::Display for LlmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<LlmError> for String {
+    fn from(error: LlmError) -> Self {
+        error.message
+    }
 }
 
-fn map_api_error(status: reqwest::StatusCode, body: &str) -> String {
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return (seconds >= 0.0).then(|| Duration::from_secs_f64(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Turn a non-2xx response into an `LlmError`, consuming the body for the message.
+async fn error_from_response(endpoint: &LlmEndpoint, response: reqwest::Response) -> LlmError {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after);
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(e) => return LlmError::network(format!("Read error: {}", e)),
+    };
+    let kind = match status.as_u16() {
+        429 => LlmErrorKind::RateLimited,
+        402 => LlmErrorKind::PaymentRequired,
+        401 | 403 => LlmErrorKind::Auth,
+        404 => LlmErrorKind::NotFound,
+        400 if body.contains("model_not_found") || body.contains("not found") => LlmErrorKind::NotFound,
+        408 => LlmErrorKind::Network,
+        code if code >= 500 => LlmErrorKind::Server,
+        _ => LlmErrorKind::Rejected,
+    };
+    LlmError {
+        kind,
+        status: Some(status.as_u16()),
+        retry_after,
+        message: map_api_error(endpoint, status, &body),
+    }
+}
+
+// ── Helpers ──
+
+fn map_api_error(endpoint: &LlmEndpoint, status: reqwest::StatusCode, body: &str) -> String {
+    if !endpoint.is_openrouter() {
+        return match status.as_u16() {
+            401 | 403 => format!("Invalid API key for provider '{}'.", endpoint.label),
+            429 => format!("{} rate limited the request. Please wait a moment and try again.", endpoint.label),
+            404 => format!("Model or endpoint not found on {} ({}).", endpoint.label, endpoint.base_url),
+            _ => format!("{} API error ({}): {}", endpoint.label, status, body),
+        };
+    }
     match status.as_u16() {
         401 => "Invalid API key. Check your key at openrouter.ai/keys".to_string(),
         402 => "Insufficient credits. Visit openrouter.ai to add funds.".to_string(),
@@ -311,6 +903,38 @@ fn map_api_error(status: reqwest::StatusCode, body: &str) -> String {
     }
 }
 
+/// Ask the provider to report token usage (and, on OpenRouter, the billed cost).
+fn request_usage_accounting(request_body: &mut Value, endpoint: &LlmEndpoint) {
+    if request_body["stream"].as_bool().unwrap_or(false) {
+        request_body["stream_options"] = json!({"include_usage": true});
+    }
+    if endpoint.is_openrouter() {
+        request_body["usage"] = json!({"include": true});
+    }
+}
+
+/// Sampling fields for an agent call: the agent's settings where given, else the
+/// call site's defaults. Provider routing only means something to OpenRouter.
+fn apply_generation_params(
+    request_body: &mut Value,
+    params: &GenerationParams,
+    endpoint: &LlmEndpoint,
+    default_temperature: f64,
+    default_max_tokens: u32,
+) {
+    request_body["temperature"] = json!(params.temperature.unwrap_or(default_temperature));
+    request_body["max_tokens"] = json!(params.max_tokens.unwrap_or(default_max_tokens));
+    if let Some(top_p) = params.top_p {
+        request_body["top_p"] = json!(top_p);
+    }
+    if let Some(seed) = params.seed {
+        request_body["seed"] = json!(seed);
+    }
+    if let Some(routing) = params.provider_routing.as_ref().filter(|_| endpoint.is_openrouter()) {
+        request_body["provider"] = json!(routing);
+    }
+}
+
 // ── Streaming tool call accumulator ──
 // OpenAI streaming sends tool_calls incrementally: first chunk has id+name,
 // subsequent chunks append to arguments string.
@@ -324,59 +948,81 @@ struct PendingToolCall {
 
 // ── Public entry point: send_message ──
 
-pub async fn send_message(
-    api_key: &str,
-    model: &str,
-    messages: Vec<Value>,
+pub async fn send_message<R: Runtime>(
+    call: &LlmCall<'_>,
+    window: &context::ConversationWindow,
     app_data_dir: &PathBuf,
     on_event: &Channel<StreamEvent>,
     conv_type: &str,
-    decision_id: Option<&str>,
-    app_handle: &tauri::AppHandle,
-) -> Result<String, String> {
+    app_handle: &tauri::AppHandle<R>,
+    cancel: &ChatCancel,
+) -> Result<ChatReply, String> {
+    let LlmCall { endpoint, model, params, usage_ctx } = *call;
+    let decision_id = usage_ctx.decision_id.as_deref();
     let client = Client::new();
     let is_decision = conv_type == "decision";
-    let system_prompt = if is_decision { DECISION_SYSTEM_PROMPT } else { SYSTEM_PROMPT };
+    let system_prompt = context::system_prompt_with_summary(
+        &prompts::read_system_prompt(app_data_dir, prompts::prompt_key_for(conv_type)),
+        window.summary.as_deref(),
+    );
 
-    // Build message list with system prompt as first message
+    // Build message list with system prompt (and conversation summary) as first message
     let mut openrouter_messages: Vec<Value> = vec![
         json!({"role": "system", "content": system_prompt}),
     ];
-    for msg in &messages {
-        openrouter_messages.push(msg.clone());
-    }
+    openrouter_messages.extend(window.to_chat_messages());
 
     let mut all_text = String::new();
+    let mut reasoning = String::new();
+    let tools = get_tools(is_decision);
 
-    loop {
-        let request_body = json!({
+    for iteration in 1..=MAX_TOOL_ITERATIONS {
+        let mut request_body = json!({
             "model": model,
             "messages": openrouter_messages,
-            "tools": get_tools(is_decision),
-            "temperature": 0.7,
-            "max_tokens": 4096,
+            "tools": tools,
             "stream": true,
         });
+        apply_generation_params(&mut request_body, params, endpoint, 0.7, 4096);
+        if iteration == MAX_TOOL_ITERATIONS {
+            request_body["tool_choice"] = json!("none");
+        }
+        request_usage_accounting(&mut request_body, endpoint);
 
-        let mut response = client
-            .post(OPENROUTER_URL)
-            .headers(openrouter_headers(api_key))
+        let request = client
+            .post(endpoint.chat_completions_url())
+            .headers(endpoint.headers())
             .json(&request_body)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.map_err(|e| format!("Read error: {}", e))?;
-            return Err(map_api_error(status, &error_text));
+            .send();
+        let mut response = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return Ok(ChatReply { text: all_text, reasoning, cancelled: true }),
+            sent = request => sent.map_err(|e| format!("Network error ({}): {}", endpoint.label, e))?,
+        };
+
+        if !response.status().is_success() {
+            return Err(error_from_response(endpoint, response).await.into());
         }
 
         let mut iteration_text = String::new();
         let mut pending_tool_calls: Vec<PendingToolCall> = Vec::new();
+        let mut iteration_usage: Option<TokenUsage> = None;
         let mut buffer = String::new();
 
-        while let Some(chunk) = response.chunk().await.map_err(|e| format!("Stream error: {}", e))? {
+        loop {
+            // Dropping `response` on cancel closes the connection and stops generation upstream.
+            let chunk = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => {
+                    usage::record(app_handle, usage_ctx, endpoint, model, iteration_usage);
+                    all_text.push_str(&iteration_text);
+                    return Ok(ChatReply { text: all_text, reasoning, cancelled: true });
+                }
+                chunk = response.chunk() => chunk.map_err(|e| format!("Stream error: {}", e))?,
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
             buffer.push_str(&String::from_utf8_lossy(&chunk));
 
             // Process complete SSE lines (data: {...}\n\n)
@@ -402,9 +1048,19 @@ pub async fn send_message(
                     Err(_) => continue,
                 };
 
+                // Usage arrives on the final chunk (often with an empty `choices` array)
+                if let Some(u) = TokenUsage::from_json(&data["usage"]) {
+                    iteration_usage = Some(u);
+                }
+
                 let choice = &data["choices"][0];
                 let delta = &choice["delta"];
 
+                if let Some(thought) = reasoning_delta(delta) {
+                    reasoning.push_str(thought);
+                    let _ = on_event.send(StreamEvent::Reasoning { token: thought.to_string() });
+                }
+
                 // Text content
                 if let Some(content) = delta["content"].as_str() {
                     if !content.is_empty() {
@@ -444,6 +1100,8 @@ pub async fn send_message(
             }
         }
 
+        usage::record(app_handle, usage_ctx, endpoint, model, iteration_usage);
+
         // Filter out empty tool calls (shouldn't happen, but defensive)
         let tool_calls: Vec<PendingToolCall> = pending_tool_calls
             .into_iter()
@@ -452,7 +1110,15 @@ pub async fn send_message(
 
         if tool_calls.is_empty() {
             all_text.push_str(&iteration_text);
-            return Ok(all_text);
+            return Ok(ChatReply { text: all_text, reasoning, cancelled: false });
+        }
+        if iteration == MAX_TOOL_ITERATIONS {
+            // The model ignored `tool_choice: none`. Its calls can't run, and whatever text came
+            // with them was written expecting their results, so it isn't a usable answer.
+            return Err(format!(
+                "The assistant kept calling tools after {} rounds and never answered. Try rephrasing the question.",
+                MAX_TOOL_ITERATIONS
+            ));
         }
 
         // Handle tool calls — build assistant message and tool results
@@ -479,62 +1145,82 @@ pub async fn send_message(
         assistant_msg["tool_calls"] = json!(assistant_tool_calls);
         openrouter_messages.push(assistant_msg);
 
-        // Execute each tool and append results
+        // Execute each valid tool call; invalid ones get the validation error back
         for tc in &tool_calls {
-            let input: Value = serde_json::from_str(&tc.arguments).unwrap_or(json!({}));
-            let result = execute_tool(&tc.name, &input, app_data_dir, decision_id, app_handle);
+            if cancel.is_cancelled() {
+                let _ = on_event.send(StreamEvent::ToolResult {
+                    tool: tc.name.clone(),
+                    success: false,
+                    error: Some("Skipped: the response was cancelled.".to_string()),
+                });
+                continue;
+            }
+            let result = match parse_tool_arguments(&tools, &tc.name, &tc.arguments) {
+                Ok(input) => {
+                    execute_tool(&tc.name, &input, app_data_dir, usage_ctx.conversation_id.as_deref(), decision_id, app_handle).await
+                }
+                Err(e) => Err(e),
+            };
+            record_tool_call(app_handle, usage_ctx, &tc.name, &tc.arguments, &result);
+            let _ = on_event.send(StreamEvent::ToolResult {
+                tool: tc.name.clone(),
+                success: result.is_ok(),
+                error: result.as_ref().err().cloned(),
+            });
             openrouter_messages.push(json!({
                 "role": "tool",
                 "tool_call_id": tc.id,
-                "content": result,
+                "content": result.unwrap_or_else(|e| e),
             }));
         }
+
+        if cancel.is_cancelled() {
+            return Ok(ChatReply { text: all_text, reasoning, cancelled: true });
+        }
     }
+
+    unreachable!("the last tool round always returns")
 }
 
 // ── Streaming LLM call for debate (no tools, emits per-token events) ──
 
-pub async fn call_llm_streaming_debate(
-    api_key: &str,
-    model: &str,
+pub async fn call_llm_streaming_debate<R: Runtime>(
+    call: &LlmCall<'_>,
     system_prompt: &str,
     user_prompt: &str,
-    app_handle: &tauri::AppHandle,
-    decision_id: &str,
-    round_number: i32,
-    exchange_number: i32,
-    agent_key: &str,
-) -> Result<String, String> {
+    app_handle: &tauri::AppHandle<R>,
+) -> Result<DebateReply, LlmError> {
+    let LlmCall { endpoint, model, params, usage_ctx } = *call;
     let client = Client::new();
-    let request_body = json!({
+    let mut request_body = json!({
         "model": model,
         "messages": [
             {"role": "system", "content": system_prompt},
             {"role": "user", "content": user_prompt},
         ],
-        "temperature": 0.7,
-        "max_tokens": 2048,
         "stream": true,
     });
+    apply_generation_params(&mut request_body, params, endpoint, 0.7, 2048);
+    request_usage_accounting(&mut request_body, endpoint);
 
     let mut response = client
-        .post(OPENROUTER_URL)
-        .headers(openrouter_headers(api_key))
+        .post(endpoint.chat_completions_url())
+        .headers(endpoint.headers())
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .map_err(|e| LlmError::network(format!("Network error ({}): {}", endpoint.label, e)))?;
 
-    let status = response.status();
-    if !status.is_success() {
-        let error_text = response.text().await.map_err(|e| format!("Read error: {}", e))?;
-        return Err(map_api_error(status, &error_text));
+    if !response.status().is_success() {
+        return Err(error_from_response(endpoint, response).await);
     }
 
     let mut all_text = String::new();
+    let mut reasoning = String::new();
+    let mut call_usage: Option<TokenUsage> = None;
     let mut buffer = String::new();
 
-    while let Some(chunk) = response.chunk().await.map_err(|e| format!("Stream error: {}", e))? {
+    while let Some(chunk) = response.chunk().await.map_err(|e| LlmError::network(format!("Stream error: {}", e)))? {
         buffer.push_str(&String::from_utf8_lossy(&chunk));
 
         while let Some(pos) = buffer.find('\n') {
@@ -559,14 +1245,30 @@ pub async fn call_llm_streaming_debate(
                 Err(_) => continue,
             };
 
-            if let Some(content) = data["choices"][0]["delta"]["content"].as_str() {
+            if let Some(u) = TokenUsage::from_json(&data["usage"]) {
+                call_usage = Some(u);
+            }
+
+            let delta = &data["choices"][0]["delta"];
+            if let Some(thought) = reasoning_delta(delta) {
+                reasoning.push_str(thought);
+                let _ = app_handle.emit("debate-agent-reasoning", json!({
+                    "decision_id": usage_ctx.decision_id,
+                    "round_number": usage_ctx.round_number,
+                    "exchange_number": usage_ctx.exchange_number,
+                    "agent": usage_ctx.agent,
+                    "token": thought,
+                }));
+            }
+
+            if let Some(content) = delta["content"].as_str() {
                 if !content.is_empty() {
                     all_text.push_str(content);
                     let _ = app_handle.emit("debate-agent-token", json!({
-                        "decision_id": decision_id,
-                        "round_number": round_number,
-                        "exchange_number": exchange_number,
-                        "agent": agent_key,
+                        "decision_id": usage_ctx.decision_id,
+                        "round_number": usage_ctx.round_number,
+                        "exchange_number": usage_ctx.exchange_number,
+                        "agent": usage_ctx.agent,
                         "token": content,
                     }));
                 }
@@ -574,48 +1276,723 @@ pub async fn call_llm_streaming_debate(
         }
     }
 
-    Ok(all_text)
+    usage::record(app_handle, usage_ctx, endpoint, model, call_usage);
+
+    Ok(DebateReply { text: all_text, reasoning })
 }
 
 // ── Non-streaming LLM call for simple one-shot generation (e.g. agent prompt creation) ──
 
-pub async fn call_llm_simple(
-    api_key: &str,
-    model: &str,
+pub async fn call_llm_simple<R: Runtime>(
+    call: &LlmCall<'_>,
     system_prompt: &str,
     user_prompt: &str,
-) -> Result<String, String> {
+    app_handle: &tauri::AppHandle<R>,
+) -> Result<String, LlmError> {
+    let LlmCall { endpoint, model, params, usage_ctx } = *call;
     let client = Client::new();
-    let request_body = json!({
+    let mut request_body = json!({
         "model": model,
         "messages": [
             {"role": "system", "content": system_prompt},
             {"role": "user", "content": user_prompt},
         ],
-        "temperature": 0.7,
-        "max_tokens": 2048,
     });
+    apply_generation_params(&mut request_body, params, endpoint, 0.7, 2048);
+    request_usage_accounting(&mut request_body, endpoint);
 
     let response = client
-        .post(OPENROUTER_URL)
-        .headers(openrouter_headers(api_key))
+        .post(endpoint.chat_completions_url())
+        .headers(endpoint.headers())
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| format!("Network error: {}", e))?;
+        .map_err(|e| LlmError::network(format!("Network error ({}): {}", endpoint.label, e)))?;
 
-    let status = response.status();
-    let body = response.text().await.map_err(|e| format!("Read error: {}", e))?;
-
-    if !status.is_success() {
-        return Err(map_api_error(status, &body));
+    if !response.status().is_success() {
+        return Err(error_from_response(endpoint, response).await);
     }
+    let body = response.text().await.map_err(|e| LlmError::network(format!("Read error: {}", e)))?;
 
     let data: Value = serde_json::from_str(&body)
-        .map_err(|e| format!("JSON parse error: {}", e))?;
+        .map_err(|e| LlmError::rejected(format!("JSON parse error: {}", e)))?;
+    usage::record(app_handle, usage_ctx, endpoint, model, TokenUsage::from_json(&data["usage"]));
 
     data["choices"][0]["message"]["content"]
         .as_str()
         .map(|s| s.to_string())
-        .ok_or_else(|| "No content in LLM response".to_string())
+        .ok_or_else(|| LlmError::rejected("No content in LLM response".to_string()))
+}
+
+// ── Structured one-shot call (JSON schema output) ──
+
+/// One-shot call whose answer must be a JSON object matching `schema`.
+/// OpenAI-style providers get `response_format: json_schema`; Anthropic, and any
+/// provider whose 400 says it doesn't support `response_format`, get a forced tool call.
+/// An answer that doesn't parse or fails the schema is a `Rejected` error.
+pub async fn call_llm_structured<R: Runtime>(
+    call: &LlmCall<'_>,
+    system_prompt: &str,
+    user_prompt: &str,
+    schema_name: &str,
+    schema: &Value,
+    app_handle: &tauri::AppHandle<R>,
+) -> Result<Value, LlmError> {
+    let LlmCall { endpoint, model, params, usage_ctx } = *call;
+    let mut request_body = json!({
+        "model": model,
+        "messages": [
+            {"role": "system", "content": system_prompt},
+            {"role": "user", "content": user_prompt},
+        ],
+    });
+    apply_generation_params(&mut request_body, params, endpoint, 0.3, 2048);
+    request_usage_accounting(&mut request_body, endpoint);
+
+    let forced_tool = endpoint.kind == ProviderKind::Anthropic;
+    let data = match request_structured(endpoint, &request_body, schema_name, schema, forced_tool).await {
+        Err(e) if !forced_tool && rejects_response_format(&e) => {
+            request_structured(endpoint, &request_body, schema_name, schema, true).await?
+        }
+        result => result?,
+    };
+    usage::record(app_handle, usage_ctx, endpoint, model, TokenUsage::from_json(&data["usage"]));
+
+    let message = &data["choices"][0]["message"];
+    let raw = message["tool_calls"]
+        .as_array()
+        .and_then(|calls| calls.iter().find(|c| c["function"]["name"] == schema_name))
+        .and_then(|c| c["function"]["arguments"].as_str())
+        .or_else(|| message["content"].as_str())
+        .unwrap_or_default();
+    // Some models still wrap the object in a code fence or a sentence.
+    let object = match (raw.find('{'), raw.rfind('}')) {
+        (Some(start), Some(end)) if end > start => &raw[start..=end],
+        _ => raw,
+    };
+    let value: Value = serde_json::from_str(object)
+        .map_err(|e| LlmError::rejected(format!("{} did not return valid JSON for {}: {}", model, schema_name, e)))?;
+
+    let mut problems = Vec::new();
+    validate_schema(&value, schema, schema_name, &mut problems);
+    if !problems.is_empty() {
+        return Err(LlmError::rejected(format!("{} returned an invalid {}: {}", model, schema_name, problems.join("; "))));
+    }
+    Ok(value)
+}
+
+/// A 400 that names `response_format` or JSON schema output: the provider doesn't
+/// support it, so a forced tool call is worth trying. Other rejections are final.
+fn rejects_response_format(error: &LlmError) -> bool {
+    let message = error.message.to_lowercase();
+    error.status == Some(400)
+        && error.kind == LlmErrorKind::Rejected
+        && ["response_format", "json_schema", "json schema"].iter().any(|needle| message.contains(needle))
+}
+
+async fn request_structured(
+    endpoint: &LlmEndpoint,
+    base_body: &Value,
+    schema_name: &str,
+    schema: &Value,
+    forced_tool: bool,
+) -> Result<Value, LlmError> {
+    let mut request_body = base_body.clone();
+    if forced_tool {
+        request_body["tools"] = json!([{
+            "type": "function",
+            "function": {
+                "name": schema_name,
+                "description": "Submit the structured answer.",
+                "parameters": schema,
+            }
+        }]);
+        request_body["tool_choice"] = json!({"type": "function", "function": {"name": schema_name}});
+    } else {
+        request_body["response_format"] = json!({
+            "type": "json_schema",
+            "json_schema": {"name": schema_name, "strict": true, "schema": schema},
+        });
+    }
+
+    let response = Client::new()
+        .post(endpoint.chat_completions_url())
+        .headers(endpoint.headers())
+        .json(&request_body)
+        .send()
+        .await
+        .map_err(|e| LlmError::network(format!("Network error ({}): {}", endpoint.label, e)))?;
+    if !response.status().is_success() {
+        return Err(error_from_response(endpoint, response).await);
+    }
+    let body = response.text().await.map_err(|e| LlmError::network(format!("Read error: {}", e)))?;
+    serde_json::from_str(&body).map_err(|e| LlmError::rejected(format!("JSON parse error: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Message;
+    use crate::mock_llm::{test_support, FixtureResponse, MockLlmServer};
+    use crate::providers;
+    use tauri::ipc::InvokeResponseBody;
+    use tempfile::tempdir;
+
+    fn collecting_channel() -> (Channel<StreamEvent>, Arc<Mutex<Vec<String>>>) {
+        let events: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let channel = Channel::new(move |body| {
+            if let InvokeResponseBody::Json(payload) = body {
+                sink.lock().unwrap().push(payload);
+            }
+            Ok(())
+        });
+        (channel, events)
+    }
+
+    /// A mock app whose default provider is a mock server answering with `responder`.
+    struct ChatFixture {
+        dir: tempfile::TempDir,
+        server: MockLlmServer,
+        app: tauri::App<tauri::test::MockRuntime>,
+        endpoint: LlmEndpoint,
+    }
+
+    impl ChatFixture {
+        async fn new<F>(responder: F) -> Self
+        where
+            F: Fn(&Value) -> Option<FixtureResponse> + Send + Sync + 'static,
+        {
+            let dir = tempdir().expect("temp directory should exist");
+            let server = MockLlmServer::scripted(responder).await.expect("mock server should start");
+            let app = test_support::mock_app_with_state(dir.path(), server.base_url());
+            let config = crate::config::load_config(&dir.path().to_path_buf());
+            let endpoint = providers::default_endpoint(&config).expect("fixture provider should resolve");
+            ChatFixture { dir, server, app, endpoint }
+        }
+
+        fn app_data_dir(&self) -> PathBuf {
+            self.dir.path().to_path_buf()
+        }
+
+        /// Send `user_message` as the only message of chat "conv-1", whose reply is "reply-1".
+        async fn send(&self, user_message: &str, channel: &Channel<StreamEvent>, cancel: &ChatCancel) -> Result<ChatReply, String> {
+            let call = LlmCall {
+                endpoint: &self.endpoint,
+                model: "fixture/model",
+                params: &GenerationParams::default(),
+                usage_ctx: &UsageContext { message_id: Some("reply-1".to_string()), ..UsageContext::chat("conv-1", None) },
+            };
+            let question = Message {
+                id: "question-1".to_string(),
+                conversation_id: "conv-1".to_string(),
+                role: "user".to_string(),
+                content: user_message.to_string(),
+                created_at: String::new(),
+                status: "complete".to_string(),
+                reasoning: None,
+                parent_id: None,
+            };
+            let window = context::ConversationWindow { summary: None, recent: vec![question] };
+            send_message(&call, &window, &self.app_data_dir(), channel, "chat", self.app.handle(), cancel).await
+        }
+    }
+
+    #[test]
+    fn unit_parse_tool_arguments_checks_json_and_schema() {
+        let tools = get_tools(true);
+
+        let input = parse_tool_arguments(&tools, "write_profile_file", r#"{"filename":"a.md","content":"x"}"#)
+            .expect("valid arguments should pass");
+        assert_eq!(input["filename"], "a.md");
+        assert!(parse_tool_arguments(&tools, "read_profile_files", "").is_ok());
+
+        let err = parse_tool_arguments(&tools, "write_profile_file", r#"{"filename":"a.md","content":"#).unwrap_err();
+        assert!(err.contains("not valid JSON"));
+
+        let err = parse_tool_arguments(&tools, "write_profile_file", r#"{"content":"x"}"#).unwrap_err();
+        assert!(err.contains("arguments.filename is required"));
+
+        let err = parse_tool_arguments(&tools, "update_decision_summary", r#"{"status":"maybe"}"#).unwrap_err();
+        assert!(err.contains("arguments.status must be one of"));
+
+        let err = parse_tool_arguments(&tools, "update_decision_summary", r#"{"options":"A or B"}"#).unwrap_err();
+        assert!(err.contains("arguments.options must be of type array"));
+
+        let err = parse_tool_arguments(&get_tools(false), "update_decision_summary", "{}").unwrap_err();
+        assert!(err.contains("unknown tool"));
+    }
+
+    #[test]
+    fn unit_tool_call_files_lists_profile_files_the_call_touched() {
+        assert_eq!(tool_call_files("rename_profile_file", r#"{"from": "job.md", "to": "career.md"}"#), vec!["job.md", "career.md"]);
+        assert_eq!(tool_call_files("confirm_profile_fact", r#"{"filename": "career.md", "fact": "Engineer"}"#), vec!["career.md"]);
+        assert!(tool_call_files("read_profile_files", "{}").is_empty());
+        assert!(tool_call_files("read_attachment", r#"{"filename": "offer.pdf"}"#).is_empty());
+        assert!(tool_call_files("write_profile_file", "not json").is_empty());
+    }
+
+    #[test]
+    fn integration_execute_tool_edits_one_profile_section_at_a_time() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app = test_support::mock_app_with_state(dir.path(), "http://127.0.0.1:9");
+        let app_data_dir = dir.path().to_path_buf();
+        profile::write_profile_file(&app_data_dir, "career.md", "# Career\n\n## Role\n- Engineer\n\n## Goals\n- Staff\n").unwrap();
+        let tools = get_tools(false);
+        let run = |name: &str, arguments: &str| {
+            let input = parse_tool_arguments(&tools, name, arguments)?;
+            tauri::async_runtime::block_on(execute_tool(name, &input, &app_data_dir, None, None, app.handle()))
+        };
+
+        run("append_to_profile_section", r#"{"filename":"career.md","heading":"Role","content":"- Remote"}"#).unwrap();
+        run("replace_profile_section", r#"{"filename":"career.md","heading":"Goals","content":"- Start a company"}"#).unwrap();
+        run("rename_profile_file", r#"{"from":"career.md","to":"work.md"}"#).unwrap();
+        let profiles = profile::read_all_profiles(&app_data_dir).unwrap();
+        assert_eq!(profiles["work.md"], "# Career\n\n## Role\n- Engineer\n- Remote\n\n## Goals\n- Start a company\n");
+
+        let err = run("replace_profile_section", r#"{"filename":"work.md","content":"x"}"#).unwrap_err();
+        assert!(err.contains("arguments.heading is required"));
+        let err = run("replace_profile_section", r#"{"filename":"work.md","heading":"Hobbies","content":"x"}"#).unwrap_err();
+        assert!(err.starts_with("Error updating profile: No section 'Hobbies'"));
+        run("merge_profile_files", r#"{"from":"work.md","into":"about.md"}"#).unwrap();
+        assert!(profile::read_all_profiles(&app_data_dir).unwrap()["about.md"].starts_with("# Career"));
+    }
+
+    #[test]
+    fn integration_execute_tool_refuses_filenames_outside_the_profile_dir() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app = test_support::mock_app_with_state(dir.path(), "http://127.0.0.1:9");
+        let app_data_dir = dir.path().to_path_buf();
+        let config_path = app_data_dir.join("config.json");
+        std::fs::write(&config_path, r#"{"api_key":"sk-test"}"#).unwrap();
+        profile::write_profile_file(&app_data_dir, "career.md", "# Career\n").unwrap();
+        let tools = get_tools(true);
+        let run = |name: &str, arguments: &str| {
+            let input = parse_tool_arguments(&tools, name, arguments)?;
+            tauri::async_runtime::block_on(execute_tool(name, &input, &app_data_dir, None, Some("d1"), app.handle()))
+        };
+
+        let calls = [
+            ("write_profile_file", r#"{"filename":"../config.json","content":"{}"}"#),
+            ("append_to_profile_section", r#"{"filename":"../config.json","content":"x"}"#),
+            ("replace_profile_section", r#"{"filename":"../config.json","heading":"Keys","content":"x"}"#),
+            ("rename_profile_file", r#"{"from":"career.md","to":"../config.json"}"#),
+            ("merge_profile_files", r#"{"from":"../config.json","into":"career.md"}"#),
+            ("delete_profile_file", r#"{"filename":"../config.json"}"#),
+        ];
+        for (name, arguments) in calls {
+            let err = run(name, arguments).unwrap_err();
+            assert!(err.ends_with("Filename '../config.json' must not contain '..'"), "{}: {}", name, err);
+        }
+        let err = run("write_profile_file", r#"{"filename":"/etc/hosts.md","content":"x"}"#).unwrap_err();
+        assert!(err.contains("must be relative"), "{}", err);
+
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), r#"{"api_key":"sk-test"}"#);
+        assert_eq!(profile::read_all_profiles(&app_data_dir).unwrap()["career.md"], "# Career\n");
+    }
+
+    #[test]
+    fn integration_read_profile_files_returns_relevant_sections_of_large_profiles() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app = test_support::mock_app_with_state(dir.path(), "http://127.0.0.1:9");
+        let app_data_dir = dir.path().to_path_buf();
+        profile::write_profile_file(&app_data_dir, "finances.md", "# Finances\n\n## Savings\n- 8 months of runway\n\n## Debt\n- None\n").unwrap();
+        let filler = "- Plays five-a-side football twice a week.\n".repeat(200);
+        profile::write_profile_file(&app_data_dir, "hobbies.md", &format!("# Hobbies\n{}", filler)).unwrap();
+        let decision_id = {
+            let state: tauri::State<'_, Mutex<AppState>> = app.state();
+            let guard = state.lock().unwrap();
+            let conversation = guard.db.create_conversation_with_type("Savings", "decision").unwrap();
+            guard.db.create_decision(&conversation.id, "Use savings for a sabbatical?").unwrap().id
+        };
+        let tools = get_tools(true);
+        let run = |arguments: &str, decision_id: Option<&str>| {
+            let input = parse_tool_arguments(&tools, "read_profile_files", arguments)?;
+            tauri::async_runtime::block_on(execute_tool("read_profile_files", &input, &app_data_dir, None, decision_id, app.handle()))
+        };
+
+        let result: Value = serde_json::from_str(&run(r#"{"query":"how much runway in savings"}"#, None).unwrap()).unwrap();
+        assert_eq!(result["sections"].as_array().unwrap().len(), 1);
+        assert_eq!(result["sections"][0]["section"], "Finances > Savings");
+        assert_eq!(result["other_files"], json!(["hobbies.md"]));
+
+        // In a decision chat the decision itself is the query.
+        let result: Value = serde_json::from_str(&run("{}", Some(&decision_id)).unwrap()).unwrap();
+        assert_eq!(result["query"], "Use savings for a sabbatical?");
+        assert!(result["sections"][0]["content"].as_str().unwrap().contains("8 months"));
+
+        let everything: Value = serde_json::from_str(&run(r#"{"all":true}"#, Some(&decision_id)).unwrap()).unwrap();
+        assert!(everything["hobbies.md"].as_str().unwrap().contains("football"));
+        let outside_decisions: Value = serde_json::from_str(&run("{}", None).unwrap()).unwrap();
+        assert!(outside_decisions["finances.md"].is_string());
+    }
+
+    #[test]
+    fn integration_review_mode_queues_profile_edits_until_approved() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app = test_support::mock_app_with_state(dir.path(), "http://127.0.0.1:9");
+        let app_data_dir = dir.path().to_path_buf();
+        let mut config = config::load_config(&app_data_dir);
+        config.review_mode = true;
+        config::save_config(&app_data_dir, &config).unwrap();
+        profile::write_profile_file(&app_data_dir, "career.md", "# Career\n\n## Role\n- Engineer\n").unwrap();
+        let tools = get_tools(false);
+        let run = |name: &str, arguments: &str| {
+            let input = parse_tool_arguments(&tools, name, arguments)?;
+            tauri::async_runtime::block_on(execute_tool(name, &input, &app_data_dir, Some("conv-1"), None, app.handle()))
+        };
+
+        let reply = run("replace_profile_section", r#"{"filename":"career.md","heading":"Role","content":"- Manager"}"#).unwrap();
+        assert!(reply.starts_with("Pending review: your change to career.md was NOT applied yet"), "{}", reply);
+        run("delete_profile_file", r#"{"filename":"career.md"}"#).unwrap();
+        let err = run("replace_profile_section", r#"{"filename":"career.md","heading":"Hobbies","content":"x"}"#).unwrap_err();
+        assert!(err.starts_with("Error preparing profile change: No section 'Hobbies'"), "{}", err);
+        assert_eq!(profile::read_all_profiles(&app_data_dir).unwrap()["career.md"], "# Career\n\n## Role\n- Engineer\n");
+
+        let state: tauri::State<'_, Mutex<AppState>> = app.state();
+        let db = &state.lock().unwrap().db;
+        let pending = db.get_pending_profile_changes("pending").unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].conversation_id.as_deref(), Some("conv-1"));
+        assert_eq!(pending[0].files, vec!["career.md".to_string()]);
+        let diff: Vec<profile_history::FileDiff> = serde_json::from_value(pending[0].diff.clone()).unwrap();
+        let changed: Vec<(&str, &str)> = diff[0].lines.iter().filter(|l| l.kind != "same").map(|l| (l.kind.as_str(), l.text.as_str())).collect();
+        assert_eq!(changed, vec![("removed", "- Engineer"), ("added", "- Manager")]);
+        assert!(serde_json::from_value::<Vec<profile_history::FileDiff>>(pending[1].diff.clone()).unwrap()[0]
+            .lines
+            .iter()
+            .all(|l| l.kind == "removed"));
+
+        // Approving applies the stored edit; rejecting leaves the file alone.
+        let edit: ProfileEdit = serde_json::from_value(pending[0].edit.clone()).unwrap();
+        edit.apply(&app_data_dir, pending[0].conversation_id.as_deref()).unwrap();
+        assert!(db.resolve_pending_profile_change(&pending[0].id, "approved", None).unwrap());
+        assert!(db.resolve_pending_profile_change(&pending[1].id, "rejected", None).unwrap());
+        assert!(!db.resolve_pending_profile_change(&pending[1].id, "approved", None).unwrap());
+        assert_eq!(profile::read_all_profiles(&app_data_dir).unwrap()["career.md"], "# Career\n\n## Role\n- Manager\n");
+        assert_eq!(profile::list_profile_facts(&app_data_dir).unwrap()[0].source_conversation.as_deref(), Some("conv-1"));
+        assert!(db.get_pending_profile_changes("pending").unwrap().is_empty());
+        assert_eq!(db.get_pending_profile_changes("rejected").unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn e2e_send_message_feeds_invalid_tool_arguments_back_to_the_model() {
+        let fixture = ChatFixture::new(|request| {
+            let messages = request["messages"].as_array()?;
+            let tool_results: Vec<&Value> = messages.iter().filter(|m| m["role"] == "tool").collect();
+            Some(match tool_results.len() {
+                0 => {
+                    let mut bad = FixtureResponse::tool_call("call_1", "write_profile_file", json!({}));
+                    bad.tool_calls[0].arguments = r##"{"filename": "career.md", "content": "# Car"##.to_string();
+                    bad
+                }
+                1 => FixtureResponse::tool_call(
+                    "call_2",
+                    "write_profile_file",
+                    json!({"filename": "career.md", "content": "# Career\n- Backend engineer"}),
+                ),
+                _ => FixtureResponse::text("Saved."),
+            })
+        })
+        .await;
+        let (channel, events) = collecting_channel();
+
+        let reply = fixture
+            .send("I'm a backend engineer.", &channel, &ChatCancel::default())
+            .await
+            .expect("tool loop should recover");
+
+        assert_eq!(reply.text, "Saved.");
+        let profile_dir = profile::get_profile_dir(&fixture.app_data_dir());
+        assert!(profile_dir.join("career.md").exists());
+        assert!(!profile_dir.join("unknown.md").exists());
+
+        let requests = fixture.server.requests();
+        assert_eq!(requests.len(), 3);
+        let feedback = requests[1]["messages"][3]["content"].as_str().unwrap_or_default();
+        assert!(feedback.starts_with("Error: arguments for write_profile_file are not valid JSON"));
+
+        let events = events.lock().unwrap();
+        let results: Vec<Value> = events
+            .iter()
+            .filter_map(|e| serde_json::from_str::<Value>(e).ok())
+            .filter(|e| e["type"] == "tool_result")
+            .collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["success"], false);
+        assert!(results[0]["error"].as_str().unwrap_or_default().contains("not valid JSON"));
+        assert_eq!(results[1]["success"], true);
+        assert!(results[1].get("error").is_none());
+    }
+
+    #[tokio::test]
+    async fn e2e_send_message_stops_tool_loop_at_iteration_cap() {
+        let fixture = ChatFixture::new(|request| {
+            Some(if request.get("tool_choice").is_some() {
+                FixtureResponse::text("Here is what I know so far.")
+            } else {
+                FixtureResponse::tool_call("call_n", "read_profile_files", json!({}))
+            })
+        })
+        .await;
+        let (channel, _events) = collecting_channel();
+
+        let reply = fixture
+            .send("What do you know about me?", &channel, &ChatCancel::default())
+            .await
+            .expect("capped loop should still answer");
+
+        assert_eq!(reply.text, "Here is what I know so far.");
+        let requests = fixture.server.requests();
+        assert_eq!(requests.len(), MAX_TOOL_ITERATIONS);
+        assert_eq!(requests[MAX_TOOL_ITERATIONS - 1]["tool_choice"], "none");
+        assert!(requests[..MAX_TOOL_ITERATIONS - 1].iter().all(|r| r.get("tool_choice").is_none()));
+    }
+
+    #[tokio::test]
+    async fn e2e_send_message_fails_when_the_model_keeps_calling_tools_on_the_last_round() {
+        let fixture = ChatFixture::new(|_| {
+            let mut response = FixtureResponse::tool_call("call_n", "read_profile_files", json!({}));
+            response.content = "Let me check one more file.".to_string();
+            Some(response)
+        })
+        .await;
+        let (channel, events) = collecting_channel();
+
+        let err = fixture
+            .send("What do you know about me?", &channel, &ChatCancel::default())
+            .await
+            .expect_err("ignoring tool_choice on the last round should fail the turn");
+
+        assert!(err.contains("kept calling tools"), "{}", err);
+        assert_eq!(fixture.server.requests().len(), MAX_TOOL_ITERATIONS);
+        // The last round's calls were never run.
+        let results = events.lock().unwrap().iter().filter(|e| e.contains("\"tool_result\"")).count();
+        assert_eq!(results, MAX_TOOL_ITERATIONS - 1);
+    }
+
+    #[tokio::test]
+    async fn e2e_send_message_streams_reasoning_apart_from_the_reply() {
+        let fixture = ChatFixture::new(|_| {
+            Some(FixtureResponse {
+                reasoning: "They asked about salary, so weigh runway first.".to_string(),
+                ..FixtureResponse::text("Start with your runway.")
+            })
+        })
+        .await;
+        let (channel, events) = collecting_channel();
+
+        let reply = fixture
+            .send("Is the salary enough?", &channel, &ChatCancel::default())
+            .await
+            .expect("reply should finish");
+
+        assert_eq!(reply.text, "Start with your runway.");
+        assert_eq!(reply.reasoning, "They asked about salary, so weigh runway first.");
+
+        let events = events.lock().unwrap();
+        let of_type = |kind: &str| -> Vec<&String> {
+            events.iter().filter(|e| e.contains(&format!("\"type\":\"{}\"", kind))).collect()
+        };
+        assert!(!of_type("reasoning").is_empty());
+        assert!(of_type("reasoning").iter().all(|e| !e.contains("Start")));
+        assert!(of_type("token").iter().all(|e| !e.contains("salary")));
+    }
+
+    #[tokio::test]
+    async fn e2e_cancelled_send_message_keeps_partial_text_and_skips_tool_calls() {
+        let fixture = ChatFixture::new(|_| {
+            let mut response = FixtureResponse::tool_call(
+                "call_1",
+                "write_profile_file",
+                json!({"filename": "career.md", "content": "# Career"}),
+            );
+            response.content = "Let me note that down.".to_string();
+            Some(response)
+        })
+        .await;
+
+        // Stop as soon as the first token reaches the UI, like the stop button would.
+        let cancel = ChatCancel::default();
+        let trigger = cancel.clone();
+        let events: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let channel: Channel<StreamEvent> = Channel::new(move |body| {
+            if let InvokeResponseBody::Json(payload) = body {
+                if payload.contains("\"token\"") {
+                    trigger.cancel();
+                }
+                sink.lock().unwrap().push(payload);
+            }
+            Ok(())
+        });
+
+        let reply = fixture
+            .send("I'm a backend engineer.", &channel, &cancel)
+            .await
+            .expect("cancelled turn should still return its partial text");
+
+        assert!(reply.cancelled);
+        assert!(!reply.text.is_empty());
+        assert!("Let me note that down.".starts_with(&reply.text));
+        assert_eq!(fixture.server.requests().len(), 1);
+        assert!(!profile::get_profile_dir(&fixture.app_data_dir()).join("career.md").exists());
+        assert!(!events.lock().unwrap().iter().any(|e| e.contains("\"tool_result\"") && e.contains("\"success\":true")));
+    }
+
+    #[test]
+    fn unit_parse_retry_after_accepts_seconds_and_http_dates() {
+        assert_eq!(parse_retry_after("7"), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        let soon = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let wait = parse_retry_after(&soon).expect("future date should parse");
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn integration_debate_call_classifies_provider_errors() {
+        let fixture = ChatFixture::new(|request| {
+            Some(match request["model"].as_str()? {
+                "busy/model" => FixtureResponse::error(429, "slow down", Some(4)),
+                "broke/model" => FixtureResponse::error(402, "no credits", None),
+                _ => FixtureResponse::error(502, "bad gateway", None),
+            })
+        })
+        .await;
+
+        let params = GenerationParams::default();
+        let usage_ctx = UsageContext::debate_turn("dec-1", 1, 1, "contrarian");
+        let (fixture, params, usage_ctx) = (&fixture, &params, &usage_ctx);
+        let call = |model: &'static str| async move {
+            let call = LlmCall { endpoint: &fixture.endpoint, model, params, usage_ctx };
+            call_llm_streaming_debate(&call, "system", "user", fixture.app.handle()).await
+        };
+
+        let rate_limited = call("busy/model").await.unwrap_err();
+        assert_eq!(rate_limited.kind, LlmErrorKind::RateLimited);
+        assert_eq!(rate_limited.retry_after, Some(Duration::from_secs(4)));
+        assert!(rate_limited.is_retriable());
+
+        let payment = call("broke/model").await.unwrap_err();
+        assert_eq!(payment.kind, LlmErrorKind::PaymentRequired);
+        assert_eq!(payment.status, Some(402));
+        assert!(!payment.is_retriable());
+        assert!(payment.affects_provider());
+
+        let outage = call("other/model").await.unwrap_err();
+        assert_eq!(outage.kind, LlmErrorKind::Server);
+        assert!(outage.is_retriable());
+        assert!(String::from(outage).contains("502"));
+    }
+
+    #[tokio::test]
+    async fn integration_structured_call_falls_back_to_forced_tool_and_validates_schema() {
+        let fixture = ChatFixture::new(|request| {
+            if request["model"] == "strict/model" {
+                return Some(FixtureResponse::error(400, "max_tokens is too large for this model", None));
+            }
+            if request.get("response_format").is_some() {
+                return Some(FixtureResponse::error(400, "response_format is not supported by this model", None));
+            }
+            Some(match request["model"].as_str()? {
+                "sloppy/model" => FixtureResponse::tool_call("call_1", "verdict", json!({"choice": ""})),
+                _ => FixtureResponse::tool_call("call_1", "verdict", json!({"choice": "Stay"})),
+            })
+        })
+        .await;
+        let schema = json!({
+            "type": "object",
+            "properties": {"choice": {"type": "string"}},
+            "required": ["choice"],
+            "additionalProperties": false,
+        });
+        let params = GenerationParams::default();
+        let usage_ctx = UsageContext::purpose("test");
+        let call = |model| LlmCall { endpoint: &fixture.endpoint, model, params: &params, usage_ctx: &usage_ctx };
+
+        let value = call_llm_structured(&call("good/model"), "system", "user", "verdict", &schema, fixture.app.handle())
+            .await
+            .expect("forced tool call should produce the object");
+        assert_eq!(value, json!({"choice": "Stay"}));
+        let first = &fixture.server.requests()[0];
+        assert_eq!(first["response_format"]["json_schema"]["name"], "verdict");
+        assert_eq!(first["response_format"]["json_schema"]["strict"], true);
+        assert_eq!(fixture.server.requests()[1]["tool_choice"]["function"]["name"], "verdict");
+
+        let err = call_llm_structured(&call("sloppy/model"), "system", "user", "verdict", &schema, fixture.app.handle())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind, LlmErrorKind::Rejected);
+        assert!(err.message.contains("verdict.choice must not be empty"), "{}", err.message);
+
+        // A 400 for some other reason is not retried as a tool call.
+        let before = fixture.server.requests().len();
+        let err = call_llm_structured(&call("strict/model"), "system", "user", "verdict", &schema, fixture.app.handle())
+            .await
+            .unwrap_err();
+        assert!(err.message.contains("max_tokens"), "{}", err.message);
+        assert_eq!(fixture.server.requests().len(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn e2e_send_message_runs_tool_loop_against_mock_server() {
+        let fixture = ChatFixture::new(|request| {
+            let messages = request["messages"].as_array()?;
+            let mut response = if messages.last()?["role"] == "tool" {
+                FixtureResponse::text("Noted your career details.")
+            } else {
+                FixtureResponse::tool_call(
+                    "call_1",
+                    "write_profile_file",
+                    json!({"filename": "career.md", "content": "# Career\n- Backend engineer"}),
+                )
+            };
+            response.usage = Some(json!({"prompt_tokens": 500, "completion_tokens": 40}));
+            Some(response)
+        })
+        .await;
+
+        prompts::write_system_prompt(&fixture.app_data_dir(), "interviewer", "You are a terse interviewer.")
+            .expect("prompt override should save");
+        let (channel, events) = collecting_channel();
+
+        let reply = fixture
+            .send("I'm a backend engineer.", &channel, &ChatCancel::default())
+            .await
+            .expect("tool loop should finish");
+
+        assert_eq!(reply.text, "Noted your career details.");
+        let saved = std::fs::read_to_string(profile::get_profile_dir(&fixture.app_data_dir()).join("career.md"))
+            .expect("tool call should write the profile file");
+        assert!(saved.contains("Backend engineer"));
+
+        let requests = fixture.server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1]["messages"][2]["tool_calls"][0]["function"]["name"], "write_profile_file");
+        assert_eq!(requests[0]["messages"][0]["content"], "You are a terse interviewer.");
+
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|e| e.contains("\"tool_use\"") && e.contains("write_profile_file")));
+        assert!(events.iter().any(|e| e.contains("\"token\"")));
+
+        // Each round-trip of the tool loop is recorded as its own usage row.
+        let state: tauri::State<'_, Mutex<AppState>> = fixture.app.state();
+        let month = chrono::Utc::now().format("%Y-%m").to_string();
+        let spend = state.lock().unwrap().db.get_monthly_usage_by_model(&month).unwrap();
+        assert_eq!(spend.len(), 1);
+        assert_eq!(spend[0].calls, 2);
+        assert_eq!(spend[0].prompt_tokens, 1000);
+        assert_eq!(spend[0].unpriced_calls, 2);
+        assert!(requests[0]["stream_options"]["include_usage"].as_bool().unwrap_or(false));
+
+        let calls = state.lock().unwrap().db.get_tool_calls_for_file("career.md").unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].tool_name, "write_profile_file");
+        assert_eq!(calls[0].conversation_id.as_deref(), Some("conv-1"));
+        assert_eq!(calls[0].message_id.as_deref(), Some("reply-1"));
+        assert!(calls[0].success);
+        assert!(calls[0].arguments.contains("Backend engineer"));
+    }
 }
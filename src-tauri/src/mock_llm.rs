@@ -0,0 +1,601 @@
+//! Deterministic stand-in for an OpenAI-compatible chat-completions server.
+//! Serves recorded fixtures keyed by request hash (or a scripted responder) over
+//! plain HTTP + SSE so debates and tool-call loops run offline in `cargo test`.
+//! The app can also boot in fixture mode via `OPEN_COUNCIL_LLM_FIXTURES=<dir>`
+//! (add `OPEN_COUNCIL_LLM_RECORD=1` to record misses from the real provider).
+
+use crate::file_history;
+use crate::providers::LlmEndpoint;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const FIXTURES_ENV: &str = "OPEN_COUNCIL_LLM_FIXTURES";
+pub const RECORD_ENV: &str = "OPEN_COUNCIL_LLM_RECORD";
+
+// ── Fixture data ──
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FixtureToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+}
+
+/// An HTTP error to answer with instead of a completion.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FixtureError {
+    pub status: u16,
+    #[serde(default)]
+    pub message: String,
+    /// Seconds for the `Retry-After` header.
+    #[serde(default)]
+    pub retry_after: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FixtureResponse {
+    #[serde(default)]
+    pub content: String,
+    /// Reasoning streamed before the content, as OpenRouter does for reasoning models.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub reasoning: String,
+    #[serde(default)]
+    pub tool_calls: Vec<FixtureToolCall>,
+    #[serde(default)]
+    pub usage: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<FixtureError>,
+}
+
+impl FixtureResponse {
+    #[cfg(test)]
+    pub fn text(content: &str) -> Self {
+        Self { content: content.to_string(), ..Self::default() }
+    }
+
+    #[cfg(test)]
+    pub fn tool_call(id: &str, name: &str, arguments: Value) -> Self {
+        Self {
+            tool_calls: vec![FixtureToolCall {
+                id: id.to_string(),
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            }],
+            ..Self::default()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn error(status: u16, message: &str, retry_after: Option<u64>) -> Self {
+        Self {
+            error: Some(FixtureError { status, message: message.to_string(), retry_after }),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FixtureFile {
+    request_hash: String,
+    model: String,
+    response: FixtureResponse,
+}
+
+/// Stable hash of the parts of a request that determine the model's answer.
+/// Sampling knobs and `stream` are ignored so streaming and one-shot calls share fixtures.
+pub fn request_hash(body: &Value) -> String {
+    let key = json!({
+        "model": body.get("model"),
+        "messages": body.get("messages"),
+        "tools": body.get("tools"),
+        "response_format": body.get("response_format"),
+    });
+    file_history::content_hash(&key.to_string())
+}
+
+/// Recorded transcripts keyed by request hash, optionally backed by a directory.
+#[derive(Default)]
+pub struct FixtureStore {
+    dir: Option<PathBuf>,
+    entries: Mutex<HashMap<String, FixtureResponse>>,
+}
+
+impl FixtureStore {
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load every `<hash>.json` fixture in `dir`. New recordings are written back there.
+    pub fn load_dir(dir: &PathBuf) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let mut entries = HashMap::new();
+        for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            let file: FixtureFile = serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid fixture {}: {}", path.display(), e))?;
+            entries.insert(file.request_hash, file.response);
+        }
+        Ok(Self { dir: Some(dir.clone()), entries: Mutex::new(entries) })
+    }
+
+    pub fn get(&self, hash: &str) -> Option<FixtureResponse> {
+        self.entries.lock().ok()?.get(hash).cloned()
+    }
+
+    pub fn insert(&self, hash: &str, model: &str, response: FixtureResponse) -> Result<(), String> {
+        if let Some(dir) = &self.dir {
+            let file = FixtureFile {
+                request_hash: hash.to_string(),
+                model: model.to_string(),
+                response: response.clone(),
+            };
+            let content = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+            fs::write(dir.join(format!("{}.json", hash)), content).map_err(|e| e.to_string())?;
+        }
+        self.entries.lock().map_err(|e| e.to_string())?.insert(hash.to_string(), response);
+        Ok(())
+    }
+}
+
+/// Scripted fallback used when no recorded fixture matches a request.
+pub type Responder = Arc<dyn Fn(&Value) -> Option<FixtureResponse> + Send + Sync>;
+
+// ── Server ──
+
+struct ServerShared {
+    store: FixtureStore,
+    responder: Option<Responder>,
+    upstream: Option<LlmEndpoint>,
+    requests: Mutex<Vec<Value>>,
+}
+
+pub struct MockLlmServer {
+    base_url: String,
+    #[cfg(test)]
+    shared: Arc<ServerShared>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockLlmServer {
+    /// Bind to an ephemeral localhost port and start serving.
+    /// `upstream`, when set, is queried for fixture misses and the answer recorded.
+    pub async fn start(
+        store: FixtureStore,
+        responder: Option<Responder>,
+        upstream: Option<LlmEndpoint>,
+    ) -> Result<Self, String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.map_err(|e| e.to_string())?;
+        let addr = listener.local_addr().map_err(|e| e.to_string())?;
+        let shared = Arc::new(ServerShared {
+            store,
+            responder,
+            upstream,
+            requests: Mutex::new(Vec::new()),
+        });
+
+        let task_shared = Arc::clone(&shared);
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let conn_shared = Arc::clone(&task_shared);
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, conn_shared).await {
+                        eprintln!("Mock LLM server connection error: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            base_url: format!("http://{}/v1", addr),
+            #[cfg(test)]
+            shared,
+            task,
+        })
+    }
+
+    /// Convenience constructor for tests: scripted responses only.
+    #[cfg(test)]
+    pub async fn scripted<F>(responder: F) -> Result<Self, String>
+    where
+        F: Fn(&Value) -> Option<FixtureResponse> + Send + Sync + 'static,
+    {
+        Self::start(FixtureStore::in_memory(), Some(Arc::new(responder)), None).await
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Every chat-completions request body received so far, in arrival order.
+    #[cfg(test)]
+    pub fn requests(&self) -> Vec<Value> {
+        self.shared.requests.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+}
+
+impl Drop for MockLlmServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<(String, Vec<u8>), String> {
+    let mut data: Vec<u8> = Vec::new();
+    let mut buf = [0u8; 8192];
+    let header_end = loop {
+        let n = stream.read(&mut buf).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err("Connection closed before headers were complete".to_string());
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let content_length = head
+        .lines()
+        .filter_map(|l| l.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut body = data[header_end..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut buf).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&buf[..n]);
+    }
+
+    let request_line = head.lines().next().unwrap_or("").to_string();
+    Ok((request_line, body))
+}
+
+async fn write_response(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<(), String> {
+    write_response_with_headers(stream, status, content_type, "", body).await
+}
+
+async fn write_response_with_headers(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    extra_headers: &str,
+    body: &str,
+) -> Result<(), String> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        extra_headers,
+        body
+    );
+    stream.write_all(response.as_bytes()).await.map_err(|e| e.to_string())?;
+    stream.shutdown().await.map_err(|e| e.to_string())
+}
+
+async fn handle_connection(mut stream: TcpStream, shared: Arc<ServerShared>) -> Result<(), String> {
+    let (request_line, body) = read_request(&mut stream).await?;
+    if !request_line.starts_with("POST ") || !request_line.contains("/chat/completions") {
+        let error = json!({"error": {"message": format!("Unsupported route: {}", request_line)}});
+        return write_response(&mut stream, "404 Not Found", "application/json", &error.to_string()).await;
+    }
+
+    let request: Value = serde_json::from_slice(&body).map_err(|e| format!("Invalid request JSON: {}", e))?;
+    if let Ok(mut requests) = shared.requests.lock() {
+        requests.push(request.clone());
+    }
+
+    let hash = request_hash(&request);
+    let model = request["model"].as_str().unwrap_or("").to_string();
+    let mut response = shared.store.get(&hash);
+    if response.is_none() {
+        response = shared.responder.as_ref().and_then(|r| r(&request));
+    }
+    if response.is_none() {
+        if let Some(upstream) = &shared.upstream {
+            let recorded = record_from_upstream(upstream, &request).await?;
+            shared.store.insert(&hash, &model, recorded.clone())?;
+            response = Some(recorded);
+        }
+    }
+
+    let Some(response) = response else {
+        let error = json!({"error": {"message": format!("No fixture for request hash {}", hash)}});
+        return write_response(&mut stream, "400 Bad Request", "application/json", &error.to_string()).await;
+    };
+
+    if let Some(error) = &response.error {
+        let status = reqwest::StatusCode::from_u16(error.status).map_err(|e| e.to_string())?;
+        let status_line = format!("{} {}", status.as_u16(), status.canonical_reason().unwrap_or("Error"));
+        let retry_after = error.retry_after.map(|s| format!("Retry-After: {}\r\n", s)).unwrap_or_default();
+        let body = json!({"error": {"message": error.message, "code": error.status}}).to_string();
+        return write_response_with_headers(&mut stream, &status_line, "application/json", &retry_after, &body).await;
+    }
+
+    if request["stream"].as_bool().unwrap_or(false) {
+        let header = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+        stream.write_all(header.as_bytes()).await.map_err(|e| e.to_string())?;
+        for event in sse_events(&model, &response) {
+            stream.write_all(event.as_bytes()).await.map_err(|e| e.to_string())?;
+        }
+        stream.shutdown().await.map_err(|e| e.to_string())
+    } else {
+        let body = completion_body(&model, &response).to_string();
+        write_response(&mut stream, "200 OK", "application/json", &body).await
+    }
+}
+
+fn finish_reason(response: &FixtureResponse) -> &'static str {
+    if response.tool_calls.is_empty() { "stop" } else { "tool_calls" }
+}
+
+/// Render a fixture as the SSE chunks an OpenAI-compatible server would stream.
+fn sse_events(model: &str, response: &FixtureResponse) -> Vec<String> {
+    let chunk = |delta: Value, finish: Option<&str>| {
+        json!({
+            "id": "fixture",
+            "object": "chat.completion.chunk",
+            "model": model,
+            "choices": [{"index": 0, "delta": delta, "finish_reason": finish}],
+        })
+    };
+
+    let mut events: Vec<Value> = Vec::new();
+    for piece in response.reasoning.split_inclusive(' ') {
+        events.push(chunk(json!({"reasoning": piece}), None));
+    }
+    for piece in response.content.split_inclusive(' ') {
+        events.push(chunk(json!({"content": piece}), None));
+    }
+    for (index, tc) in response.tool_calls.iter().enumerate() {
+        events.push(chunk(json!({"tool_calls": [{
+            "index": index,
+            "id": tc.id,
+            "type": "function",
+            "function": {"name": tc.name, "arguments": ""},
+        }]}), None));
+        events.push(chunk(json!({"tool_calls": [{
+            "index": index,
+            "function": {"arguments": tc.arguments},
+        }]}), None));
+    }
+    let mut last = chunk(json!({}), Some(finish_reason(response)));
+    if let Some(usage) = &response.usage {
+        last["usage"] = usage.clone();
+    }
+    events.push(last);
+
+    let mut lines: Vec<String> = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+    lines.push("data: [DONE]\n\n".to_string());
+    lines
+}
+
+fn completion_body(model: &str, response: &FixtureResponse) -> Value {
+    let mut message = json!({"role": "assistant", "content": response.content});
+    if !response.reasoning.is_empty() {
+        message["reasoning"] = json!(response.reasoning);
+    }
+    if !response.tool_calls.is_empty() {
+        message["tool_calls"] = json!(response.tool_calls.iter().map(|tc| json!({
+            "id": tc.id,
+            "type": "function",
+            "function": {"name": tc.name, "arguments": tc.arguments},
+        })).collect::<Vec<_>>());
+    }
+    let mut body = json!({
+        "id": "fixture",
+        "object": "chat.completion",
+        "model": model,
+        "choices": [{"index": 0, "message": message, "finish_reason": finish_reason(response)}],
+    });
+    if let Some(usage) = &response.usage {
+        body["usage"] = usage.clone();
+    }
+    body
+}
+
+/// Replay a missed request against the real provider (non-streaming) and capture the answer.
+async fn record_from_upstream(upstream: &LlmEndpoint, request: &Value) -> Result<FixtureResponse, String> {
+    let mut body = request.clone();
+    body["stream"] = json!(false);
+    let response = reqwest::Client::new()
+        .post(upstream.chat_completions_url())
+        .headers(upstream.headers())
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Recording request failed: {}", e))?;
+    let status = response.status();
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Recording upstream error ({}): {}", status, text));
+    }
+    let data: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    let message = &data["choices"][0]["message"];
+    let tool_calls = message["tool_calls"]
+        .as_array()
+        .map(|calls| calls.iter().map(|tc| FixtureToolCall {
+            id: tc["id"].as_str().unwrap_or("").to_string(),
+            name: tc["function"]["name"].as_str().unwrap_or("").to_string(),
+            arguments: tc["function"]["arguments"].as_str().unwrap_or("{}").to_string(),
+        }).collect())
+        .unwrap_or_default();
+    Ok(FixtureResponse {
+        content: message["content"].as_str().unwrap_or("").to_string(),
+        reasoning: message["reasoning"].as_str().unwrap_or("").to_string(),
+        tool_calls,
+        usage: data.get("usage").cloned(),
+        error: None,
+    })
+}
+
+/// Start the built-in fixture server when `OPEN_COUNCIL_LLM_FIXTURES` is set.
+pub async fn start_fixture_mode_from_env(upstream: Option<LlmEndpoint>) -> Result<Option<MockLlmServer>, String> {
+    let Some(dir) = std::env::var_os(FIXTURES_ENV) else {
+        return Ok(None);
+    };
+    let store = FixtureStore::load_dir(&PathBuf::from(dir))?;
+    let recording = std::env::var(RECORD_ENV).map(|v| v == "1").unwrap_or(false);
+    let server = MockLlmServer::start(store, None, if recording { upstream } else { None }).await?;
+    Ok(Some(server))
+}
+
+// ── Test harness shared by llm/debate tests ──
+
+#[cfg(test)]
+pub mod test_support {
+    use crate::commands::AppState;
+    use crate::config::{self, AppConfig, ProviderConfig};
+    use crate::db::Database;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use tauri::test::{mock_app, MockRuntime};
+    use tauri::Manager;
+
+    /// Mock Tauri app whose default provider points at the given mock server.
+    pub fn mock_app_with_state(app_data_dir: &Path, base_url: &str) -> tauri::App<MockRuntime> {
+        let config = AppConfig {
+            default_provider: "fixture".to_string(),
+            providers: vec![ProviderConfig {
+                id: "fixture".to_string(),
+                label: "Fixture".to_string(),
+                kind: "openai_compatible".to_string(),
+                base_url: base_url.to_string(),
+                api_key: String::new(),
+            }],
+            ..AppConfig::default()
+        };
+        config::save_config(&app_data_dir.to_path_buf(), &config).expect("config should save");
+
+        let app = mock_app();
+        app.manage(Mutex::new(AppState {
+            db: Database::new(":memory:").expect("in-memory database should initialize"),
+            app_data_dir: app_data_dir.to_path_buf(),
+            debate_cancel_flags: std::collections::HashMap::new(),
+            chat_cancel_handles: std::collections::HashMap::new(),
+        }));
+        app
+    }
+
+    /// Concatenated message contents of a request, for matching in scripted responders.
+    pub fn request_text(request: &serde_json::Value) -> String {
+        request["messages"]
+            .as_array()
+            .map(|msgs| {
+                msgs.iter()
+                    .filter_map(|m| m["content"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::GenerationParams;
+    use crate::config::{AppConfig, ProviderConfig};
+    use crate::llm;
+    use crate::providers;
+    use crate::usage::UsageContext;
+    use tempfile::tempdir;
+
+    fn endpoint_for(server: &MockLlmServer) -> LlmEndpoint {
+        let config = AppConfig {
+            providers: vec![ProviderConfig {
+                id: "fixture".to_string(),
+                label: "Fixture".to_string(),
+                kind: "openai_compatible".to_string(),
+                base_url: server.base_url().to_string(),
+                api_key: String::new(),
+            }],
+            ..AppConfig::default()
+        };
+        providers::resolve_endpoint(&config, "fixture").expect("fixture provider should resolve")
+    }
+
+    #[test]
+    fn unit_request_hash_ignores_sampling_and_stream_flags() {
+        let a = json!({"model": "m", "messages": [{"role": "user", "content": "hi"}], "stream": true, "temperature": 0.7});
+        let b = json!({"model": "m", "messages": [{"role": "user", "content": "hi"}], "max_tokens": 10});
+        let c = json!({"model": "m", "messages": [{"role": "user", "content": "hello"}]});
+        assert_eq!(request_hash(&a), request_hash(&b));
+        assert_ne!(request_hash(&a), request_hash(&c));
+        assert_eq!(request_hash(&a).len(), 16);
+    }
+
+    #[test]
+    fn unit_sse_events_stream_content_then_tool_calls_then_done() {
+        let response = FixtureResponse {
+            content: "one two".to_string(),
+            reasoning: String::new(),
+            tool_calls: vec![FixtureToolCall {
+                id: "call_1".to_string(),
+                name: "read_profile_files".to_string(),
+                arguments: "{}".to_string(),
+            }],
+            usage: Some(json!({"prompt_tokens": 3, "completion_tokens": 2})),
+            error: None,
+        };
+        let events = sse_events("m", &response);
+        assert_eq!(events.len(), 6);
+        assert!(events[0].contains("\"content\":\"one \""));
+        assert!(events[2].contains("read_profile_files"));
+        assert!(events[4].contains("\"finish_reason\":\"tool_calls\""));
+        assert!(events[4].contains("prompt_tokens"));
+        assert_eq!(events[5], "data: [DONE]\n\n");
+    }
+
+    #[tokio::test]
+    async fn integration_recorded_fixture_is_replayed_by_request_hash() {
+        let dir = tempdir().expect("temp directory should exist");
+        let fixtures_dir = dir.path().join("fixtures");
+
+        let request = json!({
+            "model": "fixture/model",
+            "messages": [
+                {"role": "system", "content": "sys"},
+                {"role": "user", "content": "question"},
+            ],
+        });
+        let seed = FixtureStore::load_dir(&fixtures_dir).expect("fixture dir should load");
+        seed.insert(&request_hash(&request), "fixture/model", FixtureResponse::text("recorded answer"))
+            .expect("fixture should save");
+
+        let store = FixtureStore::load_dir(&fixtures_dir).expect("fixture dir should reload");
+        let server = MockLlmServer::start(store, None, None).await.expect("server should start");
+        let endpoint = endpoint_for(&server);
+        let app = tauri::test::mock_app();
+        let call = llm::LlmCall {
+            endpoint: &endpoint,
+            model: "fixture/model",
+            params: &GenerationParams::default(),
+            usage_ctx: &UsageContext::purpose("test"),
+        };
+
+        let answer = llm::call_llm_simple(&call, "sys", "question", app.handle())
+            .await
+            .expect("fixture should be served");
+        assert_eq!(answer, "recorded answer");
+
+        let miss = llm::call_llm_simple(&call, "sys", "other question", app.handle()).await;
+        assert!(miss.expect_err("unknown request should fail").message.contains("No fixture"));
+        assert_eq!(server.requests().len(), 2);
+    }
+}
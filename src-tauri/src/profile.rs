@@ -1,6 +1,10 @@
+use crate::file_history;
+use crate::profile_history;
+use crate::safe_path;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::ops::Range;
 use std::path::PathBuf;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -9,12 +13,17 @@ pub struct ProfileFileInfo {
     pub content: String,
     pub modified_at: String,
     pub size_bytes: u64,
+    /// Revisions kept under `profile/.history`.
+    pub revision_count: u32,
 }
 
 pub fn get_profile_dir(app_data_dir: &PathBuf) -> PathBuf {
     app_data_dir.join("profile")
 }
 
+/// The profile as it may be sent to a model: markdown without frontmatter or
+/// ```private blocks, and no files marked `private: true`. The profile viewer reads
+/// [`read_all_profiles_detailed`], which keeps everything.
 pub fn read_all_profiles(app_data_dir: &PathBuf) -> Result<HashMap<String, String>, String> {
     let dir = get_profile_dir(app_data_dir);
     if !dir.exists() {
@@ -29,31 +38,347 @@ pub fn read_all_profiles(app_data_dir: &PathBuf) -> Result<HashMap<String, Strin
         if path.extension().and_then(|e| e.to_str()) == Some("md") {
             let filename = path.file_name().unwrap().to_string_lossy().to_string();
             let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-            files.insert(filename, content);
+            if let Some(shared) = shareable_content(&content) {
+                files.insert(filename, shared);
+            }
         }
     }
     Ok(files)
 }
 
 pub fn write_profile_file(app_data_dir: &PathBuf, filename: &str, content: &str) -> Result<String, String> {
+    write_with_history(app_data_dir, filename, content, "write", None, true)?;
+    Ok(format!("Successfully wrote {}", filename))
+}
+
+/// Write `content`, refreshing its fact metadata: facts new to the file are stamped as
+/// learned today from `source_conversation`. `content_facts` says whether fact
+/// metadata in `content`'s own frontmatter can be trusted.
+fn write_with_history(
+    app_data_dir: &PathBuf,
+    filename: &str,
+    content: &str,
+    action: &str,
+    source_conversation: Option<&str>,
+    content_facts: bool,
+) -> Result<(), String> {
     let dir = get_profile_dir(app_data_dir);
+    let path = safe_path::resolve(&dir, filename)?;
     fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
-    let path = dir.join(filename);
-    fs::write(&path, content).map_err(|e| e.to_string())?;
-    Ok(format!("Successfully wrote {}", filename))
+    profile_history::capture_current(app_data_dir, filename)?;
+    let previous = fs::read_to_string(&path).ok();
+    let content = with_fact_metadata(previous.as_deref(), content, source_conversation, content_facts, &today());
+    fs::write(&path, &content).map_err(|e| e.to_string())?;
+    profile_history::record(app_data_dir, filename, action, Some(&content))
 }
 
 pub fn delete_profile_file(app_data_dir: &PathBuf, filename: &str) -> Result<String, String> {
-    let dir = get_profile_dir(app_data_dir);
-    let path = dir.join(filename);
+    let path = safe_path::resolve(&get_profile_dir(app_data_dir), filename)?;
     if path.exists() {
+        profile_history::capture_current(app_data_dir, filename)?;
         fs::remove_file(&path).map_err(|e| e.to_string())?;
+        profile_history::record(app_data_dir, filename, "delete", None)?;
         Ok(format!("Successfully deleted {}", filename))
     } else {
         Ok(format!("File {} does not exist", filename))
     }
 }
 
+/// Add `content` to the section under `heading` (see [`ProfileEdit::AppendSection`]).
+/// New facts are attributed to `source_conversation`.
+pub fn append_to_profile_section(
+    app_data_dir: &PathBuf,
+    filename: &str,
+    heading: Option<&str>,
+    content: &str,
+    source_conversation: Option<&str>,
+) -> Result<String, String> {
+    let heading = heading.map(str::to_string);
+    ProfileEdit::AppendSection { filename: filename.to_string(), heading, content: content.to_string() }
+        .apply(app_data_dir, source_conversation)
+}
+
+/// Replace the text of the section under `heading` (see [`ProfileEdit::ReplaceSection`]).
+pub fn replace_profile_section(
+    app_data_dir: &PathBuf,
+    filename: &str,
+    heading: &str,
+    content: &str,
+    source_conversation: Option<&str>,
+) -> Result<String, String> {
+    let (filename, heading, content) = (filename.to_string(), heading.to_string(), content.to_string());
+    ProfileEdit::ReplaceSection { filename, heading, content }.apply(app_data_dir, source_conversation)
+}
+
+/// Append `from` to `into` and delete `from` (see [`ProfileEdit::Merge`]).
+pub fn merge_profile_files(app_data_dir: &PathBuf, from: &str, into: &str) -> Result<String, String> {
+    ProfileEdit::Merge { from: from.to_string(), into: into.to_string() }.apply(app_data_dir, None)
+}
+
+fn appended_content(existing: &str, heading: Option<&str>, content: &str) -> String {
+    let addition = content.trim_end();
+    match heading.filter(|h| !h.trim().is_empty()) {
+        None => join_blocks(existing, addition),
+        Some(heading) => {
+            let lines: Vec<&str> = existing.lines().collect();
+            match find_section(&lines, heading) {
+                Some(section) => {
+                    // Insert after the last non-blank line of the section's own text,
+                    // ahead of its subsections.
+                    let mut insert_at = section.body_end;
+                    while insert_at > 0 && lines[insert_at - 1].trim().is_empty() {
+                        insert_at -= 1;
+                    }
+                    let mut out: Vec<&str> = lines[..insert_at].to_vec();
+                    out.extend(addition.lines());
+                    if insert_at == section.body_end && section.body_end < section.end {
+                        out.push("");
+                    }
+                    out.extend(&lines[insert_at..]);
+                    with_trailing_newline(out.join("\n"))
+                }
+                None => join_blocks(existing, &format!("{}\n{}", heading_line(heading), addition)),
+            }
+        }
+    }
+}
+
+/// Replace the section's own text. Its subsections stay unless `content` has a
+/// heading of the same name, which then takes the subsection's place.
+fn replaced_content(existing: &str, filename: &str, heading: &str, content: &str) -> Result<String, String> {
+    let lines: Vec<&str> = existing.lines().collect();
+    let section = find_section(&lines, heading).ok_or_else(|| {
+        let headings: Vec<&str> = lines
+            .iter()
+            .zip(heading_levels(&lines))
+            .filter(|(_, level)| level.is_some())
+            .map(|(l, _)| l.trim())
+            .collect();
+        format!("No section '{}' in {}. Headings: {}", heading.trim(), filename, headings.join(", "))
+    })?;
+
+    let content: Vec<&str> = content.trim_end().lines().collect();
+    let named: HashSet<String> = content
+        .iter()
+        .zip(heading_levels(&content))
+        .filter(|(_, level)| level.is_some())
+        .map(|(l, _)| heading_text(l).to_lowercase())
+        .collect();
+    let mut out: Vec<&str> = lines[..=section.heading].to_vec();
+    out.extend(&content);
+    for subsection in subsections(&lines[section.body_end..section.end]) {
+        if named.contains(&heading_text(subsection[0]).to_lowercase()) {
+            continue;
+        }
+        let len = subsection.iter().rposition(|l| !l.trim().is_empty()).map_or(0, |i| i + 1);
+        out.push("");
+        out.extend(&subsection[..len]);
+    }
+    if section.end < lines.len() {
+        out.push("");
+        out.extend(&lines[section.end..]);
+    }
+    Ok(with_trailing_newline(out.join("\n")))
+}
+
+/// Rename a profile file. Refuses to overwrite an existing file; merge instead.
+pub fn rename_profile_file(app_data_dir: &PathBuf, from: &str, to: &str) -> Result<String, String> {
+    let (source, target) = rename_paths(app_data_dir, from, to)?;
+    profile_history::capture_current(app_data_dir, from)?;
+    let content = fs::read_to_string(&source).map_err(|e| e.to_string())?;
+    fs::rename(&source, &target).map_err(|e| e.to_string())?;
+    profile_history::record(app_data_dir, from, "rename", None)?;
+    profile_history::record(app_data_dir, to, "rename", Some(&content))?;
+    Ok(format!("Successfully renamed {} to {}", from, to))
+}
+
+fn rename_paths(app_data_dir: &PathBuf, from: &str, to: &str) -> Result<(PathBuf, PathBuf), String> {
+    let dir = get_profile_dir(app_data_dir);
+    let source = safe_path::resolve(&dir, from)?;
+    let target = safe_path::resolve(&dir, to)?;
+    if !source.exists() {
+        return Err(format!("File {} does not exist", from));
+    }
+    if target.exists() {
+        return Err(format!("File {} already exists; merge the files instead", to));
+    }
+    Ok((source, target))
+}
+
+/// Current content of `from` and, if it exists, of `into`.
+fn merge_inputs(app_data_dir: &PathBuf, from: &str, into: &str) -> Result<(String, Option<String>), String> {
+    let dir = get_profile_dir(app_data_dir);
+    let source_path = safe_path::resolve(&dir, from)?;
+    let target_path = safe_path::resolve(&dir, into)?;
+    if from == into {
+        return Err("Cannot merge a file into itself".to_string());
+    }
+    let source = fs::read_to_string(&source_path).map_err(|_| format!("File {} does not exist", from))?;
+    Ok((source, fs::read_to_string(&target_path).ok()))
+}
+
+/// `existing` with `source` appended; the facts of both keep their metadata.
+fn merged_content(source: String, existing: &str) -> String {
+    let (source_frontmatter, source) = parse_frontmatter(&source).unwrap_or((Frontmatter::default(), &source));
+    let (mut frontmatter, existing) = parse_frontmatter(existing).unwrap_or((Frontmatter::default(), existing));
+    frontmatter.facts.extend(source_frontmatter.facts);
+    let source = match source.trim_start().strip_prefix("# ") {
+        Some(rest) if !existing.trim().is_empty() => format!("## {}", rest),
+        _ => source.to_string(),
+    };
+    render_frontmatter(&frontmatter, &join_blocks(existing, source.trim_end()))
+}
+
+/// Put a file back as it was at `revision`, recreating it if it was deleted since.
+pub fn restore_profile_revision(app_data_dir: &PathBuf, filename: &str, revision: u32) -> Result<String, String> {
+    let content = profile_history::list_revisions(app_data_dir, filename)?
+        .into_iter()
+        .find(|r| r.revision == revision)
+        .ok_or_else(|| format!("Revision {} of {} not found", revision, filename))?
+        .content
+        .ok_or_else(|| format!("Revision {} of {} is a deletion; restore an earlier one", revision, filename))?;
+    write_with_history(app_data_dir, filename, &content, "restore", None, true)?;
+    Ok(format!("Restored {} to revision {}", filename, revision))
+}
+
+/// Level of an ATX heading line (`## Goals` is 2).
+pub fn heading_level(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    (1..=6).contains(&level).then_some(level).filter(|_| rest.is_empty() || rest.starts_with(' '))
+}
+
+pub fn heading_text(line: &str) -> &str {
+    line.trim().trim_start_matches('#').trim()
+}
+
+/// `## Goals` for a bare heading name; a heading given with `#`s is kept as is.
+fn heading_line(heading: &str) -> String {
+    let heading = heading.trim();
+    if heading_level(heading).is_some() {
+        heading.to_string()
+    } else {
+        format!("## {}", heading)
+    }
+}
+
+/// A line that belongs to a code fence, the fence lines included.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FencedLine {
+    /// The fence is a ```private block.
+    pub private: bool,
+    /// The line opens the fence.
+    pub opening: bool,
+}
+
+/// The code fence each line belongs to, if any. A fence closes only on a bare run of at
+/// least as many backticks, so a ```python line inside a block is just content; an
+/// unclosed fence runs to the end. Every markdown scan here goes through this one.
+pub(crate) fn fenced_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<Option<FencedLine>> {
+    let mut open: Option<(usize, bool)> = None;
+    lines
+        .into_iter()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let ticks = trimmed.chars().take_while(|c| *c == '`').count();
+            let info = trimmed[ticks..].trim();
+            match open {
+                Some((opening_ticks, private)) => {
+                    if ticks >= opening_ticks && info.is_empty() {
+                        open = None;
+                    }
+                    Some(FencedLine { private, opening: false })
+                }
+                None if ticks >= 3 => {
+                    let private = info.eq_ignore_ascii_case("private");
+                    open = Some((ticks, private));
+                    Some(FencedLine { private, opening: true })
+                }
+                None => None,
+            }
+        })
+        .collect()
+}
+
+/// The heading level of each line; `None` for other lines and for headings inside
+/// code fences.
+pub(crate) fn heading_levels(lines: &[&str]) -> Vec<Option<usize>> {
+    lines
+        .iter()
+        .zip(fenced_lines(lines.iter().copied()))
+        .map(|(line, fenced)| heading_level(line).filter(|_| fenced.is_none()))
+        .collect()
+}
+
+/// Where a section sits in a file's lines: its heading line, the end of its own text
+/// (its first subsection heading) and its end, subsections included.
+struct Section {
+    heading: usize,
+    body_end: usize,
+    end: usize,
+}
+
+/// The section under `heading` (case-insensitive, with or without `#`s). It ends at
+/// the next heading of the same or a higher level, or the end of the file; deeper
+/// headings before that are its subsections.
+fn find_section(lines: &[&str], heading: &str) -> Option<Section> {
+    let wanted = heading_text(heading).to_lowercase();
+    let mut found: Option<(usize, usize, Option<usize>)> = None;
+    for (index, level) in heading_levels(lines).into_iter().enumerate() {
+        let Some(level) = level else { continue };
+        match &mut found {
+            Some((start, found_level, first_child)) if level <= *found_level => {
+                return Some(Section { heading: *start, body_end: first_child.unwrap_or(index), end: index });
+            }
+            Some((_, _, first_child)) => {
+                first_child.get_or_insert(index);
+            }
+            None if heading_text(lines[index]).to_lowercase() == wanted => found = Some((index, level, None)),
+            None => {}
+        }
+    }
+    found.map(|(start, _, first_child)| Section {
+        heading: start,
+        body_end: first_child.unwrap_or(lines.len()),
+        end: lines.len(),
+    })
+}
+
+/// A section's subsections, each with its own nested headings. `lines` starts at the
+/// first subsection heading.
+fn subsections<'a>(lines: &'a [&'a str]) -> Vec<&'a [&'a str]> {
+    let mut starts: Vec<usize> = Vec::new();
+    let mut top_level = usize::MAX;
+    for (index, level) in heading_levels(lines).into_iter().enumerate() {
+        if let Some(level) = level.filter(|level| *level <= top_level) {
+            starts.push(index);
+            top_level = level;
+        }
+    }
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, start)| &lines[*start..starts.get(n + 1).copied().unwrap_or(lines.len())])
+        .collect()
+}
+
+fn join_blocks(existing: &str, addition: &str) -> String {
+    let existing = existing.trim_end();
+    if existing.is_empty() {
+        with_trailing_newline(addition.to_string())
+    } else {
+        format!("{}\n\n{}\n", existing, addition)
+    }
+}
+
+fn with_trailing_newline(mut text: String) -> String {
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
 pub fn read_all_profiles_detailed(app_data_dir: &PathBuf) -> Result<Vec<ProfileFileInfo>, String> {
     let dir = get_profile_dir(app_data_dir);
     if !dir.exists() {
@@ -67,25 +392,512 @@ pub fn read_all_profiles_detailed(app_data_dir: &PathBuf) -> Result<Vec<ProfileF
         let path = entry.path();
         if path.extension().and_then(|e| e.to_str()) == Some("md") {
             let filename = path.file_name().unwrap().to_string_lossy().to_string();
-            let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-            let metadata = fs::metadata(&path).map_err(|e| e.to_string())?;
-            let modified = metadata.modified().map_err(|e| e.to_string())?;
-            let modified_at = chrono::DateTime::<chrono::Utc>::from(modified)
-                .format("%Y-%m-%dT%H:%M:%SZ")
-                .to_string();
-            let size_bytes = metadata.len();
-            files.push(ProfileFileInfo {
-                filename,
-                content,
-                modified_at,
-                size_bytes,
-            });
+            files.push(file_info(app_data_dir, filename, &path)?);
         }
     }
     files.sort_by(|a, b| a.filename.cmp(&b.filename));
     Ok(files)
 }
 
+pub fn profile_file_info(app_data_dir: &PathBuf, filename: &str) -> Result<ProfileFileInfo, String> {
+    let path = safe_path::resolve(&get_profile_dir(app_data_dir), filename)?;
+    file_info(app_data_dir, filename.to_string(), &path)
+}
+
+fn file_info(app_data_dir: &PathBuf, filename: String, path: &std::path::Path) -> Result<ProfileFileInfo, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    let modified = metadata.modified().map_err(|e| e.to_string())?;
+    let modified_at = chrono::DateTime::<chrono::Utc>::from(modified)
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string();
+    Ok(ProfileFileInfo {
+        revision_count: profile_history::revision_count(app_data_dir, &filename),
+        filename,
+        content,
+        modified_at,
+        size_bytes: metadata.len(),
+    })
+}
+
+// ── Frontmatter and facts ──
+
+/// Days after which a fact is due for re-confirmation.
+pub const STALE_FACT_DAYS: i64 = 180;
+
+const CONFIDENCE_LEVELS: [&str; 3] = ["high", "medium", "low"];
+
+/// Metadata for one fact, i.e. one bullet line, matched to it by text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FactMeta {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_conversation: Option<String>,
+    /// `YYYY-MM-DD`, as is `last_confirmed`.
+    pub learned_at: String,
+    /// `high`, `medium` or `low`.
+    #[serde(default = "default_confidence")]
+    pub confidence: String,
+    pub last_confirmed: String,
+}
+
+fn default_confidence() -> String {
+    "medium".to_string()
+}
+
+/// The YAML block at the top of a profile file. Keys besides `facts` are kept as written.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Frontmatter {
+    /// `private: true` keeps the whole file on this machine.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub private: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub facts: Vec<FactMeta>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_yaml_ng::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileFact {
+    pub filename: String,
+    pub text: String,
+    pub source_conversation: Option<String>,
+    pub learned_at: String,
+    pub confidence: String,
+    pub last_confirmed: String,
+    /// Days since `last_confirmed`.
+    pub age_days: i64,
+    /// The fact is in a file marked `private: true`.
+    pub private: bool,
+}
+
+/// The frontmatter of `content` and the markdown after it, if it opens with a
+/// `---` block of valid YAML.
+pub fn parse_frontmatter(content: &str) -> Option<(Frontmatter, &str)> {
+    let rest = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let yaml = &rest[..offset];
+            let frontmatter = if yaml.trim().is_empty() {
+                Frontmatter::default()
+            } else {
+                serde_yaml_ng::from_str(yaml).ok()?
+            };
+            return Some((frontmatter, &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+pub fn strip_frontmatter(content: &str) -> &str {
+    parse_frontmatter(content).map(|(_, body)| body).unwrap_or(content)
+}
+
+fn render_frontmatter(frontmatter: &Frontmatter, body: &str) -> String {
+    if !frontmatter.private && frontmatter.facts.is_empty() && frontmatter.extra.is_empty() {
+        return body.to_string();
+    }
+    format!("---\n{}---\n{}", serde_yaml_ng::to_string(frontmatter).unwrap_or_default(), body)
+}
+
+/// Bullet lines of `body` without their markers, skipping code fences and repeats.
+fn fact_lines(body: &str) -> Vec<String> {
+    let mut facts: Vec<String> = Vec::new();
+    for (line, fenced) in body.lines().zip(fenced_lines(body.lines())) {
+        let trimmed = line.trim();
+        let Some(text) = ["- ", "* ", "+ "].iter().find_map(|marker| trimmed.strip_prefix(marker)) else {
+            continue;
+        };
+        let text = text.trim();
+        if fenced.is_none() && !text.is_empty() && !facts.iter().any(|f| fact_key(f) == fact_key(text)) {
+            facts.push(text.to_string());
+        }
+    }
+    facts
+}
+
+/// Facts match ignoring case and spacing.
+fn fact_key(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// `content` with frontmatter listing the facts it contains. A fact keeps the metadata
+/// it has in `content`'s own frontmatter (when `content_facts` is set) or, failing that,
+/// in `previous`; new facts are stamped with `source_conversation` and `today`. Other
+/// frontmatter keys `content` leaves out keep their values from `previous`.
+fn with_fact_metadata(
+    previous: Option<&str>,
+    content: &str,
+    source_conversation: Option<&str>,
+    content_facts: bool,
+    today: &str,
+) -> String {
+    let previous = previous.and_then(parse_frontmatter).map(|(f, _)| f).unwrap_or_default();
+    let (mut frontmatter, body) = match parse_frontmatter(content) {
+        Some((mut frontmatter, body)) => {
+            let mut extra = previous.extra.clone();
+            extra.append(&mut frontmatter.extra);
+            frontmatter.extra = extra;
+            if !content_facts {
+                frontmatter.facts.clear();
+            }
+            (frontmatter, body)
+        }
+        None => (Frontmatter { private: previous.private, facts: Vec::new(), extra: previous.extra.clone() }, content),
+    };
+    let known: Vec<FactMeta> = frontmatter.facts.drain(..).chain(previous.facts).collect();
+    frontmatter.facts = fact_lines(body)
+        .into_iter()
+        .map(|text| match known.iter().find(|f| fact_key(&f.text) == fact_key(&text)) {
+            Some(meta) => FactMeta { text, ..meta.clone() },
+            None => FactMeta {
+                text,
+                source_conversation: source_conversation.map(str::to_string),
+                learned_at: today.to_string(),
+                confidence: default_confidence(),
+                last_confirmed: today.to_string(),
+            },
+        })
+        .collect();
+    render_frontmatter(&frontmatter, body)
+}
+
+/// Frontmatter covering every fact in a file. Facts without metadata yet (the file was
+/// written by hand) date from the file's last modification.
+fn file_facts(info: &ProfileFileInfo) -> Frontmatter {
+    let modified = info.modified_at.get(..10).unwrap_or_default();
+    let content = with_fact_metadata(Some(&info.content), &info.content, None, true, modified);
+    parse_frontmatter(&content).map(|(f, _)| f).unwrap_or_default()
+}
+
+/// Every fact across the profile files with its metadata.
+pub fn list_profile_facts(app_data_dir: &PathBuf) -> Result<Vec<ProfileFact>, String> {
+    let today = chrono::Utc::now().date_naive();
+    let age = |date: &str| {
+        chrono::NaiveDate::parse_from_str(date.get(..10).unwrap_or(date), "%Y-%m-%d")
+            .map(|d| (today - d).num_days())
+            .unwrap_or(0)
+    };
+    let mut facts = Vec::new();
+    for info in read_all_profiles_detailed(app_data_dir)? {
+        let frontmatter = file_facts(&info);
+        facts.extend(frontmatter.facts.into_iter().map(|meta| ProfileFact {
+            filename: info.filename.clone(),
+            age_days: age(&meta.last_confirmed),
+            text: meta.text,
+            source_conversation: meta.source_conversation,
+            learned_at: meta.learned_at,
+            confidence: meta.confidence,
+            last_confirmed: meta.last_confirmed,
+            private: frontmatter.private,
+        }));
+    }
+    Ok(facts)
+}
+
+/// Facts not confirmed within `max_age_days` (a quarter of that for low-confidence
+/// ones), oldest first.
+pub fn stale_profile_facts(app_data_dir: &PathBuf, max_age_days: i64) -> Result<Vec<ProfileFact>, String> {
+    let mut stale: Vec<ProfileFact> = list_profile_facts(app_data_dir)?
+        .into_iter()
+        .filter(|f| f.age_days >= if f.confidence == "low" { max_age_days / 4 } else { max_age_days })
+        .collect();
+    stale.sort_by_key(|f| std::cmp::Reverse(f.age_days));
+    Ok(stale)
+}
+
+/// Mark a fact as confirmed today, optionally with a new confidence. `fact` is the
+/// bullet's text (case and spacing don't matter) or a part of it that matches only one.
+pub fn confirm_profile_fact(app_data_dir: &PathBuf, filename: &str, fact: &str, confidence: Option<&str>) -> Result<String, String> {
+    if let Some(level) = confidence.filter(|c| !CONFIDENCE_LEVELS.contains(c)) {
+        return Err(format!("Confidence must be high, medium or low, not '{}'", level));
+    }
+    let info = profile_file_info(app_data_dir, filename).map_err(|_| format!("File {} does not exist", filename))?;
+    let mut frontmatter = file_facts(&info);
+    let wanted = fact_key(fact);
+    let index = match frontmatter.facts.iter().position(|f| fact_key(&f.text) == wanted) {
+        Some(index) => index,
+        None => {
+            let partial: Vec<usize> = (0..frontmatter.facts.len())
+                .filter(|&i| !wanted.is_empty() && fact_key(&frontmatter.facts[i].text).contains(&wanted))
+                .collect();
+            match partial[..] {
+                [index] => index,
+                [] => return Err(format!("No fact matching '{}' in {}", fact.trim(), filename)),
+                _ => return Err(format!("'{}' matches several facts in {}; quote the whole line", fact.trim(), filename)),
+            }
+        }
+    };
+
+    let meta = &mut frontmatter.facts[index];
+    meta.last_confirmed = today();
+    if let Some(level) = confidence {
+        meta.confidence = level.to_string();
+    }
+    let text = meta.text.clone();
+    let content = render_frontmatter(&frontmatter, strip_frontmatter(&info.content));
+    write_with_history(app_data_dir, filename, &content, "confirm", None, true)?;
+    Ok(format!("Confirmed '{}' in {}", text, filename))
+}
+
+// ── Private sections ──
+
+/// Whether each line of `body` is part of a ```private block. Its contents stay in the
+/// file but are left out of everything sent to a model. An unclosed block runs to the
+/// end of the file.
+fn private_lines(body: &str) -> Vec<bool> {
+    fenced_lines(body.lines()).into_iter().map(|fenced| fenced.is_some_and(|f| f.private)).collect()
+}
+
+/// Line ranges of the ```private blocks in `lines`, fences included.
+fn private_blocks(lines: &[&str]) -> Vec<Range<usize>> {
+    let mut blocks: Vec<Range<usize>> = Vec::new();
+    for (index, fenced) in fenced_lines(lines.iter().copied()).into_iter().enumerate() {
+        match fenced.filter(|f| f.private) {
+            Some(f) if f.opening => blocks.push(index..index + 1),
+            Some(_) => blocks.last_mut().expect("a private block was opened").end = index + 1,
+            None => {}
+        }
+    }
+    blocks
+}
+
+/// What of a profile file may leave the machine: `None` for a file marked
+/// `private: true`, otherwise its markdown without frontmatter or private blocks.
+pub fn shareable_content(content: &str) -> Option<String> {
+    let (frontmatter, body) = parse_frontmatter(content).unwrap_or((Frontmatter::default(), content));
+    if frontmatter.private {
+        return None;
+    }
+    let mask = private_lines(body);
+    if !mask.contains(&true) {
+        return Some(body.to_string());
+    }
+    let mut kept: Vec<&str> = Vec::new();
+    let mut after_private = false;
+    for (line, private) in body.lines().zip(mask) {
+        if private {
+            after_private = true;
+            continue;
+        }
+        let blank = line.trim().is_empty();
+        if after_private && blank && kept.last().is_none_or(|l| l.trim().is_empty()) {
+            continue;
+        }
+        after_private &= blank;
+        kept.push(line);
+    }
+    let text = kept.join("\n");
+    Some(if body.ends_with('\n') && !text.is_empty() { with_trailing_newline(text) } else { text })
+}
+
+pub fn is_private_file(app_data_dir: &PathBuf, filename: &str) -> bool {
+    safe_path::resolve(&get_profile_dir(app_data_dir), filename)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| parse_frontmatter(&content).map(|(f, _)| f.private))
+        .unwrap_or(false)
+}
+
+/// `after` with any private block of `before` that it dropped put back: the assistant
+/// only ever sees the file without them, so it can't mean to remove them.
+fn keep_private_blocks(before: Option<&str>, after: String) -> String {
+    let before: Vec<&str> = before.unwrap_or_default().lines().collect();
+    private_blocks(&before).into_iter().fold(after, |after, block| {
+        if after.contains(&before[block.clone()].join("\n")) {
+            after
+        } else {
+            put_back(&before, block, &after)
+        }
+    })
+}
+
+/// `after` with lines `block` of `before` where they were: after the line that came
+/// before them, when `after` has that line exactly once, else at the end of the text of
+/// the section they were in, else at the end of the file.
+fn put_back(before: &[&str], block: Range<usize>, after: &str) -> String {
+    let lines: Vec<&str> = after.lines().collect();
+    let blank_around = (
+        before[..block.start].last().is_some_and(|l| l.trim().is_empty()),
+        before.get(block.end).is_none_or(|l| l.trim().is_empty()),
+    );
+    let anchor = before[..block.start].iter().rposition(|l| !l.trim().is_empty());
+    let after_anchor = anchor.and_then(|anchor| {
+        let mut matches = lines.iter().enumerate().filter(|(_, l)| **l == before[anchor]);
+        match (matches.next(), matches.next()) {
+            (Some((index, _)), None) => Some((index + 1, blank_around)),
+            _ => None,
+        }
+    });
+    let section_end = || {
+        let levels = heading_levels(before);
+        let heading = (0..block.start).rev().find(|i| levels[*i].is_some())?;
+        let section = find_section(&lines, before[heading])?;
+        let mut end = section.body_end;
+        while end > section.heading + 1 && lines[end - 1].trim().is_empty() {
+            end -= 1;
+        }
+        Some((end, (true, true)))
+    };
+    let Some((insert_at, (blank_before, blank_after))) = after_anchor.or_else(section_end) else {
+        return join_blocks(after, &before[block].join("\n"));
+    };
+
+    let mut out: Vec<&str> = lines[..insert_at].to_vec();
+    if blank_before {
+        out.push("");
+    }
+    out.extend(&before[block]);
+    if blank_after && lines.get(insert_at).is_some_and(|l| !l.trim().is_empty()) {
+        out.push("");
+    }
+    out.extend(&lines[insert_at..]);
+    with_trailing_newline(out.join("\n"))
+}
+
+// ── Proposed edits ──
+
+/// A profile change as one of the assistant's file tools makes it. In review mode
+/// these are queued as pending changes and applied only once the user approves.
+/// Files marked private are off limits, and private blocks survive every edit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ProfileEdit {
+    Write { filename: String, content: String },
+    Delete { filename: String },
+    /// Append `content` to the section under `heading`, after its own text and ahead of its
+    /// subsections, creating the section (and the file) when missing. Without a heading the
+    /// content goes at the end of the file.
+    AppendSection { filename: String, heading: Option<String>, content: String },
+    /// Replace the text of the section under `heading` up to its first subsection. The
+    /// heading line, the subsections `content` doesn't name and every other section stay.
+    ReplaceSection { filename: String, heading: String, content: String },
+    Rename { from: String, to: String },
+    /// Append `from` to the end of `into` and delete `from`. A leading `# Title` in
+    /// `from` becomes a `##` heading so the merged file keeps a single title.
+    Merge { from: String, into: String },
+}
+
+/// A file's content before and after an edit; `None` where the file doesn't exist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    pub filename: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Hash of each file's content before the changes (`None` where it doesn't exist), to
+/// tell later whether the files moved on since the changes were previewed.
+pub fn base_hashes(changes: &[FileChange]) -> BTreeMap<String, Option<String>> {
+    changes
+        .iter()
+        .map(|change| (change.filename.clone(), change.before.as_deref().map(file_history::content_hash)))
+        .collect()
+}
+
+impl ProfileEdit {
+    pub fn files(&self) -> Vec<String> {
+        match self {
+            ProfileEdit::Write { filename, .. }
+            | ProfileEdit::Delete { filename }
+            | ProfileEdit::AppendSection { filename, .. }
+            | ProfileEdit::ReplaceSection { filename, .. } => vec![filename.clone()],
+            ProfileEdit::Rename { from, to } => vec![from.clone(), to.clone()],
+            ProfileEdit::Merge { from, into } => vec![from.clone(), into.clone()],
+        }
+    }
+
+    /// What the edit would do to each file it touches, checked against the files as
+    /// they are now. Nothing is written.
+    pub fn preview(&self, app_data_dir: &PathBuf) -> Result<Vec<FileChange>, String> {
+        self.check_not_private(app_data_dir)?;
+        let dir = get_profile_dir(app_data_dir);
+        let read = |filename: &str| -> Result<Option<String>, String> {
+            Ok(fs::read_to_string(safe_path::resolve(&dir, filename)?).ok())
+        };
+        let change = |filename: &str, before: Option<String>, after: Option<String>| FileChange {
+            filename: filename.to_string(),
+            before,
+            after,
+        };
+        Ok(match self {
+            ProfileEdit::Write { filename, content } => {
+                let before = read(filename)?;
+                let after = keep_private_blocks(before.as_deref(), content.clone());
+                vec![change(filename, before, Some(after))]
+            }
+            ProfileEdit::Delete { filename } => vec![change(filename, read(filename)?, None)],
+            ProfileEdit::AppendSection { filename, heading, content } => {
+                let before = read(filename)?;
+                let after = appended_content(before.as_deref().unwrap_or_default(), heading.as_deref(), content);
+                vec![change(filename, before.clone(), Some(keep_private_blocks(before.as_deref(), after)))]
+            }
+            ProfileEdit::ReplaceSection { filename, heading, content } => {
+                let before = read(filename)?.ok_or_else(|| format!("File {} does not exist", filename))?;
+                let after = keep_private_blocks(Some(&before), replaced_content(&before, filename, heading, content)?);
+                vec![change(filename, Some(before), Some(after))]
+            }
+            ProfileEdit::Rename { from, to } => {
+                let (source, _) = rename_paths(app_data_dir, from, to)?;
+                let content = fs::read_to_string(source).map_err(|e| e.to_string())?;
+                vec![change(from, Some(content.clone()), None), change(to, None, Some(content))]
+            }
+            ProfileEdit::Merge { from, into } => {
+                let (source, existing) = merge_inputs(app_data_dir, from, into)?;
+                let merged = merged_content(source.clone(), existing.as_deref().unwrap_or_default());
+                vec![change(into, existing, Some(merged)), change(from, Some(source), None)]
+            }
+        })
+    }
+
+    /// Make the change, writing exactly what [`ProfileEdit::preview`] shows. Facts it
+    /// adds are attributed to `source_conversation`.
+    pub fn apply(&self, app_data_dir: &PathBuf, source_conversation: Option<&str>) -> Result<String, String> {
+        self.check_not_private(app_data_dir)?;
+        let summary = match self {
+            ProfileEdit::Delete { filename } => return delete_profile_file(app_data_dir, filename),
+            ProfileEdit::Rename { from, to } => return rename_profile_file(app_data_dir, from, to),
+            ProfileEdit::Write { filename, .. } => format!("Successfully wrote {}", filename),
+            ProfileEdit::AppendSection { filename, heading: Some(h), .. } if !h.trim().is_empty() => {
+                format!("Successfully appended to '{}' in {}", h.trim(), filename)
+            }
+            ProfileEdit::AppendSection { filename, .. } => format!("Successfully appended to {}", filename),
+            ProfileEdit::ReplaceSection { filename, heading, .. } => {
+                format!("Successfully replaced '{}' in {}", heading.trim(), filename)
+            }
+            ProfileEdit::Merge { from, into } => format!("Successfully merged {} into {}", from, into),
+        };
+        // A written file's frontmatter comes from the model, which never sees fact metadata.
+        let content_facts = !matches!(self, ProfileEdit::Write { .. });
+        for change in self.preview(app_data_dir)? {
+            match change.after {
+                Some(after) => {
+                    write_with_history(app_data_dir, &change.filename, &after, "write", source_conversation, content_facts)?
+                }
+                None => {
+                    profile_history::capture_current(app_data_dir, &change.filename)?;
+                    fs::remove_file(get_profile_dir(app_data_dir).join(&change.filename)).map_err(|e| e.to_string())?;
+                    profile_history::record(app_data_dir, &change.filename, "delete", None)?;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    fn check_not_private(&self, app_data_dir: &PathBuf) -> Result<(), String> {
+        match self.files().into_iter().find(|f| is_private_file(app_data_dir, f)) {
+            Some(filename) => Err(format!("{} is private; only the user can change it", filename)),
+            None => Ok(()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,4 +943,234 @@ mod tests {
         let deleted = delete_profile_file(&app_data_dir, "notes.md").expect("file should delete");
         assert_eq!(deleted, "Successfully deleted notes.md");
     }
+
+    #[test]
+    fn integration_section_edits_touch_only_their_section() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let path = get_profile_dir(&app_data_dir).join("career.md");
+        write_profile_file(
+            &app_data_dir,
+            "career.md",
+            "# Career\n\n## Current Role\n- Backend engineer\n\n### Team\n- 6 people\n\n## Goals\n- Staff by 2027\n```\n## not a heading\n```\n",
+        )
+        .unwrap();
+
+        append_to_profile_section(&app_data_dir, "career.md", Some("current role"), "- Remote since 2022", None).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(
+            content.contains("## Current Role\n- Backend engineer\n- Remote since 2022\n\n### Team\n- 6 people\n\n## Goals"),
+            "{}",
+            content
+        );
+
+        replace_profile_section(&app_data_dir, "career.md", "Current Role", "- Staff engineer", None).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("## Current Role\n- Staff engineer\n\n### Team\n- 6 people\n\n## Goals"), "{}", content);
+
+        replace_profile_section(&app_data_dir, "career.md", "## Goals", "- Lead a platform team", None).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.ends_with("## Goals\n- Lead a platform team\n"), "{}", content);
+        assert!(!content.contains("not a heading"));
+
+        replace_profile_section(&app_data_dir, "career.md", "Team", "- 8 people", None).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("### Team\n- 8 people\n\n## Goals"), "{}", content);
+
+        replace_profile_section(&app_data_dir, "career.md", "Current Role", "- Staff engineer\n\n### Team\n- 9 people", None).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("## Current Role\n- Staff engineer\n\n### Team\n- 9 people\n\n## Goals"), "{}", content);
+        assert_eq!(content.matches("### Team").count(), 1);
+
+        append_to_profile_section(&app_data_dir, "career.md", Some("Compensation"), "- $180k base", None).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().ends_with("\n\n## Compensation\n- $180k base\n"));
+        let err = replace_profile_section(&app_data_dir, "career.md", "Hobbies", "x", None).unwrap_err();
+        assert!(err.contains("Headings: # Career, ## Current Role"), "{}", err);
+
+        append_to_profile_section(&app_data_dir, "health.md", None, "- Runs twice a week", None).unwrap();
+        assert_eq!(read_all_profiles(&app_data_dir).unwrap()["health.md"], "- Runs twice a week\n");
+    }
+
+    #[test]
+    fn integration_rename_and_merge_profile_files() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        write_profile_file(&app_data_dir, "money.md", "# Money\n- Saves 20%").unwrap();
+        write_profile_file(&app_data_dir, "budget.md", "# Budget\n- Rent $2,100").unwrap();
+
+        rename_profile_file(&app_data_dir, "money.md", "finances.md").unwrap();
+        assert!(rename_profile_file(&app_data_dir, "finances.md", "budget.md").unwrap_err().contains("already exists"));
+        assert!(rename_profile_file(&app_data_dir, "missing.md", "other.md").is_err());
+
+        merge_profile_files(&app_data_dir, "budget.md", "finances.md").unwrap();
+        let profiles = read_all_profiles(&app_data_dir).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles["finances.md"], "# Money\n- Saves 20%\n\n## Budget\n- Rent $2,100\n");
+        assert!(merge_profile_files(&app_data_dir, "finances.md", "finances.md").is_err());
+    }
+
+    #[test]
+    fn unit_fact_metadata_survives_rewrites_and_keeps_unknown_keys() {
+        let first = with_fact_metadata(None, "# Money\n- Salary $150k\n- Rents in Austin\n", Some("conv-1"), true, "2025-01-10");
+        let (frontmatter, body) = parse_frontmatter(&first).unwrap();
+        assert_eq!(body, "# Money\n- Salary $150k\n- Rents in Austin\n");
+        assert_eq!(frontmatter.facts.len(), 2);
+        assert_eq!(frontmatter.facts[0].source_conversation.as_deref(), Some("conv-1"));
+        assert_eq!(frontmatter.facts[0].confidence, "medium");
+
+        // The model rewrites the body without frontmatter; surviving facts keep their dates.
+        let second = with_fact_metadata(Some(&first), "# Money\n- salary  $150K\n- Owns a flat\n", None, true, "2026-02-01");
+        let (frontmatter, _) = parse_frontmatter(&second).unwrap();
+        let dated: Vec<(&str, &str, Option<&str>)> = frontmatter
+            .facts
+            .iter()
+            .map(|f| (f.text.as_str(), f.learned_at.as_str(), f.source_conversation.as_deref()))
+            .collect();
+        assert_eq!(dated, vec![("salary  $150K", "2025-01-10", Some("conv-1")), ("Owns a flat", "2026-02-01", None)]);
+
+        let hand_written = "---\ntags: [money]\n---\n# Money\n```\n- not a fact\n```\n";
+        let kept = with_fact_metadata(None, hand_written, None, true, "2026-02-01");
+        assert_eq!(kept, "---\ntags:\n- money\n---\n# Money\n```\n- not a fact\n```\n");
+
+        // A model-written frontmatter can't vouch for facts or drop the keys it never saw.
+        let model_written = "---\nmood: upbeat\nfacts:\n- text: Owns a flat\n  learned_at: 1999-01-01\n  confidence: high\n  last_confirmed: 1999-01-01\n---\n# Money\n- Owns a flat\n";
+        let third = with_fact_metadata(Some(&kept), model_written, Some("conv-2"), false, "2026-03-01");
+        let (frontmatter, _) = parse_frontmatter(&third).unwrap();
+        assert_eq!(frontmatter.extra.keys().collect::<Vec<_>>(), vec!["mood", "tags"]);
+        assert_eq!(frontmatter.facts[0].learned_at, "2026-03-01");
+        assert_eq!(frontmatter.facts[0].source_conversation.as_deref(), Some("conv-2"));
+        assert_eq!(strip_frontmatter(&kept), "# Money\n```\n- not a fact\n```\n");
+        assert_eq!(strip_frontmatter("---\nnot: [valid\n---\nbody"), "---\nnot: [valid\n---\nbody");
+    }
+
+    #[test]
+    fn integration_stale_facts_are_listed_until_confirmed() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let old = "---\nfacts:\n- text: Salary $150k\n  source_conversation: conv-old\n  learned_at: 2023-03-01\n  confidence: high\n  last_confirmed: 2023-03-01\n- text: Partner may relocate\n  learned_at: 2026-01-01\n  confidence: low\n  last_confirmed: 2000-01-01\n---\n# Money\n- Salary $150k\n- Partner may relocate\n";
+        fs::create_dir_all(get_profile_dir(&app_data_dir)).unwrap();
+        fs::write(get_profile_dir(&app_data_dir).join("money.md"), old).unwrap();
+        append_to_profile_section(&app_data_dir, "money.md", None, "- Saves 20%", Some("conv-new")).unwrap();
+
+        let facts = list_profile_facts(&app_data_dir).unwrap();
+        assert_eq!(facts.len(), 3);
+        assert_eq!(facts[2].source_conversation.as_deref(), Some("conv-new"));
+        assert_eq!(facts[2].age_days, 0);
+        let stale = stale_profile_facts(&app_data_dir, STALE_FACT_DAYS).unwrap();
+        let texts: Vec<&str> = stale.iter().map(|f| f.text.as_str()).collect();
+        assert_eq!(texts, vec!["Partner may relocate", "Salary $150k"]);
+        assert_eq!(stale[1].source_conversation.as_deref(), Some("conv-old"));
+
+        assert!(confirm_profile_fact(&app_data_dir, "money.md", "a", None).unwrap_err().contains("several facts"));
+        assert!(confirm_profile_fact(&app_data_dir, "money.md", "salary", Some("sure")).unwrap_err().contains("high, medium or low"));
+        confirm_profile_fact(&app_data_dir, "money.md", "salary", None).unwrap();
+        confirm_profile_fact(&app_data_dir, "money.md", "partner may relocate", Some("high")).unwrap();
+        assert!(stale_profile_facts(&app_data_dir, STALE_FACT_DAYS).unwrap().is_empty());
+        let facts = list_profile_facts(&app_data_dir).unwrap();
+        assert_eq!((facts[0].learned_at.as_str(), facts[0].confidence.as_str()), ("2023-03-01", "high"));
+        assert_eq!(facts[1].confidence, "high");
+        assert_eq!(read_all_profiles(&app_data_dir).unwrap()["money.md"], "# Money\n- Salary $150k\n- Partner may relocate\n\n- Saves 20%\n");
+
+        // Merging carries the merged file's facts over with their metadata.
+        write_profile_file(&app_data_dir, "career.md", "# Career\n- Engineer\n").unwrap();
+        merge_profile_files(&app_data_dir, "money.md", "career.md").unwrap();
+        let merged = list_profile_facts(&app_data_dir).unwrap();
+        assert_eq!(merged.len(), 4);
+        assert!(merged.iter().any(|f| f.text == "Salary $150k" && f.source_conversation.as_deref() == Some("conv-old")));
+    }
+
+    #[test]
+    fn unit_shareable_content_drops_private_blocks_and_files() {
+        let content = "# Health\n- Runs twice a week\n\n```private\n- Diagnosis: asthma\n```\n\n## Diet\n```\n- not a fact\n```\n- Vegetarian\n";
+        assert_eq!(
+            shareable_content(content).unwrap(),
+            "# Health\n- Runs twice a week\n\n## Diet\n```\n- not a fact\n```\n- Vegetarian\n"
+        );
+        assert_eq!(shareable_content("- Public\n``` Private\n- Unclosed\n- Secret\n").unwrap(), "- Public\n");
+        assert_eq!(shareable_content("---\nprivate: true\n---\n# Medical\n"), None);
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(private_blocks(&lines), vec![3..6]);
+        assert_eq!(fact_lines(content), vec!["Runs twice a week".to_string(), "Vegetarian".to_string()]);
+
+        // A ```python line inside a private block doesn't open a fence of its own.
+        let nested = "# Health\n```private\n```python\n## not a heading\n- not a fact\n```\n## Diet\n- Vegetarian\n";
+        let lines: Vec<&str> = nested.lines().collect();
+        assert_eq!(private_blocks(&lines), vec![1..6]);
+        assert_eq!(heading_levels(&lines).iter().filter(|l| l.is_some()).count(), 2);
+        assert_eq!(fact_lines(nested), vec!["Vegetarian".to_string()]);
+        assert_eq!(shareable_content(nested).unwrap(), "# Health\n## Diet\n- Vegetarian\n");
+    }
+
+    #[test]
+    fn integration_assistant_edits_keep_private_content_local() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let path = get_profile_dir(&app_data_dir).join("money.md");
+        write_profile_file(&app_data_dir, "money.md", "# Money\n- Saves 20%\n\n```private\nAccount 1234-5678\n```\n").unwrap();
+        write_profile_file(&app_data_dir, "medical.md", "---\nprivate: true\n---\n# Medical\n- Asthma\n").unwrap();
+
+        let shared = read_all_profiles(&app_data_dir).unwrap();
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared["money.md"], "# Money\n- Saves 20%\n");
+        let local = read_all_profiles_detailed(&app_data_dir).unwrap();
+        assert!(local.iter().any(|f| f.content.contains("Account 1234-5678")));
+        assert!(local.iter().any(|f| f.content.contains("- Asthma")));
+
+        // The assistant rewrites what it was shown; the private block stays in the file.
+        let rewrite = ProfileEdit::Write { filename: "money.md".to_string(), content: "# Money\n- Saves 25%\n".to_string() };
+        rewrite.apply(&app_data_dir, None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().split("---\n").last().unwrap(), "# Money\n- Saves 25%\n\n```private\nAccount 1234-5678\n```\n");
+        replace_profile_section(&app_data_dir, "money.md", "Money", "- Saves 30%", None).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("- Saves 30%\n\n```private\nAccount 1234-5678\n```"));
+
+        // A dropped block goes back where it was, or to the end of its section's text.
+        let before = "# Money\n- Saves 20%\n```private\nAccount 1234-5678\n```\n- Rent $2,100\n\n## Goals\n- Retire at 55\n";
+        let rewritten = keep_private_blocks(Some(before), "# Money\n- Saves 20%\n- Rent $2,400\n\n## Goals\n- Retire at 55\n".to_string());
+        assert_eq!(rewritten, "# Money\n- Saves 20%\n```private\nAccount 1234-5678\n```\n- Rent $2,400\n\n## Goals\n- Retire at 55\n");
+        let replaced = replaced_content(before, "money.md", "Money", "- Saves 30%").unwrap();
+        assert_eq!(
+            keep_private_blocks(Some(before), replaced),
+            "# Money\n- Saves 30%\n\n```private\nAccount 1234-5678\n```\n\n## Goals\n- Retire at 55\n"
+        );
+
+        let err = append_to_profile_section(&app_data_dir, "medical.md", None, "- Allergic to nuts", None).unwrap_err();
+        assert_eq!(err, "medical.md is private; only the user can change it");
+        assert!(ProfileEdit::Delete { filename: "medical.md".to_string() }.apply(&app_data_dir, None).is_err());
+        assert!(merge_profile_files(&app_data_dir, "money.md", "medical.md").is_err());
+        assert!(list_profile_facts(&app_data_dir).unwrap().iter().any(|f| f.private && f.text == "Asthma"));
+
+        // The user still edits private files directly.
+        write_profile_file(&app_data_dir, "medical.md", "---\nprivate: true\n---\n# Medical\n- Asthma, mild\n").unwrap();
+        assert!(fs::read_to_string(get_profile_dir(&app_data_dir).join("medical.md")).unwrap().contains("private: true"));
+    }
+
+    #[test]
+    fn integration_every_profile_operation_rejects_unsafe_filenames() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        fs::write(app_data_dir.join("config.json"), "{\"api_key\":\"sk-test\"}").unwrap();
+        write_profile_file(&app_data_dir, "career.md", "# Career\n## Role\n- Engineer\n").unwrap();
+
+        let escape = "../config.json";
+        let attempts = [
+            write_profile_file(&app_data_dir, escape, "{}"),
+            delete_profile_file(&app_data_dir, escape),
+            append_to_profile_section(&app_data_dir, escape, None, "x", None),
+            replace_profile_section(&app_data_dir, escape, "Role", "x", None),
+            rename_profile_file(&app_data_dir, "career.md", escape),
+            rename_profile_file(&app_data_dir, escape, "stolen.md"),
+            merge_profile_files(&app_data_dir, escape, "career.md"),
+            merge_profile_files(&app_data_dir, "career.md", escape),
+        ];
+        for attempt in attempts {
+            assert_eq!(attempt.unwrap_err(), "Filename '../config.json' must not contain '..'");
+        }
+        assert_eq!(fs::read_to_string(app_data_dir.join("config.json")).unwrap(), "{\"api_key\":\"sk-test\"}");
+        assert!(read_all_profiles(&app_data_dir).unwrap()["career.md"].contains("- Engineer"));
+
+        assert!(write_profile_file(&app_data_dir, "notes.txt", "x").unwrap_err().contains("must end in .md"));
+        assert!(write_profile_file(&app_data_dir, "/tmp/x.md", "x").unwrap_err().contains("must be relative"));
+        assert!(write_profile_file(&app_data_dir, "aux.md", "x").unwrap_err().contains("is reserved"));
+        assert_eq!(read_all_profiles(&app_data_dir).unwrap().len(), 1);
+    }
 }
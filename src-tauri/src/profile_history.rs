@@ -0,0 +1,184 @@
+//! Revision history for profile files. Each change made through `profile` is kept as a
+//! numbered JSON snapshot under `profile/.history/<filename>/`, so anything the
+//! assistant rewrites or deletes can be inspected, diffed against another revision
+//! and restored.
+
+use crate::file_history::FileHistory;
+use crate::profile;
+use crate::safe_path;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+// ── Data types ──
+
+pub use crate::file_history::Revision as ProfileRevision;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffLine {
+    /// `same`, `added` or `removed`.
+    pub kind: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileDiff {
+    pub filename: String,
+    pub from_revision: u32,
+    pub to_revision: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Line diff of one file across a proposed edit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub filename: String,
+    pub lines: Vec<DiffLine>,
+}
+
+// ── Revision storage ──
+
+fn history(app_data_dir: &PathBuf, filename: &str) -> Result<FileHistory, String> {
+    safe_path::validate_filename(filename)?;
+    Ok(FileHistory::new(profile::get_profile_dir(app_data_dir).join(".history").join(filename), filename))
+}
+
+/// Number of recorded revisions of a file; revisions are numbered from 1 without gaps.
+pub fn revision_count(app_data_dir: &PathBuf, filename: &str) -> u32 {
+    history(app_data_dir, filename).map(|history| history.count()).unwrap_or(0)
+}
+
+/// Every revision of a file, newest first. Works for deleted files too.
+pub fn list_revisions(app_data_dir: &PathBuf, filename: &str) -> Result<Vec<ProfileRevision>, String> {
+    history(app_data_dir, filename)?.list()
+}
+
+/// Call before changing a file: content on disk that doesn't match the latest revision
+/// is kept as an `external` revision so the change about to happen can't lose it.
+pub fn capture_current(app_data_dir: &PathBuf, filename: &str) -> Result<(), String> {
+    let path = safe_path::resolve(&profile::get_profile_dir(app_data_dir), filename)?;
+    match fs::read_to_string(&path) {
+        Ok(on_disk) => history(app_data_dir, filename)?.capture(&on_disk),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Record a change just made to a file (`content` is `None` when it no longer exists).
+/// A change that leaves the file as the latest revision has it adds nothing.
+pub fn record(app_data_dir: &PathBuf, filename: &str, action: &str, content: Option<&str>) -> Result<(), String> {
+    history(app_data_dir, filename)?.record(action, content)
+}
+
+// ── Diff ──
+
+/// Line diff between two revisions of a file. A deleted revision counts as empty.
+pub fn diff_revisions(app_data_dir: &PathBuf, filename: &str, from_revision: u32, to_revision: u32) -> Result<ProfileDiff, String> {
+    let history = history(app_data_dir, filename)?;
+    let old = history.read(from_revision)?.content.unwrap_or_default();
+    let new = history.read(to_revision)?.content.unwrap_or_default();
+    Ok(ProfileDiff {
+        filename: filename.to_string(),
+        from_revision,
+        to_revision,
+        lines: diff_lines(&old, &new),
+    })
+}
+
+pub fn diff_change(change: &profile::FileChange) -> FileDiff {
+    FileDiff {
+        filename: change.filename.clone(),
+        lines: diff_lines(change.before.as_deref().unwrap_or_default(), change.after.as_deref().unwrap_or_default()),
+    }
+}
+
+/// Longest-common-subsequence line diff; profile files are small enough for the table.
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let line = |kind: &str, text: &str| DiffLine { kind: kind.to_string(), text: text.to_string() };
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            lines.push(line("same", a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(line("removed", a[i]));
+            i += 1;
+        } else {
+            lines.push(line("added", b[j]));
+            j += 1;
+        }
+    }
+    lines.extend(a[i..].iter().map(|text| line("removed", text)));
+    lines.extend(b[j..].iter().map(|text| line("added", text)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn unit_diff_lines_marks_added_and_removed_lines() {
+        let diff = diff_lines("# Career\n- Engineer\n- Remote\n", "# Career\n- Manager\n- Remote\n- Berlin\n");
+        let kinds: Vec<(&str, &str)> = diff.iter().map(|l| (l.kind.as_str(), l.text.as_str())).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("same", "# Career"),
+                ("removed", "- Engineer"),
+                ("added", "- Manager"),
+                ("same", "- Remote"),
+                ("added", "- Berlin"),
+            ]
+        );
+        assert!(diff_lines("", "").is_empty());
+    }
+
+    #[test]
+    fn integration_profile_changes_are_versioned_and_restorable() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let path = profile::get_profile_dir(&app_data_dir).join("values.md");
+
+        profile::write_profile_file(&app_data_dir, "values.md", "# Values\n- Family\n").unwrap();
+        profile::write_profile_file(&app_data_dir, "values.md", "# Values\n- Family\n").unwrap();
+        profile::append_to_profile_section(&app_data_dir, "values.md", None, "- Honesty", None).unwrap();
+        assert_eq!(revision_count(&app_data_dir, "values.md"), 2);
+
+        // A hand edit is captured before the next change overwrites it.
+        fs::write(&path, "# Values\n- Family\n- Honesty\n- Edited by hand\n").unwrap();
+        profile::delete_profile_file(&app_data_dir, "values.md").unwrap();
+        let revisions = list_revisions(&app_data_dir, "values.md").unwrap();
+        let actions: Vec<&str> = revisions.iter().map(|r| r.action.as_str()).collect();
+        assert_eq!(actions, vec!["delete", "external", "write", "write"]);
+        assert_eq!(revisions[0].content, None);
+
+        let diff = diff_revisions(&app_data_dir, "values.md", 2, 3).unwrap();
+        assert_eq!(diff.lines.last(), Some(&DiffLine { kind: "added".to_string(), text: "- Edited by hand".to_string() }));
+        assert!(diff_revisions(&app_data_dir, "values.md", 1, 9).unwrap_err().contains("Revision 9"));
+
+        profile::restore_profile_revision(&app_data_dir, "values.md", 3).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().ends_with("- Edited by hand\n"));
+        assert_eq!(list_revisions(&app_data_dir, "values.md").unwrap()[0].action, "restore");
+        assert!(profile::restore_profile_revision(&app_data_dir, "values.md", 4).unwrap_err().contains("deletion"));
+
+        profile::rename_profile_file(&app_data_dir, "values.md", "beliefs.md").unwrap();
+        assert_eq!(list_revisions(&app_data_dir, "values.md").unwrap()[0].action, "rename");
+        assert_eq!(revision_count(&app_data_dir, "beliefs.md"), 1);
+        let detailed = profile::read_all_profiles_detailed(&app_data_dir).unwrap();
+        assert_eq!(detailed.len(), 1);
+        assert_eq!(detailed[0].revision_count, 1);
+        assert!(list_revisions(&app_data_dir, "../config.json").is_err());
+    }
+}
@@ -0,0 +1,497 @@
+//! Relevance-ranked retrieval over profile sections. Profile files are split at their
+//! markdown headings and ranked with BM25, with heading words weighted up. A picked
+//! section brings its subsections along, so what the assistant sees of a section is
+//! what a section edit acts on. When a local embeddings model is enabled its cosine
+//! similarity is blended in. Debate briefs and the `read_profile_files` tool get the
+//! best sections instead of every file.
+
+use crate::config::AppConfig;
+use crate::db::Decision;
+use crate::profile;
+use crate::providers::{self, LlmEndpoint};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::{Mutex, OnceLock};
+
+/// Sections returned per query.
+pub const TOP_K_SECTIONS: usize = 8;
+
+/// Profiles up to this size are returned whole; ranking only pays off past it.
+const SMALL_PROFILE_CHARS: usize = 6_000;
+
+/// Heading and filename words count this many times in a section's BM25 document.
+const HEADING_WEIGHT: usize = 3;
+
+/// Section vectors kept in memory across queries; the oldest are dropped first.
+const MAX_CACHED_EMBEDDINGS: usize = 4_096;
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+// ── Data types ──
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileSection {
+    pub filename: String,
+    /// Heading trail down to this section, e.g. `["Career", "Goals"]`; empty for
+    /// text above the file's first heading.
+    pub headings: Vec<String>,
+    /// The section's text, heading line and subsections included.
+    pub content: String,
+    pub score: f64,
+    /// Text up to the first subsection, which is what the section is ranked on.
+    #[serde(skip)]
+    own_text: String,
+    /// Lines of the file the section spans, subsections included.
+    #[serde(skip)]
+    lines: Range<usize>,
+}
+
+impl ProfileSection {
+    /// Whether `other` is one of this section's subsections, at any depth.
+    fn contains(&self, other: &ProfileSection) -> bool {
+        self.filename == other.filename && self.lines.start < other.lines.start && other.lines.start < self.lines.end
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Retrieval {
+    pub sections: Vec<ProfileSection>,
+    /// Files none of whose sections made the cut.
+    pub omitted_files: Vec<String>,
+    /// True when the profile was small enough to return in full.
+    pub complete: bool,
+}
+
+// ── Sections ──
+
+/// Split a profile file at every heading outside code fences. Each section runs to
+/// the next heading of the same or a higher level, so it holds its subsections too.
+pub fn split_sections(filename: &str, content: &str) -> Vec<ProfileSection> {
+    let lines: Vec<&str> = content.lines().collect();
+    let headings: Vec<(usize, usize)> = profile::heading_levels(&lines)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, level)| level.map(|level| (index, level)))
+        .collect();
+    let text = |range: Range<usize>| lines[range].join("\n").trim().to_string();
+    let section = |headings: Vec<String>, own_end: usize, lines: Range<usize>| ProfileSection {
+        filename: filename.to_string(),
+        headings,
+        content: text(lines.clone()),
+        score: 0.0,
+        own_text: text(lines.start..own_end),
+        lines,
+    };
+
+    let mut sections: Vec<ProfileSection> = Vec::new();
+    let first_heading = headings.first().map_or(lines.len(), |(index, _)| *index);
+    if !text(0..first_heading).is_empty() {
+        sections.push(section(Vec::new(), first_heading, 0..first_heading));
+    }
+    let mut trail: Vec<(usize, String)> = Vec::new();
+    for (n, (start, level)) in headings.iter().enumerate() {
+        trail.retain(|(l, _)| l < level);
+        trail.push((*level, profile::heading_text(lines[*start]).to_string()));
+        let own_end = headings.get(n + 1).map_or(lines.len(), |(index, _)| *index);
+        let end = headings[n + 1..].iter().find(|(_, l)| l <= level).map_or(lines.len(), |(index, _)| *index);
+        sections.push(section(trail.iter().map(|(_, h)| h.clone()).collect(), own_end, *start..end));
+    }
+    sections
+}
+
+/// Words too common in profiles and decision titles to say anything about relevance.
+const STOPWORDS: [&str; 16] = [
+    "the", "and", "for", "with", "should", "what", "which", "whether", "this", "that",
+    "from", "into", "about", "over", "not", "vs",
+];
+
+/// Lowercased words of two or more characters, stopwords dropped.
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 2 && !STOPWORDS.contains(w))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Words a section is indexed under: its text, plus its heading trail and filename
+/// repeated so a `## Finances` section outranks a passing mention of money.
+fn section_terms(section: &ProfileSection) -> Vec<String> {
+    let mut terms = tokenize(&section.own_text);
+    let label = format!("{} {}", section.filename.trim_end_matches(".md"), section.headings.join(" "));
+    let label_terms = tokenize(&label);
+    for _ in 0..HEADING_WEIGHT {
+        terms.extend(label_terms.iter().cloned());
+    }
+    terms
+}
+
+// ── Ranking ──
+
+/// Okapi BM25 score of every section against `query`.
+pub fn bm25_scores(sections: &[ProfileSection], query: &str) -> Vec<f64> {
+    let documents: Vec<Vec<String>> = sections.iter().map(section_terms).collect();
+    let count = documents.len() as f64;
+    let average_length = documents.iter().map(Vec::len).sum::<usize>() as f64 / count.max(1.0);
+    let mut query_terms = tokenize(query);
+    query_terms.sort();
+    query_terms.dedup();
+
+    let idf: Vec<f64> = query_terms
+        .iter()
+        .map(|term| {
+            let containing = documents.iter().filter(|d| d.contains(term)).count() as f64;
+            (1.0 + (count - containing + 0.5) / (containing + 0.5)).ln()
+        })
+        .collect();
+
+    documents
+        .iter()
+        .map(|document| {
+            let length = document.len() as f64;
+            query_terms
+                .iter()
+                .zip(&idf)
+                .map(|(term, idf)| {
+                    let frequency = document.iter().filter(|t| *t == term).count() as f64;
+                    idf * frequency * (BM25_K1 + 1.0)
+                        / (frequency + BM25_K1 * (1.0 - BM25_B + BM25_B * length / average_length.max(1.0)))
+                })
+                .sum()
+        })
+        .collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| *x as f64 * *y as f64).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| (*x as f64).powi(2)).sum::<f64>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 { 0.0 } else { dot / denominator }
+}
+
+/// Pick the `k` best sections. BM25 is scaled to 0–1 by the best hit; with
+/// embeddings (the query's vector, then one per section) half the score is
+/// cosine similarity. Sections scoring nothing are never picked.
+fn rank(
+    profiles: &HashMap<String, String>,
+    query: &str,
+    k: usize,
+    embeddings: Option<(&[f32], &[Vec<f32>])>,
+) -> Retrieval {
+    let mut filenames: Vec<&String> = profiles.keys().collect();
+    filenames.sort();
+    let mut sections: Vec<ProfileSection> = filenames
+        .iter()
+        .flat_map(|name| split_sections(name, &profiles[*name]))
+        .collect();
+
+    if fits_whole(profiles) {
+        let outermost = sections.iter().filter(|s| !sections.iter().any(|o| o.contains(s))).cloned().collect();
+        return Retrieval { sections: outermost, omitted_files: Vec::new(), complete: true };
+    }
+
+    let bm25 = bm25_scores(&sections, query);
+    let best = bm25.iter().cloned().fold(0.0, f64::max);
+    for (index, section) in sections.iter_mut().enumerate() {
+        let lexical = if best > 0.0 { bm25[index] / best } else { 0.0 };
+        section.score = match embeddings {
+            Some((query_vector, vectors)) => 0.5 * lexical + 0.5 * cosine(query_vector, &vectors[index]).max(0.0),
+            None => lexical,
+        };
+    }
+
+    sections.retain(|s| s.score > 0.0);
+    sections.sort_by(|a, b| b.score.total_cmp(&a.score));
+    // A section already carries its subsections; picking one of them too would repeat it.
+    let mut picked: Vec<ProfileSection> = Vec::new();
+    for section in sections {
+        if picked.len() == k {
+            break;
+        }
+        if !picked.iter().any(|p| p.contains(&section)) {
+            picked.retain(|p| !section.contains(p));
+            picked.push(section);
+        }
+    }
+    picked.sort_by(|a, b| a.filename.cmp(&b.filename).then(a.lines.start.cmp(&b.lines.start)));
+
+    let omitted_files = filenames
+        .into_iter()
+        .filter(|name| !picked.iter().any(|s| &&s.filename == name))
+        .cloned()
+        .collect();
+    Retrieval { sections: picked, omitted_files, complete: false }
+}
+
+/// Whether the profile is small enough that [`rank`] returns all of it.
+fn fits_whole(profiles: &HashMap<String, String>) -> bool {
+    profiles.values().map(String::len).sum::<usize>() <= SMALL_PROFILE_CHARS
+}
+
+/// The `k` sections most relevant to `query`. Embedding similarity is blended in
+/// only when `profile_embeddings` is enabled and served from this machine; otherwise,
+/// or when the embeddings request fails, sections are ranked by BM25 alone. Profiles
+/// small enough to be returned whole are never sent for embedding.
+pub async fn retrieve(profiles: &HashMap<String, String>, query: &str, k: usize, config: &AppConfig) -> Retrieval {
+    let enabled = config.profile_embeddings.as_ref().filter(|e| e.enabled && !e.model.trim().is_empty());
+    let Some(settings) = enabled.filter(|_| !fits_whole(profiles)) else {
+        return rank(profiles, query, k, None);
+    };
+    let provider = if settings.provider.trim().is_empty() { config.default_provider.as_str() } else { settings.provider.as_str() };
+    let endpoint = match providers::resolve_endpoint(config, provider) {
+        Ok(endpoint) if endpoint.is_local() => endpoint,
+        Ok(_) => {
+            eprintln!("Profile embeddings skipped: provider '{}' is not a server on this machine", provider);
+            return rank(profiles, query, k, None);
+        }
+        Err(e) => {
+            eprintln!("Profile embeddings unavailable: {}", e);
+            return rank(profiles, query, k, None);
+        }
+    };
+
+    let mut filenames: Vec<&String> = profiles.keys().collect();
+    filenames.sort();
+    let mut inputs = vec![query.to_string()];
+    inputs.extend(filenames.iter().flat_map(|name| split_sections(name, &profiles[*name])).map(|s| s.own_text));
+    match embed(&endpoint, &settings.model, &inputs).await {
+        Ok(vectors) => rank(profiles, query, k, Some((vectors[0].as_slice(), &vectors[1..]))),
+        Err(e) => {
+            eprintln!("Profile embeddings failed, ranking by keywords only: {}", e);
+            rank(profiles, query, k, None)
+        }
+    }
+}
+
+// ── Embeddings ──
+
+/// Vectors by [`embedding_key`], bounded to [`MAX_CACHED_EMBEDDINGS`].
+#[derive(Default)]
+struct EmbeddingCache {
+    vectors: HashMap<u64, Vec<f32>>,
+    order: VecDeque<u64>,
+}
+
+impl EmbeddingCache {
+    fn insert(&mut self, key: u64, vector: Vec<f32>) {
+        if self.vectors.insert(key, vector).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > MAX_CACHED_EMBEDDINGS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.vectors.remove(&oldest);
+            }
+        }
+    }
+}
+
+static EMBEDDINGS: OnceLock<Mutex<EmbeddingCache>> = OnceLock::new();
+
+fn embedding_cache() -> &'static Mutex<EmbeddingCache> {
+    EMBEDDINGS.get_or_init(|| Mutex::new(EmbeddingCache::default()))
+}
+
+fn embedding_key(model: &str, text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (model, text).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// One vector per input, requesting only texts not embedded with `model` recently.
+async fn embed(endpoint: &LlmEndpoint, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let keys: Vec<u64> = inputs.iter().map(|text| embedding_key(model, text)).collect();
+    let mut vectors: Vec<Option<Vec<f32>>> = {
+        let cache = embedding_cache().lock().map_err(|e| e.to_string())?;
+        keys.iter().map(|key| cache.vectors.get(key).cloned()).collect()
+    };
+    let missing: Vec<usize> = (0..inputs.len()).filter(|i| vectors[*i].is_none()).collect();
+
+    if !missing.is_empty() {
+        let texts: Vec<&String> = missing.iter().map(|i| &inputs[*i]).collect();
+        let response = reqwest::Client::new()
+            .post(endpoint.embeddings_url())
+            .headers(endpoint.headers())
+            .json(&json!({ "model": model, "input": texts }))
+            .send()
+            .await
+            .map_err(|e| format!("Embeddings request failed: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Embeddings API error ({}): {}", status, body));
+        }
+        let body: Value = response.json().await.map_err(|e| format!("Invalid embeddings response: {}", e))?;
+        let data = body["data"].as_array().ok_or("Embeddings response has no data")?;
+        if data.len() != missing.len() {
+            return Err(format!("Expected {} embeddings, got {}", missing.len(), data.len()));
+        }
+        let mut cache = embedding_cache().lock().map_err(|e| e.to_string())?;
+        for (position, item) in data.iter().enumerate() {
+            let index = item["index"].as_u64().map(|i| i as usize).unwrap_or(position);
+            let input = *missing.get(index).ok_or("Embedding index out of range")?;
+            let vector: Vec<f32> = item["embedding"]
+                .as_array()
+                .ok_or("Embedding is not an array")?
+                .iter()
+                .map(|v| v.as_f64().unwrap_or(0.0) as f32)
+                .collect();
+            cache.insert(keys[input], vector.clone());
+            vectors[input] = Some(vector);
+        }
+    }
+
+    vectors.into_iter().map(|vector| vector.ok_or_else(|| "Missing embedding".to_string())).collect()
+}
+
+// ── Queries and output ──
+
+/// What a decision is about: its title plus the options and variables from its summary.
+pub fn decision_query(decision: &Decision) -> String {
+    let mut parts = vec![decision.title.clone()];
+    if let Some(summary) = decision.summary_json.as_deref().and_then(|s| serde_json::from_str::<Value>(s).ok()) {
+        for option in summary["options"].as_array().into_iter().flatten() {
+            parts.push(option["label"].as_str().unwrap_or_default().to_string());
+            parts.push(option["description"].as_str().unwrap_or_default().to_string());
+        }
+        for variable in summary["variables"].as_array().into_iter().flatten() {
+            parts.push(variable["label"].as_str().unwrap_or_default().to_string());
+            parts.push(variable["value"].as_str().unwrap_or_default().to_string());
+        }
+    }
+    parts.retain(|p| !p.trim().is_empty());
+    parts.join(" ")
+}
+
+/// Markdown for a brief: picked sections grouped under their filename.
+pub fn format_sections(retrieval: &Retrieval) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut current_file = "";
+    for section in &retrieval.sections {
+        if section.filename != current_file {
+            current_file = &section.filename;
+            out.push(format!("### {}", current_file));
+        }
+        out.push(section.content.clone());
+    }
+    if !retrieval.omitted_files.is_empty() {
+        out.push(format!("(Not relevant here: {})", retrieval.omitted_files.join(", ")));
+    }
+    out.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn large_profile() -> HashMap<String, String> {
+        let filler = "- Enjoys long walks and reading novels on weekends.\n".repeat(150);
+        HashMap::from([
+            (
+                "career.md".to_string(),
+                "# Career\n\n## Current Role\n- Staff engineer at a fintech startup\n\n## Compensation\n- $210k base, equity vesting through 2027\n".to_string(),
+            ),
+            ("finances.md".to_string(), "# Finances\n\n## Savings\n- Emergency fund covers 8 months\n\n## Housing\n- Rents in Austin for $2,400\n".to_string()),
+            ("hobbies.md".to_string(), format!("# Hobbies\n\n## Weekends\n{}", filler)),
+            ("family.md".to_string(), "# Family\n- Partner works remotely\n```\n# not a heading\n```\n".to_string()),
+        ])
+    }
+
+    #[test]
+    fn unit_split_sections_follows_headings_outside_code_fences() {
+        let sections = split_sections("family.md", "Intro line\n# Family\n- Partner\n## Kids\n- One\n```\n# not a heading\n```\n# Pets\n- Dog");
+        let trails: Vec<Vec<String>> = sections.iter().map(|s| s.headings.clone()).collect();
+        assert_eq!(
+            trails,
+            vec![
+                vec![],
+                vec!["Family".to_string()],
+                vec!["Family".to_string(), "Kids".to_string()],
+                vec!["Pets".to_string()],
+            ]
+        );
+        assert!(sections[2].content.contains("# not a heading"));
+        assert!(sections[1].content.contains("## Kids\n- One"));
+        assert!(!sections[1].content.contains("Pets"));
+    }
+
+    #[test]
+    fn integration_a_retrieved_section_can_be_replaced_without_losing_its_subsections() {
+        let dir = tempfile::tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let mut profiles = large_profile();
+        profiles.insert(
+            "career.md".to_string(),
+            "# Career\n\n## Current Role\n- Staff engineer at a fintech startup\n\n### Team\n- Leads 6 people\n\n## Goals\n- Principal by 2028\n".to_string(),
+        );
+        profile::write_profile_file(&app_data_dir, "career.md", &profiles["career.md"]).unwrap();
+
+        let retrieval = rank(&profiles, "staff engineer role at the startup", 1, None);
+        let section = &retrieval.sections[0];
+        assert_eq!(section.headings, vec!["Career".to_string(), "Current Role".to_string()]);
+        assert!(section.content.contains("### Team\n- Leads 6 people"));
+
+        // The assistant sends back the section it saw, minus the heading line, with one fact changed.
+        let (_, body) = section.content.split_once('\n').unwrap();
+        let content = body.replace("Staff engineer", "Principal engineer");
+        profile::replace_profile_section(&app_data_dir, "career.md", "Current Role", &content, None).unwrap();
+        let saved = profile::strip_frontmatter(&profile::read_all_profiles(&app_data_dir).unwrap()["career.md"]).to_string();
+        assert_eq!(
+            saved,
+            "# Career\n\n## Current Role\n- Principal engineer at a fintech startup\n\n### Team\n- Leads 6 people\n\n## Goals\n- Principal by 2028\n"
+        );
+    }
+
+    #[test]
+    fn unit_bm25_ranks_matching_sections_and_favours_headings() {
+        let retrieval = rank(&large_profile(), "Should I take the job offer with higher compensation and equity?", 3, None);
+        assert!(!retrieval.complete);
+        assert_eq!(retrieval.sections[0].headings, vec!["Career".to_string(), "Compensation".to_string()]);
+        assert!(retrieval.sections.iter().all(|s| s.filename == "career.md"));
+        assert!(retrieval.omitted_files.contains(&"hobbies.md".to_string()));
+
+        let housing = rank(&large_profile(), "rent or buy a house in Austin", 2, None);
+        assert_eq!(housing.sections.len(), 1);
+        assert!(housing.sections[0].content.contains("$2,400"));
+        let text = format_sections(&housing);
+        assert!(text.starts_with("### finances.md\n\n## Housing"));
+        assert!(text.ends_with("(Not relevant here: career.md, family.md, hobbies.md)"));
+    }
+
+    #[test]
+    fn unit_small_profiles_are_returned_whole_and_embeddings_blend_in() {
+        let small = HashMap::from([("values.md".to_string(), "# Values\n- Family first".to_string())]);
+        let retrieval = rank(&small, "career move", 1, None);
+        assert!(retrieval.complete);
+        assert_eq!(retrieval.sections.len(), 1);
+
+        // No word overlap with "Savings", but the vectors say it's the closest match.
+        let profiles = large_profile();
+        let sections: Vec<ProfileSection> = {
+            let mut names: Vec<&String> = profiles.keys().collect();
+            names.sort();
+            names.into_iter().flat_map(|n| split_sections(n, &profiles[n])).collect()
+        };
+        let vectors: Vec<Vec<f32>> = sections
+            .iter()
+            .map(|s| if s.headings.last().map(String::as_str) == Some("Savings") { vec![1.0, 0.0] } else { vec![0.0, 1.0] })
+            .collect();
+        let blended = rank(&profiles, "rainy day cushion", 1, Some((&[1.0, 0.0][..], &vectors[..])));
+        assert!(blended.sections[0].content.contains("Emergency fund"));
+    }
+
+    #[test]
+    fn unit_embedding_cache_drops_its_oldest_vectors() {
+        let mut cache = EmbeddingCache::default();
+        for key in 0..MAX_CACHED_EMBEDDINGS as u64 + 2 {
+            cache.insert(key, vec![key as f32]);
+        }
+        assert_eq!(cache.vectors.len(), MAX_CACHED_EMBEDDINGS);
+        assert!(!cache.vectors.contains_key(&0) && !cache.vectors.contains_key(&1));
+        assert!(cache.vectors.contains_key(&(MAX_CACHED_EMBEDDINGS as u64 + 1)));
+    }
+}
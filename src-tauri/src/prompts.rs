@@ -0,0 +1,288 @@
+//! System prompts for the chat assistant — the profile interviewer and the decision
+//! analyst. The built-in text is used until a prompt is overridden; only then is it
+//! written to `prompts/<key>.md`, so unedited prompts follow app updates. Every change
+//! made through the app is kept as a numbered revision under `prompts/.history/<key>/`.
+
+use crate::file_history::FileHistory;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ── Data types ──
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemPromptInfo {
+    pub key: String,
+    pub label: String,
+    pub filename: String,
+    pub content: String,
+    /// True while the prompt isn't overridden, or its override matches the built-in text.
+    pub is_default: bool,
+    /// Latest revision saved through the app; 0 until the prompt is first changed.
+    pub version: u32,
+    pub modified_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemPromptVersion {
+    pub version: u32,
+    pub content: String,
+    pub saved_at: String,
+}
+
+// ── Built-in prompts ──
+
+/// Key, label and default text of every overridable system prompt.
+const SYSTEM_PROMPTS: [(&str, &str, &str); 2] = [
+    ("interviewer", "Profile interviewer", INTERVIEWER_PROMPT),
+    ("decision", "Decision analyst", DECISION_SYSTEM_PROMPT),
+];
+
+/// Key of the prompt used for a conversation type.
+pub fn prompt_key_for(conv_type: &str) -> &'static str {
+    if conv_type == "decision" { "decision" } else { "interviewer" }
+}
+
+pub fn default_system_prompt(key: &str) -> Option<&'static str> {
+    SYSTEM_PROMPTS.iter().find(|(k, _, _)| *k == key).map(|(_, _, text)| *text)
+}
+
+fn known_prompt(key: &str) -> Result<(&'static str, &'static str), String> {
+    SYSTEM_PROMPTS
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, label, text)| (*label, *text))
+        .ok_or_else(|| format!("Unknown system prompt '{}'", key))
+}
+
+// ── Prompt file I/O ──
+
+pub fn get_prompts_dir(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("prompts")
+}
+
+fn prompt_path(app_data_dir: &Path, key: &str) -> PathBuf {
+    get_prompts_dir(app_data_dir).join(format!("{}.md", key))
+}
+
+fn history(app_data_dir: &Path, key: &str) -> FileHistory {
+    FileHistory::new(get_prompts_dir(app_data_dir).join(".history").join(key), &format!("{}.md", key))
+}
+
+/// The prompt to send, falling back to the built-in text when the file is missing or blank.
+pub fn read_system_prompt(app_data_dir: &Path, key: &str) -> String {
+    fs::read_to_string(prompt_path(app_data_dir, key))
+        .ok()
+        .filter(|content| !content.trim().is_empty())
+        .or_else(|| default_system_prompt(key).map(str::to_string))
+        .unwrap_or_default()
+}
+
+pub fn list_system_prompts(app_data_dir: &Path) -> Result<Vec<SystemPromptInfo>, String> {
+    SYSTEM_PROMPTS.iter().map(|(key, _, _)| prompt_info(app_data_dir, key)).collect()
+}
+
+fn prompt_info(app_data_dir: &Path, key: &str) -> Result<SystemPromptInfo, String> {
+    let (label, default_content) = known_prompt(key)?;
+    let path = prompt_path(app_data_dir, key);
+    let content = read_system_prompt(app_data_dir, key);
+    let modified_at = fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .map(|modified| chrono::DateTime::<chrono::Utc>::from(modified).format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_default();
+    Ok(SystemPromptInfo {
+        key: key.to_string(),
+        label: label.to_string(),
+        filename: format!("{}.md", key),
+        is_default: content == default_content,
+        version: history(app_data_dir, key).count(),
+        content,
+        modified_at,
+    })
+}
+
+/// Override a prompt. Saving unchanged text is a no-op.
+pub fn write_system_prompt(app_data_dir: &Path, key: &str, content: &str) -> Result<SystemPromptInfo, String> {
+    save_system_prompt(app_data_dir, key, content, "write")
+}
+
+/// Save `content` as the prompt and record it as a revision. Text matching the
+/// built-in prompt removes the override file instead of freezing the default.
+fn save_system_prompt(app_data_dir: &Path, key: &str, content: &str, action: &str) -> Result<SystemPromptInfo, String> {
+    let (_, default_content) = known_prompt(key)?;
+    if content.trim().is_empty() {
+        return Err("System prompt cannot be empty".to_string());
+    }
+    if read_system_prompt(app_data_dir, key) == content {
+        return prompt_info(app_data_dir, key);
+    }
+
+    let history = history(app_data_dir, key);
+    let path = prompt_path(app_data_dir, key);
+    if let Ok(on_disk) = fs::read_to_string(&path) {
+        history.capture(&on_disk)?;
+    }
+    if content == default_content {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    } else {
+        fs::create_dir_all(get_prompts_dir(app_data_dir)).map_err(|e| e.to_string())?;
+        fs::write(&path, content).map_err(|e| e.to_string())?;
+    }
+    history.record(action, Some(content))?;
+    prompt_info(app_data_dir, key)
+}
+
+/// Go back to the built-in text; the edited text stays in the history.
+pub fn reset_system_prompt(app_data_dir: &Path, key: &str) -> Result<SystemPromptInfo, String> {
+    let (_, default_content) = known_prompt(key)?;
+    save_system_prompt(app_data_dir, key, default_content, "reset")
+}
+
+/// Previously saved texts of a prompt, newest first.
+pub fn list_system_prompt_versions(app_data_dir: &Path, key: &str) -> Result<Vec<SystemPromptVersion>, String> {
+    known_prompt(key)?;
+    Ok(history(app_data_dir, key)
+        .list()?
+        .into_iter()
+        .map(|revision| SystemPromptVersion {
+            version: revision.revision,
+            content: revision.content.unwrap_or_default(),
+            saved_at: revision.saved_at,
+        })
+        .collect())
+}
+
+/// Make a saved version current again, as a new revision.
+pub fn restore_system_prompt_version(app_data_dir: &Path, key: &str, version: u32) -> Result<SystemPromptInfo, String> {
+    known_prompt(key)?;
+    let content = history(app_data_dir, key)
+        .read(version)
+        .map_err(|_| format!("Version {} of '{}' not found", version, key))?
+        .content
+        .unwrap_or_default();
+    save_system_prompt(app_data_dir, key, &content, "restore")
+}
+
+// ── Prompt constants ──
+
+pub const INTERVIEWER_PROMPT: &str = r#"You are a personal decision-making assistant. Your primary job right now is to deeply understand the user — who they are, what they value, what their life situation looks like, and what matters most to them.
+
+You have access to a set of profile files stored as markdown on the user's machine. These files contain what you've learned about the user so far. Before every response, you should read the relevant profile files to remind yourself what you know.
+
+As you learn new things about the user through conversation, you should update or create profile files to remember this information. Be organized — create separate files for different aspects of the user's life (career, finances, family, values, goals, health, etc.). Don't ask permission to save — just save what you learn naturally.
+
+When saving profile information:
+- Write in a clear, structured markdown format
+- Use headers and bullet points for organization
+- Include context and nuance, not just bare facts
+- Update existing files rather than duplicating information
+- For small changes, append to or replace a single section rather than rewriting the whole file
+- Create new files when you discover a new significant aspect of the user's life
+
+Be conversational and warm. Ask thoughtful follow-up questions. Don't interrogate — let understanding develop naturally through genuine conversation. You're building a relationship, not filling out a form.
+
+When you have enough context about the user and they bring you a decision to make, you should:
+1. Read all relevant profile files
+2. Consider all variables and how they interact
+3. Weigh tradeoffs against the user's stated values and priorities
+4. Give a clear, committed recommendation with transparent reasoning
+5. Explain what they'd be giving up with your recommended choice
+
+But for now, focus on learning about the user. The better you understand them, the better your future recommendations will be."#;
+
+pub const DECISION_SYSTEM_PROMPT: &str = r#"You are a personal decision-making assistant. The user is working through a specific decision and needs your help analyzing it thoroughly.
+
+You have access to the user's profile files — markdown files that contain everything you've learned about them: their values, priorities, life situation, constraints, finances, career, family, and goals. READ THESE FIRST before engaging with the decision.
+
+Your job is to:
+
+1. GATHER INFORMATION (status: "exploring")
+   - What are they deciding between? (Surface all options, including ones they haven't considered)
+   - What's the timeline? Is this reversible?
+   - What triggered this decision now?
+   - What factors are at play? (financial, career, emotional, relational, health, etc.)
+   - Ask focused questions, one or two at a time. Don't overwhelm.
+   - Push back if the user is framing the decision too narrowly ("should I quit?" is rarely binary)
+
+2. UPDATE THE DECISION SUMMARY
+   CRITICAL: Call `update_decision_summary` on EVERY response. This populates the structured panel the user sees alongside the chat.
+   - On your VERY FIRST response: identify the options being considered and key variables, then call the tool with at least `options` and `variables` populated, plus set `status` to "exploring".
+   - On subsequent responses: update the summary progressively — add new options, refine variables, add pros/cons.
+   - Even for seemingly simple or casual decisions (what to watch, where to eat, what to buy), ALWAYS structure them with options and variables. Every decision has factors worth analyzing.
+
+3. HAND OFF TO COMMITTEE (status: "analyzing")
+   Once you have gathered enough information to frame the decision clearly — meaning you have identified the realistic options AND the key variables/factors — set `status` to "analyzing" in your next `update_decision_summary` call. This AUTOMATICALLY triggers the committee of AI advisors to debate the decision from multiple perspectives. You do NOT make the recommendation yourself. The committee does that.
+   - Do NOT wait for perfection. 2-3 exchanges is usually enough. If the user has given you the core decision and enough context to identify options and factors, hand it off.
+   - Do NOT try to analyze or recommend yourself. Your role is to gather info and frame the decision, then let the committee handle analysis and recommendation.
+   - The committee debate starts automatically when you set status to "analyzing". Do not tell the user to click anything.
+
+Guidelines:
+- Ask focused questions, one or two at a time. Don't overwhelm.
+- Name cognitive biases if you spot them (sunk cost, anchoring, status quo bias, etc.)
+- Be honest even if it's not what they want to hear
+- If you don't have enough information from the profile files, ask for it
+- If new information emerges that should be saved to the profile, update the profile files too
+- Use `search_past_decisions` and `get_decision_detail` to find similar decisions the user made before and how they turned out; cite them when they are relevant
+- Early in a decision, call `list_stale_facts`. If the decision leans on an old fact (salary, savings, family situation, location), ask the user whether it still holds, then call `confirm_profile_fact` or update the profile
+
+6. REFLECT ON OUTCOMES
+   When you see a message starting with "[DECISION OUTCOME LOGGED]", the user has reported how their decision turned out. This is a critical learning moment:
+
+   a) READ PROFILE FILES first to understand the full context of who this person is
+   b) COMPARE: your recommendation vs. what the user chose vs. what actually happened
+   c) ANALYZE: factors you over/underweighted, biases at play, what the user's intuition captured that your analysis missed (or vice versa), unpredictable external factors vs foreseeable outcomes
+   d) UPDATE PROFILE FILES with lessons learned — create or update a "decision-patterns.md" file tracking what works for this user and what doesn't, and update other relevant profiles if the outcome reveals new info about their values, risk tolerance, or priorities. Be specific — e.g. "user's read on organizational culture tends to be more reliable than quantitative analysis" rather than "user trusts gut feelings"
+   e) SHARE your reflection transparently in the chat. Be honest about what you got right, what you got wrong, and how this will change your future recommendations for this user"#;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn integration_system_prompt_edits_are_versioned_and_resettable() {
+        let dir = tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let path = app_data_dir.join("prompts/interviewer.md");
+
+        // Nothing on disk: the built-in text is used, and listing writes nothing.
+        assert_eq!(read_system_prompt(&app_data_dir, "decision"), DECISION_SYSTEM_PROMPT);
+        let listed = list_system_prompts(&app_data_dir).expect("prompts should list");
+        assert_eq!(listed.iter().map(|p| p.key.as_str()).collect::<Vec<_>>(), vec!["interviewer", "decision"]);
+        assert!(listed.iter().all(|p| p.is_default && p.version == 0));
+        assert!(!path.exists());
+
+        let edited = write_system_prompt(&app_data_dir, "interviewer", "Ask one question at a time.").unwrap();
+        assert!(!edited.is_default);
+        assert_eq!(edited.version, 1);
+        assert_eq!(read_system_prompt(&app_data_dir, "interviewer"), "Ask one question at a time.");
+        assert_eq!(write_system_prompt(&app_data_dir, "interviewer", "Ask one question at a time.").unwrap().version, 1);
+
+        // Resetting drops the override, so the prompt follows the built-in text again.
+        let reset = reset_system_prompt(&app_data_dir, "interviewer").unwrap();
+        assert!(reset.is_default);
+        assert_eq!(reset.version, 2);
+        assert!(!path.exists());
+        let versions = list_system_prompt_versions(&app_data_dir, "interviewer").unwrap();
+        assert_eq!(versions.iter().map(|v| v.version).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(versions[1].content, "Ask one question at a time.");
+
+        let restored = restore_system_prompt_version(&app_data_dir, "interviewer", 1).unwrap();
+        assert_eq!(restored.content, "Ask one question at a time.");
+        assert_eq!(restored.version, 3);
+
+        // A hand edit is kept as a version before the next save replaces it.
+        fs::write(&path, "Edited by hand.").unwrap();
+        assert_eq!(read_system_prompt(&app_data_dir, "interviewer"), "Edited by hand.");
+        assert_eq!(write_system_prompt(&app_data_dir, "interviewer", "Be brief.").unwrap().version, 5);
+        assert_eq!(list_system_prompt_versions(&app_data_dir, "interviewer").unwrap()[1].content, "Edited by hand.");
+
+        assert!(write_system_prompt(&app_data_dir, "interviewer", "  ").is_err());
+        assert!(write_system_prompt(&app_data_dir, "moderator", "text").is_err());
+        assert!(restore_system_prompt_version(&app_data_dir, "interviewer", 42).is_err());
+        assert_eq!(prompt_key_for("chat"), "interviewer");
+    }
+}
@@ -0,0 +1,412 @@
+//! LLM provider resolution — maps the provider entries in `AppConfig` to concrete
+//! chat-completions endpoints. Every provider speaks the OpenAI-compatible protocol
+//! (OpenRouter, OpenAI, Anthropic's compatibility layer, Ollama, llama.cpp, vLLM).
+
+use crate::config::{AppConfig, FallbackModel, ProviderConfig};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+pub const OPENROUTER_PROVIDER_ID: &str = "openrouter";
+
+const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+
+// ── Data types ──
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    OpenRouter,
+    OpenAi,
+    Anthropic,
+    OpenAiCompatible,
+}
+
+impl ProviderKind {
+    pub fn parse(kind: &str) -> Result<Self, String> {
+        match kind.trim().to_lowercase().as_str() {
+            "openrouter" => Ok(Self::OpenRouter),
+            "openai" => Ok(Self::OpenAi),
+            "anthropic" => Ok(Self::Anthropic),
+            "openai_compatible" | "local" => Ok(Self::OpenAiCompatible),
+            other => Err(format!("Unknown provider kind '{}'", other)),
+        }
+    }
+
+    fn default_base_url(self) -> Option<&'static str> {
+        match self {
+            Self::OpenRouter => Some(OPENROUTER_BASE_URL),
+            Self::OpenAi => Some(OPENAI_BASE_URL),
+            Self::Anthropic => Some(ANTHROPIC_BASE_URL),
+            Self::OpenAiCompatible => None,
+        }
+    }
+
+    /// Self-hosted servers usually run without auth; hosted APIs always need a key.
+    fn requires_api_key(self) -> bool {
+        !matches!(self, Self::OpenAiCompatible)
+    }
+}
+
+/// Fully resolved endpoint for a single chat-completions call.
+#[derive(Debug, Clone)]
+pub struct LlmEndpoint {
+    pub provider_id: String,
+    pub label: String,
+    pub kind: ProviderKind,
+    pub base_url: String,
+    pub api_key: String,
+}
+
+/// Provider entry as shown in Settings (API key masked).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProviderSummary {
+    pub id: String,
+    pub label: String,
+    pub kind: String,
+    pub base_url: String,
+    pub api_key_set: bool,
+    pub builtin: bool,
+}
+
+impl LlmEndpoint {
+    pub fn chat_completions_url(&self) -> String {
+        format!("{}/chat/completions", self.base_url.trim_end_matches('/'))
+    }
+
+    pub fn embeddings_url(&self) -> String {
+        format!("{}/embeddings", self.base_url.trim_end_matches('/'))
+    }
+
+    pub fn headers(&self) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if !self.api_key.is_empty() {
+            if let Ok(value) = format!("Bearer {}", self.api_key).parse() {
+                headers.insert("Authorization", value);
+            }
+        }
+        match self.kind {
+            ProviderKind::OpenRouter => {
+                headers.insert("HTTP-Referer", "https://opencouncil.app".parse().unwrap());
+                headers.insert("X-Title", "Open Council".parse().unwrap());
+            }
+            ProviderKind::Anthropic => {
+                if let Ok(value) = self.api_key.parse() {
+                    headers.insert("x-api-key", value);
+                }
+                headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+            }
+            ProviderKind::OpenAi | ProviderKind::OpenAiCompatible => {}
+        }
+        headers.insert("Content-Type", "application/json".parse().unwrap());
+        headers
+    }
+
+    pub fn is_openrouter(&self) -> bool {
+        self.kind == ProviderKind::OpenRouter
+    }
+
+    /// Self-hosted servers cost nothing per token.
+    pub fn is_self_hosted(&self) -> bool {
+        self.kind == ProviderKind::OpenAiCompatible
+    }
+
+    /// A self-hosted server on this machine, reached over loopback.
+    pub fn is_local(&self) -> bool {
+        let host = reqwest::Url::parse(&self.base_url).ok().and_then(|url| url.host_str().map(str::to_string));
+        self.is_self_hosted()
+            && host.is_some_and(|host| {
+                host == "localhost"
+                    || host.trim_matches(|c| c == '[' || c == ']').parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+            })
+    }
+}
+
+// ── Resolution ──
+
+fn builtin_openrouter(config: &AppConfig) -> ProviderConfig {
+    ProviderConfig {
+        id: OPENROUTER_PROVIDER_ID.to_string(),
+        label: "OpenRouter".to_string(),
+        kind: "openrouter".to_string(),
+        base_url: String::new(),
+        api_key: config.openrouter_api_key.clone(),
+    }
+}
+
+/// All providers available to the app: the built-in OpenRouter entry followed by
+/// user-configured ones.
+pub fn all_providers(config: &AppConfig) -> Vec<ProviderConfig> {
+    let mut providers = vec![builtin_openrouter(config)];
+    providers.extend(
+        config.providers.iter()
+            .filter(|p| p.id != OPENROUTER_PROVIDER_ID)
+            .cloned(),
+    );
+    providers
+}
+
+pub fn provider_summaries(config: &AppConfig) -> Vec<ProviderSummary> {
+    all_providers(config)
+        .into_iter()
+        .map(|p| {
+            let builtin = p.id == OPENROUTER_PROVIDER_ID;
+            let base_url = if p.base_url.trim().is_empty() {
+                ProviderKind::parse(&p.kind)
+                    .ok()
+                    .and_then(|k| k.default_base_url())
+                    .unwrap_or("")
+                    .to_string()
+            } else {
+                p.base_url.clone()
+            };
+            ProviderSummary {
+                label: if p.label.is_empty() { p.id.clone() } else { p.label.clone() },
+                id: p.id,
+                kind: p.kind,
+                base_url,
+                api_key_set: !p.api_key.is_empty(),
+                builtin,
+            }
+        })
+        .collect()
+}
+
+// Set once at startup when the app runs against the local fixture server.
+static FIXTURE_BASE_URL: OnceLock<String> = OnceLock::new();
+
+/// Route every LLM call through the mock fixture server (see `mock_llm`).
+pub fn enable_fixture_mode(base_url: &str) {
+    let _ = FIXTURE_BASE_URL.set(base_url.to_string());
+}
+
+/// Resolve a provider id to an endpoint, validating base URL and credentials.
+pub fn resolve_endpoint(config: &AppConfig, provider_id: &str) -> Result<LlmEndpoint, String> {
+    if let Some(base_url) = FIXTURE_BASE_URL.get() {
+        return Ok(LlmEndpoint {
+            provider_id: "fixture".to_string(),
+            label: "Fixture server".to_string(),
+            kind: ProviderKind::OpenAiCompatible,
+            base_url: base_url.clone(),
+            api_key: String::new(),
+        });
+    }
+
+    let provider_id = if provider_id.trim().is_empty() {
+        OPENROUTER_PROVIDER_ID
+    } else {
+        provider_id.trim()
+    };
+    let provider = all_providers(config)
+        .into_iter()
+        .find(|p| p.id == provider_id)
+        .ok_or_else(|| format!("Provider '{}' is not configured. Check Settings.", provider_id))?;
+
+    let kind = ProviderKind::parse(&provider.kind)?;
+    let base_url = if provider.base_url.trim().is_empty() {
+        kind.default_base_url()
+            .ok_or_else(|| format!("Provider '{}' needs a base URL (e.g. http://localhost:11434/v1).", provider.id))?
+            .to_string()
+    } else {
+        provider.base_url.trim().to_string()
+    };
+
+    if kind.requires_api_key() && provider.api_key.is_empty() {
+        return Err(if provider.id == OPENROUTER_PROVIDER_ID {
+            "API key not set. Please go to Settings to add your OpenRouter API key.".to_string()
+        } else {
+            format!("API key not set for provider '{}'. Please add it in Settings.", provider.id)
+        });
+    }
+
+    Ok(LlmEndpoint {
+        label: if provider.label.is_empty() { provider.id.clone() } else { provider.label },
+        provider_id: provider.id,
+        kind,
+        base_url,
+        api_key: provider.api_key,
+    })
+}
+
+/// Endpoint for the app-wide default provider (chat, decision chat, prompt generation).
+pub fn default_endpoint(config: &AppConfig) -> Result<LlmEndpoint, String> {
+    resolve_endpoint(config, &config.default_provider)
+}
+
+/// Endpoint for a committee agent, honouring per-agent provider overrides.
+pub fn endpoint_for_agent(config: &AppConfig, agent_key: &str) -> Result<LlmEndpoint, String> {
+    match config.agent_providers.get(agent_key).filter(|p| !p.trim().is_empty()) {
+        Some(provider_id) => resolve_endpoint(config, provider_id),
+        None => default_endpoint(config),
+    }
+}
+
+/// Models to try for an agent, in order: its own model, then its configured
+/// fallbacks. Entries whose provider does not resolve are skipped, so a broken
+/// provider only errors out when nothing else is usable.
+pub fn agent_model_chain(
+    config: &AppConfig,
+    agent_key: &str,
+    primary_model: &str,
+) -> Result<Vec<(LlmEndpoint, String)>, String> {
+    let primary = FallbackModel { provider: String::new(), model: primary_model.to_string() };
+    let fallbacks = config.agent_fallbacks.get(agent_key).map(Vec::as_slice).unwrap_or_default();
+
+    let mut chain: Vec<(LlmEndpoint, String)> = Vec::new();
+    let mut first_error = None;
+    for entry in std::iter::once(&primary).chain(fallbacks) {
+        let model = entry.model.trim();
+        if model.is_empty() {
+            continue;
+        }
+        let endpoint = if entry.provider.trim().is_empty() {
+            endpoint_for_agent(config, agent_key)
+        } else {
+            resolve_endpoint(config, &entry.provider)
+        };
+        match endpoint {
+            Ok(endpoint) => {
+                if !chain.iter().any(|(e, m)| e.provider_id == endpoint.provider_id && m == model) {
+                    chain.push((endpoint, model.to_string()));
+                }
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match (chain.is_empty(), first_error) {
+        (true, Some(e)) => Err(e),
+        (true, None) => Err(format!("No model configured for agent '{}'", agent_key)),
+        _ => Ok(chain),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ollama() -> ProviderConfig {
+        ProviderConfig {
+            id: "ollama".to_string(),
+            label: "Ollama".to_string(),
+            kind: "openai_compatible".to_string(),
+            base_url: "http://localhost:11434/v1/".to_string(),
+            api_key: String::new(),
+        }
+    }
+
+    #[test]
+    fn unit_default_endpoint_is_openrouter_with_referer_headers() {
+        let config = AppConfig {
+            openrouter_api_key: "sk-or-test".to_string(),
+            ..AppConfig::default()
+        };
+
+        let endpoint = default_endpoint(&config).expect("openrouter should resolve");
+        assert!(endpoint.is_openrouter());
+        assert_eq!(endpoint.chat_completions_url(), "https://openrouter.ai/api/v1/chat/completions");
+
+        let headers = endpoint.headers();
+        assert_eq!(headers.get("Authorization").unwrap(), "Bearer sk-or-test");
+        assert!(headers.contains_key("HTTP-Referer"));
+    }
+
+    #[test]
+    fn unit_missing_openrouter_key_reports_settings_hint() {
+        let err = default_endpoint(&AppConfig::default()).expect_err("empty key should fail");
+        assert!(err.contains("OpenRouter API key"));
+    }
+
+    #[test]
+    fn unit_local_provider_needs_no_key_and_sends_no_auth_header() {
+        let mut config = AppConfig::default();
+        config.providers.push(ollama());
+        config.agent_providers.insert("contrarian".to_string(), "ollama".to_string());
+
+        let endpoint = endpoint_for_agent(&config, "contrarian").expect("local provider should resolve");
+        assert_eq!(endpoint.provider_id, "ollama");
+        assert_eq!(endpoint.chat_completions_url(), "http://localhost:11434/v1/chat/completions");
+        assert!(!endpoint.headers().contains_key("Authorization"));
+        assert!(endpoint.is_local());
+        config.providers.push(ProviderConfig { id: "lab".to_string(), base_url: "http://10.0.0.7:8000/v1".to_string(), ..ollama() });
+        assert!(!resolve_endpoint(&config, "lab").unwrap().is_local());
+
+        // Agents without an override fall back to the default provider.
+        assert!(endpoint_for_agent(&config, "rationalist").is_err());
+    }
+
+    #[test]
+    fn unit_resolve_endpoint_validates_kind_base_url_and_id() {
+        let mut config = AppConfig::default();
+        config.providers.push(ProviderConfig {
+            base_url: String::new(),
+            ..ollama()
+        });
+        config.providers.push(ProviderConfig {
+            id: "weird".to_string(),
+            label: String::new(),
+            kind: "carrier-pigeon".to_string(),
+            base_url: "http://x".to_string(),
+            api_key: String::new(),
+        });
+
+        assert!(resolve_endpoint(&config, "ollama").unwrap_err().contains("base URL"));
+        assert!(resolve_endpoint(&config, "weird").unwrap_err().contains("Unknown provider kind"));
+        assert!(resolve_endpoint(&config, "missing").unwrap_err().contains("not configured"));
+    }
+
+    #[test]
+    fn unit_agent_model_chain_orders_fallbacks_and_skips_unusable_providers() {
+        let mut config = AppConfig {
+            openrouter_api_key: "sk-or-test".to_string(),
+            ..AppConfig::default()
+        };
+        config.providers.push(ollama());
+        config.agent_fallbacks.insert("contrarian".to_string(), vec![
+            FallbackModel { provider: String::new(), model: "openai/gpt-4o-mini".to_string() },
+            FallbackModel { provider: "missing".to_string(), model: "x".to_string() },
+            FallbackModel { provider: "ollama".to_string(), model: "llama3.1".to_string() },
+            FallbackModel { provider: String::new(), model: "anthropic/claude-sonnet-4-5".to_string() },
+        ]);
+
+        let chain = agent_model_chain(&config, "contrarian", "anthropic/claude-sonnet-4-5").unwrap();
+        let labels: Vec<(&str, &str)> = chain.iter().map(|(e, m)| (e.provider_id.as_str(), m.as_str())).collect();
+        assert_eq!(labels, vec![
+            ("openrouter", "anthropic/claude-sonnet-4-5"),
+            ("openrouter", "openai/gpt-4o-mini"),
+            ("ollama", "llama3.1"),
+        ]);
+
+        // Without an OpenRouter key only the local fallback is usable.
+        config.openrouter_api_key.clear();
+        let chain = agent_model_chain(&config, "contrarian", "anthropic/claude-sonnet-4-5").unwrap();
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].0.provider_id, "ollama");
+        assert!(agent_model_chain(&config, "rationalist", "anthropic/claude-sonnet-4-5").is_err());
+    }
+
+    #[test]
+    fn unit_anthropic_provider_uses_default_url_and_api_key_header() {
+        let mut config = AppConfig::default();
+        config.providers.push(ProviderConfig {
+            id: "anthropic".to_string(),
+            label: "Anthropic".to_string(),
+            kind: "anthropic".to_string(),
+            base_url: String::new(),
+            api_key: "sk-ant".to_string(),
+        });
+        config.default_provider = "anthropic".to_string();
+
+        let endpoint = default_endpoint(&config).expect("anthropic should resolve");
+        assert_eq!(endpoint.chat_completions_url(), "https://api.anthropic.com/v1/chat/completions");
+        assert_eq!(endpoint.headers().get("x-api-key").unwrap(), "sk-ant");
+
+        let summaries = provider_summaries(&config);
+        assert_eq!(summaries.len(), 2);
+        assert!(summaries[0].builtin);
+        assert!(!summaries[0].api_key_set);
+        assert!(summaries[1].api_key_set);
+    }
+}
@@ -0,0 +1,79 @@
+//! Retry policy for LLM calls: which failures are worth another attempt on the
+//! same model, and how long to wait before it (exponential backoff with jitter,
+//! or the provider's `Retry-After` when it sends one).
+
+use crate::llm::LlmError;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Extra attempts on the same model after the first one fails.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    /// Longest backoff; a `Retry-After` beyond this moves on to the next fallback instead.
+    pub max_delay: Duration,
+}
+
+/// Debate turns: a couple of quick retries, then fall back to the next model.
+pub const DEBATE_RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_retries: 2,
+    base_delay: Duration::from_millis(1_000),
+    max_delay: Duration::from_secs(20),
+};
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (0-based), or `None` to give up on this model.
+    pub fn delay_for(&self, attempt: u32, error: &LlmError) -> Option<Duration> {
+        if !error.is_retriable() || attempt >= self.max_retries {
+            return None;
+        }
+        if let Some(retry_after) = error.retry_after {
+            return (retry_after <= self.max_delay).then_some(retry_after);
+        }
+        let ceiling = self.base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay);
+        Some(jitter(ceiling))
+    }
+}
+
+/// Random delay in `[ceiling / 2, ceiling]` so parallel callers don't retry in lockstep.
+fn jitter(ceiling: Duration) -> Duration {
+    let half = ceiling / 2;
+    let span = (ceiling - half).as_millis() as u64;
+    if span == 0 {
+        return ceiling;
+    }
+    let random = (uuid::Uuid::new_v4().as_u128() % span as u128) as u64;
+    half + Duration::from_millis(random)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::LlmErrorKind;
+
+    fn error(kind: LlmErrorKind, retry_after: Option<Duration>) -> LlmError {
+        LlmError { kind, status: None, retry_after, message: String::new() }
+    }
+
+    #[test]
+    fn unit_delay_for_classifies_errors_and_backs_off_with_jitter() {
+        let policy = DEBATE_RETRY_POLICY;
+
+        for attempt in 0..2 {
+            let delay = policy.delay_for(attempt, &error(LlmErrorKind::Server, None)).expect("5xx should retry");
+            let ceiling = policy.base_delay * 2u32.pow(attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {} waited {:?}", attempt, delay);
+        }
+        assert!(policy.delay_for(0, &error(LlmErrorKind::Network, None)).is_some());
+        assert_eq!(policy.delay_for(2, &error(LlmErrorKind::Server, None)), None);
+
+        assert_eq!(policy.delay_for(0, &error(LlmErrorKind::PaymentRequired, None)), None);
+        assert_eq!(policy.delay_for(0, &error(LlmErrorKind::Auth, None)), None);
+        assert_eq!(policy.delay_for(0, &error(LlmErrorKind::NotFound, None)), None);
+
+        let rate_limited = error(LlmErrorKind::RateLimited, Some(Duration::from_secs(3)));
+        assert_eq!(policy.delay_for(0, &rate_limited), Some(Duration::from_secs(3)));
+        let long_wait = error(LlmErrorKind::RateLimited, Some(Duration::from_secs(120)));
+        assert_eq!(policy.delay_for(0, &long_wait), None);
+    }
+}
@@ -0,0 +1,128 @@
+//! Filename checks for the profile and agent directories. Filenames come from the
+//! model's tool calls and from the UI, so each one must name a plain `.md` file
+//! directly inside its directory before anything touches the disk.
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    Empty,
+    /// `/etc/passwd`, `C:\...` or a UNC path.
+    Absolute(String),
+    /// A `..` component.
+    Traversal(String),
+    /// Any other `/` or `\`; the directories are flat.
+    Nested(String),
+    /// Anything but `.md`.
+    Extension(String),
+    /// Hidden files, Windows device names and names Windows would silently rewrite.
+    Reserved(String),
+}
+
+impl std::fmt::Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathError::Empty => f.write_str("Filename is empty"),
+            PathError::Absolute(name) => write!(f, "Filename '{}' must be relative", name),
+            PathError::Traversal(name) => write!(f, "Filename '{}' must not contain '..'", name),
+            PathError::Nested(name) => write!(f, "Filename '{}' must not contain a path separator", name),
+            PathError::Extension(name) => write!(f, "Filename '{}' must end in .md", name),
+            PathError::Reserved(name) => write!(f, "Filename '{}' is reserved", name),
+        }
+    }
+}
+
+impl From<PathError> for String {
+    fn from(error: PathError) -> Self {
+        error.to_string()
+    }
+}
+
+const WINDOWS_DEVICE_NAMES: [&str; 4] = ["con", "prn", "aux", "nul"];
+
+/// Check that `filename` is a plain markdown filename such as `career.md`.
+pub fn validate_filename(filename: &str) -> Result<(), PathError> {
+    let name = filename.to_string();
+    if filename.trim().is_empty() {
+        return Err(PathError::Empty);
+    }
+    let bytes = filename.as_bytes();
+    let has_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if filename.starts_with('/') || filename.starts_with('\\') || has_drive || Path::new(filename).is_absolute() {
+        return Err(PathError::Absolute(name));
+    }
+    if filename.split(['/', '\\']).any(|part| part == "..") {
+        return Err(PathError::Traversal(name));
+    }
+    if filename.contains(['/', '\\']) {
+        return Err(PathError::Nested(name));
+    }
+    let Some(stem) = filename.strip_suffix(".md") else {
+        return Err(PathError::Extension(name));
+    };
+
+    let device = stem.split('.').next().unwrap_or_default().to_lowercase();
+    let numbered_device = (device.starts_with("com") || device.starts_with("lpt"))
+        && device.len() == 4
+        && device.as_bytes()[3].is_ascii_digit();
+    if stem.is_empty()
+        || stem.starts_with('.')
+        || stem.ends_with(['.', ' '])
+        || filename.chars().any(|c| c.is_control() || "<>:\"|?*".contains(c))
+        || WINDOWS_DEVICE_NAMES.contains(&device.as_str())
+        || numbered_device
+    {
+        return Err(PathError::Reserved(name));
+    }
+    Ok(())
+}
+
+/// `dir/filename` once `filename` passes [`validate_filename`].
+pub fn resolve(dir: &Path, filename: &str) -> Result<PathBuf, PathError> {
+    validate_filename(filename)?;
+    Ok(dir.join(filename))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_validate_filename_accepts_plain_markdown_names_only() {
+        for ok in ["career.md", "values & beliefs.md", "2025-goals.md", "notes.v2.md"] {
+            assert_eq!(validate_filename(ok), Ok(()), "{}", ok);
+        }
+
+        let cases = [
+            ("", PathError::Empty),
+            ("  ", PathError::Empty),
+            ("/etc/passwd.md", PathError::Absolute("/etc/passwd.md".to_string())),
+            ("\\\\server\\share.md", PathError::Absolute("\\\\server\\share.md".to_string())),
+            ("C:\\config.md", PathError::Absolute("C:\\config.md".to_string())),
+            ("../config.json", PathError::Traversal("../config.json".to_string())),
+            ("..\\secrets.md", PathError::Traversal("..\\secrets.md".to_string())),
+            ("notes/../../x.md", PathError::Traversal("notes/../../x.md".to_string())),
+            ("nested/career.md", PathError::Nested("nested/career.md".to_string())),
+            ("config.json", PathError::Extension("config.json".to_string())),
+            ("career.MD", PathError::Extension("career.MD".to_string())),
+            ("career", PathError::Extension("career".to_string())),
+            (".md", PathError::Reserved(".md".to_string())),
+            (".history.md", PathError::Reserved(".history.md".to_string())),
+            ("CON.md", PathError::Reserved("CON.md".to_string())),
+            ("lpt1.notes.md", PathError::Reserved("lpt1.notes.md".to_string())),
+            ("what?.md", PathError::Reserved("what?.md".to_string())),
+            ("trailing .md", PathError::Reserved("trailing .md".to_string())),
+        ];
+        for (filename, expected) in cases {
+            assert_eq!(validate_filename(filename), Err(expected), "{}", filename);
+        }
+    }
+
+    #[test]
+    fn unit_resolve_joins_only_validated_names() {
+        let dir = Path::new("/data/profile");
+        assert_eq!(resolve(dir, "career.md").unwrap(), dir.join("career.md"));
+        let err: String = resolve(dir, "../config.json").unwrap_err().into();
+        assert_eq!(err, "Filename '../config.json' must not contain '..'");
+    }
+}
@@ -0,0 +1,256 @@
+//! Token usage and cost accounting. Every LLM call in `llm.rs` reports its
+//! `usage` block here; rows land in the `llm_usage` table, linked to the
+//! conversation, decision or debate turn that triggered the call.
+
+use crate::commands::AppState;
+use crate::db::NewLlmUsage;
+use crate::providers::LlmEndpoint;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::{Manager, Runtime};
+
+pub const PURPOSE_CHAT: &str = "chat";
+pub const PURPOSE_DEBATE_TURN: &str = "debate_turn";
+pub const PURPOSE_DEBATE_STEERING: &str = "debate_steering";
+pub const PURPOSE_AGENT_GENERATION: &str = "agent_generation";
+pub const PURPOSE_CONTEXT_SUMMARY: &str = "context_summary";
+
+// ── Data types ──
+
+/// Token counts parsed from a chat-completions `usage` object.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    /// Cost in USD as reported by the provider (OpenRouter usage accounting).
+    pub reported_cost: Option<f64>,
+}
+
+impl TokenUsage {
+    pub fn from_json(usage: &Value) -> Option<Self> {
+        if !usage.is_object() {
+            return None;
+        }
+        Some(Self {
+            prompt_tokens: usage["prompt_tokens"].as_i64().unwrap_or(0),
+            completion_tokens: usage["completion_tokens"].as_i64().unwrap_or(0),
+            reported_cost: usage["cost"].as_f64(),
+        })
+    }
+}
+
+/// What an LLM call was made for; decides which rows a cost report picks up.
+#[derive(Debug, Clone, Default)]
+pub struct UsageContext {
+    pub purpose: String,
+    pub conversation_id: Option<String>,
+    pub decision_id: Option<String>,
+    pub round_number: Option<i32>,
+    pub exchange_number: Option<i32>,
+    pub agent: Option<String>,
+    /// The assistant reply a chat call is for. Its id is picked before the turn starts,
+    /// so usage rows and tool calls can point at it before it is saved.
+    pub message_id: Option<String>,
+}
+
+impl UsageContext {
+    pub fn chat(conversation_id: &str, decision_id: Option<&str>) -> Self {
+        Self {
+            purpose: PURPOSE_CHAT.to_string(),
+            conversation_id: Some(conversation_id.to_string()),
+            decision_id: decision_id.map(str::to_string),
+            ..Self::default()
+        }
+    }
+
+    pub fn debate_turn(decision_id: &str, round_number: i32, exchange_number: i32, agent: &str) -> Self {
+        Self {
+            purpose: PURPOSE_DEBATE_TURN.to_string(),
+            decision_id: Some(decision_id.to_string()),
+            round_number: Some(round_number),
+            exchange_number: Some(exchange_number),
+            agent: Some(agent.to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub fn debate_steering(decision_id: &str, exchange_number: i32) -> Self {
+        Self {
+            purpose: PURPOSE_DEBATE_STEERING.to_string(),
+            decision_id: Some(decision_id.to_string()),
+            exchange_number: Some(exchange_number),
+            agent: Some("moderator".to_string()),
+            ..Self::default()
+        }
+    }
+
+    pub fn context_summary(conversation_id: &str, decision_id: Option<&str>) -> Self {
+        Self {
+            purpose: PURPOSE_CONTEXT_SUMMARY.to_string(),
+            ..Self::chat(conversation_id, decision_id)
+        }
+    }
+
+    pub fn purpose(purpose: &str) -> Self {
+        Self {
+            purpose: purpose.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+// ── Pricing ──
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub prompt_per_million: f64,
+    pub completion_per_million: f64,
+}
+
+// Filled from the OpenRouter models list whenever Settings fetches it, and from
+// `model_pricing.json` at startup so estimates have rates before that happens.
+static PRICING: OnceLock<Mutex<HashMap<String, ModelPricing>>> = OnceLock::new();
+
+fn pricing_table() -> &'static Mutex<HashMap<String, ModelPricing>> {
+    PRICING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn remember_pricing(model: &str, pricing: ModelPricing) {
+    if let Ok(mut table) = pricing_table().lock() {
+        table.insert(model.to_string(), pricing);
+    }
+}
+
+pub fn cached_pricing(model: &str) -> Option<ModelPricing> {
+    pricing_table().lock().ok()?.get(model).copied()
+}
+
+fn pricing_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("model_pricing.json")
+}
+
+/// Persist the cached rates.
+pub fn save_pricing(app_data_dir: &Path) -> Result<(), String> {
+    let table = pricing_table().lock().map_err(|e| e.to_string())?.clone();
+    let content = serde_json::to_string_pretty(&table).map_err(|e| e.to_string())?;
+    fs::write(pricing_path(app_data_dir), content).map_err(|e| e.to_string())
+}
+
+/// Load rates saved by [`save_pricing`], keeping any already fetched this session.
+pub fn load_pricing(app_data_dir: &Path) {
+    let Ok(content) = fs::read_to_string(pricing_path(app_data_dir)) else {
+        return;
+    };
+    let saved: HashMap<String, ModelPricing> = serde_json::from_str(&content).unwrap_or_default();
+    if let Ok(mut table) = pricing_table().lock() {
+        for (model, pricing) in saved {
+            table.entry(model).or_insert(pricing);
+        }
+    }
+}
+
+/// Provider-reported cost wins; otherwise price the tokens from the cached
+/// per-million rates. `None` means the call could not be priced.
+pub fn compute_cost(model: &str, usage: &TokenUsage) -> Option<f64> {
+    if usage.reported_cost.is_some() {
+        return usage.reported_cost;
+    }
+    let pricing = cached_pricing(model)?;
+    Some(
+        usage.prompt_tokens as f64 * pricing.prompt_per_million / 1_000_000.0
+            + usage.completion_tokens as f64 * pricing.completion_per_million / 1_000_000.0,
+    )
+}
+
+// ── Recording ──
+
+/// Persist one LLM call. Calls whose provider reported no usage are still
+/// recorded (zero tokens, unpriced) so reports can flag them.
+pub fn record<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    ctx: &UsageContext,
+    endpoint: &LlmEndpoint,
+    model: &str,
+    usage: Option<TokenUsage>,
+) {
+    let cost_usd = usage.as_ref().and_then(|u| compute_cost(model, u));
+    let usage = usage.unwrap_or_default();
+    let Some(state) = app_handle.try_state::<Mutex<AppState>>() else {
+        return;
+    };
+    let Ok(state_guard) = state.lock() else {
+        return;
+    };
+    if let Err(e) = state_guard.db.record_llm_usage(&NewLlmUsage {
+        purpose: &ctx.purpose,
+        conversation_id: ctx.conversation_id.as_deref(),
+        message_id: ctx.message_id.as_deref(),
+        decision_id: ctx.decision_id.as_deref(),
+        round_number: ctx.round_number,
+        exchange_number: ctx.exchange_number,
+        agent: ctx.agent.as_deref(),
+        provider: &endpoint.provider_id,
+        model,
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        cost_usd,
+    }) {
+        eprintln!("Failed to record LLM usage: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn unit_token_usage_parses_openai_and_openrouter_shapes() {
+        let usage = TokenUsage::from_json(&json!({
+            "prompt_tokens": 1200,
+            "completion_tokens": 300,
+            "total_tokens": 1500,
+            "cost": 0.0042,
+        }))
+        .expect("usage object should parse");
+        assert_eq!(usage.prompt_tokens, 1200);
+        assert_eq!(usage.completion_tokens, 300);
+        assert_eq!(usage.reported_cost, Some(0.0042));
+
+        assert!(TokenUsage::from_json(&Value::Null).is_none());
+    }
+
+    #[test]
+    fn unit_compute_cost_prefers_reported_cost_then_cached_pricing() {
+        remember_pricing("test/priced-model", ModelPricing {
+            prompt_per_million: 3.0,
+            completion_per_million: 15.0,
+        });
+        let usage = TokenUsage { prompt_tokens: 1_000_000, completion_tokens: 200_000, reported_cost: None };
+
+        let computed = compute_cost("test/priced-model", &usage).expect("cached pricing should apply");
+        assert!((computed - 6.0).abs() < 1e-9);
+
+        let reported = TokenUsage { reported_cost: Some(0.5), ..usage };
+        assert_eq!(compute_cost("test/priced-model", &reported), Some(0.5));
+        assert_eq!(compute_cost("test/unknown-model", &usage), None);
+    }
+
+    #[test]
+    fn integration_pricing_survives_a_restart() {
+        let dir = tempfile::tempdir().expect("temp directory should exist");
+        let app_data_dir = dir.path().to_path_buf();
+        let pricing = ModelPricing { prompt_per_million: 0.5, completion_per_million: 1.5 };
+        remember_pricing("test/persisted-model", pricing);
+        save_pricing(&app_data_dir).unwrap();
+
+        pricing_table().lock().unwrap().remove("test/persisted-model");
+        assert_eq!(cached_pricing("test/persisted-model"), None);
+        load_pricing(&app_data_dir);
+        assert_eq!(cached_pricing("test/persisted-model"), Some(pricing));
+    }
+}
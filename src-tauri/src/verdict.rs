@@ -0,0 +1,399 @@
+//! The moderator's verdict on a committee debate as a typed value. It is requested
+//! as schema-checked JSON; scraping the markdown synthesis is only the fallback
+//! for models that can't produce it.
+
+use crate::llm;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+pub const VERDICT_SCHEMA_NAME: &str = "moderator_verdict";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    High,
+    Medium,
+    Low,
+}
+
+impl Confidence {
+    fn label(self) -> &'static str {
+        match self {
+            Confidence::High => "High",
+            Confidence::Medium => "Medium",
+            Confidence::Low => "Low",
+        }
+    }
+
+    /// Lenient read of free text such as "High — the runway math is clear".
+    fn from_text(text: &str) -> Self {
+        let lower = text.to_lowercase();
+        if lower.contains("high") {
+            Confidence::High
+        } else if lower.contains("low") {
+            Confidence::Low
+        } else {
+            Confidence::Medium
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerdictRecommendation {
+    pub choice: String,
+    pub confidence: Confidence,
+    pub reasoning: String,
+    #[serde(default)]
+    pub tradeoffs: String,
+    #[serde(default)]
+    pub next_steps: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModeratorVerdict {
+    #[serde(default)]
+    pub consensus_points: Vec<String>,
+    #[serde(default)]
+    pub key_disagreements: Vec<String>,
+    #[serde(default)]
+    pub biases_identified: Vec<String>,
+    /// Always present on structured verdicts; a scraped synthesis may lack one.
+    #[serde(default)]
+    pub recommendation: Option<VerdictRecommendation>,
+}
+
+/// How a verdict was obtained, persisted alongside it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerdictSource {
+    Structured,
+    Markdown,
+}
+
+impl VerdictSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VerdictSource::Structured => "structured",
+            VerdictSource::Markdown => "markdown",
+        }
+    }
+}
+
+/// JSON schema sent as `response_format` (or as the forced tool's parameters).
+/// Every property is required and closed so it also satisfies OpenAI strict mode.
+pub fn verdict_schema() -> Value {
+    let points = json!({"type": "array", "items": {"type": "string"}});
+    json!({
+        "type": "object",
+        "properties": {
+            "consensus_points": points,
+            "key_disagreements": points,
+            "biases_identified": points,
+            "recommendation": {
+                "type": "object",
+                "properties": {
+                    "choice": {"type": "string", "description": "The option the person should take"},
+                    "confidence": {"type": "string", "enum": ["high", "medium", "low"]},
+                    "reasoning": {"type": "string", "description": "Why this is the right call, weighing the debate"},
+                    "tradeoffs": {"type": "string", "description": "What the person gives up with this choice"},
+                    "next_steps": {"type": "array", "items": {"type": "string"}},
+                },
+                "required": ["choice", "confidence", "reasoning", "tradeoffs", "next_steps"],
+                "additionalProperties": false,
+            },
+        },
+        "required": ["consensus_points", "key_disagreements", "biases_identified", "recommendation"],
+        "additionalProperties": false,
+    })
+}
+
+impl ModeratorVerdict {
+    /// Validate a structured answer against `verdict_schema` and tidy it up.
+    pub fn from_structured(value: &Value) -> Result<Self, String> {
+        let mut problems = Vec::new();
+        llm::validate_schema(value, &verdict_schema(), VERDICT_SCHEMA_NAME, &mut problems);
+        if !problems.is_empty() {
+            return Err(format!("Invalid moderator verdict: {}", problems.join("; ")));
+        }
+        let mut verdict: ModeratorVerdict = serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid moderator verdict: {}", e))?;
+        if verdict.recommendation.is_none() {
+            return Err("Invalid moderator verdict: recommendation is missing".to_string());
+        }
+        verdict.tidy();
+        Ok(verdict)
+    }
+
+    /// Fallback: scrape the headings requested by `agents::moderator_prompt`.
+    pub fn from_markdown(text: &str) -> Self {
+        let rec_section = extract_section(text, "Recommendation");
+        ModeratorVerdict {
+            consensus_points: split_to_points(&extract_section(text, "Where the Committee Agreed")),
+            key_disagreements: split_to_points(&extract_section(text, "Key Disagreements")),
+            biases_identified: split_to_points(&extract_section(text, "Biases & Blind Spots Identified")),
+            recommendation: parse_moderator_recommendation(&rec_section, text),
+        }
+    }
+
+    /// Render with the same headings the markdown prompt asks for, so the
+    /// transcript and TTS read the same whichever way the verdict was produced.
+    pub fn to_markdown(&self) -> String {
+        let mut sections = vec![
+            format!("## Where the Committee Agreed\n{}", bullets(&self.consensus_points)),
+            format!("## Key Disagreements\n{}", bullets(&self.key_disagreements)),
+            format!("## Biases & Blind Spots Identified\n{}", bullets(&self.biases_identified)),
+        ];
+        if let Some(rec) = &self.recommendation {
+            sections.push(format!(
+                "## Recommendation\n**Choice**: {}\n**Confidence**: {}\n**Reasoning**: {}",
+                rec.choice,
+                rec.confidence.label(),
+                rec.reasoning,
+            ));
+            if !rec.tradeoffs.is_empty() {
+                sections.push(format!("## What You're Giving Up\n{}", rec.tradeoffs));
+            }
+            if !rec.next_steps.is_empty() {
+                sections.push(format!("## Action Plan\n{}", bullets(&rec.next_steps)));
+            }
+        }
+        sections.retain(|s| !s.ends_with('\n'));
+        sections.join("\n\n")
+    }
+
+    /// `debate_summary` / `recommendation` update for `decisions::merge_summary`.
+    pub fn summary_update(&self, final_votes: Map<String, Value>) -> Value {
+        let mut update = json!({
+            "debate_summary": {
+                "consensus_points": self.consensus_points,
+                "key_disagreements": self.key_disagreements,
+                "biases_identified": self.biases_identified,
+                "final_votes": final_votes,
+            },
+        });
+        if let Some(rec) = &self.recommendation {
+            update["recommendation"] = json!({
+                "choice": rec.choice,
+                "confidence": rec.confidence,
+                "reasoning": rec.reasoning,
+                "tradeoffs": if rec.tradeoffs.is_empty() { None } else { Some(&rec.tradeoffs) },
+                "next_steps": if rec.next_steps.is_empty() { None } else { Some(&rec.next_steps) },
+            });
+        }
+        update
+    }
+
+    fn tidy(&mut self) {
+        for points in [&mut self.consensus_points, &mut self.key_disagreements, &mut self.biases_identified] {
+            tidy_points(points);
+        }
+        if let Some(rec) = &mut self.recommendation {
+            rec.choice = rec.choice.trim().to_string();
+            rec.reasoning = rec.reasoning.trim().to_string();
+            rec.tradeoffs = rec.tradeoffs.trim().to_string();
+            tidy_points(&mut rec.next_steps);
+        }
+    }
+}
+
+fn tidy_points(points: &mut Vec<String>) {
+    *points = points.iter().map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect();
+}
+
+fn bullets(points: &[String]) -> String {
+    points.iter().map(|p| format!("- {}", p)).collect::<Vec<_>>().join("\n")
+}
+
+// ── Markdown fallback ──
+
+/// Extract a markdown section by heading.
+fn extract_section(text: &str, heading: &str) -> String {
+    let marker = format!("## {}", heading);
+    if let Some(start) = text.find(&marker) {
+        let after = &text[start + marker.len()..];
+        let end = after.find("\n## ").unwrap_or(after.len());
+        after[..end].trim().to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Split text into bullet points.
+fn split_to_points(text: &str) -> Vec<String> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    text.lines()
+        .map(|l| l.trim().trim_start_matches('-').trim_start_matches('*').trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect()
+}
+
+/// Parse the moderator's recommendation section.
+fn parse_moderator_recommendation(rec_section: &str, full_text: &str) -> Option<VerdictRecommendation> {
+    if rec_section.is_empty() && !full_text.contains("**Choice**") {
+        return None;
+    }
+
+    let text = if rec_section.is_empty() { full_text } else { rec_section };
+
+    let choice = extract_bold_value(text, "Choice")
+        .unwrap_or_else(|| "See moderator's synthesis".to_string());
+    let confidence = extract_bold_value(text, "Confidence").unwrap_or_default();
+    let reasoning = extract_bold_value(text, "Reasoning")
+        .unwrap_or_else(|| {
+            rec_section.lines()
+                .filter(|l| !l.starts_with("**"))
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+
+    Some(VerdictRecommendation {
+        choice,
+        confidence: Confidence::from_text(&confidence),
+        reasoning,
+        tradeoffs: extract_section(full_text, "What You're Giving Up"),
+        next_steps: split_to_points(&extract_section(full_text, "Action Plan")),
+    })
+}
+
+/// Extract value after **Label**: pattern
+fn extract_bold_value(text: &str, label: &str) -> Option<String> {
+    let pattern = format!("**{}**:", label);
+    if let Some(pos) = text.find(&pattern) {
+        let after = &text[pos + pattern.len()..];
+        let end = after.find('\n').unwrap_or(after.len());
+        let value = after[..end].trim().to_string();
+        if value.is_empty() { None } else { Some(value) }
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_extract_section_reads_content_until_next_heading() {
+        let content = r#"
+## Where the Committee Agreed
+- Shared value
+- Shared risk
+
+## Key Disagreements
+- Cost vs growth
+"#;
+
+        let section = extract_section(content, "Where the Committee Agreed");
+        assert!(section.contains("Shared value"));
+        assert!(!section.contains("Key Disagreements"));
+    }
+
+    #[test]
+    fn unit_split_to_points_strips_bullets_and_empty_lines() {
+        let points = split_to_points(
+            r#"
+- First
+* Second
+
+Third
+"#,
+        );
+        assert_eq!(points, vec!["First", "Second", "Third"]);
+    }
+
+    #[test]
+    fn unit_parse_moderator_recommendation_extracts_choice_confidence_and_steps() {
+        let full_text = r#"
+## Recommendation
+**Choice**: Option B
+**Confidence**: High
+**Reasoning**: Better upside with manageable risk.
+
+## What You're Giving Up
+- Predictability
+- Familiar team
+
+## Action Plan
+- Call recruiter today
+- Draft a 90-day transition plan
+"#;
+
+        let rec_section = extract_section(full_text, "Recommendation");
+        let recommendation =
+            parse_moderator_recommendation(&rec_section, full_text).expect("recommendation should parse");
+
+        assert_eq!(recommendation.choice, "Option B");
+        assert_eq!(recommendation.confidence, Confidence::High);
+        assert_eq!(recommendation.reasoning, "Better upside with manageable risk.");
+        assert_eq!(
+            recommendation.next_steps[0],
+            "Call recruiter today"
+        );
+        assert_eq!(
+            recommendation.tradeoffs,
+            "- Predictability\n- Familiar team"
+        );
+    }
+
+    #[test]
+    fn unit_parse_moderator_recommendation_returns_none_without_recommendation_fields() {
+        let no_recommendation = "## Where the Committee Agreed\n- Point A";
+        assert!(parse_moderator_recommendation("", no_recommendation).is_none());
+    }
+
+    fn structured_verdict() -> Value {
+        json!({
+            "consensus_points": ["Runway matters most", "  "],
+            "key_disagreements": ["Timing of the move"],
+            "biases_identified": [],
+            "recommendation": {
+                "choice": " Take the offer ",
+                "confidence": "high",
+                "reasoning": "Growth outweighs the short-term risk.",
+                "tradeoffs": "Less predictable income for a year.",
+                "next_steps": ["Negotiate the start date"]
+            }
+        })
+    }
+
+    #[test]
+    fn unit_from_structured_validates_against_the_schema_and_tidies_points() {
+        let verdict = ModeratorVerdict::from_structured(&structured_verdict()).expect("verdict should validate");
+        assert_eq!(verdict.consensus_points, vec!["Runway matters most"]);
+        let rec = verdict.recommendation.expect("recommendation should be present");
+        assert_eq!(rec.choice, "Take the offer");
+        assert_eq!(rec.confidence, Confidence::High);
+
+        let mut bad_confidence = structured_verdict();
+        bad_confidence["recommendation"]["confidence"] = json!("certain");
+        let err = ModeratorVerdict::from_structured(&bad_confidence).unwrap_err();
+        assert!(err.contains("moderator_verdict.recommendation.confidence must be one of"), "{}", err);
+
+        let mut missing_choice = structured_verdict();
+        missing_choice["recommendation"]["choice"] = json!("");
+        assert!(ModeratorVerdict::from_structured(&missing_choice).is_err());
+
+        let mut no_recommendation = structured_verdict();
+        no_recommendation.as_object_mut().unwrap().remove("recommendation");
+        assert!(ModeratorVerdict::from_structured(&no_recommendation).is_err());
+    }
+
+    #[test]
+    fn unit_to_markdown_round_trips_through_the_markdown_fallback() {
+        let verdict = ModeratorVerdict::from_structured(&structured_verdict()).unwrap();
+        let markdown = verdict.to_markdown();
+        assert!(markdown.starts_with("## Where the Committee Agreed\n- Runway matters most"));
+        assert!(!markdown.contains("## Biases"), "empty sections should be left out");
+        assert!(markdown.contains("**Confidence**: High"));
+        assert_eq!(ModeratorVerdict::from_markdown(&markdown), verdict);
+
+        let update = verdict.summary_update(Map::new());
+        assert_eq!(update["recommendation"]["confidence"], "high");
+        assert_eq!(update["recommendation"]["next_steps"][0], "Negotiate the start date");
+        assert_eq!(update["debate_summary"]["key_disagreements"][0], "Timing of the move");
+    }
+}
@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { revealItemInDir } from "@tauri-apps/plugin-opener";
 import { FolderOpen, Plus, Trash2, Users } from "lucide-react";
 import { Button } from "@/components/ui/button";
@@ -26,6 +27,11 @@ interface AgentFileInfo {
   size_bytes: number;
 }
 
+interface FilesChangedEvent {
+  filenames: string[];
+  error?: string;
+}
+
 interface SettingsResponse {
   api_key_set: boolean;
   api_key_preview: string;
@@ -46,6 +52,7 @@ export default function CommitteeView({ onNavigateToChat }: CommitteeViewProps)
   const [registry, setRegistry] = useState<AgentMeta[]>([]);
   const [showCreateDialog, setShowCreateDialog] = useState(false);
   const [deleteAgent, setDeleteAgent] = useState<AgentMeta | null>(null);
+  const [registryError, setRegistryError] = useState<string | null>(null);
 
   useEffect(() => {
     loadFiles();
@@ -53,12 +60,28 @@ export default function CommitteeView({ onNavigateToChat }: CommitteeViewProps)
     loadRegistry();
   }, []);
 
+  // Reload when agent files are edited outside the app
+  useEffect(() => {
+    const unlisten = listen<FilesChangedEvent>("agent-files-changed", (event) => {
+      loadFiles();
+      loadRegistry();
+      if (event.payload.error) {
+        setRegistryError(event.payload.error);
+      }
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   async function loadRegistry() {
     try {
       const agents = await invoke<AgentMeta[]>("get_agent_registry");
       setRegistry(agents);
+      setRegistryError(null);
     } catch (err) {
       console.error("Failed to load agent registry:", err);
+      setRegistryError(String(err));
     }
   }
 
@@ -176,6 +199,12 @@ export default function CommitteeView({ onNavigateToChat }: CommitteeViewProps)
         </div>
       </div>
 
+      {registryError && (
+        <div className="px-4 py-2 border-b border-border bg-destructive/10 text-sm text-destructive shrink-0">
+          {registryError}
+        </div>
+      )}
+
       {/* Body */}
       {files.length === 0 ? (
         <div className="flex-1 flex items-center justify-center">
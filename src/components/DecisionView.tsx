@@ -45,17 +45,23 @@ interface Message {
   role: string;
   content: string;
   created_at: string;
+  status?: "complete" | "cancelled";
+  reasoning?: string | null;
+  parent_id?: string | null;
 }
 
 interface SendMessageResponse {
   conversation_id: string;
   response: string;
+  cancelled?: boolean;
 }
 
 interface StreamEvent {
-  type: "token" | "tool_use";
+  type: "token" | "reasoning" | "tool_use" | "tool_result";
   token?: string;
   tool?: string;
+  success?: boolean;
+  error?: string;
 }
 
 interface Decision {
@@ -330,6 +336,7 @@ export default function DecisionView({
       await invoke<SendMessageResponse>("send_message", {
         conversationId,
         message: text,
+        requestId: crypto.randomUUID(),
         onEvent: channel,
       });
 
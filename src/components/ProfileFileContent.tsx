@@ -12,6 +12,7 @@ interface ProfileFileInfo {
   content: string;
   modified_at: string;
   size_bytes: number;
+  revision_count?: number;
 }
 
 interface ProfileFileContentProps {
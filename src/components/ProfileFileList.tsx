@@ -6,6 +6,7 @@ interface ProfileFileInfo {
   content: string;
   modified_at: string;
   size_bytes: number;
+  revision_count?: number;
 }
 
 interface ProfileFileListProps {
@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { revealItemInDir } from "@tauri-apps/plugin-opener";
 import { FolderOpen, User } from "lucide-react";
 import { Button } from "@/components/ui/button";
@@ -13,6 +14,7 @@ interface ProfileFileInfo {
   content: string;
   modified_at: string;
   size_bytes: number;
+  revision_count?: number;
 }
 
 interface ProfileViewProps {
@@ -29,6 +31,16 @@ export default function ProfileView({ onNavigateToChat }: ProfileViewProps) {
     loadFiles();
   }, []);
 
+  // Reload when profile files are edited outside the app
+  useEffect(() => {
+    const unlisten = listen<{ filenames: string[] }>("profile-files-changed", () => {
+      loadFiles();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   async function loadFiles() {
     try {
       const result = await invoke<ProfileFileInfo[]>("get_profile_files_detailed");
@@ -92,11 +92,13 @@ export default function Settings({ onClose, onSaved, mustSetKey }: SettingsProps
 
     try {
       await invoke("save_settings", {
-        apiKey: apiKey.trim(),
-        model: model.trim(),
-        elevenlabsApiKey: elevenlabsApiKey.trim() || null,
-        ttsProvider,
-        elevenlabsModel,
+        settings: {
+          api_key: apiKey.trim(),
+          model: model.trim(),
+          elevenlabs_api_key: elevenlabsApiKey.trim() || null,
+          tts_provider: ttsProvider,
+          elevenlabs_model: elevenlabsModel,
+        },
       });
       onSaved();
     } catch (err) {
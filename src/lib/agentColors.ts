@@ -7,6 +7,22 @@ export interface AgentMeta {
   builtin: boolean;
   sort_order: number;
   voice_gender: string; // "male" | "female"
+  generation?: GenerationParams;
+}
+
+export interface GenerationParams {
+  temperature?: number;
+  top_p?: number;
+  max_tokens?: number;
+  seed?: number;
+  provider_routing?: {
+    order?: string[];
+    ignore?: string[];
+    allow_fallbacks?: boolean;
+    require_parameters?: boolean;
+    data_collection?: "allow" | "deny";
+    sort?: "price" | "throughput" | "latency";
+  };
 }
 
 export const COLOR_MAP: Record<